@@ -0,0 +1,26 @@
+//! Feeds arbitrary input through the front end, which must reject it with an error rather than
+//! panic.
+//!
+//! The bytes are tokenized if they are valid UTF-8, and the tokens are parsed if tokenizing
+//! succeeds. Run it from the root of the repository with a nightly toolchain and `cargo-fuzz`:
+//!
+//! ```text
+//! cargo install cargo-fuzz
+//! cargo +nightly fuzz run parse fuzz/corpus/parse
+//! ```
+//!
+//! The seed corpus in `fuzz/corpus/parse` holds a few valid programs to start from; the inputs
+//! found by the fuzzer are added to it.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rengo::parser::parse::parse;
+use rengo::parser::tokenize::tokenize;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(source) = core::str::from_utf8(data) {
+        if let Ok(tokens) = tokenize(source) {
+            let _ = parse(&tokens);
+        }
+    }
+});
@@ -0,0 +1,95 @@
+use alloc::vec::Vec;
+use crate::asm::instruction::Instruction;
+use crate::asm::size::encoded_size;
+
+/// The prefix of the labels that start loops. `align_loops` pads the code before these labels.
+///
+/// The code generator emits no such labels yet, as it rejects `while` loops, so `--align-loops`
+/// has no effect on the programs it compiles until it does.
+pub const LOOP_LABEL_PREFIX: &str = "loop";
+
+/// Inserts `Nop`s before every loop label so that each loop starts at a multiple of `alignment`
+/// bytes.
+///
+/// Positions are counted with `encoded_size` from the first instruction, which is assumed to be
+/// aligned itself (sections start aligned to 16 bytes). Labels that don't start with
+/// `LOOP_LABEL_PREFIX` are left as they are.
+///
+/// ## Parameters:
+/// - `instructions`: The code to pad.
+/// - `alignment`: The alignment of the loop labels in bytes. It must be a power of two.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::asm::instruction::Instruction;
+/// # use rengo::asm::arg::Arg;
+/// # use rengo::asm::reg::Reg;
+/// # use rengo::asm::align::align_loops;
+/// let instructions = vec![
+///     Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(5)),  // 5 bytes
+///     Instruction::Label("loop0".to_string()),
+/// ];
+/// assert_eq!(align_loops(instructions, 8), vec![
+///     Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(5)),
+///     Instruction::Nop,
+///     Instruction::Nop,
+///     Instruction::Nop,
+///     Instruction::Label("loop0".to_string()),
+/// ]);
+/// ```
+pub fn align_loops(instructions: Vec<Instruction>, alignment: usize) -> Vec<Instruction> {
+    debug_assert!(alignment.is_power_of_two(), "Alignment must be a power of two: {}", alignment);
+    let mut aligned = Vec::with_capacity(instructions.len());
+    let mut position = 0;
+    for instruction in instructions {
+        if matches!(&instruction, Instruction::Label(name) if name.starts_with(LOOP_LABEL_PREFIX)) {
+            let padding = (alignment - position % alignment) % alignment;
+            aligned.extend(core::iter::repeat_n(Instruction::Nop, padding));
+            position += padding;
+        }
+        position += encoded_size(&instruction);
+        aligned.push(instruction);
+    }
+    aligned
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec;
+    use expectest::prelude::*;
+    use super::*;
+    use crate::asm::arg::Arg::{Constant, Registry};
+    use crate::asm::instruction::Instruction::{Inc, Label, Mov, Nop};
+    use crate::asm::reg::Reg::Rax;
+
+    mod align_loops {
+        use super::*;
+
+        #[test]
+        fn pads_loop_labels_to_the_alignment() {
+            let instructions = vec![
+                Mov(Registry(Rax), Constant(5)),  // 5 bytes
+                Inc(Registry(Rax)),               // 3 bytes
+                Inc(Registry(Rax)),               // 3 bytes
+                Label("loop0".to_string()),
+                Inc(Registry(Rax)),
+            ];
+            let aligned = align_loops(instructions, 16);
+            expect!(aligned.iter().filter(|instruction| **instruction == Nop).count()).to(be_equal_to(5));
+            expect!(&aligned[3..9]).to(be_equal_to(&[Nop, Nop, Nop, Nop, Nop, Label("loop0".to_string())][..]));
+        }
+
+        #[test]
+        fn does_not_pad_aligned_loops() {
+            let instructions = vec![Label("loop0".to_string()), Inc(Registry(Rax))];
+            expect!(align_loops(instructions.clone(), 16)).to(be_equal_to(instructions));
+        }
+
+        #[test]
+        fn ignores_other_labels() {
+            let instructions = vec![Inc(Registry(Rax)), Label("stmt0".to_string()), Inc(Registry(Rax))];
+            expect!(align_loops(instructions.clone(), 16)).to(be_equal_to(instructions));
+        }
+    }
+}
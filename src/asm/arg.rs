@@ -1,12 +1,13 @@
+use alloc::string::String;
 use crate::asm::reg::Reg;
 
 /// Represents an argument in an assembly instruction.
 ///
 /// The `Arg` enum is used to model the operands that can be utilized in assembly instructions.
-/// These operands can either be numeric constants, CPU registers, or registers with an offset,
-/// providing the flexibility needed to represent different types of assembly arguments.
+/// These operands can either be numeric constants, CPU registers, or memory operands, providing
+/// the flexibility needed to represent different types of assembly arguments.
 #[derive(Debug, PartialEq, Clone)]
-pub(crate) enum Arg {
+pub enum Arg {
     /// A numeric constant.
     ///
     /// The `Constant` variant holds a 64-bit integer (`i64`) that represents an immediate value
@@ -17,6 +18,7 @@ pub(crate) enum Arg {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::asm::arg::Arg;
     /// let arg = Arg::Constant(42);
     /// ```
     Constant(i64),
@@ -31,6 +33,8 @@ pub(crate) enum Arg {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
     /// let arg = Arg::Registry(Reg::Rax);
     /// ```
     Registry(Reg),
@@ -45,7 +49,143 @@ pub(crate) enum Arg {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
     /// let arg = Arg::RegistryOffset(Reg::Rsp, 8);
     /// ```
     RegistryOffset(Reg, i64),
+
+    /// The memory at a label, addressed relative to the instruction pointer.
+    ///
+    /// The `Label` variant refers to data placed under a label, like a string in the data
+    /// section. Rip-relative addressing keeps the code position-independent; `Lea` turns it into
+    /// the address of the data.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::arg::Arg;
+    /// let arg = Arg::Label("str0".to_string());
+    /// ```
+    Label(String),
+
+    /// A memory operand in any of the forms of `MemOperand`.
+    ///
+    /// `RegistryOffset` and `Label` are shorthands for the two forms the compiler uses, and
+    /// `Arg::memory` writes them out as the `MemOperand` they stand for.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::arg::MemOperand;
+    /// let arg = Arg::Memory(MemOperand::Absolute(0x1000));
+    /// ```
+    Memory(MemOperand),
+}
+
+/// The address of a memory operand.
+#[derive(Debug, PartialEq, Clone)]
+pub enum MemOperand {
+    /// A base register plus a displacement in bytes, like `[rbp - 8]`.
+    Base(Reg, i64),
+
+    /// The memory at a label, addressed relative to the instruction pointer, like `[rel str0]`.
+    Label(String),
+
+    /// A fixed address, like `[abs 0x1000]`, which only works in code that is not
+    /// position-independent.
+    Absolute(u64),
+}
+
+/// The `Rax` register as an argument.
+pub const RAX: Arg = Arg::Registry(Reg::Rax);
+
+/// The `Rcx` register as an argument.
+pub const RCX: Arg = Arg::Registry(Reg::Rcx);
+
+/// The `Rsp` register as an argument.
+pub const RSP: Arg = Arg::Registry(Reg::Rsp);
+
+/// The `Rbp` register as an argument.
+pub const RBP: Arg = Arg::Registry(Reg::Rbp);
+
+/// Wraps a register as an argument, so that `Rax.into()` can stand for `Arg::Registry(Reg::Rax)`.
+impl From<Reg> for Arg {
+    fn from(reg: Reg) -> Self {
+        Arg::Registry(reg)
+    }
+}
+
+/// Wraps a number as a constant argument, so that `42.into()` can stand for `Arg::Constant(42)`.
+impl From<i64> for Arg {
+    fn from(value: i64) -> Self {
+        Arg::Constant(value)
+    }
+}
+
+impl Arg {
+    /// Returns the memory operand the argument refers to, with the `RegistryOffset` and `Label`
+    /// shorthands written out, or `None` for a constant or a register.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
+    /// # use rengo::asm::arg::MemOperand;
+    /// assert_eq!(Arg::RegistryOffset(Reg::Rsp, -1).memory(), Some(MemOperand::Base(Reg::Rsp, -8)));
+    /// assert_eq!(Arg::Registry(Reg::Rax).memory(), None);
+    /// ```
+    pub fn memory(&self) -> Option<MemOperand> {
+        match self {
+            Arg::Constant(_) | Arg::Registry(_) => None,
+            Arg::RegistryOffset(reg, offset) => Some(MemOperand::Base(reg.clone(), 8 * offset)),
+            Arg::Label(label) => Some(MemOperand::Label(label.clone())),
+            Arg::Memory(memory) => Some(memory.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use expectest::prelude::*;
+    use super::*;
+
+    mod memory {
+        use super::*;
+
+        #[test]
+        fn writes_out_the_shorthands() {
+            expect!(Arg::RegistryOffset(Reg::Rbp, -2).memory()).to(be_some().value(MemOperand::Base(Reg::Rbp, -16)));
+            expect!(Arg::Label("str0".to_string()).memory()).to(be_some().value(MemOperand::Label("str0".to_string())));
+            let absolute = MemOperand::Absolute(0x1000);
+            expect!(Arg::Memory(absolute.clone()).memory()).to(be_some().value(absolute));
+        }
+
+        #[test]
+        fn is_none_for_values() {
+            expect!(Arg::Constant(8).memory()).to(be_none());
+            expect!(Arg::Registry(Reg::Rsp).memory()).to(be_none());
+        }
+    }
+
+    mod from {
+        use super::*;
+
+        #[test]
+        fn wraps_registers() {
+            expect!(Arg::from(Reg::Rax)).to(be_equal_to(Arg::Registry(Reg::Rax)));
+            expect!(RAX).to(be_equal_to(Arg::Registry(Reg::Rax)));
+            expect!([RCX, RSP, RBP]).to(be_equal_to([Reg::Rcx.into(), Reg::Rsp.into(), Reg::Rbp.into()]));
+        }
+
+        #[test]
+        fn wraps_constants() {
+            let arg: Arg = 42.into();
+            expect!(arg).to(be_equal_to(Arg::Constant(42)));
+            expect!(Arg::from(-1)).to(be_equal_to(Arg::Constant(-1)));
+        }
+    }
 }
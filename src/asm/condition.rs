@@ -0,0 +1,48 @@
+/// Represents the condition tested by a conditional instruction, like `jne` or `setl`.
+///
+/// A condition is tested against the flags set by the last `Instruction::Cmp`, comparing its
+/// first argument to its second one as signed integers, except for `Below`. After an
+/// `Instruction::Test`, the bitwise AND of its arguments is compared to 0 instead.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Condition {
+    /// The first argument was less than the second one (`l`).
+    Less,
+
+    /// The first argument was greater than the second one (`g`).
+    Greater,
+
+    /// The arguments were different (`ne`).
+    NotEqual,
+
+    /// The arguments were equal (`e`).
+    Equal,
+
+    /// The first argument was less than the second one, compared as unsigned integers (`b`). A
+    /// negative number compares as a large one, so a single `Below` checks that a number is both
+    /// at least 0 and less than a positive bound.
+    Below,
+}
+
+impl Condition {
+    /// Returns the suffix that names the condition in mnemonics, like `l` in `setl`.
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Condition::Less => "l",
+            Condition::Greater => "g",
+            Condition::NotEqual => "ne",
+            Condition::Equal => "e",
+            Condition::Below => "b",
+        }
+    }
+
+    /// Returns whether the condition holds after comparing `lhs` to `rhs`.
+    pub fn holds(&self, lhs: i64, rhs: i64) -> bool {
+        match self {
+            Condition::Less => lhs < rhs,
+            Condition::Greater => lhs > rhs,
+            Condition::NotEqual => lhs != rhs,
+            Condition::Equal => lhs == rhs,
+            Condition::Below => (lhs as u64) < (rhs as u64),
+        }
+    }
+}
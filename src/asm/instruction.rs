@@ -1,4 +1,8 @@
+use alloc::boxed::Box;
+use alloc::string::String;
 use crate::asm::arg::Arg;
+use crate::asm::condition::Condition;
+use crate::parser::span::Span;
 
 /// Represents an assembly instruction.
 ///
@@ -10,10 +14,33 @@ use crate::asm::arg::Arg;
 /// - `Inc(Arg)`: Increments the value in the specified argument by one.
 /// - `Dec(Arg)`: Decrements the value in the specified argument by one.
 /// - `Mov(Arg, Arg)`: Copies data from a source to a destination.
+/// - `Movzx(Arg, Arg)`: Copies a byte into a whole register, filling the rest with zeroes.
+/// - `Movsx(Arg, Arg)`: Copies a byte into a whole register, filling the rest with its sign bit.
 /// - `Add(Arg, Arg)`: Adds two values and stores the result in the destination.
 /// - `Sub(Arg, Arg)`: Subtracts one value from another and stores the result in the destination.
+/// - `And(Arg, Arg)`: Computes the bitwise AND of two values and stores it in the destination.
+/// - `Or(Arg, Arg)`: Computes the bitwise OR of two values and stores it in the destination.
+/// - `Xor(Arg, Arg)`: Computes the bitwise XOR of two values and stores it in the destination.
+/// - `Shl(Arg, Arg)`: Shifts the destination to the left by the given count.
+/// - `Sar(Arg, Arg)`: Arithmetically shifts the destination to the right by the given count.
+/// - `Label(String)`: Marks a position in the code that can be referred to by name.
+/// - `Push(Arg)`: Pushes a value onto the stack.
+/// - `Pop(Arg)`: Pops a value from the stack into the destination.
+/// - `Lea(Arg, Arg)`: Loads the address of a memory operand into a register.
+/// - `Nop`: Does nothing, taking one byte of code.
+/// - `Cmp(Arg, Arg)`: Compares two values, setting the flags tested by conditional instructions.
+/// - `Test(Arg, Arg)`: Sets the flags tested by conditional instructions from the bitwise AND of
+///   two values, e.g. to test a register against zero.
+/// - `Set(Condition, Arg)`: Sets a byte to 1 if the condition holds and to 0 otherwise.
+/// - `Jump(Condition, String)`: Jumps to a label if the condition holds.
+/// - `Jmp(String)`: Jumps to a label unconditionally.
+/// - `Cmov(Condition, Arg, Arg)`: Copies the source into the destination if the condition holds.
+/// - `Call(String)`: Calls the function with the given symbol.
+/// - `Ret`: Returns from the current function to its caller.
+/// - `Located(Span, Box<Instruction>)`: An instruction along with the span of the source that
+///   produced it.
 #[derive(Debug, PartialEq, Clone)]
-pub(crate) enum Instruction {
+pub enum Instruction {
     /// The `Inc` (increment) instruction.
     ///
     /// This instruction increases the value stored in the specified argument by one. The argument
@@ -22,6 +49,9 @@ pub(crate) enum Instruction {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
     /// // Example of incrementing the value in a register
     /// Instruction::Inc(Arg::Registry(Reg::Rax));
     /// ```
@@ -35,6 +65,9 @@ pub(crate) enum Instruction {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
     /// // Example of decrementing the value in a register
     /// Instruction::Dec(Arg::Registry(Reg::Rax));
     /// ```
@@ -49,6 +82,9 @@ pub(crate) enum Instruction {
     /// # Examples
     ///
     /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
     /// // Example of moving a constant value into a register
     /// Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(42));
     ///
@@ -57,6 +93,38 @@ pub(crate) enum Instruction {
     /// ```
     Mov(Arg, Arg),
 
+    /// The `Movzx` (move with zero extension) instruction.
+    ///
+    /// This instruction copies the byte-sized source (second field), like `Al` or a slot read as a
+    /// byte, into the destination register (first field), setting the rest of the register to
+    /// zeroes. It turns the byte written by a `Set` into a value of the whole register.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
+    /// Instruction::Movzx(Arg::Registry(Reg::Rax), Arg::Registry(Reg::Al));
+    /// ```
+    Movzx(Arg, Arg),
+
+    /// The `Movsx` (move with sign extension) instruction.
+    ///
+    /// This instruction copies the byte-sized source (second field) into the destination register
+    /// (first field) like `Movzx`, but fills the rest of the register with copies of the highest
+    /// bit of the byte, so the byte is read as a number from -128 to 127.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
+    /// Instruction::Movsx(Arg::Registry(Reg::Rax), Arg::Registry(Reg::Al));
+    /// ```
+    Movsx(Arg, Arg),
+
     /// The `Add` (addition) instruction.
     ///
     /// This instruction adds the value of the second argument to the value of the first argument
@@ -66,6 +134,9 @@ pub(crate) enum Instruction {
     /// # Examples
     ///
     /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
     /// // Example of adding a constant value to the value in a register
     /// Instruction::Add(Arg::Registry(Reg::Rax), Arg::Constant(1));
     ///
@@ -83,6 +154,9 @@ pub(crate) enum Instruction {
     /// # Examples
     ///
     /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
     /// // Example of subtracting a constant value from the value in a register
     /// Instruction::Sub(Arg::Registry(Reg::Rax), Arg::Constant(1));
     ///
@@ -90,4 +164,321 @@ pub(crate) enum Instruction {
     /// Instruction::Sub(Arg::Registry(Reg::Rax), Arg::Registry(Reg::Rbx));
     /// ```
     Sub(Arg, Arg),
+
+    /// The `And` (bitwise AND) instruction.
+    ///
+    /// This instruction computes the bitwise AND of both arguments and stores the result in the
+    /// first (destination) argument.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
+    /// Instruction::And(Arg::Registry(Reg::Rax), Arg::Registry(Reg::Rcx));
+    /// ```
+    And(Arg, Arg),
+
+    /// The `Or` (bitwise OR) instruction.
+    ///
+    /// This instruction computes the bitwise OR of both arguments and stores the result in the
+    /// first (destination) argument.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
+    /// Instruction::Or(Arg::Registry(Reg::Rax), Arg::Registry(Reg::Rcx));
+    /// ```
+    Or(Arg, Arg),
+
+    /// The `Xor` (bitwise exclusive OR) instruction.
+    ///
+    /// This instruction computes the bitwise XOR of both arguments and stores the result in the
+    /// first (destination) argument.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
+    /// Instruction::Xor(Arg::Registry(Reg::Rax), Arg::Registry(Reg::Rcx));
+    /// ```
+    Xor(Arg, Arg),
+
+    /// The `Shl` (shift left) instruction.
+    ///
+    /// This instruction shifts the first argument to the left by the number of bits given by the
+    /// second argument, filling with zeroes. The count must be either a constant or the `Cl`
+    /// register, and only its lowest six bits are used.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
+    /// Instruction::Shl(Arg::Registry(Reg::Rax), Arg::Registry(Reg::Cl));
+    /// ```
+    Shl(Arg, Arg),
+
+    /// The `Sar` (shift arithmetic right) instruction.
+    ///
+    /// This instruction shifts the first argument to the right by the number of bits given by the
+    /// second argument, filling with copies of the sign bit. The count must be either a constant
+    /// or the `Cl` register, and only its lowest six bits are used.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
+    /// Instruction::Sar(Arg::Registry(Reg::Rax), Arg::Registry(Reg::Cl));
+    /// ```
+    Sar(Arg, Arg),
+
+    /// A label.
+    ///
+    /// This is not an instruction executed by the CPU, but a named position in the code that other
+    /// instructions can refer to. It is rendered as the name followed by a colon.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// Instruction::Label("loop_start".to_string());
+    /// ```
+    Label(String),
+
+    /// The `Push` instruction.
+    ///
+    /// This instruction decrements `Rsp` by eight and stores the value of the argument at the new
+    /// top of the stack.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
+    /// Instruction::Push(Arg::Registry(Reg::Rbp));
+    /// ```
+    Push(Arg),
+
+    /// The `Pop` instruction.
+    ///
+    /// This instruction loads the value at the top of the stack into the argument and increments
+    /// `Rsp` by eight.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
+    /// Instruction::Pop(Arg::Registry(Reg::Rbp));
+    /// ```
+    Pop(Arg),
+
+    /// The `Lea` (load effective address) instruction.
+    ///
+    /// This instruction computes the address of the memory operand in the second argument and
+    /// stores it in the first argument, without accessing memory. The destination must be a
+    /// register and the source a `RegistryOffset`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
+    /// Instruction::Lea(Arg::Registry(Reg::Rax), Arg::RegistryOffset(Reg::Rsp, -1));
+    /// ```
+    Lea(Arg, Arg),
+
+    /// The `Nop` (no operation) instruction.
+    ///
+    /// This instruction does nothing. It is encoded in a single byte, so it is used as padding to
+    /// move the following code to an aligned address.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// Instruction::Nop;
+    /// ```
+    Nop,
+
+    /// The `Cmp` (compare) instruction.
+    ///
+    /// This instruction subtracts the second argument from the first one without storing the
+    /// result, only setting the flags that conditional instructions test.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
+    /// Instruction::Cmp(Arg::Registry(Reg::Rax), Arg::Registry(Reg::Rcx));
+    /// ```
+    Cmp(Arg, Arg),
+
+    /// The `Test` instruction.
+    ///
+    /// This instruction computes the bitwise AND of its arguments without storing it, only setting
+    /// the flags that conditional instructions test, as if the result was compared to 0. Testing a
+    /// register against itself is the idiomatic way to compare it to zero, with a shorter encoding
+    /// than `Cmp` with a constant.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
+    /// Instruction::Test(Arg::Registry(Reg::Rax), Arg::Registry(Reg::Rax));
+    /// ```
+    Test(Arg, Arg),
+
+    /// The `Set<cc>` instruction.
+    ///
+    /// This instruction sets its byte-sized argument to 1 if the condition holds after the last
+    /// `Cmp`, and to 0 otherwise. The rest of the enclosing register is left as it is.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::condition::Condition;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
+    /// Instruction::Set(Condition::Less, Arg::Registry(Reg::Al));
+    /// ```
+    Set(Condition, Arg),
+
+    /// The `J<cc>` (conditional jump) instruction.
+    ///
+    /// This instruction continues execution at the label if the condition holds after the last
+    /// `Cmp`, and at the next instruction otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::condition::Condition;
+    /// Instruction::Jump(Condition::NotEqual, "assert0".to_string());
+    /// ```
+    Jump(Condition, String),
+
+    /// The `Jmp` (unconditional jump) instruction.
+    ///
+    /// This instruction always continues execution at the label.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// Instruction::Jmp("epilogue".to_string());
+    /// ```
+    Jmp(String),
+
+    /// The `Cmov<cc>` (conditional move) instruction.
+    ///
+    /// This instruction copies the source (third field) into the destination register (second
+    /// field) if the condition holds after the last `Cmp`, and leaves it as it is otherwise. Unlike
+    /// a jump around a `Mov`, it does not branch.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// # use rengo::asm::condition::Condition;
+    /// # use rengo::asm::arg::Arg;
+    /// # use rengo::asm::reg::Reg;
+    /// Instruction::Cmov(Condition::Greater, Arg::Registry(Reg::Rax), Arg::Registry(Reg::Rcx));
+    /// ```
+    Cmov(Condition, Arg, Arg),
+
+    /// The `Call` instruction.
+    ///
+    /// This instruction calls the function with the given symbol, which is declared as external
+    /// when the code is assembled.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// Instruction::Call("rengo_assert_failed".to_string());
+    /// ```
+    Call(String),
+
+    /// The `Ret` instruction.
+    ///
+    /// This instruction pops the return address pushed by the matching `Call` and continues
+    /// there, ending the current function.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::instruction::Instruction;
+    /// Instruction::Ret;
+    /// ```
+    Ret,
+
+    /// An instruction that carries the span of the AST node it was generated for.
+    ///
+    /// It behaves exactly like the wrapped instruction, and is rendered as it followed by a comment
+    /// with the line and column of the span. The compiler attaches spans to the instructions that
+    /// can stop the program, so that a trap there can be reported at its place in the source.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::span::Span;
+    /// # use rengo::asm::instruction::Instruction;
+    /// let span = Span { start: 0, end: 6, line: 1, col: 1 };
+    /// Instruction::Located(span, Box::new(Instruction::Call("rengo_assert_failed".to_string())));
+    /// ```
+    Located(Span, Box<Instruction>),
+}
+
+impl Instruction {
+    /// Attaches a span to the instruction, if there is one.
+    ///
+    /// ## Example:
+    /// ```rust
+    /// # use rengo::parser::span::Span;
+    /// # use rengo::asm::instruction::Instruction;
+    /// let span = Span { start: 0, end: 6, line: 1, col: 1 };
+    /// assert_eq!(Instruction::Ret.located(Some(span)), Instruction::Located(span, Box::new(Instruction::Ret)));
+    /// assert_eq!(Instruction::Ret.located(None), Instruction::Ret);
+    /// ```
+    pub fn located(self, span: Option<Span>) -> Instruction {
+        match span {
+            Some(span) => Instruction::Located(span, Box::new(self)),
+            None => self,
+        }
+    }
+
+    /// Returns the instruction without the span attached to it, if any.
+    pub fn unlocated(&self) -> &Instruction {
+        match self {
+            Instruction::Located(_, instruction) => instruction.unlocated(),
+            instruction => instruction,
+        }
+    }
+
+    /// Returns the span attached to the instruction, if any.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Instruction::Located(span, _) => Some(*span),
+            _ => None,
+        }
+    }
 }
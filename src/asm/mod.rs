@@ -1,4 +1,11 @@
-pub(crate) mod reg;
-pub(crate) mod arg;
-pub(crate) mod instruction;
-pub(crate) mod to_string;
\ No newline at end of file
+pub mod reg;
+pub mod arg;
+pub mod align;
+pub mod condition;
+pub mod instruction;
+pub mod program;
+#[cfg(test)]
+pub(crate) mod simulate;
+pub mod size;
+pub mod style;
+pub mod to_string;
@@ -0,0 +1,127 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::asm::instruction::Instruction;
+use crate::asm::style::AsmStyle;
+
+/// A null-terminated string placed in the data section under a label.
+///
+/// # Example
+///
+/// ```rust
+/// # use rengo::asm::program::Data;
+/// let data = Data { label: "str0".to_string(), value: "hi".to_string() };
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct Data {
+    /// The label the code refers to the string by.
+    pub label: String,
+
+    /// The contents of the string, without the terminating null byte.
+    pub value: String,
+}
+
+/// A program split into the sections of an object file: the data it refers to, and its code.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Program {
+    /// The contents of the `.data` section.
+    pub data: Vec<Data>,
+
+    /// The contents of the `.text` section.
+    pub text: Vec<Instruction>,
+}
+
+impl Program {
+    /// Renders the data section of the program, or nothing if it has no data.
+    ///
+    /// Each string is declared with `db` and a terminating null byte, so the runtime can read it
+    /// as a C string. Printable characters are kept between quotes, and any other byte (including
+    /// the bytes of non-ASCII characters) is written as a number.
+    ///
+    /// ## Example:
+    /// ```rust
+    /// # use rengo::asm::program::Program;
+    /// # use rengo::asm::program::Data;
+    /// # use rengo::asm::style::AsmStyle;
+    /// let program = Program { data: vec![Data { label: "str0".to_string(), value: "hi".to_string() }], text: vec![] };
+    /// assert_eq!(program.data_to_string(&AsmStyle::default()), "section .data\nstr0: db \"hi\", 0\n");
+    /// ```
+    pub fn data_to_string(&self, style: &AsmStyle) -> String {
+        if self.data.is_empty() {
+            return String::new();
+        }
+        let nl = style.line_ending.as_str();
+        let mut section = format!("section .data{nl}");
+        for data in &self.data {
+            section.push_str(&format!("{}: db {}{nl}", data.label, db_operands(&data.value)));
+        }
+        section
+    }
+}
+
+/// Returns the operands of a `db` declaring `value` followed by a null byte, e.g. `"a b", 10, 0`.
+fn db_operands(value: &str) -> String {
+    let mut operands = Vec::new();
+    let mut quoted = String::new();
+    for byte in value.bytes() {
+        if (byte.is_ascii_graphic() || byte == b' ') && byte != b'"' {
+            quoted.push(char::from(byte));
+        } else {
+            if !quoted.is_empty() {
+                operands.push(format!("\"{}\"", core::mem::take(&mut quoted)));
+            }
+            operands.push(format!("{}", byte));
+        }
+    }
+    if !quoted.is_empty() {
+        operands.push(format!("\"{}\"", quoted));
+    }
+    operands.push(String::from("0"));
+    operands.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec;
+    use expectest::prelude::*;
+    use crate::asm::style::LineEnding;
+    use super::*;
+
+    fn data(label: &str, value: &str) -> Data {
+        Data { label: label.to_string(), value: value.to_string() }
+    }
+
+    mod data_to_string {
+        use super::*;
+
+        #[test]
+        fn declares_each_string_under_its_label() {
+            let program = Program { data: vec![data("str0", "hi"), data("str1", "")], text: vec![] };
+            expect!(program.data_to_string(&AsmStyle::default()))
+                .to(be_equal_to("section .data\nstr0: db \"hi\", 0\nstr1: db 0\n".to_string()));
+        }
+
+        #[test]
+        fn is_empty_without_data() {
+            expect!(Program::default().data_to_string(&AsmStyle::default())).to(be_equal_to(String::new()));
+        }
+
+        #[test]
+        fn follows_the_line_ending_of_the_style() {
+            let program = Program { data: vec![data("str0", "hi")], text: vec![] };
+            let style = AsmStyle { line_ending: LineEnding::CrLf, ..AsmStyle::default() };
+            expect!(program.data_to_string(&style)).to(be_equal_to("section .data\r\nstr0: db \"hi\", 0\r\n".to_string()));
+        }
+    }
+
+    mod db_operands {
+        use super::*;
+
+        #[test]
+        fn writes_unprintable_bytes_as_numbers() {
+            expect!(db_operands("a\tb")).to(be_equal_to("\"a\", 9, \"b\", 0".to_string()));
+            expect!(db_operands("é")).to(be_equal_to("195, 169, 0".to_string()));
+        }
+    }
+}
@@ -4,8 +4,8 @@
 /// assembly language and low-level programming. Each variant corresponds to a specific register
 /// that plays a distinct role in CPU operations, such as storing function return values,
 /// intermediate computation results, or managing the stack pointer.
-#[derive(Debug, PartialEq, Clone)]
-pub(crate) enum Reg {
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub enum Reg {
     /// The `Rax` register.
     ///
     /// The `Rax` register is a general-purpose register commonly used in x86-64 architecture. It
@@ -15,6 +15,7 @@ pub(crate) enum Reg {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::asm::reg::Reg;
     /// let reg = Reg::Rax;
     /// ```
     Rax,
@@ -29,7 +30,236 @@ pub(crate) enum Reg {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::asm::reg::Reg;
     /// let reg = Reg::Rsp;
     /// ```
     Rsp,
+
+    /// The `Rcx` register.
+    ///
+    /// The `Rcx` register is a general-purpose register in x86-64 architecture. Besides holding
+    /// intermediate values, its low byte (`Cl`) is the only register that can hold a variable shift
+    /// count for instructions like `shl` and `sar`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::reg::Reg;
+    /// let reg = Reg::Rcx;
+    /// ```
+    Rcx,
+
+    /// The `Cl` register.
+    ///
+    /// The `Cl` register is the lowest 8 bits of `Rcx`. Writing to `Rcx` also changes `Cl`, so
+    /// values are usually moved into `Rcx` and then read back through `Cl`, as done for variable
+    /// shift counts.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::reg::Reg;
+    /// let reg = Reg::Cl;
+    /// ```
+    Cl,
+
+    /// The `Rbp` register.
+    ///
+    /// The `Rbp` register is the base pointer register in x86-64 architecture. It is used as a
+    /// frame pointer: it holds the value `Rsp` had when the current stack frame was set up, so
+    /// local variables can be addressed at fixed offsets even while `Rsp` moves.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::reg::Reg;
+    /// let reg = Reg::Rbp;
+    /// ```
+    Rbp,
+
+    /// The `Al` register.
+    ///
+    /// The `Al` register is the lowest 8 bits of `Rax`. Instructions that produce a single byte,
+    /// like `setl`, write their result to it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::reg::Reg;
+    /// let reg = Reg::Al;
+    /// ```
+    Al,
+
+    /// The `Rdi` register.
+    ///
+    /// The `Rdi` register holds the first argument of a function call under the System V calling
+    /// convention, like the address of the string passed to the runtime's `print_str`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::reg::Reg;
+    /// let reg = Reg::Rdi;
+    /// ```
+    Rdi,
+
+    /// The `Rsi` register.
+    ///
+    /// The `Rsi` register holds the second argument of a function call under the System V calling
+    /// convention.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::reg::Reg;
+    /// let reg = Reg::Rsi;
+    /// ```
+    Rsi,
+
+    /// The `Rdx` register.
+    ///
+    /// The `Rdx` register holds the third argument of a function call under the System V calling
+    /// convention, and the second one under Win64.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::reg::Reg;
+    /// let reg = Reg::Rdx;
+    /// ```
+    Rdx,
+
+    /// The `Rbx` register.
+    ///
+    /// The `Rbx` register is a general-purpose register that every calling convention makes
+    /// callee-saved, so a value kept in it survives a function call.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::reg::Reg;
+    /// let reg = Reg::Rbx;
+    /// ```
+    Rbx,
+}
+
+/// A calling convention, which decides among other things which registers a call preserves.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Abi {
+    /// The System V AMD64 ABI, used on Linux and macOS.
+    #[default]
+    SystemV,
+
+    /// The Microsoft x64 calling convention, used on Windows.
+    Win64,
+}
+
+impl Abi {
+    /// Returns the calling convention of the operating system named `os`, as named by
+    /// `std::env::consts::OS`.
+    pub fn for_os(os: &str) -> Abi {
+        match os {
+            "windows" => Abi::Win64,
+            _ => Abi::SystemV,
+        }
+    }
+
+    /// Returns the size in bytes of the red zone: the area below `Rsp` that a function may use
+    /// without moving `Rsp`, since neither signal nor interrupt handlers write there. System V has
+    /// one of 128 bytes; Win64 has none, so anything below `Rsp` can be overwritten at any time.
+    pub fn red_zone(&self) -> usize {
+        match self {
+            Abi::SystemV => 128,
+            Abi::Win64 => 0,
+        }
+    }
+}
+
+impl Reg {
+    /// Returns `true` if a function following `abi` must give the register back with the value it
+    /// had on entry, so the caller can rely on it across the call.
+    ///
+    /// `Rsp` counts as callee-saved, since a call returns with the stack pointer where it was. A
+    /// partial register like `Cl` is saved or not along with the register it is part of. `Rdi` and
+    /// `Rsi` are the registers here on which the two conventions disagree.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::asm::reg::Reg;
+    /// # use rengo::asm::reg::Abi;
+    /// assert!(Reg::Rdi.is_callee_saved(Abi::Win64));
+    /// assert!(!Reg::Rdi.is_callee_saved(Abi::SystemV));
+    /// ```
+    pub fn is_callee_saved(&self, abi: Abi) -> bool {
+        match self {
+            Reg::Rbx | Reg::Rsp | Reg::Rbp => true,
+            Reg::Rdi | Reg::Rsi => abi == Abi::Win64,
+            Reg::Rax | Reg::Al | Reg::Rcx | Reg::Cl | Reg::Rdx => false,
+        }
+    }
+
+    /// Returns `true` if a function following `abi` may overwrite the register, so the caller has
+    /// to save it itself if it needs its value after the call.
+    pub fn is_caller_saved(&self, abi: Abi) -> bool {
+        !self.is_callee_saved(abi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expectest::prelude::*;
+    use super::*;
+
+    mod red_zone {
+        use super::*;
+
+        #[test]
+        fn only_exists_under_system_v() {
+            expect!(Abi::SystemV.red_zone()).to(be_equal_to(128));
+            expect!(Abi::Win64.red_zone()).to(be_equal_to(0));
+            expect!(Abi::for_os("windows").red_zone()).to(be_equal_to(0));
+        }
+    }
+
+    mod is_callee_saved {
+        use super::*;
+
+        #[test]
+        fn classifies_the_registers_under_system_v() {
+            expect!(Reg::Rbx.is_callee_saved(Abi::SystemV)).to(be_true());
+            expect!(Reg::Rbp.is_callee_saved(Abi::SystemV)).to(be_true());
+            expect!(Reg::Rax.is_caller_saved(Abi::SystemV)).to(be_true());
+            expect!(Reg::Rdi.is_caller_saved(Abi::SystemV)).to(be_true());
+            expect!(Reg::Rsi.is_caller_saved(Abi::SystemV)).to(be_true());
+            expect!(Reg::Rdx.is_caller_saved(Abi::SystemV)).to(be_true());
+        }
+
+        #[test]
+        fn keeps_rdi_under_win64() {
+            expect!(Reg::Rdi.is_callee_saved(Abi::Win64)).to(be_true());
+            expect!(Reg::Rsi.is_callee_saved(Abi::Win64)).to(be_true());
+            expect!(Reg::Rbx.is_callee_saved(Abi::Win64)).to(be_true());
+            expect!(Reg::Rcx.is_caller_saved(Abi::Win64)).to(be_true());
+        }
+
+        #[test]
+        fn follows_the_full_register() {
+            for abi in [Abi::SystemV, Abi::Win64] {
+                expect!(Reg::Al.is_callee_saved(abi)).to(be_equal_to(Reg::Rax.is_callee_saved(abi)));
+                expect!(Reg::Cl.is_callee_saved(abi)).to(be_equal_to(Reg::Rcx.is_callee_saved(abi)));
+            }
+        }
+    }
+
+    mod for_os {
+        use super::*;
+
+        #[test]
+        fn uses_win64_only_on_windows() {
+            expect!(Abi::for_os("windows")).to(be_equal_to(Abi::Win64));
+            expect!(Abi::for_os("linux")).to(be_equal_to(Abi::SystemV));
+            expect!(Abi::for_os("macos")).to(be_equal_to(Abi::SystemV));
+        }
+    }
 }
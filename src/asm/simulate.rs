@@ -0,0 +1,377 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use crate::asm::arg::{Arg, MemOperand};
+use crate::asm::condition::Condition;
+use crate::asm::instruction::Instruction;
+use crate::asm::reg::Reg;
+
+/// The value of `Rsp` when a simulation starts. Any address works, as long as the stack has room
+/// to grow downwards from it.
+const STACK_TOP: i64 = 0x10000;
+
+/// A minimal model of the machine state touched by the instructions the compiler emits.
+#[derive(Debug, Default)]
+struct Machine {
+    rax: i64,
+    rcx: i64,
+    rsp: i64,
+    rbp: i64,
+    rdi: i64,
+    rsi: i64,
+    rdx: i64,
+    rbx: i64,
+    memory: BTreeMap<i64, i64>,
+    /// The arguments of the last `Cmp`, which conditional instructions test. A `Test` is recorded
+    /// as the comparison of the AND of its arguments with 0, which sets the same flags.
+    compared: (i64, i64),
+}
+
+impl Machine {
+    fn reg(&self, reg: &Reg) -> i64 {
+        match reg {
+            Reg::Rax => self.rax,
+            Reg::Rcx => self.rcx,
+            Reg::Cl => self.rcx & 0xff,
+            Reg::Al => self.rax & 0xff,
+            Reg::Rsp => self.rsp,
+            Reg::Rbp => self.rbp,
+            Reg::Rdi => self.rdi,
+            Reg::Rsi => self.rsi,
+            Reg::Rdx => self.rdx,
+            Reg::Rbx => self.rbx,
+        }
+    }
+
+    fn set_reg(&mut self, reg: &Reg, value: i64) {
+        match reg {
+            Reg::Rax => self.rax = value,
+            Reg::Rcx => self.rcx = value,
+            Reg::Cl => self.rcx = (self.rcx & !0xff) | (value & 0xff),
+            Reg::Al => self.rax = (self.rax & !0xff) | (value & 0xff),
+            Reg::Rsp => self.rsp = value,
+            Reg::Rbp => self.rbp = value,
+            Reg::Rdi => self.rdi = value,
+            Reg::Rsi => self.rsi = value,
+            Reg::Rdx => self.rdx = value,
+            Reg::Rbx => self.rbx = value,
+        }
+    }
+
+    fn address(&self, arg: &Arg) -> Result<i64, String> {
+        match arg.memory() {
+            Some(MemOperand::Base(reg, displacement)) => Ok(self.reg(&reg) + displacement),
+            Some(MemOperand::Absolute(address)) => Ok(address as i64),
+            _ => Err(format!("Not a memory operand: {:?}", arg)),
+        }
+    }
+
+    fn read(&self, arg: &Arg) -> Result<i64, String> {
+        match arg {
+            Arg::Constant(value) => Ok(*value),
+            Arg::Registry(reg) => Ok(self.reg(reg)),
+            Arg::Label(label) | Arg::Memory(MemOperand::Label(label)) => Err(format!("Cannot read the data at {}", label)),
+            Arg::RegistryOffset(..) | Arg::Memory(_) => {
+                let address = self.address(arg)?;
+                self.memory
+                    .get(&address)
+                    .copied()
+                    .ok_or(format!("Read from uninitialized memory at {:#x}", address))
+            }
+        }
+    }
+
+    fn write(&mut self, arg: &Arg, value: i64) -> Result<(), String> {
+        match arg {
+            Arg::Constant(_) => Err(format!("Cannot write to a constant: {:?}", arg)),
+            Arg::Label(label) | Arg::Memory(MemOperand::Label(label)) => Err(format!("Cannot write to the data at {}", label)),
+            Arg::Registry(reg) => {
+                self.set_reg(reg, value);
+                Ok(())
+            }
+            Arg::RegistryOffset(..) | Arg::Memory(_) => {
+                let address = self.address(arg)?;
+                self.memory.insert(address, value);
+                Ok(())
+            }
+        }
+    }
+
+    fn update(&mut self, dest: &Arg, src: &Arg, operation: fn(i64, i64) -> i64) -> Result<(), String> {
+        let value = operation(self.read(dest)?, self.read(src)?);
+        self.write(dest, value)
+    }
+
+    fn holds(&self, condition: &Condition) -> bool {
+        let (lhs, rhs) = self.compared;
+        condition.holds(lhs, rhs)
+    }
+}
+
+/// Runs a sequence of instructions on a simulated machine and returns the final value of `Rax`.
+///
+/// This is a reference for the semantics of the generated code, so compiled programs can be
+/// checked against the interpreter without assembling them. It fails on operations the real
+/// machine would not perform, such as reading stack memory that was never written. Calls to a label
+/// among the instructions push the index of the next instruction as their return address; calls to
+/// any other symbol leave the generated code, so they fail. A `Ret` with nothing pushed ends the
+/// simulation, like the `ret` that ends the program.
+pub(crate) fn simulate(instructions: &[Instruction]) -> Result<i64, String> {
+    let mut machine = Machine { rsp: STACK_TOP, ..Machine::default() };
+    let mut next = 0;
+    while let Some(instruction) = instructions.get(next) {
+        next += 1;
+        match instruction.unlocated() {
+            Instruction::Inc(dest) => machine.update(dest, &Arg::Constant(1), i64::wrapping_add)?,
+            Instruction::Dec(dest) => machine.update(dest, &Arg::Constant(1), i64::wrapping_sub)?,
+            Instruction::Mov(dest, src) => {
+                let value = machine.read(src)?;
+                machine.write(dest, value)?;
+            }
+            // Only the lowest byte of the source is read, as from a byte-sized register or slot
+            Instruction::Movzx(dest, src) => {
+                let value = machine.read(src)? & 0xff;
+                machine.write(dest, value)?;
+            }
+            Instruction::Movsx(dest, src) => {
+                let value = i64::from(machine.read(src)? as i8);
+                machine.write(dest, value)?;
+            }
+            Instruction::Add(dest, src) => machine.update(dest, src, i64::wrapping_add)?,
+            Instruction::Sub(dest, src) => machine.update(dest, src, i64::wrapping_sub)?,
+            Instruction::And(dest, src) => machine.update(dest, src, |dest, src| dest & src)?,
+            Instruction::Or(dest, src) => machine.update(dest, src, |dest, src| dest | src)?,
+            Instruction::Xor(dest, src) => machine.update(dest, src, |dest, src| dest ^ src)?,
+            Instruction::Shl(dest, src) => machine.update(dest, src, |dest, count| dest << (count & 63))?,
+            Instruction::Sar(dest, src) => machine.update(dest, src, |dest, count| dest >> (count & 63))?,
+            Instruction::Label(_) | Instruction::Nop => {}
+            Instruction::Push(src) => {
+                let value = machine.read(src)?;
+                machine.rsp -= 8;
+                machine.memory.insert(machine.rsp, value);
+            }
+            Instruction::Pop(dest) => {
+                let value = machine.read(&Arg::RegistryOffset(Reg::Rsp, 0))?;
+                machine.rsp += 8;
+                machine.write(dest, value)?;
+            }
+            Instruction::Lea(dest, src) => {
+                let address = machine.address(src)?;
+                machine.write(dest, address)?;
+            }
+            Instruction::Cmp(lhs, rhs) => machine.compared = (machine.read(lhs)?, machine.read(rhs)?),
+            Instruction::Test(lhs, rhs) => machine.compared = (machine.read(lhs)? & machine.read(rhs)?, 0),
+            Instruction::Set(condition, dest) => {
+                let value = i64::from(machine.holds(condition));
+                machine.write(dest, value)?;
+            }
+            Instruction::Jump(condition, label) => {
+                if machine.holds(condition) {
+                    next = position_of(instructions, label)?;
+                }
+            }
+            Instruction::Jmp(label) => next = position_of(instructions, label)?,
+            Instruction::Cmov(condition, dest, src) => {
+                if machine.holds(condition) {
+                    let value = machine.read(src)?;
+                    machine.write(dest, value)?;
+                }
+            }
+            Instruction::Call(symbol) => {
+                let target = position_of(instructions, symbol)
+                    .map_err(|_| format!("Call to external function {}", symbol))?;
+                machine.rsp -= 8;
+                machine.memory.insert(machine.rsp, next as i64);
+                next = target;
+            }
+            Instruction::Located(..) => unreachable!("the span was taken off the instruction"),
+            Instruction::Ret => {
+                if machine.rsp == STACK_TOP {
+                    break;
+                }
+                let address = machine.read(&Arg::RegistryOffset(Reg::Rsp, 0))?;
+                machine.rsp += 8;
+                next = address as usize;
+            }
+        }
+    }
+    Ok(machine.rax)
+}
+
+/// Returns the index of the instruction that defines a label, which a jump continues after.
+fn position_of(instructions: &[Instruction], label: &str) -> Result<usize, String> {
+    instructions
+        .iter()
+        .position(|instruction| matches!(instruction, Instruction::Label(name) if name == label))
+        .ok_or(format!("Jump to undefined label {}", label))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec;
+    use expectest::prelude::*;
+    use super::*;
+    use Arg::{Constant, Registry, RegistryOffset};
+    use Instruction::{Add, Call, Cmov, Cmp, Jmp, Jump, Label, Mov, Pop, Push, Ret, Set, Shl, Test};
+    use Reg::{Al, Cl, Rax, Rbp, Rcx, Rsp};
+
+    #[test]
+    fn it_moves_values_through_the_stack() {
+        let instructions = vec![
+            Mov(Registry(Rax), Constant(420)),
+            Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+            Mov(Registry(Rax), Constant(0)),
+            Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+        ];
+        expect!(simulate(&instructions)).to(be_ok().value(420));
+    }
+
+    #[test]
+    fn it_addresses_memory_operands_like_the_shorthands() {
+        let instructions = vec![
+            Mov(Registry(Rax), Constant(7)),
+            Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+            Mov(Registry(Rcx), Arg::Memory(MemOperand::Base(Rsp, -8))),
+            Mov(Arg::Memory(MemOperand::Absolute(0x2000)), Registry(Rcx)),
+            Mov(Registry(Rax), Arg::Memory(MemOperand::Absolute(0x2000))),
+        ];
+        expect!(simulate(&instructions)).to(be_ok().value(7));
+        let label = vec![Mov(Registry(Rax), Arg::Memory(MemOperand::Label("str0".to_string())))];
+        expect!(simulate(&label)).to(be_err().value("Cannot read the data at str0".to_string()));
+    }
+
+    #[test]
+    fn it_reads_shift_counts_from_cl() {
+        let instructions = vec![
+            Mov(Registry(Rcx), Constant(0x104)),  // Only the low byte ends up in Cl
+            Mov(Registry(Rax), Constant(1)),
+            Shl(Registry(Rax), Registry(Cl)),
+        ];
+        expect!(simulate(&instructions)).to(be_ok().value(16));
+    }
+
+    #[test]
+    fn it_extends_the_lowest_byte() {
+        let extend = |instruction| simulate(&[Mov(Registry(Rcx), Constant(0x1ff)), instruction]);
+        expect!(extend(Instruction::Movzx(Registry(Rax), Registry(Cl)))).to(be_ok().value(0xff));
+        expect!(extend(Instruction::Movsx(Registry(Rax), Registry(Cl)))).to(be_ok().value(-1));
+        let instructions = vec![
+            Mov(Registry(Rax), Constant(-1)),
+            Cmp(Registry(Rax), Constant(0)),
+            Set(Condition::Less, Registry(Al)),
+            Instruction::Movzx(Registry(Rax), Registry(Al)),
+        ];
+        expect!(simulate(&instructions)).to(be_ok().value(1));
+    }
+
+    #[test]
+    fn it_restores_pushed_values() {
+        let instructions = vec![
+            Mov(Registry(Rbp), Constant(7)),
+            Push(Registry(Rbp)),
+            Mov(Registry(Rbp), Constant(0)),
+            Pop(Registry(Rax)),
+        ];
+        expect!(simulate(&instructions)).to(be_ok().value(7));
+    }
+
+    #[test]
+    fn it_sets_bytes_from_comparisons() {
+        let instructions = vec![
+            Mov(Registry(Rax), Constant(-1)),
+            Cmp(Registry(Rax), Constant(2)),
+            Mov(Registry(Rax), Constant(0)),
+            Set(Condition::Less, Registry(Al)),
+        ];
+        expect!(simulate(&instructions)).to(be_ok().value(1));
+    }
+
+    #[test]
+    fn it_moves_only_when_the_condition_holds() {
+        let instructions = vec![
+            Mov(Registry(Rax), Constant(3)),
+            Mov(Registry(Rcx), Constant(7)),
+            Cmp(Registry(Rax), Registry(Rcx)),
+            Cmov(Condition::Less, Registry(Rax), Registry(Rcx)),
+            Cmov(Condition::Greater, Registry(Rcx), Registry(Rax)),
+        ];
+        expect!(simulate(&instructions)).to(be_ok().value(7));
+    }
+
+    #[test]
+    fn it_follows_conditional_jumps() {
+        let instructions = vec![
+            Mov(Registry(Rax), Constant(1)),
+            Cmp(Registry(Rax), Constant(0)),
+            Jump(Condition::NotEqual, "done".to_string()),
+            Call("rengo_assert_failed".to_string()),
+            Label("done".to_string()),
+        ];
+        expect!(simulate(&instructions)).to(be_ok().value(1));
+    }
+
+    #[test]
+    fn it_tests_the_and_of_its_arguments() {
+        for (value, expected) in [(5, 1), (0, 0), (-3, 1)] {
+            let instructions = vec![
+                Mov(Registry(Rax), Constant(value)),
+                Test(Registry(Rax), Registry(Rax)),
+                Mov(Registry(Rax), Constant(0)),
+                Set(Condition::NotEqual, Registry(Al)),
+            ];
+            expect!(simulate(&instructions)).to(be_ok().value(expected));
+        }
+        let negative = vec![
+            Mov(Registry(Rax), Constant(-3)),
+            Test(Registry(Rax), Registry(Rax)),
+            Mov(Registry(Rax), Constant(0)),
+            Set(Condition::Less, Registry(Al)),
+        ];
+        expect!(simulate(&negative)).to(be_ok().value(1));
+    }
+
+    #[test]
+    fn it_follows_unconditional_jumps() {
+        let instructions = vec![
+            Mov(Registry(Rax), Constant(1)),
+            Jmp("done".to_string()),
+            Mov(Registry(Rax), Constant(2)),
+            Label("done".to_string()),
+        ];
+        expect!(simulate(&instructions)).to(be_ok().value(1));
+        expect!(simulate(&[Jmp("nowhere".to_string())])).to(be_err());
+    }
+
+    #[test]
+    fn it_fails_on_calls() {
+        let instructions = vec![
+            Mov(Registry(Rax), Constant(0)),
+            Cmp(Registry(Rax), Constant(0)),
+            Jump(Condition::NotEqual, "done".to_string()),
+            Call("rengo_assert_failed".to_string()),
+            Label("done".to_string()),
+        ];
+        expect!(simulate(&instructions)).to(be_err());
+    }
+
+    #[test]
+    fn it_calls_and_returns_from_labels() {
+        let instructions = vec![
+            Mov(Registry(Rax), Constant(1)),
+            Call("twice".to_string()),
+            Call("twice".to_string()),
+            Ret,
+            Label("twice".to_string()),
+            Add(Registry(Rax), Registry(Rax)),
+            Ret,
+        ];
+        expect!(simulate(&instructions)).to(be_ok().value(4));
+    }
+
+    #[test]
+    fn it_fails_on_uninitialized_reads() {
+        let instructions = vec![Mov(Registry(Rax), RegistryOffset(Rsp, -1))];
+        expect!(simulate(&instructions)).to(be_err());
+    }
+}
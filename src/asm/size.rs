@@ -0,0 +1,187 @@
+use crate::asm::arg::{Arg, MemOperand};
+use crate::asm::instruction::Instruction;
+use crate::asm::reg::Reg;
+
+/// Returns the number of bytes NASM encodes an `Instruction` into.
+///
+/// The sizes follow the shortest encodings NASM picks with its default optimizations: a `mov` of
+/// a constant that fits in 32 unsigned bits uses the 32-bit form, immediates that fit in a signed
+/// byte use the short forms, and memory operands use an 8-bit displacement when possible. Labels
+/// take no space, and conditional jumps are assumed to reach their label with a short jump.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::asm::size::encoded_size;
+/// # use rengo::asm::instruction::Instruction;
+/// # use rengo::asm::arg::Arg;
+/// # use rengo::asm::reg::Reg;
+/// assert_eq!(encoded_size(&Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(42))), 5);
+/// assert_eq!(encoded_size(&Instruction::Inc(Arg::Registry(Reg::Rax))), 3);
+/// ```
+pub fn encoded_size(instruction: &Instruction) -> usize {
+    match instruction {
+        Instruction::Label(_) => 0,
+        Instruction::Located(_, instruction) => encoded_size(instruction),
+        Instruction::Nop | Instruction::Ret => 1,
+        Instruction::Jump(_, _) | Instruction::Jmp(_) => 2,
+        Instruction::Call(_) => 5,
+        Instruction::Set(_, dest) => 2 + operand_size(dest),
+        Instruction::Cmov(_, dest, src) | Instruction::Movzx(dest, src) | Instruction::Movsx(dest, src) => {
+            3 + operand_size(dest).max(operand_size(src))  // Two opcode bytes after the REX prefix
+        }
+        Instruction::Push(Arg::Constant(value)) => if fits_in_byte(*value) { 2 } else { 5 },
+        Instruction::Push(arg) | Instruction::Pop(arg) => match arg.memory() {
+            Some(memory) => 1 + memory_size(&memory),
+            None => 1,
+        },
+        Instruction::Inc(dest) | Instruction::Dec(dest) => 2 + operand_size(dest),
+        Instruction::Mov(Arg::Registry(Reg::Cl | Reg::Al), Arg::Constant(_)) => 2,
+        Instruction::Mov(Arg::Registry(_), Arg::Constant(value)) => {
+            if u32::try_from(*value).is_ok() {
+                5  // mov r32, imm32 zero-extends to the whole register
+            } else if i32::try_from(*value).is_ok() {
+                7
+            } else {
+                10  // movabs
+            }
+        }
+        Instruction::Mov(dest, Arg::Constant(_)) => 2 + operand_size(dest) + 4,
+        Instruction::Add(dest, Arg::Constant(value))
+        | Instruction::Sub(dest, Arg::Constant(value))
+        | Instruction::And(dest, Arg::Constant(value))
+        | Instruction::Or(dest, Arg::Constant(value))
+        | Instruction::Xor(dest, Arg::Constant(value))
+        | Instruction::Cmp(dest, Arg::Constant(value)) => {
+            if fits_in_byte(*value) {
+                2 + operand_size(dest) + 1
+            } else if *dest == Arg::Registry(Reg::Rax) {
+                6  // The accumulator has a form without a ModR/M byte
+            } else {
+                2 + operand_size(dest) + 4
+            }
+        }
+        Instruction::Test(dest, Arg::Constant(_)) => {
+            if *dest == Arg::Registry(Reg::Rax) {
+                6  // There is no form with a byte-sized constant, but the accumulator has a short one
+            } else {
+                2 + operand_size(dest) + 4
+            }
+        }
+        Instruction::Shl(dest, Arg::Constant(1)) | Instruction::Sar(dest, Arg::Constant(1)) => 2 + operand_size(dest),
+        Instruction::Shl(dest, Arg::Constant(_)) | Instruction::Sar(dest, Arg::Constant(_)) => 3 + operand_size(dest),
+        Instruction::Shl(dest, _) | Instruction::Sar(dest, _) => 2 + operand_size(dest),
+        Instruction::Mov(dest, src)
+        | Instruction::Add(dest, src)
+        | Instruction::Sub(dest, src)
+        | Instruction::And(dest, src)
+        | Instruction::Or(dest, src)
+        | Instruction::Xor(dest, src)
+        | Instruction::Cmp(dest, src)
+        | Instruction::Test(dest, src)
+        | Instruction::Lea(dest, src) => 2 + operand_size(dest).max(operand_size(src)),
+    }
+}
+
+/// Returns whether a constant can be encoded as a sign-extended byte.
+fn fits_in_byte(value: i64) -> bool {
+    i8::try_from(value).is_ok()
+}
+
+/// Returns the bytes an operand adds after the opcode: the ModR/M byte, plus the SIB byte and
+/// displacement of a memory operand.
+fn operand_size(arg: &Arg) -> usize {
+    match arg.memory() {
+        Some(memory) => memory_size(&memory),
+        None => 1,
+    }
+}
+
+/// Returns the bytes a memory operand takes: the ModR/M byte, plus its SIB byte and displacement.
+fn memory_size(memory: &MemOperand) -> usize {
+    let (base, displacement) = match memory {
+        MemOperand::Base(base, displacement) => (base, *displacement),
+        MemOperand::Label(_) => return 5,  // A 32-bit displacement from Rip
+        MemOperand::Absolute(_) => return 6,  // A SIB byte with no base and a 32-bit address
+    };
+    let sib = usize::from(*base == Reg::Rsp);  // Rsp can only be a base through a SIB byte
+    let displacement_size = if displacement == 0 && *base != Reg::Rbp {
+        0
+    } else if fits_in_byte(displacement) {
+        1  // Rbp always needs a displacement, as the form without one means Rip-relative
+    } else {
+        4
+    };
+    1 + sib + displacement_size
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use expectest::prelude::*;
+    use super::*;
+    use Arg::{Constant, Registry, RegistryOffset};
+    use crate::asm::condition::Condition;
+    use Instruction::{Add, Call, Cmov, Cmp, Dec, Inc, Jmp, Jump, Mov, Nop, Pop, Push, Set, Shl, Sub, Test};
+    use Reg::{Al, Cl, Rax, Rbp, Rcx, Rsp};
+
+    mod encoded_size {
+        use super::*;
+
+        #[test]
+        fn sizes_moves_of_constants() {
+            expect!(encoded_size(&Mov(Registry(Rax), Constant(42)))).to(be_equal_to(5));  // b8 2a 00 00 00
+            expect!(encoded_size(&Mov(Registry(Rax), Constant(-1)))).to(be_equal_to(7));  // 48 c7 c0 ff ff ff ff
+            expect!(encoded_size(&Mov(Registry(Rax), Constant(i64::MAX)))).to(be_equal_to(10));
+        }
+
+        #[test]
+        fn sizes_comparisons_and_control_flow() {
+            expect!(encoded_size(&Cmp(Registry(Rax), Registry(Rcx)))).to(be_equal_to(3));  // 48 39 c8
+            expect!(encoded_size(&Cmp(Registry(Rax), Constant(0)))).to(be_equal_to(4));  // 48 83 f8 00
+            expect!(encoded_size(&Test(Registry(Rax), Registry(Rax)))).to(be_equal_to(3));  // 48 85 c0
+            expect!(encoded_size(&Test(Registry(Rax), Constant(1)))).to(be_equal_to(6));  // 48 a9 01 00 00 00
+            expect!(encoded_size(&Set(Condition::Less, Registry(Al)))).to(be_equal_to(3));  // 0f 9c c0
+            expect!(encoded_size(&Jump(Condition::NotEqual, "assert0".to_string()))).to(be_equal_to(2));
+            expect!(encoded_size(&Jmp("epilogue".to_string()))).to(be_equal_to(2));
+            expect!(encoded_size(&Call("rengo_assert_failed".to_string()))).to(be_equal_to(5));
+            expect!(encoded_size(&Cmov(Condition::Greater, Registry(Rax), Registry(Rcx)))).to(be_equal_to(4));  // 48 0f 4f c1
+        }
+
+        #[test]
+        fn sizes_extensions_of_bytes() {
+            expect!(encoded_size(&Instruction::Movzx(Registry(Rax), Registry(Al)))).to(be_equal_to(4));  // 48 0f b6 c0
+            expect!(encoded_size(&Instruction::Movsx(Registry(Rax), Registry(Cl)))).to(be_equal_to(4));  // 48 0f be c1
+            expect!(encoded_size(&Instruction::Movzx(Registry(Rax), RegistryOffset(Rsp, -1)))).to(be_equal_to(6));  // 48 0f b6 44 24 f8
+        }
+
+        #[test]
+        fn sizes_memory_operands() {
+            expect!(encoded_size(&Mov(RegistryOffset(Rsp, -1), Registry(Rax)))).to(be_equal_to(5));  // 48 89 44 24 f8
+            expect!(encoded_size(&Mov(Registry(Rax), RegistryOffset(Rbp, -1)))).to(be_equal_to(4));  // 48 8b 45 f8
+            expect!(encoded_size(&Mov(Registry(Rax), RegistryOffset(Rsp, 0)))).to(be_equal_to(4));  // 48 8b 04 24
+            expect!(encoded_size(&Mov(Registry(Rax), RegistryOffset(Rbp, -17)))).to(be_equal_to(7));  // disp32
+        }
+
+        #[test]
+        fn sizes_every_form_of_memory_operand() {
+            let load = |memory| encoded_size(&Mov(Registry(Rax), Arg::Memory(memory)));
+            expect!(load(MemOperand::Base(Rsp, -4))).to(be_equal_to(5));  // 48 8b 44 24 fc
+            expect!(load(MemOperand::Base(Rax, 0))).to(be_equal_to(3));  // 48 8b 00
+            expect!(load(MemOperand::Label("str0".to_string()))).to(be_equal_to(7));  // 48 8b 05 + rel32
+            expect!(load(MemOperand::Absolute(0x1000))).to(be_equal_to(8));  // 48 8b 04 25 00 10 00 00
+        }
+
+        #[test]
+        fn sizes_frame_and_arithmetic_instructions() {
+            expect!(encoded_size(&Push(Registry(Rbp)))).to(be_equal_to(1));
+            expect!(encoded_size(&Pop(Registry(Rbp)))).to(be_equal_to(1));
+            expect!(encoded_size(&Sub(Registry(Rsp), Constant(16)))).to(be_equal_to(4));  // 48 83 ec 10
+            expect!(encoded_size(&Add(Registry(Rax), Constant(1000)))).to(be_equal_to(6));  // 48 05 e8 03 00 00
+            expect!(encoded_size(&Add(Registry(Rax), Registry(Rcx)))).to(be_equal_to(3));
+            expect!(encoded_size(&Shl(Registry(Rax), Registry(Cl)))).to(be_equal_to(3));
+            expect!(encoded_size(&Inc(Registry(Rax)))).to(be_equal_to(3));
+            expect!(encoded_size(&Dec(Registry(Rax)))).to(be_equal_to(3));
+            expect!(encoded_size(&Nop)).to(be_equal_to(1));
+        }
+    }
+}
@@ -0,0 +1,41 @@
+/// Controls the layout of the assembly code produced by `asm_to_string_with_style`.
+///
+/// The default style renders every line flush-left, which keeps the output byte-for-byte stable for
+/// tests that compare it against expected strings.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct AsmStyle {
+    /// Indent instructions by four spaces while keeping labels flush-left, following the
+    /// conventional layout of hand-written assembly.
+    pub indent: bool,
+
+    /// The characters that end each line of the code.
+    pub line_ending: LineEnding,
+}
+
+/// The characters that end a line of assembly code.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum LineEnding {
+    /// A line feed (`\n`), as used on Unix.
+    #[default]
+    Lf,
+
+    /// A carriage return followed by a line feed (`\r\n`), which some assemblers on Windows
+    /// prefer.
+    CrLf,
+}
+
+impl LineEnding {
+    /// Returns the characters that end a line.
+    ///
+    /// ## Example:
+    /// ```rust
+    /// # use rengo::asm::style::LineEnding;
+    /// assert_eq!(LineEnding::CrLf.as_str(), "\r\n");
+    /// ```
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
@@ -1,9 +1,13 @@
-use crate::asm::arg::Arg;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use crate::asm::arg::{Arg, MemOperand};
 use crate::asm::instruction::Instruction;
 use crate::asm::reg::Reg;
+use crate::asm::style::AsmStyle;
 use Arg::{Constant, Registry};
-use Instruction::{Add, Mov, Sub};
-use Reg::{Rax, Rsp};
+use Instruction::{Add, And, Call, Cmov, Cmp, Jmp, Jump, Label, Lea, Mov, Movsx, Movzx, Nop, Or, Pop, Push, Ret, Sar, Set, Shl, Sub, Test, Xor};
+use Reg::{Al, Cl, Rax, Rbp, Rbx, Rcx, Rdi, Rdx, Rsi, Rsp};
 
 /// Converts a vector of assembly `Instruction`s into a formatted assembly code string.
 ///
@@ -22,28 +26,110 @@ use Reg::{Rax, Rsp};
 /// # Example
 ///
 /// ```rust
+/// # use rengo::asm::instruction::Instruction;
+/// # use rengo::asm::arg::Arg;
+/// # use rengo::asm::reg::Reg;
+/// # use rengo::asm::to_string::asm_to_string;
 /// let instructions = vec![Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(42))];
 /// let asm_code = asm_to_string(instructions);
 /// assert_eq!(asm_code, "mov rax, 42");
 /// ```
-pub(crate) fn asm_to_string(instructions: Vec<Instruction>) -> String {
+pub fn asm_to_string(instructions: Vec<Instruction>) -> String {
+    asm_to_string_with_style(instructions, &AsmStyle::default())
+}
+
+/// Converts a vector of assembly `Instruction`s into assembly code laid out according to a style.
+///
+/// This works like `asm_to_string`, but the `AsmStyle` decides how each line is laid out. With
+/// `indent` set, instructions are indented by four spaces while labels stay flush-left, and lines
+/// are joined with the style's `line_ending`.
+///
+/// # Arguments
+///
+/// * `instructions` - A vector of `Instruction`s that represent the assembly code.
+/// * `style` - The `AsmStyle` to render the code with.
+///
+/// # Returns
+///
+/// * A `String` containing the formatted assembly code.
+///
+/// # Example
+///
+/// ```rust
+/// # use rengo::asm::instruction::Instruction;
+/// # use rengo::asm::arg::Arg;
+/// # use rengo::asm::reg::Reg;
+/// # use rengo::asm::to_string::asm_to_string_with_style;
+/// # use rengo::asm::style::AsmStyle;
+/// let instructions = vec![
+///     Instruction::Label("start".to_string()),
+///     Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(42)),
+/// ];
+/// let asm_code = asm_to_string_with_style(instructions, &AsmStyle { indent: true, ..AsmStyle::default() });
+/// assert_eq!(asm_code, "start:\n    mov rax, 42");
+/// ```
+pub fn asm_to_string_with_style(instructions: Vec<Instruction>, style: &AsmStyle) -> String {
     instructions
         .iter()
         .map(|instruction| match instruction {
-            Instruction::Inc(dest) => format!("inc {}", arg_to_string(dest)),
-            Instruction::Dec(dest) => format!("dec {}", arg_to_string(dest)),
-            Mov(dest, src) => format!("mov {}, {}", arg_to_string(dest), arg_to_string(src)),
-            Add(dest, src) => format!("add {}, {}", arg_to_string(dest), arg_to_string(src)),
-            Sub(dest, src) => format!("sub {}, {}", arg_to_string(dest), arg_to_string(src)),
+            Label(_) => instruction_to_string(instruction),
+            _ if style.indent => format!("    {}", instruction_to_string(instruction)),
+            _ => instruction_to_string(instruction),
         })
         .collect::<Vec<String>>()
-        .join("\n")
+        .join(style.line_ending.as_str())
+}
+
+/// Converts a single `Instruction` into its assembly syntax, without any indentation.
+fn instruction_to_string(instruction: &Instruction) -> String {
+    match instruction {
+        Instruction::Inc(dest) => format!("inc {}", arg_to_string(dest)),
+        Instruction::Dec(dest) => format!("dec {}", arg_to_string(dest)),
+        Mov(dest, src) => format!("mov {}, {}", arg_to_string(dest), arg_to_string(src)),
+        Movzx(dest, src) => format!("movzx {}, {}", arg_to_string(dest), byte_to_string(src)),
+        Movsx(dest, src) => format!("movsx {}, {}", arg_to_string(dest), byte_to_string(src)),
+        Add(dest, src) => format!("add {}, {}", arg_to_string(dest), arg_to_string(src)),
+        Sub(dest, src) => format!("sub {}, {}", arg_to_string(dest), arg_to_string(src)),
+        And(dest, src) => format!("and {}, {}", arg_to_string(dest), arg_to_string(src)),
+        Or(dest, src) => format!("or {}, {}", arg_to_string(dest), arg_to_string(src)),
+        Xor(dest, src) => format!("xor {}, {}", arg_to_string(dest), arg_to_string(src)),
+        Shl(dest, src) => format!("shl {}, {}", arg_to_string(dest), arg_to_string(src)),
+        Sar(dest, src) => format!("sar {}, {}", arg_to_string(dest), arg_to_string(src)),
+        Label(name) => format!("{}:", name),
+        Push(src) => format!("push {}", arg_to_string(src)),
+        Pop(dest) => format!("pop {}", arg_to_string(dest)),
+        Lea(dest, src) => format!("lea {}, {}", arg_to_string(dest), arg_to_string(src)),
+        Nop => "nop".to_string(),
+        Cmp(lhs, rhs) => format!("cmp {}, {}", arg_to_string(lhs), arg_to_string(rhs)),
+        Test(lhs, rhs) => format!("test {}, {}", arg_to_string(lhs), arg_to_string(rhs)),
+        Set(condition, dest) => format!("set{} {}", condition.suffix(), arg_to_string(dest)),
+        Jump(condition, label) => format!("j{} {}", condition.suffix(), label),
+        Jmp(label) => format!("jmp {}", label),
+        Cmov(condition, dest, src) => {
+            format!("cmov{} {}, {}", condition.suffix(), arg_to_string(dest), arg_to_string(src))
+        }
+        Call(symbol) => format!("call {}", symbol),
+        Ret => "ret".to_string(),
+        Instruction::Located(span, instruction) => {
+            format!("{} ; line {}, column {}", instruction_to_string(instruction), span.line, span.col)
+        }
+    }
+}
+
+/// Converts the byte-sized source of an extension to its string representation. A memory operand
+/// needs the `byte` size, which a register like `al` already implies.
+fn byte_to_string(arg: &Arg) -> String {
+    match arg {
+        Arg::RegistryOffset(..) | Arg::Label(_) | Arg::Memory(_) => format!("byte {}", arg_to_string(arg)),
+        _ => arg_to_string(arg),
+    }
 }
 
 /// Converts an `Arg` to its string representation.
 ///
 /// The `arg_to_string` function translates an `Arg` (which can be a constant value or a register)
-/// into a string that represents its value in assembly code.
+/// into a string that represents its value in assembly code. Memory operands are rendered by
+/// `memory_to_string`.
 ///
 /// # Arguments
 ///
@@ -55,7 +141,7 @@ pub(crate) fn asm_to_string(instructions: Vec<Instruction>) -> String {
 ///
 /// # Example
 ///
-/// ```rust
+/// ```ignore
 /// let arg = Arg::Constant(42);
 /// let arg_str = arg_to_string(&arg);
 /// assert_eq!(arg_str, "42");
@@ -64,7 +150,25 @@ fn arg_to_string(arg: &Arg) -> String {
     match arg {
         Constant(value) => value.to_string(),
         Registry(reg) => reg_to_string(reg),
-        Arg::RegistryOffset(reg, offset) => format!("[{} + {}]", reg_to_string(reg), 8 * offset),
+        Arg::RegistryOffset(reg, offset) => memory_to_string(&MemOperand::Base(reg.clone(), 8 * offset)),
+        Arg::Label(label) => memory_to_string(&MemOperand::Label(label.clone())),
+        Arg::Memory(memory) => memory_to_string(memory),
+    }
+}
+
+/// Converts a `MemOperand` to its string representation: bracketed, with the displacement from a
+/// base register in bytes and left out when it is 0 (`[rsp]` rather than `[rsp + 0]`). A label is
+/// marked `rel` and an absolute address `abs`, so that neither depends on the `default` addressing
+/// of the file.
+fn memory_to_string(memory: &MemOperand) -> String {
+    match memory {
+        MemOperand::Base(reg, 0) => format!("[{}]", reg_to_string(reg)),
+        MemOperand::Base(reg, displacement) if *displacement < 0 => {
+            format!("[{} - {}]", reg_to_string(reg), displacement.unsigned_abs())
+        }
+        MemOperand::Base(reg, displacement) => format!("[{} + {}]", reg_to_string(reg), displacement),
+        MemOperand::Label(label) => format!("[rel {}]", label),
+        MemOperand::Absolute(address) => format!("[abs {:#x}]", address),
     }
 }
 
@@ -84,7 +188,7 @@ fn arg_to_string(arg: &Arg) -> String {
 ///
 /// # Example
 ///
-/// ```rust
+/// ```ignore
 /// let reg = Reg::Rax;
 /// let reg_str = reg_to_string(&reg);
 /// assert_eq!(reg_str, "rax");
@@ -93,13 +197,25 @@ fn reg_to_string(reg: &Reg) -> String {
     match reg {
         Rax => "rax".to_string(),
         Rsp => "rsp".to_string(),
+        Rcx => "rcx".to_string(),
+        Cl => "cl".to_string(),
+        Rbp => "rbp".to_string(),
+        Al => "al".to_string(),
+        Rdi => "rdi".to_string(),
+        Rsi => "rsi".to_string(),
+        Rdx => "rdx".to_string(),
+        Rbx => "rbx".to_string(),
     }
 }
 
 #[cfg(test)]
 mod tests {
     // Add this line
+    use alloc::vec;
     use super::*;
+    use crate::asm::condition::Condition;
+    use crate::asm::style::LineEnding;
+    use crate::parser::span::Span;
     use expectest::prelude::*;
     use proptest::prelude::*;
     use proptest::strategy::Strategy;
@@ -113,6 +229,12 @@ mod tests {
             let reg_str = reg_to_string(&reg);
             expect!(reg_str).to(be_equal_to("rax"));
         }
+
+        #[test]
+        fn it_converts_rcx_and_cl_to_string() {
+            expect!(reg_to_string(&Rcx)).to(be_equal_to("rcx"));
+            expect!(reg_to_string(&Cl)).to(be_equal_to("cl"));
+        }
     }
 
     mod arg_to_string {
@@ -127,12 +249,18 @@ mod tests {
             }
 
             #[test]
-            fn it_converts_registry_to_string(reg in prop_oneof![Just(Rax)]) {
+            fn it_converts_registry_to_string(reg in prop_oneof![Just(Rax), Just(Rcx), Just(Cl), Just(Rbp)]) {
                 let arg = Registry(reg.clone());
                 let arg_str = arg_to_string(&arg);
                 expect!(arg_str).to(be_equal_to(reg_to_string(&reg)));
             }
         );
+
+        #[test]
+        fn it_converts_registry_offset_to_string() {
+            expect!(arg_to_string(&Arg::RegistryOffset(Rsp, -1))).to(be_equal_to("[rsp - 8]"));
+            expect!(arg_to_string(&Arg::RegistryOffset(Rbp, 2))).to(be_equal_to("[rbp + 16]"));
+        }
     }
 
     mod asm_to_string {
@@ -146,24 +274,173 @@ mod tests {
                         any::<i64>().prop_map(|value| Mov(Registry(Rax), Constant(value))),
                         any::<i64>().prop_map(|value| Add(Registry(Rax), Constant(value))),
                         any::<i64>().prop_map(|value| Sub(Registry(Rax), Constant(value))),
+                        any::<i64>().prop_map(|value| And(Registry(Rax), Constant(value))),
+                        any::<i64>().prop_map(|value| Or(Registry(Rax), Constant(value))),
+                        any::<i64>().prop_map(|value| Xor(Registry(Rax), Constant(value))),
+                        Just(Shl(Registry(Rax), Registry(Cl))),
+                        Just(Sar(Registry(Rax), Registry(Cl))),
+                        "[a-z_][a-z0-9_]*".prop_map(Label),
+                        Just(Push(Registry(Rbp))),
+                        Just(Pop(Registry(Rbp))),
+                        (-64i64..64).prop_map(|slot| Lea(Registry(Rax), Arg::RegistryOffset(Rsp, slot))),
+                        Just(Nop),
+                        Just(Cmp(Registry(Rax), Registry(Rcx))),
+                        Just(Test(Registry(Rax), Registry(Rax))),
+                        Just(Set(Condition::Less, Registry(Al))),
+                        "[a-z_][a-z0-9_]*".prop_map(|label| Jump(Condition::NotEqual, label)),
+                        "[a-z_][a-z0-9_]*".prop_map(Jmp),
+                        Just(Cmov(Condition::Greater, Registry(Rax), Registry(Rcx))),
+                        Just(Movzx(Registry(Rax), Registry(Al))),
+                        Just(Movsx(Registry(Rcx), Registry(Cl))),
+                        "[a-z_][a-z0-9_]*".prop_map(Call),
+                        Just(Ret),
+                        ("[a-z_][a-z0-9_]*", 1usize..100, 1usize..100).prop_map(|(symbol, line, col)| {
+                            Call(symbol).located(Some(Span { start: 0, end: 1, line, col }))
+                        }),
+                        (1usize..100, 1usize..100).prop_map(|(line, col)| {
+                            Ret.located(Some(Span { start: 0, end: 1, line, col }))
+                        }),
                     ],
                     1..100,
                 )
             ) {
-                let asm_code = asm_to_string(instructions.clone());
-                let expected = instructions
-                    .iter()
-                    .map(|instruction| match instruction {
+                fn expected_line(instruction: &Instruction) -> String {
+                    match instruction {
                         Instruction::Inc(dest) => format!("inc {}", arg_to_string(dest)),
                         Instruction::Dec(dest) => format!("dec {}", arg_to_string(dest)),
                         Mov(dest, src) => format!("mov {}, {}", arg_to_string(dest), arg_to_string(src)),
+                        Movzx(dest, src) => format!("movzx {}, {}", arg_to_string(dest), arg_to_string(src)),
+                        Movsx(dest, src) => format!("movsx {}, {}", arg_to_string(dest), arg_to_string(src)),
                         Add(dest, src) => format!("add {}, {}", arg_to_string(dest), arg_to_string(src)),
                         Sub(dest, src) => format!("sub {}, {}", arg_to_string(dest), arg_to_string(src)),
-                    })
+                        And(dest, src) => format!("and {}, {}", arg_to_string(dest), arg_to_string(src)),
+                        Or(dest, src) => format!("or {}, {}", arg_to_string(dest), arg_to_string(src)),
+                        Xor(dest, src) => format!("xor {}, {}", arg_to_string(dest), arg_to_string(src)),
+                        Shl(dest, src) => format!("shl {}, {}", arg_to_string(dest), arg_to_string(src)),
+                        Sar(dest, src) => format!("sar {}, {}", arg_to_string(dest), arg_to_string(src)),
+                        Label(name) => format!("{}:", name),
+                        Push(src) => format!("push {}", arg_to_string(src)),
+                        Pop(dest) => format!("pop {}", arg_to_string(dest)),
+                        Lea(dest, src) => format!("lea {}, {}", arg_to_string(dest), arg_to_string(src)),
+                        Nop => "nop".to_string(),
+                        Cmp(lhs, rhs) => format!("cmp {}, {}", arg_to_string(lhs), arg_to_string(rhs)),
+                        Test(lhs, rhs) => format!("test {}, {}", arg_to_string(lhs), arg_to_string(rhs)),
+                        Set(condition, dest) => format!("set{} {}", condition.suffix(), arg_to_string(dest)),
+                        Jump(condition, label) => format!("j{} {}", condition.suffix(), label),
+                        Jmp(label) => format!("jmp {}", label),
+                        Cmov(condition, dest, src) => {
+                            format!("cmov{} {}, {}", condition.suffix(), arg_to_string(dest), arg_to_string(src))
+                        }
+                        Call(symbol) => format!("call {}", symbol),
+                        Ret => "ret".to_string(),
+                        Instruction::Located(span, instruction) => {
+                            format!("{} ; line {}, column {}", expected_line(instruction), span.line, span.col)
+                        }
+                    }
+                }
+
+                let asm_code = asm_to_string(instructions.clone());
+                let expected = instructions
+                    .iter()
+                    .map(expected_line)
                     .collect::<Vec<String>>()
                     .join("\n");
                 expect!(asm_code).to(be_equal_to(expected));
             }
         );
+
+        proptest!(
+            #[test]
+            fn it_renders_extensions_of_bytes(
+                dest in prop_oneof![Just(Rax), Just(Rcx), Just(Rdx), Just(Rbx)],
+                src in prop_oneof![Just(Al), Just(Cl)],
+                slot in -64i64..0,
+            ) {
+                let dest_name = reg_to_string(&dest);
+                let src_name = reg_to_string(&src);
+                expect!(asm_to_string(vec![Movzx(Registry(dest.clone()), Registry(src.clone()))]))
+                    .to(be_equal_to(format!("movzx {}, {}", dest_name, src_name)));
+                expect!(asm_to_string(vec![Movsx(Registry(dest.clone()), Registry(src))]))
+                    .to(be_equal_to(format!("movsx {}, {}", dest_name, src_name)));
+                // A slot has no size of its own, so the byte is spelled out
+                expect!(asm_to_string(vec![Movzx(Registry(dest), Arg::RegistryOffset(Rsp, slot))]))
+                    .to(be_equal_to(format!("movzx {}, byte [rsp - {}]", dest_name, -8 * slot)));
+            }
+        );
+
+        #[test]
+        fn it_renders_test() {
+            expect!(asm_to_string(vec![Test(Registry(Rax), Registry(Rax))])).to(be_equal_to("test rax, rax"));
+        }
+
+        #[test]
+        fn it_renders_conditional_instructions() {
+            let instructions = vec![
+                Cmp(Registry(Rax), Constant(0)),
+                Set(Condition::Greater, Registry(Al)),
+                Jump(Condition::NotEqual, "assert0".to_string()),
+                Jmp("epilogue".to_string()),
+                Cmov(Condition::Less, Registry(Rax), Registry(Rcx)),
+                Call("rengo_assert_failed".to_string()),
+            ];
+            expect!(asm_to_string(instructions))
+                .to(be_equal_to("cmp rax, 0\nsetg al\njne assert0\njmp epilogue\ncmovl rax, rcx\ncall rengo_assert_failed"));
+        }
+
+        #[test]
+        fn it_renders_memory_operands() {
+            expect!(arg_to_string(&Arg::RegistryOffset(Rsp, 0))).to(be_equal_to("[rsp]"));
+            expect!(arg_to_string(&Arg::RegistryOffset(Rsp, -2))).to(be_equal_to("[rsp - 16]"));
+            expect!(arg_to_string(&Arg::RegistryOffset(Rbp, 1))).to(be_equal_to("[rbp + 8]"));
+            expect!(arg_to_string(&Arg::Label("str0".to_string()))).to(be_equal_to("[rel str0]"));
+        }
+
+        #[test]
+        fn it_renders_every_form_of_memory_operand() {
+            let memory = |operand| arg_to_string(&Arg::Memory(operand));
+            expect!(memory(MemOperand::Base(Rsp, 0))).to(be_equal_to("[rsp]"));
+            expect!(memory(MemOperand::Base(Rbp, -12))).to(be_equal_to("[rbp - 12]"));
+            expect!(memory(MemOperand::Base(Rax, 4))).to(be_equal_to("[rax + 4]"));
+            expect!(memory(MemOperand::Base(Rsp, i64::MIN))).to(be_equal_to("[rsp - 9223372036854775808]"));
+            expect!(memory(MemOperand::Label("str0".to_string()))).to(be_equal_to("[rel str0]"));
+            expect!(memory(MemOperand::Absolute(0x1000))).to(be_equal_to("[abs 0x1000]"));
+        }
+
+        #[test]
+        fn it_renders_the_shorthands_like_the_memory_operands_they_stand_for() {
+            for arg in [Arg::RegistryOffset(Rsp, -2), Arg::RegistryOffset(Rbp, 3), Arg::Label("str1".to_string())] {
+                expect!(arg_to_string(&arg)).to(be_equal_to(arg_to_string(&Arg::Memory(arg.memory().unwrap()))));
+            }
+            let extension = Movzx(Registry(Rax), Arg::Memory(MemOperand::Absolute(0x10)));
+            expect!(asm_to_string(vec![extension])).to(be_equal_to("movzx rax, byte [abs 0x10]"));
+        }
+
+        #[test]
+        fn it_renders_lea() {
+            let instructions = vec![Lea(Registry(Rax), Arg::RegistryOffset(Rsp, -1))];
+            expect!(asm_to_string(instructions)).to(be_equal_to("lea rax, [rsp - 8]"));
+        }
+
+        #[test]
+        fn it_renders_labels_flush_left() {
+            let instructions = vec![
+                Label("start".to_string()),
+                Mov(Registry(Rax), Constant(420)),
+                Instruction::Inc(Registry(Rax)),
+            ];
+            let flush = asm_to_string(instructions.clone());
+            expect!(flush).to(be_equal_to("start:\nmov rax, 420\ninc rax"));
+            let indented = asm_to_string_with_style(instructions, &AsmStyle { indent: true, ..AsmStyle::default() });
+            expect!(indented).to(be_equal_to("start:\n    mov rax, 420\n    inc rax"));
+        }
+
+        #[test]
+        fn it_ends_lines_with_the_style_line_ending() {
+            let instructions = vec![Mov(Registry(Rax), Constant(420)), Instruction::Inc(Registry(Rax))];
+            let lf = asm_to_string_with_style(instructions.clone(), &AsmStyle::default());
+            expect!(lf).to(be_equal_to("mov rax, 420\ninc rax"));
+            let crlf = asm_to_string_with_style(instructions, &AsmStyle { line_ending: LineEnding::CrLf, ..AsmStyle::default() });
+            expect!(crlf).to(be_equal_to("mov rax, 420\r\ninc rax"));
+        }
     }
 }
@@ -1,18 +1,100 @@
 use std::path::Path;
 use std::process::Command;
+use crate::error::RengoError;
 
-pub(crate) fn assemble(asm_output_path: &Path, obj_output_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    let format = match std::env::consts::OS {
-        "windows" => "win64",
-        "linux" => "elf64",
-        "macos" => "macho64",
-        _ => return Err("Unsupported operating system".into()),
-    };
-
-    Command::new("nasm")
-        .args(&["-f", format, asm_output_path.to_str().unwrap(), "-o", obj_output_path.to_str().unwrap()])
+/// Assembles a NASM source file into an object file for the given operating system.
+///
+/// ## Parameters:
+/// - `asm_output_path`: The path of the assembly file to assemble.
+/// - `obj_output_path`: The path of the object file to produce.
+/// - `os`: The target operating system, as named by `std::env::consts::OS`.
+///
+/// ## Returns:
+/// A `RengoError::Assemble` if the operating system is not supported, `nasm` cannot be run, or it
+/// fails.
+pub fn assemble(asm_output_path: &Path, obj_output_path: &Path, os: &str) -> Result<(), RengoError> {
+    let status = Command::new("nasm")
+        .args(nasm_args(asm_output_path, obj_output_path, os)?)
         .status()
-        .expect("Failed to assemble .asm to .obj");
-
+        .map_err(|error| RengoError::Assemble(format!("could not run nasm: {}", error)))?;
+    if !status.success() {
+        return Err(RengoError::Assemble(format!("nasm exited with {}", status)));
+    }
     Ok(())
 }
+
+/// Returns the arguments `assemble` passes to `nasm` for the given operating system.
+pub fn nasm_args(asm_output_path: &Path, obj_output_path: &Path, os: &str) -> Result<Vec<String>, RengoError> {
+    let format = object_format(os).map_err(RengoError::Assemble)?;
+    Ok(vec![
+        "-f".to_string(),
+        format.to_string(),
+        asm_output_path.to_str().unwrap().to_string(),
+        "-o".to_string(),
+        obj_output_path.to_str().unwrap().to_string(),
+    ])
+}
+
+/// Returns the NASM object format (the `-f` argument) for an operating system.
+///
+/// ## Parameters:
+/// - `os`: The operating system, as named by `std::env::consts::OS`.
+///
+/// ## Returns:
+/// The name of the object format, or an error message if the operating system is not supported.
+pub fn object_format(os: &str) -> Result<&'static str, String> {
+    match os {
+        "windows" => Ok("win64"),
+        "linux" => Ok("elf64"),
+        "macos" => Ok("macho64"),
+        _ => Err(format!("Unsupported operating system: {}", os)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expectest::prelude::*;
+    use super::*;
+
+    mod object_format {
+        use super::*;
+
+        #[test]
+        fn maps_supported_systems_to_their_format() {
+            expect!(object_format("windows")).to(be_ok().value("win64"));
+            expect!(object_format("linux")).to(be_ok().value("elf64"));
+            expect!(object_format("macos")).to(be_ok().value("macho64"));
+        }
+
+        #[test]
+        fn fails_on_unsupported_systems() {
+            expect!(object_format("freebsd")).to(be_err().value("Unsupported operating system: freebsd".to_string()));
+        }
+    }
+
+    mod nasm_args {
+        use super::*;
+
+        #[test]
+        fn selects_the_format_of_the_target() {
+            let args = nasm_args(Path::new("out.asm"), Path::new("out.obj"), "windows").unwrap();
+            expect!(args).to(be_equal_to(["-f", "win64", "out.asm", "-o", "out.obj"].map(String::from).to_vec()));
+        }
+
+        #[test]
+        fn fails_on_unsupported_systems() {
+            let result = nasm_args(Path::new("out.asm"), Path::new("out.obj"), "plan9");
+            expect!(result).to(be_err().value(RengoError::Assemble("Unsupported operating system: plan9".to_string())));
+        }
+    }
+
+    mod assemble {
+        use super::*;
+
+        #[test]
+        fn fails_on_a_missing_source_file() {
+            let result = assemble(Path::new("build/does-not-exist.asm"), Path::new("build/does-not-exist.obj"), "linux");
+            expect!(matches!(result, Err(RengoError::Assemble(_)))).to(be_true());
+        }
+    }
+}
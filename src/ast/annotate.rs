@@ -1,2 +0,0 @@
-use crate::ast::expression::Expression;
-
@@ -0,0 +1,117 @@
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use proptest::prelude::*;
+use crate::ast::expression::Expression;
+use crate::ast::operator::BinaryOperator;
+
+/// The names `let` bindings are drawn from. Keeping the pool small makes generated programs reuse
+/// and shadow the bindings they introduce.
+const NAMES: [&str; 4] = ["a", "b", "c", "d"];
+
+/// Generates arbitrary well-formed programs: trees of at most `depth` levels below the root, in
+/// which every identifier refers to an enclosing `let`.
+///
+/// A `let` may rebind a name that is already in scope, in which case its value reads the binding
+/// being shadowed.
+pub(crate) fn expression(depth: u32) -> BoxedStrategy<Expression<()>> {
+    expression_in(Vec::new(), depth)
+}
+
+/// Generates programs like `expression`, but only made of numbers, increments, decrements, `let`
+/// bindings and identifiers, which every backend compiles.
+pub(crate) fn straight_line(depth: u32) -> BoxedStrategy<Expression<()>> {
+    straight_line_in(Vec::new(), depth)
+}
+
+/// Generates a number, or an identifier among the names in `bound`.
+fn leaf(bound: &[String]) -> BoxedStrategy<Expression<()>> {
+    let number = any::<i64>().prop_map(|value| Expression::Number(value, ()));
+    if bound.is_empty() {
+        return number.boxed();
+    }
+    prop_oneof![
+        number,
+        proptest::sample::select(bound.to_vec()).prop_map(|name| Expression::Identifier(name, ())),
+    ]
+    .boxed()
+}
+
+/// Generates a `let` (or `let mut`) binding with a value from `value` and a body from `body`, which
+/// is given the names in scope of the body.
+fn binding(
+    bound: Vec<String>,
+    value: impl Fn(Vec<String>) -> BoxedStrategy<Expression<()>> + Clone + 'static,
+    body: impl Fn(Vec<String>) -> BoxedStrategy<Expression<()>> + Clone + 'static,
+) -> BoxedStrategy<Expression<()>> {
+    proptest::sample::select(&NAMES[..])
+        .prop_flat_map(move |name| {
+            let name = name.to_string();
+            let mut body_bound = bound.clone();
+            if !body_bound.contains(&name) {
+                body_bound.push(name.clone());
+            }
+            (Just(name), any::<bool>(), value(bound.clone()), body(body_bound))
+                .prop_map(|(name, mutable, value, body)| match mutable {
+                    true => Expression::LetMut(name, Box::new(value), Box::new(body), ()),
+                    false => Expression::Let(name, Box::new(value), Box::new(body), ()),
+                })
+        })
+        .boxed()
+}
+
+/// Generates a program for `straight_line` in which the names in `bound` are in scope.
+fn straight_line_in(bound: Vec<String>, depth: u32) -> BoxedStrategy<Expression<()>> {
+    let leaf = leaf(&bound);
+    if depth == 0 {
+        return leaf;
+    }
+
+    let inner = move |bound: Vec<String>| straight_line_in(bound, depth - 1);
+    let increment = inner(bound.clone()).prop_map(|expr| Expression::Increment(Box::new(expr), ()));
+    let decrement = inner(bound.clone()).prop_map(|expr| Expression::Decrement(Box::new(expr), ()));
+    prop_oneof![leaf, increment, decrement, binding(bound, inner, inner)].boxed()
+}
+
+/// Generates a program in which the names in `bound` are in scope.
+fn expression_in(bound: Vec<String>, depth: u32) -> BoxedStrategy<Expression<()>> {
+    let leaf = leaf(&bound);
+    if depth == 0 {
+        return leaf;
+    }
+
+    let inner = || expression_in(bound.clone(), depth - 1);
+    let increment = inner().prop_map(|expr| Expression::Increment(Box::new(expr), ()));
+    let decrement = inner().prop_map(|expr| Expression::Decrement(Box::new(expr), ()));
+    let binary = (operator(), inner(), inner())
+        .prop_map(|(operator, lhs, rhs)| Expression::Binary(operator, Box::new(lhs), Box::new(rhs), ()));
+    let sequence = proptest::collection::vec(inner(), 2..4)
+        .prop_map(|statements| Expression::Sequence(statements, ()));
+
+    // Arrays are only generated indexed within their bounds, since they are not values otherwise
+    let element = (proptest::collection::vec(inner(), 1..4), any::<proptest::sample::Index>()).prop_map(|(elements, index)| {
+        let position = Expression::Number(index.index(elements.len()) as i64, ());
+        Expression::Index(Box::new(Expression::Array(elements, ())), Box::new(position), ())
+    });
+
+    let nested = move |bound: Vec<String>| expression_in(bound, depth - 1);
+    prop_oneof![leaf, increment, decrement, binary, sequence, element, binding(bound, nested, nested)].boxed()
+}
+
+/// Generates any binary operator.
+fn operator() -> impl Strategy<Value = BinaryOperator> {
+    proptest::sample::select(&[
+        BinaryOperator::Add,
+        BinaryOperator::Subtract,
+        BinaryOperator::BitAnd,
+        BinaryOperator::BitOr,
+        BinaryOperator::BitXor,
+        BinaryOperator::ShiftLeft,
+        BinaryOperator::ShiftRight,
+        BinaryOperator::Less,
+        BinaryOperator::Greater,
+        BinaryOperator::Equal,
+        BinaryOperator::Min,
+        BinaryOperator::Max,
+    ][..])
+}
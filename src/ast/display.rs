@@ -0,0 +1,280 @@
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+use crate::ast::expression::Expression;
+use crate::parser::parse::{infix_operator, BUILTINS};
+
+/// Prints an expression as source code, in the canonical layout of `rengo fmt`.
+///
+/// Statements are separated by `; `, binary operators and `=` are surrounded by single spaces, and
+/// postfix operators and the arguments of calls are written right after what they apply to, as in
+/// `let x = 5; x++ + min(x, 2)`. Parentheses are only added where the tree could not be read back
+/// without them, so parsing the printed source gives the same expression again, and printing a
+/// parsed program twice gives the same text.
+///
+/// `Expression::While` has no syntax yet, so it is printed as `while <condition> then <body>`,
+/// which does not parse.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parser::parse::parse;
+/// # use rengo::parser::tokenize::tokenize;
+/// let program = parse(&tokenize("let   x=5 ;x ++").unwrap()).unwrap();
+/// assert_eq!(program.to_string(), "let x = 5; x++");
+/// ```
+impl<T> fmt::Display for Expression<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_expression(f, self)
+    }
+}
+
+/// Writes an expression where a sequence of statements can stand, like the whole program or the
+/// inside of parentheses.
+fn write_expression<T>(f: &mut fmt::Formatter<'_>, expression: &Expression<T>) -> fmt::Result {
+    let Expression::Sequence(statements, _) = expression else {
+        return write_statement(f, expression, true);
+    };
+    for (index, statement) in statements.iter().enumerate() {
+        if index > 0 {
+            write!(f, "; ")?;
+        }
+        write_statement(f, statement, index + 1 == statements.len())?;
+    }
+    Ok(())
+}
+
+/// Writes a single statement. The body of a `let` or a `fn` extends to the end of the sequence it
+/// is in, so they are only written bare when they are `last` in it.
+fn write_statement<T>(f: &mut fmt::Formatter<'_>, expression: &Expression<T>, last: bool) -> fmt::Result {
+    match expression {
+        Expression::Let(..)
+        | Expression::LetMut(..)
+        | Expression::Declare(..)
+        | Expression::LetTuple(..)
+        | Expression::Func(..) if !last => write_parenthesized(f, expression),
+        Expression::Let(name, value, body, _) => write_binding(f, "let ", name, value, body),
+        Expression::LetMut(name, value, body, _) => write_binding(f, "let mut ", name, value, body),
+        Expression::Declare(name, body, _) => {
+            write!(f, "let mut {}; ", name)?;
+            write_expression(f, body)
+        }
+        Expression::LetTuple(names, value, body, _) => write_binding(f, "let ", &pattern(names), value, body),
+        Expression::Func(function, rest, _) => {
+            write!(f, "fn {}({}) = ", function.name, function.parameters.join(", "))?;
+            write_statement(f, &function.body, false)?;
+            write!(f, "; ")?;
+            write_expression(f, rest)
+        }
+        Expression::Sequence(..) => write_parenthesized(f, expression),
+        Expression::Return(value, _) => {
+            write!(f, "return ")?;
+            write_binary(f, value, 0)
+        }
+        Expression::If(condition, then, otherwise, _) => {
+            write!(f, "if ")?;
+            write_binary(f, condition, 0)?;
+            write!(f, " then ")?;
+            write_statement(f, then, false)?;
+            write!(f, " else ")?;
+            write_statement(f, otherwise, false)
+        }
+        Expression::While(condition, body, _) => {
+            write!(f, "while ")?;
+            write_binary(f, condition, 0)?;
+            write!(f, " then ")?;
+            write_statement(f, body, false)
+        }
+        Expression::Assign(name, value, _) => {
+            write!(f, "{} = ", name)?;
+            match **value {
+                Expression::Assign(..) => write_statement(f, value, false),
+                _ => write_binary(f, value, 0),
+            }
+        }
+        _ => write_binary(f, expression, 0),
+    }
+}
+
+/// Writes `<keyword><name> = <value>; <body>`.
+fn write_binding<T>(
+    f: &mut fmt::Formatter<'_>,
+    keyword: &str,
+    name: &str,
+    value: &Expression<T>,
+    body: &Expression<T>,
+) -> fmt::Result {
+    write!(f, "{}{} = ", keyword, name)?;
+    write_statement(f, value, false)?;
+    write!(f, "; ")?;
+    write_expression(f, body)
+}
+
+/// Returns the pattern of a destructuring `let`, as in `(x, y)`.
+fn pattern(names: &[String]) -> String {
+    format!("({})", names.join(", "))
+}
+
+/// Writes a chain of binary operators whose precedence is at least `min_precedence`, so that it
+/// is read back as one operand of an operator with a lower precedence. Anything looser is
+/// parenthesized.
+fn write_binary<T>(f: &mut fmt::Formatter<'_>, expression: &Expression<T>, min_precedence: u8) -> fmt::Result {
+    match expression {
+        Expression::Binary(operator, lhs, rhs, _) => match infix_operator(*operator) {
+            Some((_, precedence)) if precedence < min_precedence => write_parenthesized(f, expression),
+            Some((spelling, precedence)) => {
+                // Operators of the same precedence associate to the left
+                write_binary(f, lhs, precedence)?;
+                write!(f, " {} ", spelling)?;
+                write_binary(f, rhs, precedence + 1)
+            }
+            None => write_term(f, expression),
+        },
+        Expression::Let(..)
+        | Expression::LetMut(..)
+        | Expression::Declare(..)
+        | Expression::LetTuple(..)
+        | Expression::Func(..)
+        | Expression::Sequence(..)
+        | Expression::Return(..)
+        | Expression::If(..)
+        | Expression::While(..)
+        | Expression::Assign(..) => write_parenthesized(f, expression),
+        _ => write_term(f, expression),
+    }
+}
+
+/// Writes a factor followed by any number of `++`, `--` and indices.
+fn write_term<T>(f: &mut fmt::Formatter<'_>, expression: &Expression<T>) -> fmt::Result {
+    match expression {
+        Expression::Increment(expr, _) => {
+            write_term(f, expr)?;
+            write!(f, "++")
+        }
+        Expression::Decrement(expr, _) => {
+            write_term(f, expr)?;
+            write!(f, "--")
+        }
+        Expression::Index(array, index, _) => {
+            write_term(f, array)?;
+            write_list(f, "[", "]", core::iter::once(&**index))
+        }
+        _ => write_factor(f, expression),
+    }
+}
+
+/// Writes an operand that needs no parentheses to be read back whole, parenthesizing anything else.
+fn write_factor<T>(f: &mut fmt::Formatter<'_>, expression: &Expression<T>) -> fmt::Result {
+    match expression {
+        Expression::Number(value, _) => write!(f, "{}", value),
+        Expression::Identifier(name, _) => write!(f, "{}", name),
+        Expression::Print(text, _) => write!(f, "print(\"{}\")", text),
+        Expression::Assert(condition, _) => {
+            write!(f, "assert(")?;
+            write_expression(f, condition)?;
+            write!(f, ")")
+        }
+        Expression::Tuple(elements, _) => write_list(f, "(", ")", elements.iter()),
+        Expression::Array(elements, _) => write_list(f, "[", "]", elements.iter()),
+        Expression::Call(name, arguments, _) => {
+            write!(f, "{}", name)?;
+            write_list(f, "(", ")", arguments.iter())
+        }
+        Expression::Binary(operator, lhs, rhs, _) if infix_operator(*operator).is_none() => {
+            let (name, _) = BUILTINS.iter().find(|(_, builtin)| builtin == operator).expect("not a built-in");
+            write!(f, "{}", name)?;
+            write_list(f, "(", ")", [&**lhs, &**rhs].into_iter())
+        }
+        _ => write_parenthesized(f, expression),
+    }
+}
+
+/// Writes a comma-separated list of expressions between `open` and `close`.
+fn write_list<'a, T: 'a>(
+    f: &mut fmt::Formatter<'_>,
+    open: &str,
+    close: &str,
+    expressions: impl Iterator<Item = &'a Expression<T>>,
+) -> fmt::Result {
+    write!(f, "{}", open)?;
+    for (index, expression) in expressions.enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write_expression(f, expression)?;
+    }
+    write!(f, "{}", close)
+}
+
+/// Writes an expression between parentheses, which the parser reads as the expression alone.
+fn write_parenthesized<T>(f: &mut fmt::Formatter<'_>, expression: &Expression<T>) -> fmt::Result {
+    write!(f, "(")?;
+    write_expression(f, expression)?;
+    write!(f, ")")
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+    use expectest::prelude::*;
+    use proptest::prelude::*;
+    use crate::ast::arbitrary;
+    use crate::parser::parse::parse;
+    use crate::parser::tokenize::tokenize;
+
+    fn format_source(source: &str) -> String {
+        parse(&tokenize(source).unwrap()).unwrap().to_string()
+    }
+
+    mod fmt {
+        use super::*;
+
+        #[test]
+        fn uses_canonical_spacing() {
+            expect!(format_source("let   x=5 ;x ++")).to(be_equal_to("let x = 5; x++".to_string()));
+            expect!(format_source("fn f( a,b )=a+b;f(1 ,2)")).to(be_equal_to("fn f(a, b) = a + b; f(1, 2)".to_string()));
+            expect!(format_source("let(a,b)=(1,2);max(a,b)--")).to(be_equal_to("let (a, b) = (1, 2); max(a, b)--".to_string()));
+            expect!(format_source("[ 10,20 ,30 ] [1]++")).to(be_equal_to("[10, 20, 30][1]++".to_string()));
+        }
+
+        #[test]
+        fn drops_the_parentheses_that_change_nothing() {
+            expect!(format_source("((1 + 2)) + (3 << 4)")).to(be_equal_to("1 + 2 + (3 << 4)".to_string()));
+            expect!(format_source("(x++)--")).to(be_equal_to("x++--".to_string()));
+            expect!(format_source(";(let x = 1; x);;")).to(be_equal_to("let x = 1; x".to_string()));
+        }
+
+        #[test]
+        fn keeps_the_parentheses_the_tree_needs() {
+            expect!(format_source("1 - (2 - 3)")).to(be_equal_to("1 - (2 - 3)".to_string()));
+            expect!(format_source("(1 + 2)++")).to(be_equal_to("(1 + 2)++".to_string()));
+            expect!(format_source("(let x = 1; x); 2")).to(be_equal_to("(let x = 1; x); 2".to_string()));
+            expect!(format_source("(if a then 1 else 2) + 3")).to(be_equal_to("(if a then 1 else 2) + 3".to_string()));
+        }
+
+        #[test]
+        fn separates_a_negative_number_from_a_subtraction() {
+            expect!(format_source("x - -1")).to(be_equal_to("x - -1".to_string()));
+            expect!(format_source("x-1")).to(be_equal_to("x - 1".to_string()));
+        }
+
+        #[test]
+        fn writes_statements_and_literals() {
+            let source = "let mut x; x = y = 2; if x == 2 then return x else print(\"a b\"); assert(x < 3)";
+            expect!(format_source(source)).to(be_equal_to(source.to_string()));
+        }
+
+        proptest!(
+            #[test]
+            fn is_read_back_as_the_same_program(expr in arbitrary::expression(4)) {
+                let source = expr.to_string();
+                prop_assert_eq!(parse(&tokenize(&source).unwrap()), Ok(expr), "{}", source);
+            }
+
+            #[test]
+            fn is_idempotent(expr in arbitrary::expression(4)) {
+                let once = expr.to_string();
+                prop_assert_eq!(format_source(&once), once);
+            }
+        );
+    }
+}
@@ -1,44 +1,622 @@
-/// Trait representing an expression in an abstract syntax tree (AST).
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::ast::operator::BinaryOperator;
+
+/// The binding name that discards a value: `let _ = value; body` evaluates `value` only for its
+/// effects, and `_` cannot be referenced in `body`.
+pub const DISCARD: &str = "_";
+
+/// Represents an expression in an abstract syntax tree (AST).
 ///
-/// The `Expression` trait is designed to be implemented by various types of expressions within an
-/// AST. Each expression carries metadata of type `T`, which can be used to store additional
-/// information related to the expression, such as source code locations, type annotations, or other
-/// relevant data.
+/// The `Expression` enum models every kind of expression the language supports. Each variant
+/// carries metadata of type `T`, which can be used to store additional information related to the
+/// expression, such as source code locations, type annotations, or other relevant data.
 ///
 /// # Type Parameters
 /// - `T`: The type of metadata associated with the expression.
-///
-/// # Required Methods
-/// - `metadata`: Returns the metadata associated with the expression.
-pub(crate) trait Expression<T> {
-    /// Returns the metadata associated with the expression.
-    ///
-    /// # Returns
-    /// - `T`: The metadata associated with the expression.
-    fn metadata(&self) -> T;
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expression<T> {
+    /// A numeric literal.
+    ///
+    /// Holds a 64-bit integer (`i64`) value along with its metadata.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Number(42, ());
+    /// ```
+    Number(i64, T),
+
+    /// An increment operation (`expr++`).
+    ///
+    /// Wraps an expression and increases its value by one. `parse_with_spans` locates it at its
+    /// `++`, rather than at the operand.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Increment(Box::new(Expression::Number(42, ())), ());
+    /// ```
+    Increment(Box<Expression<T>>, T),
+
+    /// A decrement operation (`expr--`).
+    ///
+    /// Wraps an expression and decreases its value by one. `parse_with_spans` locates it at its
+    /// `--`, rather than at the operand.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Decrement(Box::new(Expression::Number(42, ())), ());
+    /// ```
+    Decrement(Box<Expression<T>>, T),
+
+    /// A `let` binding (`let name = value; body`).
+    ///
+    /// Binds the value of the second field to the name in the first field, and evaluates the body
+    /// (third field) with the binding in scope. If the name is `DISCARD`, the value is evaluated
+    /// and then dropped, without binding anything.
+    ///
+    /// The binding is immutable: it cannot be the target of an `Assign`. See `LetMut`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Let(
+    ///     "x".to_string(),
+    ///     Box::new(Expression::Number(5, ())),
+    ///     Box::new(Expression::Identifier("x".to_string(), ())),
+    ///     (),
+    /// );
+    /// ```
+    Let(String, Box<Expression<T>>, Box<Expression<T>>, T),
+
+    /// A mutable `let` binding (`let mut name = value; body`).
+    ///
+    /// Like `Let`, but the binding can be the target of an `Assign` in the body. It is a variant
+    /// of its own rather than a flag of `Let` so that `Expression` does not grow.
+    LetMut(String, Box<Expression<T>>, Box<Expression<T>>, T),
+
+    /// A mutable variable declared without a value (`let mut name; body`).
+    ///
+    /// The body (second field) is evaluated with the variable in scope, and has to assign it before
+    /// reading it, which `check_initialized` makes sure of.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Declare(
+    ///     "x".to_string(),
+    ///     Box::new(Expression::Assign("x".to_string(), Box::new(Expression::Number(5, ())), ())),
+    ///     (),
+    /// );
+    /// ```
+    Declare(String, Box<Expression<T>>, T),
+
+    /// An identifier (variable name).
+    ///
+    /// References a variable previously bound by a `let` expression.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Identifier("x".to_string(), ());
+    /// ```
+    Identifier(String, T),
+
+    /// A binary operation (`lhs <op> rhs`).
+    ///
+    /// Applies the `BinaryOperator` in the first field to the left-hand side (second field) and the
+    /// right-hand side (third field). The left-hand side is always evaluated first.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// # use rengo::ast::operator::BinaryOperator;
+    /// let expr = Expression::Binary(
+    ///     BinaryOperator::BitAnd,
+    ///     Box::new(Expression::Number(6, ())),
+    ///     Box::new(Expression::Number(3, ())),
+    ///     (),
+    /// );
+    /// ```
+    Binary(BinaryOperator, Box<Expression<T>>, Box<Expression<T>>, T),
+
+    /// A sequence of statements (`first; second; ...`).
+    ///
+    /// Evaluates the statements in order. The value of the sequence is the value of its last
+    /// statement; the values of the others are discarded, but they are still evaluated for their
+    /// effects, like assignments, failed assertions or `return`s. A `let` in a sequence takes the
+    /// rest of it as its body, so a program ending in `let`s has the value of its final statement.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Sequence(
+    ///     vec![Expression::Number(1, ()), Expression::Number(2, ())],
+    ///     (),
+    /// );
+    /// ```
+    Sequence(Vec<Expression<T>>, T),
+
+    /// An assignment to a bound variable (`name = value`).
+    ///
+    /// Stores the value of the second field in the variable named by the first field. The value of
+    /// the assignment is the value assigned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Assign("x".to_string(), Box::new(Expression::Number(5, ())), ());
+    /// ```
+    Assign(String, Box<Expression<T>>, T),
+
+    /// A conditional (`if condition then ... else ...`).
+    ///
+    /// Evaluates the condition (first field) and then only one of the branches: the second field
+    /// if the condition is nonzero, the third one otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::If(
+    ///     Box::new(Expression::Number(1, ())),
+    ///     Box::new(Expression::Number(2, ())),
+    ///     Box::new(Expression::Number(3, ())),
+    ///     (),
+    /// );
+    /// ```
+    If(Box<Expression<T>>, Box<Expression<T>>, Box<Expression<T>>, T),
+
+    /// A loop (`while condition ...`).
+    ///
+    /// Evaluates the body (second field) for as long as the condition (first field) is nonzero.
+    /// The value of the loop is always 0.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::While(
+    ///     Box::new(Expression::Number(0, ())),
+    ///     Box::new(Expression::Number(1, ())),
+    ///     (),
+    /// );
+    /// ```
+    While(Box<Expression<T>>, Box<Expression<T>>, T),
+
+    /// An assertion (`assert(condition)`).
+    ///
+    /// Evaluates the condition and stops the program with a failure if it is zero. Otherwise, the
+    /// value of the assertion is the value of the condition.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Assert(Box::new(Expression::Number(1, ())), ());
+    /// ```
+    Assert(Box<Expression<T>>, T),
+
+    /// An early exit (`return value`).
+    ///
+    /// Evaluates the value and ends the whole program with it, skipping whatever would have been
+    /// evaluated next.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Return(Box::new(Expression::Number(5, ())), ());
+    /// ```
+    Return(Box<Expression<T>>, T),
+
+    /// A tuple literal (`(a, b)`).
+    ///
+    /// Groups the values of its elements. A tuple is not a value on its own: it can only be the
+    /// value destructured by a `LetTuple`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Tuple(vec![Expression::Number(1, ()), Expression::Number(2, ())], ());
+    /// ```
+    Tuple(Vec<Expression<T>>, T),
+
+    /// A destructuring binding (`let (x, y) = (a, b); body`).
+    ///
+    /// Binds each name in the first field to the matching element of the tuple in the second
+    /// field, and evaluates the body (third field) with the bindings in scope. Every element is
+    /// evaluated before any name is bound, and names that are `DISCARD` bind nothing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::LetTuple(
+    ///     vec!["x".to_string(), "y".to_string()],
+    ///     Box::new(Expression::Tuple(vec![Expression::Number(1, ()), Expression::Number(2, ())], ())),
+    ///     Box::new(Expression::Identifier("y".to_string(), ())),
+    ///     (),
+    /// );
+    /// ```
+    LetTuple(Vec<String>, Box<Expression<T>>, Box<Expression<T>>, T),
+
+    /// An array literal (`[a, b, c]`).
+    ///
+    /// Holds the values of its elements, which are integers, and has a fixed size. Like a tuple,
+    /// an array is not a value on its own: it can only be the array of an `Index`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Array(vec![Expression::Number(10, ()), Expression::Number(20, ())], ());
+    /// ```
+    Array(Vec<Expression<T>>, T),
+
+    /// An element of an array (`array[index]`).
+    ///
+    /// Evaluates the array (first field) and then the index (second field), and has the value of
+    /// the element at that index, counting from 0. For now, the array has to be an `Array` literal.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Index(
+    ///     Box::new(Expression::Array(vec![Expression::Number(10, ()), Expression::Number(20, ())], ())),
+    ///     Box::new(Expression::Number(1, ())),
+    ///     (),
+    /// );
+    /// ```
+    Index(Box<Expression<T>>, Box<Expression<T>>, T),
+
+    /// A call to the `print` built-in (`print("...")`).
+    ///
+    /// Writes the string, followed by a newline, to the standard output of the program. Its value
+    /// is 0.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Print("hello".to_string(), ());
+    /// ```
+    Print(String, T),
+
+    /// A function definition (`fn name(a, b) = body; rest`).
+    ///
+    /// Defines the `Function` in the first field, and evaluates the second field with it in scope.
+    /// The definition is boxed as a whole to keep every node as small as the other variants.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Function;
+    /// # use rengo::ast::expression::Expression;
+    /// let id = Function {
+    ///     name: "id".to_string(),
+    ///     parameters: vec!["x".to_string()],
+    ///     body: Expression::Identifier("x".to_string(), ()),
+    /// };
+    /// let expr = Expression::Func(
+    ///     Box::new(id),
+    ///     Box::new(Expression::Call("id".to_string(), vec![Expression::Number(1, ())], ())),
+    ///     (),
+    /// );
+    /// ```
+    Func(Box<Function<T>>, Box<Expression<T>>, T),
+
+    /// A call to a function defined by an enclosing `Func` (`name(a, b)`).
+    ///
+    /// Evaluates the arguments from left to right and then the body of the function with its
+    /// parameters bound to them. The value of the call is the value of the body.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Call("f".to_string(), vec![Expression::Number(1, ())], ());
+    /// ```
+    Call(String, Vec<Expression<T>>, T),
 }
 
-/// Trait representing a literal expression in an abstract syntax tree (AST).
-///
-/// The `Literal` trait is intended to be implemented by types that represent literal values within
-/// an AST. Literals are the basic building blocks of expressions, such as numeric values, strings,
-/// or boolean constants. This trait can be extended to include metadata of type `T`, which can
-/// provide additional context or information related to the literal expression.
+/// A function defined by an `Expression::Func`.
 ///
-/// # Type Parameters
-/// - `T`: The type of metadata associated with the literal.
-pub(crate) trait Literal<T> {}
+/// The body only sees the parameters, not the variables around the definition, but it can call
+/// the function itself and any function defined before it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Function<T> {
+    /// The name the function is called by.
+    pub name: String,
 
-/// A trait representing a unary expression in an abstract syntax tree (AST).
-///
-/// The `UnaryExpression` trait is a marker trait that can be implemented by types that represent
-/// unary expressions in an abstract syntax tree (AST). Unary expressions are operations that
-/// involve a single operand, such as negation, increment, or decrement operations.
-///
-/// ## Type Parameters:
-/// - `T`: The type of the operand or the result of the unary expression. This could represent a
-///   numeric type, a boolean type, or any other type that unary operations can be applied to.
-pub(crate) trait UnaryExpression<T> {
-    /// Returns a reference to the operand of the unary expression.
-    fn expr(&self) -> &dyn Expression<T>;
+    /// The names the arguments of a call are bound to in the body, in order.
+    pub parameters: Vec<String>,
+
+    /// The expression whose value a call evaluates to.
+    pub body: Expression<T>,
+}
+
+impl<T> Expression<T> {
+    /// Returns the metadata attached to this node.
+    pub fn metadata(&self) -> &T {
+        match self {
+            Expression::Number(_, metadata)
+            | Expression::Increment(_, metadata)
+            | Expression::Decrement(_, metadata)
+            | Expression::Let(_, _, _, metadata)
+            | Expression::LetMut(_, _, _, metadata)
+            | Expression::Declare(_, _, metadata)
+            | Expression::Identifier(_, metadata)
+            | Expression::Print(_, metadata)
+            | Expression::Binary(_, _, _, metadata)
+            | Expression::Sequence(_, metadata)
+            | Expression::Assign(_, _, metadata)
+            | Expression::If(_, _, _, metadata)
+            | Expression::While(_, _, metadata)
+            | Expression::Assert(_, metadata)
+            | Expression::Return(_, metadata)
+            | Expression::Tuple(_, metadata)
+            | Expression::LetTuple(_, _, _, metadata)
+            | Expression::Array(_, metadata)
+            | Expression::Index(_, _, metadata)
+            | Expression::Func(_, _, metadata)
+            | Expression::Call(_, _, metadata) => metadata,
+        }
+    }
+
+    /// Returns the maximum nesting of the tree: the number of nodes on its longest path from this
+    /// node down to a leaf, so a lone number has a depth of 1.
+    ///
+    /// Only nesting counts: operands, parentheses and the value of a binding or the body of a
+    /// function are one level deeper than the node holding them, but the statements after a `let`
+    /// or a function definition, and those of a sequence, follow each other on the same level. A
+    /// long program of flat statements is thus no deeper than its deepest statement.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Increment(Box::new(Expression::Number(1, ())), ());
+    /// assert_eq!(expr.depth(), 2);
+    /// ```
+    pub fn depth(&self) -> usize {
+        match self {
+            Expression::Let(_, value, body, _)
+            | Expression::LetMut(_, value, body, _)
+            | Expression::LetTuple(_, value, body, _) => (value.depth() + 1).max(body.depth()),
+            Expression::Func(function, rest, _) => (function.body.depth() + 1).max(rest.depth()),
+            Expression::Sequence(expressions, _) => expressions.iter().map(Expression::depth).max().unwrap_or(1),
+            _ => self.deepest_child(Expression::depth) + 1,
+        }
+    }
+
+    /// Returns the number of nodes on the longest path of the tree from this node down to a leaf,
+    /// statements that follow each other included. This is how deep evaluating or compiling the
+    /// tree recurses, while `depth` is how deeply it nests.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// // let x = 1; x
+    /// let x = Box::new(Expression::Identifier("x".to_string(), ()));
+    /// let expr = Expression::Let("x".to_string(), Box::new(Expression::Number(1, ())), x, ());
+    /// assert_eq!(expr.height(), 2);
+    /// ```
+    pub fn height(&self) -> usize {
+        self.deepest_child(Expression::height) + 1
+    }
+
+    /// Returns the largest `measure` of the children of this node, or 0 for a leaf.
+    fn deepest_child(&self, measure: fn(&Expression<T>) -> usize) -> usize {
+        match self {
+            Expression::Number(..) | Expression::Identifier(..) | Expression::Print(..) => 0,
+            Expression::Increment(expression, _)
+            | Expression::Decrement(expression, _)
+            | Expression::Assign(_, expression, _)
+            | Expression::Declare(_, expression, _)
+            | Expression::Assert(expression, _)
+            | Expression::Return(expression, _) => measure(expression),
+            Expression::Let(_, first, second, _)
+            | Expression::LetMut(_, first, second, _)
+            | Expression::LetTuple(_, first, second, _)
+            | Expression::Binary(_, first, second, _)
+            | Expression::Index(first, second, _)
+            | Expression::While(first, second, _) => measure(first).max(measure(second)),
+            Expression::Func(function, rest, _) => measure(&function.body).max(measure(rest)),
+            Expression::If(condition, then, otherwise, _) => {
+                measure(condition).max(measure(then)).max(measure(otherwise))
+            }
+            Expression::Sequence(expressions, _)
+            | Expression::Tuple(expressions, _)
+            | Expression::Array(expressions, _)
+            | Expression::Call(_, expressions, _) => expressions.iter().map(measure).max().unwrap_or(0),
+        }
+    }
+
+    /// Rebuilds the tree with the metadata of every node replaced by the result of `f`.
+    ///
+    /// `f` is called on each node before its children, which are visited in evaluation order, so
+    /// it sees the nodes in the same order as a `Visitor`. Mapping to `()` compares two trees
+    /// while ignoring their metadata.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::ast::expression::Expression;
+    /// let expr = Expression::Increment(Box::new(Expression::Number(1, "inner")), "outer");
+    /// assert_eq!(expr.map_metadata(&mut |_| ()), Expression::Increment(Box::new(Expression::Number(1, ())), ()));
+    /// ```
+    pub fn map_metadata<U>(self, f: &mut impl FnMut(T) -> U) -> Expression<U> {
+        match self {
+            Expression::Number(value, metadata) => Expression::Number(value, f(metadata)),
+            Expression::Increment(expression, metadata) => {
+                let metadata = f(metadata);
+                Expression::Increment(map_boxed(*expression, f), metadata)
+            }
+            Expression::Decrement(expression, metadata) => {
+                let metadata = f(metadata);
+                Expression::Decrement(map_boxed(*expression, f), metadata)
+            }
+            Expression::Let(identifier, value, body, metadata) => {
+                let metadata = f(metadata);
+                let value = map_boxed(*value, f);
+                Expression::Let(identifier, value, map_boxed(*body, f), metadata)
+            }
+            Expression::LetMut(identifier, value, body, metadata) => {
+                let metadata = f(metadata);
+                let value = map_boxed(*value, f);
+                Expression::LetMut(identifier, value, map_boxed(*body, f), metadata)
+            }
+            Expression::Declare(identifier, body, metadata) => {
+                let metadata = f(metadata);
+                Expression::Declare(identifier, map_boxed(*body, f), metadata)
+            }
+            Expression::Identifier(identifier, metadata) => Expression::Identifier(identifier, f(metadata)),
+            Expression::Print(text, metadata) => Expression::Print(text, f(metadata)),
+            Expression::Binary(operator, lhs, rhs, metadata) => {
+                let metadata = f(metadata);
+                let lhs = map_boxed(*lhs, f);
+                Expression::Binary(operator, lhs, map_boxed(*rhs, f), metadata)
+            }
+            Expression::Sequence(statements, metadata) => {
+                let metadata = f(metadata);
+                let statements = statements.into_iter().map(|statement| statement.map_metadata(f)).collect();
+                Expression::Sequence(statements, metadata)
+            }
+            Expression::Assign(identifier, value, metadata) => {
+                let metadata = f(metadata);
+                Expression::Assign(identifier, map_boxed(*value, f), metadata)
+            }
+            Expression::If(condition, then, otherwise, metadata) => {
+                let metadata = f(metadata);
+                let condition = map_boxed(*condition, f);
+                let then = map_boxed(*then, f);
+                Expression::If(condition, then, map_boxed(*otherwise, f), metadata)
+            }
+            Expression::While(condition, body, metadata) => {
+                let metadata = f(metadata);
+                let condition = map_boxed(*condition, f);
+                Expression::While(condition, map_boxed(*body, f), metadata)
+            }
+            Expression::Assert(condition, metadata) => {
+                let metadata = f(metadata);
+                Expression::Assert(map_boxed(*condition, f), metadata)
+            }
+            Expression::Return(value, metadata) => {
+                let metadata = f(metadata);
+                Expression::Return(map_boxed(*value, f), metadata)
+            }
+            Expression::Tuple(elements, metadata) => {
+                let metadata = f(metadata);
+                let elements = elements.into_iter().map(|element| element.map_metadata(f)).collect();
+                Expression::Tuple(elements, metadata)
+            }
+            Expression::LetTuple(identifiers, value, body, metadata) => {
+                let metadata = f(metadata);
+                let value = map_boxed(*value, f);
+                Expression::LetTuple(identifiers, value, map_boxed(*body, f), metadata)
+            }
+            Expression::Array(elements, metadata) => {
+                let metadata = f(metadata);
+                let elements = elements.into_iter().map(|element| element.map_metadata(f)).collect();
+                Expression::Array(elements, metadata)
+            }
+            Expression::Index(array, index, metadata) => {
+                let metadata = f(metadata);
+                let array = map_boxed(*array, f);
+                Expression::Index(array, map_boxed(*index, f), metadata)
+            }
+            Expression::Func(function, rest, metadata) => {
+                let metadata = f(metadata);
+                let Function { name, parameters, body } = *function;
+                let function = Box::new(Function { name, parameters, body: body.map_metadata(f) });
+                Expression::Func(function, map_boxed(*rest, f), metadata)
+            }
+            Expression::Call(name, arguments, metadata) => {
+                let metadata = f(metadata);
+                let arguments = arguments.into_iter().map(|argument| argument.map_metadata(f)).collect();
+                Expression::Call(name, arguments, metadata)
+            }
+        }
+    }
+}
+
+/// Maps the metadata of a child and boxes it, as part of `Expression::map_metadata`.
+fn map_boxed<T, U>(expression: Expression<T>, f: &mut impl FnMut(T) -> U) -> Box<Expression<U>> {
+    Box::new(expression.map_metadata(f))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use expectest::prelude::*;
+    use super::*;
+
+    fn number(value: i64) -> Box<Expression<()>> {
+        Box::new(Expression::Number(value, ()))
+    }
+
+    mod depth {
+        use super::*;
+
+        #[test]
+        fn counts_a_leaf_as_one() {
+            expect!(Expression::Number(1, ()).depth()).to(be_equal_to(1));
+        }
+
+        #[test]
+        fn counts_each_level_of_nesting() {
+            expect!(Expression::Increment(number(1), ()).depth()).to(be_equal_to(2));
+        }
+
+        #[test]
+        fn follows_the_deepest_child() {
+            // let x = 1; let y = x++; (x, y--): the value of `y` and the tuple are both 3 deep
+            let body = Expression::Tuple(vec![
+                Expression::Identifier("x".to_string(), ()),
+                Expression::Decrement(Box::new(Expression::Identifier("y".to_string(), ())), ()),
+            ], ());
+            let inner = Expression::Let(
+                "y".to_string(),
+                Box::new(Expression::Increment(Box::new(Expression::Identifier("x".to_string(), ())), ())),
+                Box::new(body),
+                (),
+            );
+            let expr = Expression::Let("x".to_string(), number(1), Box::new(inner), ());
+            expect!(expr.depth()).to(be_equal_to(3));
+            expect!(expr.height()).to(be_equal_to(5));
+        }
+
+        #[test]
+        fn does_not_count_statements_that_follow_each_other() {
+            // let x0 = 0; let x1 = x0++; ...; let x39 = x38++; x39
+            let body = (0..40).rev().fold(Expression::Identifier("x39".to_string(), ()), |body, index| {
+                let value = match index {
+                    0 => *number(0),
+                    _ => Expression::Increment(Box::new(Expression::Identifier(format!("x{}", index - 1), ())), ()),
+                };
+                Expression::Let(format!("x{}", index), Box::new(value), Box::new(body), ())
+            });
+            expect!(body.depth()).to(be_equal_to(3));
+            let statements = Expression::Sequence(vec![*number(1), Expression::Increment(number(2), ()), *number(3)], ());
+            expect!(statements.depth()).to(be_equal_to(2));
+        }
+    }
 }
@@ -0,0 +1,391 @@
+use alloc::boxed::Box;
+use crate::ast::expression::{Expression, Function};
+use crate::ast::operator::BinaryOperator;
+
+/// A pass that rewrites an `Expression` tree into a new one.
+///
+/// Implementors override `fold_expression` to rewrite the nodes they care about, and call
+/// `walk_expression` to fold the children first. The default implementation rebuilds the tree
+/// unchanged.
+///
+/// # Example
+///
+/// ```rust
+/// # use rengo::ast::fold::Folder;
+/// # use rengo::ast::expression::Expression;
+/// # use rengo::ast::fold::walk_expression;
+/// struct Zero;
+///
+/// impl<T> Folder<T> for Zero {
+///     fn fold_expression(&mut self, expression: Expression<T>) -> Expression<T> {
+///         match walk_expression(self, expression) {
+///             Expression::Number(_, metadata) => Expression::Number(0, metadata),
+///             other => other,
+///         }
+///     }
+/// }
+/// ```
+pub trait Folder<T> {
+    /// Folds a node of the tree. By default, it folds the node's children and keeps the node.
+    fn fold_expression(&mut self, expression: Expression<T>) -> Expression<T> {
+        walk_expression(self, expression)
+    }
+}
+
+/// Folds the children of an `Expression`, in the order they are evaluated, and rebuilds the node
+/// around them. The node itself and its metadata are kept as they are.
+///
+/// ## Parameters:
+/// - `folder`: The folder to call on each child.
+/// - `expression`: The node whose children are folded.
+///
+/// ## Returns:
+/// The node with its children replaced by their folded versions.
+pub fn walk_expression<T, F: Folder<T> + ?Sized>(folder: &mut F, expression: Expression<T>) -> Expression<T> {
+    match expression {
+        Expression::Number(..) | Expression::Identifier(..) | Expression::Print(..) => expression,
+        Expression::Increment(expr, metadata) => {
+            Expression::Increment(Box::new(folder.fold_expression(*expr)), metadata)
+        }
+        Expression::Decrement(expr, metadata) => {
+            Expression::Decrement(Box::new(folder.fold_expression(*expr)), metadata)
+        }
+        Expression::Let(identifier, value, body, metadata) => {
+            let value = folder.fold_expression(*value);
+            let body = folder.fold_expression(*body);
+            Expression::Let(identifier, Box::new(value), Box::new(body), metadata)
+        }
+        Expression::LetMut(identifier, value, body, metadata) => {
+            let value = folder.fold_expression(*value);
+            let body = folder.fold_expression(*body);
+            Expression::LetMut(identifier, Box::new(value), Box::new(body), metadata)
+        }
+        Expression::Declare(identifier, body, metadata) => {
+            Expression::Declare(identifier, Box::new(folder.fold_expression(*body)), metadata)
+        }
+        Expression::Binary(operator, lhs, rhs, metadata) => {
+            let lhs = folder.fold_expression(*lhs);
+            let rhs = folder.fold_expression(*rhs);
+            Expression::Binary(operator, Box::new(lhs), Box::new(rhs), metadata)
+        }
+        Expression::Sequence(statements, metadata) => Expression::Sequence(
+            statements.into_iter().map(|statement| folder.fold_expression(statement)).collect(),
+            metadata,
+        ),
+        Expression::Assign(identifier, value, metadata) => {
+            Expression::Assign(identifier, Box::new(folder.fold_expression(*value)), metadata)
+        }
+        Expression::Tuple(elements, metadata) => Expression::Tuple(
+            elements.into_iter().map(|element| folder.fold_expression(element)).collect(),
+            metadata,
+        ),
+        Expression::LetTuple(identifiers, value, body, metadata) => {
+            let value = folder.fold_expression(*value);
+            let body = folder.fold_expression(*body);
+            Expression::LetTuple(identifiers, Box::new(value), Box::new(body), metadata)
+        }
+        Expression::Array(elements, metadata) => Expression::Array(
+            elements.into_iter().map(|element| folder.fold_expression(element)).collect(),
+            metadata,
+        ),
+        Expression::Index(array, index, metadata) => {
+            let array = folder.fold_expression(*array);
+            let index = folder.fold_expression(*index);
+            Expression::Index(Box::new(array), Box::new(index), metadata)
+        }
+        Expression::Func(function, rest, metadata) => {
+            let Function { name, parameters, body } = *function;
+            let function = Function { name, parameters, body: folder.fold_expression(body) };
+            let rest = folder.fold_expression(*rest);
+            Expression::Func(Box::new(function), Box::new(rest), metadata)
+        }
+        Expression::Call(name, arguments, metadata) => Expression::Call(
+            name,
+            arguments.into_iter().map(|argument| folder.fold_expression(argument)).collect(),
+            metadata,
+        ),
+        Expression::If(condition, then, otherwise, metadata) => {
+            let condition = folder.fold_expression(*condition);
+            let then = folder.fold_expression(*then);
+            let otherwise = folder.fold_expression(*otherwise);
+            Expression::If(Box::new(condition), Box::new(then), Box::new(otherwise), metadata)
+        }
+        Expression::While(condition, body, metadata) => {
+            let condition = folder.fold_expression(*condition);
+            let body = folder.fold_expression(*body);
+            Expression::While(Box::new(condition), Box::new(body), metadata)
+        }
+        Expression::Assert(condition, metadata) => {
+            Expression::Assert(Box::new(folder.fold_expression(*condition)), metadata)
+        }
+        Expression::Return(value, metadata) => {
+            Expression::Return(Box::new(folder.fold_expression(*value)), metadata)
+        }
+    }
+}
+
+/// Replaces every operation whose operands are all numeric literals with its result.
+///
+/// Folding follows the semantics of the generated code, where shift counts are masked to their low
+/// 6 bits. Operations that would overflow (incrementing `i64::MAX`, adding two large constants,
+/// ...) are left unfolded instead of being wrapped here, so the program overflows at run time
+/// exactly as it would without folding. The folded node keeps the metadata of the operation it
+/// replaces.
+///
+/// # Example
+///
+/// ```rust
+/// # use rengo::parser::parse::parse;
+/// # use rengo::parser::tokenize::tokenize;
+/// # use rengo::ast::fold::fold_constants;
+/// # use rengo::ast::expression::Expression;
+/// let expr = parse(&tokenize("(6 & 3)++").unwrap()).unwrap();
+/// assert_eq!(fold_constants(expr), Expression::Number(3, ()));
+/// ```
+pub fn fold_constants<T>(expression: Expression<T>) -> Expression<T> {
+    ConstantFolder.fold_expression(expression)
+}
+
+/// The `Folder` behind `fold_constants`.
+struct ConstantFolder;
+
+impl<T> Folder<T> for ConstantFolder {
+    fn fold_expression(&mut self, expression: Expression<T>) -> Expression<T> {
+        match walk_expression(self, expression) {
+            Expression::Increment(expr, metadata) => match *expr {
+                Expression::Number(value, _) if value != i64::MAX => Expression::Number(value + 1, metadata),
+                expr => Expression::Increment(Box::new(expr), metadata),
+            },
+            Expression::Decrement(expr, metadata) => match *expr {
+                Expression::Number(value, _) if value != i64::MIN => Expression::Number(value - 1, metadata),
+                expr => Expression::Decrement(Box::new(expr), metadata),
+            },
+            Expression::Binary(operator, lhs, rhs, metadata) => match (*lhs, *rhs) {
+                (Expression::Number(lhs, lhs_metadata), Expression::Number(rhs, rhs_metadata)) => {
+                    match apply(operator, lhs, rhs) {
+                        Some(value) => Expression::Number(value, metadata),
+                        None => Expression::Binary(
+                            operator,
+                            Box::new(Expression::Number(lhs, lhs_metadata)),
+                            Box::new(Expression::Number(rhs, rhs_metadata)),
+                            metadata,
+                        ),
+                    }
+                }
+                (lhs, rhs) => Expression::Binary(operator, Box::new(lhs), Box::new(rhs), metadata),
+            },
+            other => other,
+        }
+    }
+}
+
+/// Removes every `Increment` directly wrapping a `Decrement`, and every `Decrement` directly
+/// wrapping an `Increment`, whatever the operand is.
+///
+/// Since arithmetic wraps, adding 1 and then subtracting it gives back the operand even at the
+/// bounds of `i64`, so unlike `fold_constants`, this also applies to identifiers and to operations
+/// that would overflow. The operand keeps its own metadata.
+///
+/// # Example
+///
+/// ```rust
+/// # use rengo::parser::parse::parse;
+/// # use rengo::parser::tokenize::tokenize;
+/// # use rengo::ast::fold::simplify;
+/// let expr = parse(&tokenize("let x = 1; x++--").unwrap()).unwrap();
+/// assert_eq!(simplify(expr), parse(&tokenize("let x = 1; x").unwrap()).unwrap());
+/// ```
+pub fn simplify<T>(expression: Expression<T>) -> Expression<T> {
+    Simplifier.fold_expression(expression)
+}
+
+/// The `Folder` behind `simplify`.
+struct Simplifier;
+
+impl<T> Folder<T> for Simplifier {
+    fn fold_expression(&mut self, expression: Expression<T>) -> Expression<T> {
+        match walk_expression(self, expression) {
+            Expression::Increment(expr, metadata) => match *expr {
+                Expression::Decrement(operand, _) => *operand,
+                expr => Expression::Increment(Box::new(expr), metadata),
+            },
+            Expression::Decrement(expr, metadata) => match *expr {
+                Expression::Increment(operand, _) => *operand,
+                expr => Expression::Decrement(Box::new(expr), metadata),
+            },
+            other => other,
+        }
+    }
+}
+
+/// Computes the result of a binary operation on two constants, or `None` if it overflows.
+fn apply(operator: BinaryOperator, lhs: i64, rhs: i64) -> Option<i64> {
+    match operator {
+        BinaryOperator::Add => lhs.checked_add(rhs),
+        BinaryOperator::Subtract => lhs.checked_sub(rhs),
+        BinaryOperator::BitAnd => Some(lhs & rhs),
+        BinaryOperator::BitOr => Some(lhs | rhs),
+        BinaryOperator::BitXor => Some(lhs ^ rhs),
+        // Masking the count is how shifts are defined, not an overflow
+        BinaryOperator::ShiftLeft => Some(lhs.wrapping_shl(rhs as u32)),
+        BinaryOperator::ShiftRight => Some(lhs.wrapping_shr(rhs as u32)),
+        BinaryOperator::Less => Some(i64::from(lhs < rhs)),
+        BinaryOperator::Greater => Some(i64::from(lhs > rhs)),
+        BinaryOperator::Equal => Some(i64::from(lhs == rhs)),
+        BinaryOperator::Min => Some(lhs.min(rhs)),
+        BinaryOperator::Max => Some(lhs.max(rhs)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::string::ToString;
+    use expectest::prelude::*;
+    use proptest::prelude::*;
+    use crate::ast::arbitrary;
+    use crate::interpreter::eval::{eval, Bindings};
+    use crate::parser::parse::parse;
+    use crate::parser::tokenize::tokenize;
+    use super::*;
+
+    fn parse_source(source: &str) -> Expression<()> {
+        parse(&tokenize(source).unwrap()).unwrap()
+    }
+
+    mod walk_expression {
+        use super::*;
+
+        struct Identity;
+
+        impl<T> Folder<T> for Identity {}
+
+        #[test]
+        fn it_rebuilds_the_tree_unchanged() {
+            let expr = parse_source("let x = 1; (x ^ 2)--; x");
+            expect!(Identity.fold_expression(expr.clone())).to(be_equal_to(expr));
+        }
+    }
+
+    mod fold_constants {
+        use super::*;
+
+        #[test]
+        fn it_folds_literal_operations() {
+            expect!(fold_constants(parse_source("420++--++"))).to(be_equal_to(Expression::Number(421, ())));
+            expect!(fold_constants(parse_source("(6 & 3) << 4"))).to(be_equal_to(Expression::Number(32, ())));
+        }
+
+        #[test]
+        fn it_keeps_operations_on_identifiers() {
+            let expr = parse_source("let x = 1 | 2; x++");
+            let expected = Expression::Let(
+                "x".to_string(),
+                Box::new(Expression::Number(3, ())),
+                Box::new(Expression::Increment(Box::new(Expression::Identifier("x".to_string(), ())), ())),
+                (),
+            );
+            expect!(fold_constants(expr)).to(be_equal_to(expected));
+        }
+
+        #[test]
+        fn it_leaves_overflowing_operations_unfolded() {
+            let increment = Expression::Increment(Box::new(Expression::Number(i64::MAX, ())), ());
+            expect!(fold_constants(increment.clone())).to(be_equal_to(increment));
+            let decrement = Expression::Decrement(Box::new(Expression::Number(i64::MIN, ())), ());
+            expect!(fold_constants(decrement.clone())).to(be_equal_to(decrement));
+            let sum = Expression::Binary(
+                BinaryOperator::Add,
+                Box::new(Expression::Number(i64::MAX, ())),
+                Box::new(Expression::Number(1, ())),
+                (),
+            );
+            expect!(fold_constants(sum.clone())).to(be_equal_to(sum));
+        }
+
+        /// Generates constants that are mostly close to the bounds of `i64`.
+        fn boundary() -> impl Strategy<Value = i64> {
+            prop_oneof![
+                (i64::MAX - 2)..=i64::MAX,
+                i64::MIN..=(i64::MIN + 2),
+                -2i64..=2,
+                any::<i64>(),
+            ]
+        }
+
+        proptest!(
+            #[test]
+            fn it_agrees_with_the_unfolded_program_at_the_bounds(
+                a in boundary(),
+                b in boundary(),
+                operator in proptest::sample::select(&[BinaryOperator::Add, BinaryOperator::Subtract][..]),
+                increments in 0usize..3,
+            ) {
+                let mut expr = Expression::Binary(
+                    operator,
+                    Box::new(Expression::Number(a, ())),
+                    Box::new(Expression::Number(b, ())),
+                    (),
+                );
+                for _ in 0..increments {
+                    expr = Expression::Increment(Box::new(expr), ());
+                }
+                let expected = eval(&expr, &mut Bindings::new());
+                expect!(eval(&fold_constants(expr), &mut Bindings::new())).to(be_equal_to(expected));
+            }
+
+            #[test]
+            fn it_preserves_the_result(a in any::<i64>(), b in 0i64..64, c in any::<i64>()) {
+                let expr = parse_source(&format!("let x = {} ^ {}; (x << {})++ | ({} >> {})--", a, c, b, c, b));
+                let expected = eval(&expr, &mut Bindings::new());
+                expect!(eval(&fold_constants(expr), &mut Bindings::new())).to(be_equal_to(expected));
+            }
+        );
+    }
+
+    mod simplify {
+        use super::*;
+
+        #[test]
+        fn it_cancels_an_increment_with_a_decrement() {
+            expect!(simplify(parse_source("let x = 1; x++--"))).to(be_equal_to(parse_source("let x = 1; x")));
+            expect!(simplify(parse_source("let x = 1; x--++--"))).to(be_equal_to(parse_source("let x = 1; x--")));
+            expect!(simplify(parse_source("420++--"))).to(be_equal_to(Expression::Number(420, ())));
+        }
+
+        #[test]
+        fn it_cancels_inside_other_operations() {
+            let expr = parse_source("let x = 1; (x-- ++ + 2++--)++ ; x");
+            expect!(simplify(expr)).to(be_equal_to(parse_source("let x = 1; (x + 2)++ ; x")));
+        }
+
+        #[test]
+        fn it_keeps_increments_of_the_same_direction() {
+            let expr = parse_source("let x = 1; x++++");
+            expect!(simplify(expr.clone())).to(be_equal_to(expr));
+        }
+
+        proptest!(
+            #[test]
+            fn it_preserves_the_result_of_counters(
+                value in prop_oneof![Just(i64::MAX), Just(i64::MIN), any::<i64>()],
+                operators in prop::collection::vec(prop_oneof![Just("++"), Just("--")], 0..8),
+                identifier in any::<bool>(),
+            ) {
+                let source = match identifier {
+                    true => format!("let x = {}; x{}", value, operators.concat()),
+                    false => format!("({}){}", value, operators.concat()),
+                };
+                let expr = parse_source(&source);
+                let expected = eval(&expr, &mut Bindings::new());
+                expect!(eval(&simplify(expr), &mut Bindings::new())).to(be_equal_to(expected));
+            }
+
+            #[test]
+            fn it_preserves_the_result_of_any_program(expr in arbitrary::expression(4)) {
+                let expected = eval(&expr, &mut Bindings::new());
+                expect!(eval(&simplify(expr), &mut Bindings::new())).to(be_equal_to(expected));
+            }
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::ast::expression::Expression;
+
+/// Serializes an `Expression` into a JSON string.
+///
+/// The `to_json` function produces a compact, deterministic JSON representation of an expression
+/// tree, meant for editor integrations and other tools. Each node is an object with a single key
+/// naming the variant, whose value holds the variant's fields: a bare value when there is only one
+/// field, or an array otherwise. Metadata is not serialized.
+///
+/// # Arguments
+///
+/// * `expression` - A reference to the `Expression` to serialize.
+///
+/// # Returns
+///
+/// * A `String` containing the JSON representation of the expression.
+///
+/// # Example
+///
+/// ```rust
+/// # use rengo::ast::expression::Expression;
+/// # use rengo::ast::json::to_json;
+/// let expr = Expression::Let(
+///     "x".to_string(),
+///     Box::new(Expression::Number(5, ())),
+///     Box::new(Expression::Identifier("x".to_string(), ())),
+///     ()
+/// );
+/// assert_eq!(to_json(&expr), r#"{"Let":["x",{"Number":5},{"Identifier":"x"}]}"#);
+/// ```
+pub fn to_json<T>(expression: &Expression<T>) -> String {
+    match expression {
+        Expression::Number(value, _) => format!("{{\"Number\":{}}}", value),
+        Expression::Increment(expr, _) => format!("{{\"Increment\":{}}}", to_json(expr)),
+        Expression::Decrement(expr, _) => format!("{{\"Decrement\":{}}}", to_json(expr)),
+        Expression::Let(identifier, value, body, _) => format!(
+            "{{\"Let\":[{},{},{}]}}",
+            string_to_json(identifier),
+            to_json(value),
+            to_json(body)
+        ),
+        Expression::LetMut(identifier, value, body, _) => format!(
+            "{{\"LetMut\":[{},{},{}]}}",
+            string_to_json(identifier),
+            to_json(value),
+            to_json(body)
+        ),
+        Expression::Declare(identifier, body, _) => {
+            format!("{{\"Declare\":[{},{}]}}", string_to_json(identifier), to_json(body))
+        }
+        Expression::Print(text, _) => format!("{{\"Print\":{}}}", string_to_json(text)),
+        Expression::Identifier(identifier, _) => {
+            format!("{{\"Identifier\":{}}}", string_to_json(identifier))
+        }
+        Expression::Binary(operator, lhs, rhs, _) => format!(
+            "{{\"Binary\":[\"{:?}\",{},{}]}}",
+            operator,
+            to_json(lhs),
+            to_json(rhs)
+        ),
+        Expression::Sequence(statements, _) => format!(
+            "{{\"Sequence\":[{}]}}",
+            statements.iter().map(to_json).collect::<Vec<String>>().join(",")
+        ),
+        Expression::Assign(identifier, value, _) => {
+            format!("{{\"Assign\":[{},{}]}}", string_to_json(identifier), to_json(value))
+        }
+        Expression::If(condition, then, otherwise, _) => format!(
+            "{{\"If\":[{},{},{}]}}",
+            to_json(condition),
+            to_json(then),
+            to_json(otherwise)
+        ),
+        Expression::While(condition, body, _) => {
+            format!("{{\"While\":[{},{}]}}", to_json(condition), to_json(body))
+        }
+        Expression::Assert(condition, _) => format!("{{\"Assert\":{}}}", to_json(condition)),
+        Expression::Return(value, _) => format!("{{\"Return\":{}}}", to_json(value)),
+        Expression::Tuple(elements, _) => format!(
+            "{{\"Tuple\":[{}]}}",
+            elements.iter().map(to_json).collect::<Vec<String>>().join(",")
+        ),
+        Expression::LetTuple(identifiers, value, body, _) => format!(
+            "{{\"LetTuple\":[[{}],{},{}]}}",
+            identifiers.iter().map(|identifier| string_to_json(identifier)).collect::<Vec<String>>().join(","),
+            to_json(value),
+            to_json(body)
+        ),
+        Expression::Array(elements, _) => format!(
+            "{{\"Array\":[{}]}}",
+            elements.iter().map(to_json).collect::<Vec<String>>().join(",")
+        ),
+        Expression::Index(array, index, _) => format!("{{\"Index\":[{},{}]}}", to_json(array), to_json(index)),
+        Expression::Func(function, rest, _) => format!(
+            "{{\"Func\":[{},[{}],{},{}]}}",
+            string_to_json(&function.name),
+            function.parameters.iter().map(|parameter| string_to_json(parameter)).collect::<Vec<String>>().join(","),
+            to_json(&function.body),
+            to_json(rest)
+        ),
+        Expression::Call(name, arguments, _) => format!(
+            "{{\"Call\":[{},[{}]]}}",
+            string_to_json(name),
+            arguments.iter().map(to_json).collect::<Vec<String>>().join(",")
+        ),
+    }
+}
+
+/// Converts a string into a quoted JSON string literal, escaping it as needed.
+pub(crate) fn string_to_json(string: &str) -> String {
+    let mut json = String::from("\"");
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use expectest::prelude::*;
+    use super::*;
+    use crate::parser::parse::parse;
+    use crate::parser::tokenize::tokenize;
+
+    mod to_json {
+        use super::*;
+
+        #[test]
+        fn serializes_let_with_increment() {
+            let expr = parse(&tokenize("let x = 5; x++").unwrap()).unwrap();
+            expect!(to_json(&expr)).to(be_equal_to(
+                r#"{"Let":["x",{"Number":5},{"Increment":{"Identifier":"x"}}]}"#
+            ));
+        }
+
+        #[test]
+        fn serializes_declarations() {
+            let expr = parse(&tokenize("let mut x; x = 1").unwrap()).unwrap();
+            expect!(to_json(&expr)).to(be_equal_to(r#"{"Declare":["x",{"Assign":["x",{"Number":1}]}]}"#));
+        }
+
+        #[test]
+        fn serializes_decrement_and_negative_numbers() {
+            let expr = parse(&tokenize("-5--").unwrap()).unwrap();
+            expect!(to_json(&expr)).to(be_equal_to(r#"{"Decrement":{"Number":-5}}"#));
+        }
+
+        #[test]
+        fn serializes_binary_operators() {
+            let expr = parse(&tokenize("1 << 4").unwrap()).unwrap();
+            expect!(to_json(&expr)).to(be_equal_to(
+                r#"{"Binary":["ShiftLeft",{"Number":1},{"Number":4}]}"#
+            ));
+        }
+
+        #[test]
+        fn serializes_sequences() {
+            let expr = parse(&tokenize("1; x").unwrap()).unwrap();
+            expect!(to_json(&expr)).to(be_equal_to(r#"{"Sequence":[{"Number":1},{"Identifier":"x"}]}"#));
+        }
+
+        #[test]
+        fn serializes_tuples() {
+            let expr = parse(&tokenize("let (x, y) = (1, 2); y").unwrap()).unwrap();
+            expect!(to_json(&expr)).to(be_equal_to(
+                r#"{"LetTuple":[["x","y"],{"Tuple":[{"Number":1},{"Number":2}]},{"Identifier":"y"}]}"#
+            ));
+        }
+
+        #[test]
+        fn serializes_arrays() {
+            let expr = parse(&tokenize("[1, 2][0]").unwrap()).unwrap();
+            expect!(to_json(&expr)).to(be_equal_to(r#"{"Index":[{"Array":[{"Number":1},{"Number":2}]},{"Number":0}]}"#));
+        }
+
+        #[test]
+        fn serializes_assertions() {
+            let expr = parse(&tokenize("assert(1 > 0)").unwrap()).unwrap();
+            expect!(to_json(&expr)).to(be_equal_to(
+                r#"{"Assert":{"Binary":["Greater",{"Number":1},{"Number":0}]}}"#
+            ));
+        }
+
+        #[test]
+        fn serializes_prints() {
+            let expr = parse(&tokenize("print(\"a\\b\")").unwrap()).unwrap();
+            expect!(to_json(&expr)).to(be_equal_to(r#"{"Print":"a\\b"}"#));
+        }
+    }
+
+    mod string_to_json {
+        use super::*;
+
+        #[test]
+        fn escapes_special_characters() {
+            expect!(string_to_json("a\"b\\c\n")).to(be_equal_to(r#""a\"b\\c\u000a""#));
+        }
+    }
+}
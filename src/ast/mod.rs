@@ -1,8 +1,9 @@
-pub(crate) mod expression;
-pub(crate) mod annotate;
-pub(crate) mod number;
-pub(crate) mod decrement;
-pub(crate) mod increment;
-pub(crate) mod r#if;
-pub(crate) mod identifier;
-pub(crate) mod r#let;
+#[cfg(test)]
+pub(crate) mod arbitrary;
+pub mod display;
+pub mod expression;
+pub mod fold;
+pub mod json;
+pub mod node_id;
+pub mod operator;
+pub mod visit;
@@ -0,0 +1,90 @@
+use crate::ast::expression::Expression;
+use crate::parser::span::{Span, SourceSpan};
+
+/// Identifies a node of an abstract syntax tree (AST).
+///
+/// Passes that need to attach data to specific nodes, like lints or optimizations, can key maps
+/// by `NodeId` after numbering a tree with `number_nodes`. IDs are only unique within the tree
+/// they were assigned in.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct NodeId(pub u32);
+
+impl SourceSpan for NodeId {
+    fn span(&self) -> Option<Span> {
+        None
+    }
+}
+
+/// Replaces the metadata of every node with a distinct `NodeId`.
+///
+/// IDs are assigned in increasing order from `NodeId(0)`, visiting each node before its children
+/// and the children in evaluation order, so the root always gets `NodeId(0)`.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::ast::node_id::number_nodes;
+/// # use rengo::ast::expression::Expression;
+/// # use rengo::ast::node_id::NodeId;
+/// let expr = number_nodes(Expression::Increment(Box::new(Expression::Number(1, ())), ()));
+/// assert_eq!(expr, Expression::Increment(Box::new(Expression::Number(1, NodeId(1))), NodeId(0)));
+/// ```
+pub fn number_nodes<T>(expression: Expression<T>) -> Expression<NodeId> {
+    let mut next = 0;
+    expression.map_metadata(&mut |_| {
+        let id = NodeId(next);
+        next += 1;
+        id
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeSet;
+    use expectest::prelude::*;
+    use proptest::prelude::*;
+    use crate::ast::arbitrary;
+    use crate::ast::visit::{walk_expression, Visitor};
+    use crate::parser::parse::parse_with_ids;
+    use crate::parser::tokenize::tokenize;
+    use super::*;
+
+    /// Collects the ID of every node, counting how many nodes there are.
+    #[derive(Default)]
+    struct CollectIds {
+        ids: BTreeSet<NodeId>,
+        nodes: usize,
+    }
+
+    impl Visitor<NodeId> for CollectIds {
+        fn visit_expression(&mut self, expression: &Expression<NodeId>) {
+            self.ids.insert(*expression.metadata());
+            self.nodes += 1;
+            walk_expression(self, expression);
+        }
+    }
+
+    mod number_nodes {
+        use super::*;
+
+        #[test]
+        fn it_gives_every_parsed_node_a_distinct_id() {
+            let expr = parse_with_ids(&tokenize("let x = 1 | 2; assert(x > 0); max(x, 3)++").unwrap()).unwrap();
+            let mut collector = CollectIds::default();
+            collector.visit_expression(&expr);
+            expect!(collector.nodes).to(be_equal_to(13));
+            expect!(collector.ids.len()).to(be_equal_to(collector.nodes));
+            expect!(expr.metadata()).to(be_equal_to(&NodeId(0)));
+        }
+
+        proptest!(
+            #[test]
+            fn it_keeps_the_tree_apart_from_the_ids(expr in arbitrary::expression(4)) {
+                let numbered = number_nodes(expr.clone());
+                let mut collector = CollectIds::default();
+                collector.visit_expression(&numbered);
+                expect!(collector.ids.len()).to(be_equal_to(collector.nodes));
+                expect!(numbered.map_metadata(&mut |_| ())).to(be_equal_to(expr));
+            }
+        );
+    }
+}
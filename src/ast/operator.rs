@@ -0,0 +1,65 @@
+/// Represents a binary operator in an abstract syntax tree (AST).
+///
+/// The `BinaryOperator` enum identifies the operation performed by an `Expression::Binary` node on
+/// its two operands. Every operator works on 64-bit signed integers.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BinaryOperator {
+    /// The addition operator (`+`).
+    ///
+    /// Wraps around on overflow, matching the behavior of the x86-64 `add` instruction.
+    Add,
+
+    /// The subtraction operator (`-`).
+    ///
+    /// Wraps around on overflow, matching the behavior of the x86-64 `sub` instruction.
+    Subtract,
+
+    /// The bitwise AND operator (`&`).
+    BitAnd,
+
+    /// The bitwise OR operator (`|`).
+    BitOr,
+
+    /// The bitwise XOR operator (`^`).
+    BitXor,
+
+    /// The left shift operator (`<<`).
+    ///
+    /// Only the lowest six bits of the shift count are used, matching the behavior of the x86-64
+    /// `shl` instruction, so `1 << 64` evaluates to `1`.
+    ShiftLeft,
+
+    /// The arithmetic right shift operator (`>>`).
+    ///
+    /// The sign bit is preserved while shifting, so `-1 >> 1` evaluates to `-1`. As with
+    /// `ShiftLeft`, only the lowest six bits of the shift count are used.
+    ShiftRight,
+
+    /// The less-than operator (`<`).
+    ///
+    /// Compares its operands as signed integers, evaluating to `1` if the left one is smaller and
+    /// to `0` otherwise.
+    Less,
+
+    /// The greater-than operator (`>`).
+    ///
+    /// Compares its operands as signed integers, evaluating to `1` if the left one is greater and
+    /// to `0` otherwise.
+    Greater,
+
+    /// The equality operator (`==`).
+    ///
+    /// Evaluates to `1` if its operands are equal and to `0` otherwise.
+    Equal,
+
+    /// The `min` built-in (`min(a, b)`).
+    ///
+    /// Evaluates to the smaller of its operands, compared as signed integers. It is written as a
+    /// call rather than infix, but like any other operator it takes exactly two operands.
+    Min,
+
+    /// The `max` built-in (`max(a, b)`).
+    ///
+    /// Evaluates to the greater of its operands, compared as signed integers.
+    Max,
+}
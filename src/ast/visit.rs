@@ -0,0 +1,128 @@
+use crate::ast::expression::Expression;
+
+/// A read-only pass over an `Expression` tree.
+///
+/// Implementors override `visit_expression` to inspect the nodes they care about, and call
+/// `walk_expression` to keep descending into the children. The default implementation simply
+/// visits every node, so a visitor only has to handle what it is interested in.
+///
+/// # Example
+///
+/// ```rust
+/// # use rengo::ast::visit::Visitor;
+/// # use rengo::ast::visit::walk_expression;
+/// # use rengo::ast::expression::Expression;
+/// struct CountNumbers(usize);
+///
+/// impl<T> Visitor<T> for CountNumbers {
+///     fn visit_expression(&mut self, expression: &Expression<T>) {
+///         if let Expression::Number(..) = expression {
+///             self.0 += 1;
+///         }
+///         walk_expression(self, expression);
+///     }
+/// }
+/// ```
+pub trait Visitor<T> {
+    /// Visits a node of the tree. By default, it visits the node's children in evaluation order.
+    fn visit_expression(&mut self, expression: &Expression<T>) {
+        walk_expression(self, expression);
+    }
+}
+
+/// Visits the children of an `Expression`, in the order they are evaluated.
+///
+/// ## Parameters:
+/// - `visitor`: The visitor to call on each child.
+/// - `expression`: The node whose children are visited. The node itself is not visited.
+pub fn walk_expression<T, V: Visitor<T> + ?Sized>(visitor: &mut V, expression: &Expression<T>) {
+    match expression {
+        Expression::Number(..) | Expression::Identifier(..) | Expression::Print(..) => {}
+        Expression::Increment(expr, _) | Expression::Decrement(expr, _) => visitor.visit_expression(expr),
+        Expression::Let(_, value, body, _)
+        | Expression::LetMut(_, value, body, _)
+        | Expression::LetTuple(_, value, body, _) => {
+            visitor.visit_expression(value);
+            visitor.visit_expression(body);
+        }
+        Expression::Binary(_, lhs, rhs, _) | Expression::Index(lhs, rhs, _) => {
+            visitor.visit_expression(lhs);
+            visitor.visit_expression(rhs);
+        }
+        Expression::Sequence(statements, _)
+        | Expression::Tuple(statements, _)
+        | Expression::Array(statements, _)
+        | Expression::Call(_, statements, _) => {
+            for statement in statements {
+                visitor.visit_expression(statement);
+            }
+        }
+        Expression::Assign(_, value, _)
+        | Expression::Declare(_, value, _)
+        | Expression::Assert(value, _)
+        | Expression::Return(value, _) => {
+            visitor.visit_expression(value)
+        }
+        Expression::If(condition, then, otherwise, _) => {
+            visitor.visit_expression(condition);
+            visitor.visit_expression(then);
+            visitor.visit_expression(otherwise);
+        }
+        Expression::While(condition, body, _) => {
+            visitor.visit_expression(condition);
+            visitor.visit_expression(body);
+        }
+        Expression::Func(function, rest, _) => {
+            visitor.visit_expression(&function.body);
+            visitor.visit_expression(rest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use expectest::prelude::*;
+    use crate::parser::parse::parse;
+    use crate::parser::tokenize::tokenize;
+    use super::*;
+
+    struct CountNumbers(usize);
+
+    impl<T> Visitor<T> for CountNumbers {
+        fn visit_expression(&mut self, expression: &Expression<T>) {
+            if let Expression::Number(..) = expression {
+                self.0 += 1;
+            }
+            walk_expression(self, expression);
+        }
+    }
+
+    struct CollectIdentifiers(Vec<String>);
+
+    impl<T> Visitor<T> for CollectIdentifiers {
+        fn visit_expression(&mut self, expression: &Expression<T>) {
+            if let Expression::Identifier(name, _) = expression {
+                self.0.push(name.clone());
+            }
+            walk_expression(self, expression);
+        }
+    }
+
+    #[test]
+    fn it_counts_number_nodes() {
+        let expr = parse(&tokenize("let x = 1; 2 | 3").unwrap()).unwrap();
+        let mut counter = CountNumbers(0);
+        counter.visit_expression(&expr);
+        expect!(counter.0).to(be_equal_to(3));
+    }
+
+    #[test]
+    fn it_visits_children_in_evaluation_order() {
+        let expr = parse(&tokenize("let x = y; (x & z)++").unwrap()).unwrap();
+        let mut collector = CollectIdentifiers(Vec::new());
+        collector.visit_expression(&expr);
+        expect!(collector.0).to(be_equal_to(["y", "x", "z"].map(|name| name.to_string()).to_vec()));
+    }
+}
@@ -0,0 +1,718 @@
+use rengo::asm::reg::Abi;
+use rengo::asm::style::LineEnding;
+use rengo::compiler::options::{CompileOptions, OptLevel};
+use rengo::compiler::lint::{LintOptions, DEFAULT_MAX_DEPTH};
+use crate::diagnostics::ColorChoice;
+use rengo::parser::token::Token;
+use rengo::parser::tokenize::{tokenize, TokenizeOptions};
+use rengo::radix::Radix;
+
+/// Command-line options accepted by the compiler.
+///
+/// The `Args` struct holds the result of parsing the process arguments with `parse_args`. Flags
+/// default to `false` and are enabled by passing them anywhere on the command line.
+#[derive(Debug, PartialEq, Default)]
+pub(crate) struct Args {
+    /// The paths of the files of the program to compile, in order. Their programs are joined
+    /// with `;`, so the bindings of each file are in scope in the files after it.
+    pub(crate) inputs: Vec<String>,
+
+    /// Print the program back in its canonical layout and stop, instead of compiling it
+    /// (`rengo fmt <input>`).
+    pub(crate) fmt: bool,
+
+    /// Print the parsed AST as JSON and stop, instead of compiling it (`--emit-ast-json`).
+    pub(crate) emit_ast_json: bool,
+
+    /// Print the tokens of the program with their positions as JSON and stop, instead of parsing
+    /// them (`--emit-tokens-json`).
+    pub(crate) emit_tokens_json: bool,
+
+    /// Print the generated assembly and stop, instead of assembling and linking it (`--emit-asm`).
+    pub(crate) emit_asm: bool,
+
+    /// Reject `let` bindings that shadow a name of the same scope (`--deny-shadowing`).
+    pub(crate) deny_shadowing: bool,
+
+    /// Emit each top-level statement under its own label (`--label-statements`).
+    pub(crate) label_statements: bool,
+
+    /// Check indices against the bounds of their arrays at runtime (`--sanitize`).
+    pub(crate) sanitize: bool,
+
+    /// The operating system to produce an executable for, instead of the host's
+    /// (`--target-os linux|macos|windows`).
+    pub(crate) target_os: Option<String>,
+
+    /// The radix the executable prints its result in (`--radix dec|hex`).
+    pub(crate) radix: Radix,
+
+    /// When to colorize error messages (`--color always|never|auto`).
+    pub(crate) color: ColorChoice,
+
+    /// Pad the code so that loops start at a multiple of this many bytes (`--align-loops <n>`).
+    ///
+    /// Reserved for now: the code generator rejects `while` loops, so the compiled code has no loop
+    /// labels for `align_loops` to pad, and the flag leaves it as it is.
+    pub(crate) align_loops: Option<usize>,
+
+    /// What to produce from the assembly (`--emit=exe|obj`), to run the program with the
+    /// interpreter instead (`--emit=run-interpreted`), or to lower it to LLVM IR
+    /// (`--emit=llvm-ir`).
+    pub(crate) emit: Emit,
+
+    /// Reject two numbers with no operator between them while tokenizing (`--strict-lexing`).
+    pub(crate) strict_lexing: bool,
+
+    /// Link a position-independent executable (`--pie`) or not (`--no-pie`), instead of the
+    /// linker's default.
+    pub(crate) pie: Option<bool>,
+
+    /// Print the compiled instructions, one `Debug`-formatted `Instruction` per line, before
+    /// rendering them as assembly (`--dump-ir`).
+    pub(crate) dump_ir: bool,
+
+    /// The last stage to run before stopping (`--stop-after=ir`), or `None` to run them all.
+    pub(crate) stop_after: Option<Stage>,
+
+    /// Remove the intermediate assembly and object files once the executable is linked
+    /// (`--clean`), instead of keeping them (`--keep-temps`, the default).
+    pub(crate) clean: bool,
+
+    /// Print the grammar of the language and stop (`--grammar`). No input is needed.
+    pub(crate) grammar: bool,
+
+    /// Print the description of an error code and stop (`--explain <code>`). No input is needed.
+    pub(crate) explain: Option<String>,
+
+    /// Report the wall-clock duration of each compilation phase on standard error (`--time`).
+    pub(crate) time: bool,
+
+    /// Print the comment lines at the start of the input, its documentation, before compiling it
+    /// (`--doc`).
+    pub(crate) doc: bool,
+
+    /// The characters that end each line of the generated assembly (`--line-ending lf|crlf`).
+    pub(crate) line_ending: LineEnding,
+
+    /// Treat warnings as errors, stopping before the program is compiled if there are any
+    /// (`--strict`).
+    pub(crate) strict: bool,
+
+    /// Only check that the program compiles, reporting its diagnostics without writing any output
+    /// (`--check`).
+    pub(crate) check: bool,
+
+    /// How much to optimize the program (`-O0`, `-O1`, `-O2`).
+    pub(crate) opt_level: OptLevel,
+
+    /// Make the executable also exit with its result (`--exit-code-mode=truncate|error`), or
+    /// `None` to always exit with `0`. With `--emit=run-interpreted`, the result is the exit status
+    /// either way, and `None` truncates it.
+    pub(crate) exit_code_mode: Option<ExitCodeMode>,
+
+    /// Warn when the program nests deeper than this (`--max-depth <n>`), instead of
+    /// `DEFAULT_MAX_DEPTH`.
+    pub(crate) max_depth: Option<usize>,
+
+    /// Names bound to a value around the program, in the order they were given
+    /// (`--define NAME=VALUE`, which can be repeated).
+    pub(crate) defines: Vec<(String, i64)>,
+
+    /// Refuse an input file larger than this many bytes (`--max-program-size <bytes>`), instead of
+    /// `DEFAULT_MAX_PROGRAM_SIZE`.
+    pub(crate) max_program_size: Option<u64>,
+}
+
+/// The largest input file read when `--max-program-size` is not given, in bytes.
+pub(crate) const DEFAULT_MAX_PROGRAM_SIZE: u64 = 4 * 1024 * 1024;
+
+/// The options `link` needs, selected by the command-line flags.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub(crate) struct LinkOptions {
+    /// The operating system to link for, as named by `std::env::consts::OS`.
+    pub(crate) os: String,
+
+    /// The radix the runtime prints the result in.
+    pub(crate) radix: Radix,
+
+    /// Whether to link a position-independent executable, or `None` for the linker's default.
+    pub(crate) pie: Option<bool>,
+
+    /// How the runtime turns the result into an exit code, or `None` to always exit with `0`.
+    pub(crate) exit_code_mode: Option<ExitCodeMode>,
+}
+
+/// How the runtime fits the 64-bit result into the 8 bits of an exit code the OS keeps.
+///
+/// The result is printed in full either way; the exit code is for callers that only look at it.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum ExitCodeMode {
+    /// Exit with the low 8 bits of the result, as the OS would keep them.
+    Truncate,
+
+    /// Exit with the result if it is between `0` and `254`, and otherwise report it on standard
+    /// error and exit with `rengo::runner::OUT_OF_RANGE_EXIT_CODE`, so a truncated value is never
+    /// mistaken for the result.
+    Error,
+}
+
+/// The final artifact the compiler produces.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub(crate) enum Emit {
+    /// An executable linked with the runtime in `src/main.c`, entered through `_start`.
+    #[default]
+    Exe,
+
+    /// An object file that is not linked, exporting `rengo_main` so it can be called from C.
+    Obj,
+
+    /// Nothing: the program is evaluated by the interpreter, which prints what it prints and its
+    /// result like the executable would, without needing `nasm` or `clang`.
+    RunInterpreted,
+
+    /// Textual LLVM IR defining `@main`, written to standard output instead of any file.
+    LlvmIr,
+}
+
+/// A stage of the compiler that `--stop-after` can stop at.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Stage {
+    /// Compiling to instructions, right before they are rendered as assembly.
+    Ir,
+}
+
+impl Args {
+    /// Returns the `CompileOptions` selected by the command-line flags.
+    pub(crate) fn compile_options(&self) -> CompileOptions {
+        CompileOptions {
+            deny_shadowing: self.deny_shadowing,
+            frame: true,
+            target_abi: Abi::for_os(self.target_os()),
+            label_statements: self.label_statements,
+            sanitize: self.sanitize,
+            ..CompileOptions::default()
+        }
+        .with_opt_level(self.opt_level)
+    }
+
+    /// Returns the `LinkOptions` selected by the command-line flags.
+    pub(crate) fn link_options(&self) -> LinkOptions {
+        LinkOptions {
+            os: self.target_os().to_string(),
+            radix: self.radix,
+            pie: self.pie,
+            exit_code_mode: self.exit_code_mode,
+        }
+    }
+
+    /// Returns the `LintOptions` selected by the command-line flags.
+    pub(crate) fn lint_options(&self) -> LintOptions {
+        LintOptions { max_depth: self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH) }
+    }
+
+    /// Returns the largest input file to read, in bytes.
+    pub(crate) fn max_program_size(&self) -> u64 {
+        self.max_program_size.unwrap_or(DEFAULT_MAX_PROGRAM_SIZE)
+    }
+
+    /// Returns the `TokenizeOptions` selected by the command-line flags.
+    pub(crate) fn tokenize_options(&self) -> TokenizeOptions {
+        TokenizeOptions { strict: self.strict_lexing }
+    }
+
+    /// Returns the symbol the generated code is entered through: `_start` for executables, and
+    /// `rengo_main` for objects (with the leading underscore C symbols get on macOS).
+    pub(crate) fn entry_symbol(&self) -> &'static str {
+        match (self.emit, self.target_os()) {
+            (Emit::Exe | Emit::RunInterpreted | Emit::LlvmIr, _) => "_start",
+            (Emit::Obj, "macos") => "_rengo_main",
+            (Emit::Obj, _) => "rengo_main",
+        }
+    }
+
+    /// Returns the operating system to target: the one given with `--target-os`, or the host's.
+    pub(crate) fn target_os(&self) -> &str {
+        self.target_os.as_deref().unwrap_or(std::env::consts::OS)
+    }
+}
+
+/// Parses the command-line arguments of the compiler.
+///
+/// ## Parameters:
+/// - `args`: The process arguments, including the program name as the first element.
+///
+/// ## Returns:
+/// A `Result` containing the parsed `Args`, or a `String` error message if an option is unknown or
+/// there is no input file (which is allowed with `--grammar` or `--explain`). Several input files
+/// make a single program, except with `rengo fmt` and `--emit-tokens-json`, which take only one.
+///
+/// A first argument of `fmt` selects the formatter instead of the compiler, as in
+/// `rengo fmt program.rg`; the options that follow are parsed the same way.
+///
+/// ## Example:
+/// ```ignore
+/// let args = vec!["rengo".to_string(), "--emit-ast-json".to_string(), "in.rg".to_string()];
+/// let parsed = parse_args(&args).unwrap();
+/// assert!(parsed.emit_ast_json);
+/// ```
+pub(crate) fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut inputs = Vec::new();
+    let mut args = args.iter().skip(1).peekable();
+    if args.peek().map(|arg| arg.as_str()) == Some("fmt") {
+        parsed.fmt = true;
+        args.next();
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--emit-ast-json" => parsed.emit_ast_json = true,
+            "--emit-asm" => parsed.emit_asm = true,
+            "--emit-tokens-json" => parsed.emit_tokens_json = true,
+            "--deny-shadowing" => parsed.deny_shadowing = true,
+            "--label-statements" => parsed.label_statements = true,
+            "--sanitize" => parsed.sanitize = true,
+            "--strict-lexing" => parsed.strict_lexing = true,
+            "--pie" => parsed.pie = Some(true),
+            "--no-pie" => parsed.pie = Some(false),
+            "--target-os" => parsed.target_os = Some(parse_target_os(args.next())?),
+            "--radix" => parsed.radix = parse_radix(args.next())?,
+            "--color" => parsed.color = parse_color(args.next())?,
+            "--emit=exe" => parsed.emit = Emit::Exe,
+            "--emit=obj" => parsed.emit = Emit::Obj,
+            "--emit=run-interpreted" => parsed.emit = Emit::RunInterpreted,
+            "--emit=llvm-ir" => parsed.emit = Emit::LlvmIr,
+            "--dump-ir" => parsed.dump_ir = true,
+            "--stop-after=ir" => parsed.stop_after = Some(Stage::Ir),
+            option if option.starts_with("--exit-code-mode=") => {
+                parsed.exit_code_mode = Some(parse_exit_code_mode(&option["--exit-code-mode=".len()..])?)
+            }
+            "--grammar" => parsed.grammar = true,
+            "--explain" => parsed.explain = Some(args.next().ok_or("Missing value for --explain")?.clone()),
+            "--clean" => parsed.clean = true,
+            "--keep-temps" => parsed.clean = false,
+            "--time" => parsed.time = true,
+            "--doc" => parsed.doc = true,
+            "--strict" => parsed.strict = true,
+            "--check" => parsed.check = true,
+            "-O0" => parsed.opt_level = OptLevel::O0,
+            "-O1" => parsed.opt_level = OptLevel::O1,
+            "-O2" => parsed.opt_level = OptLevel::O2,
+            option if option.starts_with("-O") => return Err(format!("Unknown optimization level: {}", option)),
+            "--align-loops" => parsed.align_loops = Some(parse_alignment(args.next())?),
+            "--max-depth" => parsed.max_depth = Some(parse_max_depth(args.next())?),
+            "--define" => parsed.defines.push(parse_define(args.next())?),
+            "--max-program-size" => parsed.max_program_size = Some(parse_max_program_size(args.next())?),
+            "--line-ending" => parsed.line_ending = parse_line_ending(args.next())?,
+            option if option.starts_with("--") => return Err(format!("Unknown option: {}", option)),
+            input => inputs.push(input.to_string()),
+        }
+    }
+    match inputs.as_slice() {
+        [] if parsed.grammar || parsed.explain.is_some() => {}
+        [_] => {}
+        [_, _, ..] if !parsed.fmt && !parsed.emit_tokens_json => {}
+        _ => return Err("Invalid number of arguments".to_string()),
+    }
+    parsed.inputs = inputs;
+    Ok(parsed)
+}
+
+/// Validates the value of the `--target-os` option.
+fn parse_target_os(value: Option<&String>) -> Result<String, String> {
+    match value.map(String::as_str) {
+        Some(os @ ("linux" | "macos" | "windows")) => Ok(os.to_string()),
+        Some(os) => Err(format!("Unsupported target OS: {} (expected linux, macos or windows)", os)),
+        None => Err("Missing value for --target-os".to_string()),
+    }
+}
+
+/// Validates the value of the `--radix` option.
+fn parse_radix(value: Option<&String>) -> Result<Radix, String> {
+    match value.map(String::as_str) {
+        Some("dec") => Ok(Radix::Decimal),
+        Some("hex") => Ok(Radix::Hex),
+        Some(radix) => Err(format!("Unsupported radix: {} (expected dec or hex)", radix)),
+        None => Err("Missing value for --radix".to_string()),
+    }
+}
+
+/// Validates the value of the `--exit-code-mode=` option.
+fn parse_exit_code_mode(value: &str) -> Result<ExitCodeMode, String> {
+    match value {
+        "truncate" => Ok(ExitCodeMode::Truncate),
+        "error" => Ok(ExitCodeMode::Error),
+        mode => Err(format!("Unsupported exit code mode: {} (expected truncate or error)", mode)),
+    }
+}
+
+/// Validates the value of the `--color` option.
+fn parse_color(value: Option<&String>) -> Result<ColorChoice, String> {
+    match value.map(String::as_str) {
+        Some("always") => Ok(ColorChoice::Always),
+        Some("never") => Ok(ColorChoice::Never),
+        Some("auto") => Ok(ColorChoice::Auto),
+        Some(color) => Err(format!("Unsupported color mode: {} (expected always, never or auto)", color)),
+        None => Err("Missing value for --color".to_string()),
+    }
+}
+
+/// Validates the value of the `--line-ending` option.
+fn parse_line_ending(value: Option<&String>) -> Result<LineEnding, String> {
+    match value.map(String::as_str) {
+        Some("lf") => Ok(LineEnding::Lf),
+        Some("crlf") => Ok(LineEnding::CrLf),
+        Some(ending) => Err(format!("Unsupported line ending: {} (expected lf or crlf)", ending)),
+        None => Err("Missing value for --line-ending".to_string()),
+    }
+}
+
+/// Validates the value of the `--align-loops` option, which must be a power of two.
+fn parse_alignment(value: Option<&String>) -> Result<usize, String> {
+    let value = value.ok_or("Missing value for --align-loops")?;
+    match value.parse::<usize>() {
+        Ok(alignment) if alignment.is_power_of_two() => Ok(alignment),
+        _ => Err(format!("Invalid alignment: {} (expected a power of two)", value)),
+    }
+}
+
+/// Validates the value of the `--max-depth` option.
+fn parse_max_depth(value: Option<&String>) -> Result<usize, String> {
+    let value = value.ok_or("Missing value for --max-depth")?;
+    value.parse::<usize>().map_err(|_| format!("Invalid depth: {} (expected a number)", value))
+}
+
+/// Validates the value of the `--max-program-size` option.
+fn parse_max_program_size(value: Option<&String>) -> Result<u64, String> {
+    let value = value.ok_or("Missing value for --max-program-size")?;
+    value.parse::<u64>().map_err(|_| format!("Invalid size: {} (expected a number of bytes)", value))
+}
+
+/// Validates the value of the `--define` option, a name that could be bound by `let` and a number.
+fn parse_define(value: Option<&String>) -> Result<(String, i64), String> {
+    let value = value.ok_or("Missing value for --define")?;
+    let invalid = || format!("Invalid definition: {} (expected NAME=VALUE)", value);
+    let (name, number) = value.split_once('=').ok_or_else(invalid)?;
+    match (tokenize(name), number.parse::<i64>()) {
+        (Ok(tokens), Ok(number)) if tokens == [Token::Identifier(name.to_string())] => Ok((name.to_string(), number)),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expectest::prelude::*;
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        std::iter::once("rengo").chain(args.iter().copied()).map(String::from).collect()
+    }
+
+    mod parse_args {
+        use super::*;
+
+        #[test]
+        fn parses_input() {
+            let parsed = parse_args(&args(&["program.rg"])).unwrap();
+            expect!(parsed).to(be_equal_to(Args { inputs: vec!["program.rg".to_string()], ..Args::default() }));
+        }
+
+        #[test]
+        fn parses_emit_ast_json() {
+            let parsed = parse_args(&args(&["--emit-ast-json", "program.rg"])).unwrap();
+            expect!(parsed.emit_ast_json).to(be_true());
+        }
+
+        #[test]
+        fn parses_emit_tokens_json() {
+            let parsed = parse_args(&args(&["--emit-tokens-json", "program.rg"])).unwrap();
+            expect!(parsed.emit_tokens_json).to(be_true());
+        }
+
+        #[test]
+        fn parses_emit_asm() {
+            let parsed = parse_args(&args(&["--emit-asm", "program.rg"])).unwrap();
+            expect!(parsed.emit_asm).to(be_true());
+        }
+
+        #[test]
+        fn parses_deny_shadowing() {
+            let parsed = parse_args(&args(&["program.rg", "--deny-shadowing"])).unwrap();
+            expect!(parsed.compile_options()).to(be_equal_to(CompileOptions {
+                deny_shadowing: true,
+                frame: true,
+                ..CompileOptions::default()
+            }));
+        }
+
+        #[test]
+        fn targets_the_calling_convention_of_the_os() {
+            let parsed = parse_args(&args(&["--target-os", "windows", "program.rg"])).unwrap();
+            expect!(parsed.compile_options().target_abi).to(be_equal_to(Abi::Win64));
+            let parsed = parse_args(&args(&["--target-os", "linux", "program.rg"])).unwrap();
+            expect!(parsed.compile_options().target_abi).to(be_equal_to(Abi::SystemV));
+        }
+
+        #[test]
+        fn parses_label_statements() {
+            let parsed = parse_args(&args(&["--label-statements", "program.rg"])).unwrap();
+            expect!(parsed.compile_options().label_statements).to(be_true());
+        }
+
+        #[test]
+        fn parses_sanitize() {
+            let parsed = parse_args(&args(&["--sanitize", "program.rg"])).unwrap();
+            expect!(parsed.compile_options().sanitize).to(be_true());
+            expect!(parse_args(&args(&["program.rg"])).unwrap().compile_options().sanitize).to(be_false());
+        }
+
+        #[test]
+        fn parses_target_os() {
+            let parsed = parse_args(&args(&["--target-os", "windows", "program.rg"])).unwrap();
+            expect!(parsed.target_os()).to(be_equal_to("windows"));
+            let nasm_args = rengo::assemble::nasm_args(
+                std::path::Path::new("out.asm"),
+                std::path::Path::new("out.obj"),
+                parsed.target_os(),
+            ).unwrap();
+            expect!(nasm_args[1].as_str()).to(be_equal_to("win64"));
+        }
+
+        #[test]
+        fn defaults_to_the_host_os() {
+            let parsed = parse_args(&args(&["program.rg"])).unwrap();
+            expect!(parsed.target_os()).to(be_equal_to(std::env::consts::OS));
+        }
+
+        #[test]
+        fn fails_on_invalid_target_os() {
+            let result = parse_args(&args(&["--target-os", "plan9", "program.rg"]));
+            expect!(result).to(be_err());
+            let result = parse_args(&args(&["program.rg", "--target-os"]));
+            expect!(result).to(be_err().value("Missing value for --target-os".to_string()));
+        }
+
+        #[test]
+        fn parses_exit_code_mode() {
+            let parsed = parse_args(&args(&["--exit-code-mode=error", "program.rg"])).unwrap();
+            expect!(parsed.link_options().exit_code_mode).to(be_some().value(ExitCodeMode::Error));
+            let parsed = parse_args(&args(&["--exit-code-mode=truncate", "program.rg"])).unwrap();
+            expect!(parsed.exit_code_mode).to(be_some().value(ExitCodeMode::Truncate));
+            expect!(parse_args(&args(&["program.rg"])).unwrap().exit_code_mode).to(be_none());
+            let result = parse_args(&args(&["--exit-code-mode=wrap", "program.rg"]));
+            expect!(result).to(be_err().value("Unsupported exit code mode: wrap (expected truncate or error)".to_string()));
+        }
+
+        #[test]
+        fn parses_radix() {
+            let parsed = parse_args(&args(&["--radix", "hex", "program.rg"])).unwrap();
+            expect!(parsed.radix).to(be_equal_to(Radix::Hex));
+            let parsed = parse_args(&args(&["program.rg"])).unwrap();
+            expect!(parsed.radix).to(be_equal_to(Radix::Decimal));
+        }
+
+        #[test]
+        fn fails_on_invalid_radix() {
+            let result = parse_args(&args(&["--radix", "octal", "program.rg"]));
+            expect!(result).to(be_err().value("Unsupported radix: octal (expected dec or hex)".to_string()));
+            let result = parse_args(&args(&["program.rg", "--radix"]));
+            expect!(result).to(be_err().value("Missing value for --radix".to_string()));
+        }
+
+        #[test]
+        fn parses_color() {
+            let parsed = parse_args(&args(&["--color", "never", "program.rg"])).unwrap();
+            expect!(parsed.color).to(be_equal_to(ColorChoice::Never));
+            let parsed = parse_args(&args(&["program.rg"])).unwrap();
+            expect!(parsed.color).to(be_equal_to(ColorChoice::Auto));
+            let result = parse_args(&args(&["--color", "sometimes", "program.rg"]));
+            expect!(result).to(be_err());
+        }
+
+        #[test]
+        fn parses_strict() {
+            expect!(parse_args(&args(&["--strict", "program.rg"])).unwrap().strict).to(be_true());
+            expect!(parse_args(&args(&["program.rg"])).unwrap().strict).to(be_false());
+        }
+
+        #[test]
+        fn parses_opt_level() {
+            expect!(parse_args(&args(&["program.rg"])).unwrap().opt_level).to(be_equal_to(OptLevel::O0));
+            let parsed = parse_args(&args(&["-O2", "program.rg"])).unwrap();
+            expect!(parsed.opt_level).to(be_equal_to(OptLevel::O2));
+            expect!(parsed.compile_options().linear_scan).to(be_true());
+            let parsed = parse_args(&args(&["-O1", "program.rg"])).unwrap();
+            expect!(parsed.compile_options().linear_scan).to(be_false());
+            let result = parse_args(&args(&["-O3", "program.rg"]));
+            expect!(result).to(be_err().value("Unknown optimization level: -O3".to_string()));
+        }
+
+        #[test]
+        fn parses_check() {
+            expect!(parse_args(&args(&["--check", "program.rg"])).unwrap().check).to(be_true());
+            expect!(parse_args(&args(&["program.rg"])).unwrap().check).to(be_false());
+        }
+
+        #[test]
+        fn parses_line_ending() {
+            let parsed = parse_args(&args(&["--line-ending", "crlf", "program.rg"])).unwrap();
+            expect!(parsed.line_ending).to(be_equal_to(LineEnding::CrLf));
+            let parsed = parse_args(&args(&["program.rg"])).unwrap();
+            expect!(parsed.line_ending).to(be_equal_to(LineEnding::Lf));
+            let result = parse_args(&args(&["--line-ending", "cr", "program.rg"]));
+            expect!(result).to(be_err().value("Unsupported line ending: cr (expected lf or crlf)".to_string()));
+        }
+
+        #[test]
+        fn parses_max_depth() {
+            let parsed = parse_args(&args(&["--max-depth", "8", "program.rg"])).unwrap();
+            expect!(parsed.lint_options().max_depth).to(be_equal_to(8));
+            let parsed = parse_args(&args(&["program.rg"])).unwrap();
+            expect!(parsed.lint_options().max_depth).to(be_equal_to(DEFAULT_MAX_DEPTH));
+            let result = parse_args(&args(&["--max-depth", "deep", "program.rg"]));
+            expect!(result).to(be_err().value("Invalid depth: deep (expected a number)".to_string()));
+            let result = parse_args(&args(&["program.rg", "--max-depth"]));
+            expect!(result).to(be_err().value("Missing value for --max-depth".to_string()));
+        }
+
+        #[test]
+        fn parses_max_program_size() {
+            let parsed = parse_args(&args(&["--max-program-size", "1024", "program.rg"])).unwrap();
+            expect!(parsed.max_program_size()).to(be_equal_to(1024));
+            let parsed = parse_args(&args(&["program.rg"])).unwrap();
+            expect!(parsed.max_program_size()).to(be_equal_to(DEFAULT_MAX_PROGRAM_SIZE));
+            let result = parse_args(&args(&["--max-program-size", "-1", "program.rg"]));
+            expect!(result).to(be_err().value("Invalid size: -1 (expected a number of bytes)".to_string()));
+            let result = parse_args(&args(&["program.rg", "--max-program-size"]));
+            expect!(result).to(be_err().value("Missing value for --max-program-size".to_string()));
+        }
+
+        #[test]
+        fn parses_defines_in_order() {
+            let parsed = parse_args(&args(&["--define", "x=41", "program.rg", "--define", "y=-2"])).unwrap();
+            expect!(parsed.defines).to(be_equal_to(vec![("x".to_string(), 41), ("y".to_string(), -2)]));
+            for definition in ["x", "x=", "=1", "x=one", "let=1", "x y=1"] {
+                let result = parse_args(&args(&["--define", definition, "program.rg"]));
+                expect!(result).to(be_err().value(format!("Invalid definition: {} (expected NAME=VALUE)", definition)));
+            }
+            let result = parse_args(&args(&["program.rg", "--define"]));
+            expect!(result).to(be_err().value("Missing value for --define".to_string()));
+        }
+
+        #[test]
+        fn parses_align_loops() {
+            let parsed = parse_args(&args(&["--align-loops", "16", "program.rg"])).unwrap();
+            expect!(parsed.align_loops).to(be_some().value(16));
+            let result = parse_args(&args(&["--align-loops", "12", "program.rg"]));
+            expect!(result).to(be_err().value("Invalid alignment: 12 (expected a power of two)".to_string()));
+            let result = parse_args(&args(&["program.rg", "--align-loops"]));
+            expect!(result).to(be_err().value("Missing value for --align-loops".to_string()));
+        }
+
+        #[test]
+        fn parses_emit() {
+            let parsed = parse_args(&args(&["--emit=obj", "program.rg"])).unwrap();
+            expect!(parsed.emit).to(be_equal_to(Emit::Obj));
+            expect!(parsed.entry_symbol()).to(be_equal_to(if cfg!(target_os = "macos") { "_rengo_main" } else { "rengo_main" }));
+            let parsed = parse_args(&args(&["--emit=run-interpreted", "program.rg"])).unwrap();
+            expect!(parsed.emit).to(be_equal_to(Emit::RunInterpreted));
+            let parsed = parse_args(&args(&["--emit=llvm-ir", "program.rg"])).unwrap();
+            expect!(parsed.emit).to(be_equal_to(Emit::LlvmIr));
+            let parsed = parse_args(&args(&["program.rg"])).unwrap();
+            expect!(parsed.emit).to(be_equal_to(Emit::Exe));
+            expect!(parsed.entry_symbol()).to(be_equal_to("_start"));
+        }
+
+        #[test]
+        fn parses_strict_lexing() {
+            let parsed = parse_args(&args(&["--strict-lexing", "program.rg"])).unwrap();
+            expect!(parsed.tokenize_options()).to(be_equal_to(TokenizeOptions { strict: true }));
+            let parsed = parse_args(&args(&["program.rg"])).unwrap();
+            expect!(parsed.tokenize_options()).to(be_equal_to(TokenizeOptions::default()));
+        }
+
+        #[test]
+        fn parses_pie() {
+            expect!(parse_args(&args(&["--pie", "program.rg"])).unwrap().link_options().pie).to(be_some().value(true));
+            expect!(parse_args(&args(&["--no-pie", "program.rg"])).unwrap().link_options().pie).to(be_some().value(false));
+            expect!(parse_args(&args(&["program.rg"])).unwrap().link_options().pie).to(be_none());
+        }
+
+        #[test]
+        fn parses_dump_ir() {
+            let parsed = parse_args(&args(&["--dump-ir", "--stop-after=ir", "program.rg"])).unwrap();
+            expect!(parsed.dump_ir).to(be_true());
+            expect!(parsed.stop_after).to(be_some().value(Stage::Ir));
+            let parsed = parse_args(&args(&["program.rg"])).unwrap();
+            expect!(parsed.dump_ir).to(be_false());
+            expect!(parsed.stop_after).to(be_none());
+        }
+
+        #[test]
+        fn parses_clean() {
+            expect!(parse_args(&args(&["--clean", "program.rg"])).unwrap().clean).to(be_true());
+            expect!(parse_args(&args(&["--clean", "--keep-temps", "program.rg"])).unwrap().clean).to(be_false());
+            expect!(parse_args(&args(&["program.rg"])).unwrap().clean).to(be_false());
+        }
+
+        #[test]
+        fn parses_grammar_without_input() {
+            expect!(parse_args(&args(&["--grammar"])).unwrap().grammar).to(be_true());
+            expect!(parse_args(&args(&["--grammar", "program.rg"])).unwrap().grammar).to(be_true());
+        }
+
+        #[test]
+        fn parses_explain_without_input() {
+            expect!(parse_args(&args(&["--explain", "E001"])).unwrap().explain).to(be_some().value("E001".to_string()));
+            expect!(parse_args(&args(&["--explain"]))).to(be_err().value("Missing value for --explain".to_string()));
+        }
+
+        #[test]
+        fn parses_time() {
+            expect!(parse_args(&args(&["--time", "program.rg"])).unwrap().time).to(be_true());
+            expect!(parse_args(&args(&["program.rg"])).unwrap().time).to(be_false());
+        }
+
+        #[test]
+        fn parses_doc() {
+            expect!(parse_args(&args(&["--doc", "program.rg"])).unwrap().doc).to(be_true());
+            expect!(parse_args(&args(&["program.rg"])).unwrap().doc).to(be_false());
+        }
+
+        #[test]
+        fn parses_the_fmt_subcommand() {
+            let parsed = parse_args(&args(&["fmt", "program.rg"])).unwrap();
+            expect!(parsed.fmt).to(be_true());
+            expect!(parsed.inputs).to(be_equal_to(vec!["program.rg".to_string()]));
+            expect!(parse_args(&args(&["program.rg"])).unwrap().fmt).to(be_false());
+            // Only the first argument names a subcommand
+            let parsed = parse_args(&args(&["program.rg", "fmt"])).unwrap();
+            expect!(parsed.fmt).to(be_false());
+            expect!(parsed.inputs).to(be_equal_to(vec!["program.rg".to_string(), "fmt".to_string()]));
+        }
+
+        #[test]
+        fn fails_on_unknown_option() {
+            let result = parse_args(&args(&["--unknown", "program.rg"]));
+            expect!(result).to(be_err().value("Unknown option: --unknown".to_string()));
+        }
+
+        #[test]
+        fn fails_without_input() {
+            let result = parse_args(&args(&[]));
+            expect!(result).to(be_err());
+        }
+
+        #[test]
+        fn parses_several_inputs_in_order() {
+            let parsed = parse_args(&args(&["a.rg", "--strict", "b.rg", "c.rg"])).unwrap();
+            expect!(parsed.inputs).to(be_equal_to(vec!["a.rg".to_string(), "b.rg".to_string(), "c.rg".to_string()]));
+        }
+
+        #[test]
+        fn fails_with_several_inputs_to_a_single_file_mode() {
+            expect!(parse_args(&args(&["fmt", "a.rg", "b.rg"]))).to(be_err());
+            expect!(parse_args(&args(&["--emit-tokens-json", "a.rg", "b.rg"]))).to(be_err());
+        }
+    }
+}
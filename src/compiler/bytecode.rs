@@ -0,0 +1,264 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::ast::expression::{Expression, DISCARD};
+use crate::compiler::error::CompileError;
+use crate::compiler::llvm::construct;
+use crate::compiler::suggest::closest_name;
+
+/// An instruction of the stack machine run by `run_bytecode`.
+///
+/// The machine has a stack of values and a row of numbered slots, which hold the values of
+/// variables. Every program leaves its value alone on the stack.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Op {
+    /// Pushes the constant.
+    PushConst(i64),
+
+    /// Adds one to the value on top of the stack, wrapping on overflow.
+    Inc,
+
+    /// Subtracts one from the value on top of the stack, wrapping on overflow.
+    Dec,
+
+    /// Pushes the value of the slot.
+    Load(usize),
+
+    /// Pops the value on top of the stack into the slot.
+    Store(usize),
+}
+
+/// Compiles a program into bytecode for `run_bytecode`, as a portable alternative to the native
+/// code of `compile_expression`.
+///
+/// Each `let` stores its value in the slot after those of the bindings around it, so a shadowing
+/// binding gets a slot of its own, and a slot is reused once the binding it held goes out of scope.
+///
+/// Only numbers, increments, decrements, `let` bindings and identifiers are compiled so far, like
+/// in `compile_llvm_ir`.
+///
+/// ## Errors:
+/// - `CompileError::UndefinedVariable` if an identifier is not bound by an enclosing `let`.
+/// - `CompileError::Unsupported` for any other construct.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parser::parse::parse;
+/// # use rengo::parser::tokenize::tokenize;
+/// # use rengo::compiler::bytecode::compile_to_bytecode;
+/// # use rengo::compiler::bytecode::Op;
+/// let program = parse(&tokenize("let x = 41; x++").unwrap()).unwrap();
+/// assert_eq!(
+///     compile_to_bytecode(&program).unwrap(),
+///     vec![Op::PushConst(41), Op::Store(0), Op::Load(0), Op::Inc]
+/// );
+/// ```
+pub fn compile_to_bytecode<T>(expression: &Expression<T>) -> Result<Vec<Op>, CompileError> {
+    let mut emitter = Emitter::default();
+    emitter.emit(expression)?;
+    Ok(emitter.ops)
+}
+
+/// Runs bytecode from `compile_to_bytecode`, returning the value it leaves on top of the stack.
+///
+/// ## Panics:
+/// If the bytecode pops or returns from an empty stack, or loads a slot that was never stored,
+/// which bytecode from `compile_to_bytecode` never does.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::compiler::bytecode::run_bytecode;
+/// # use rengo::compiler::bytecode::Op;
+/// assert_eq!(run_bytecode(&[Op::PushConst(41), Op::Store(0), Op::Load(0), Op::Inc]), 42);
+/// ```
+pub fn run_bytecode(ops: &[Op]) -> i64 {
+    let mut stack: Vec<i64> = Vec::new();
+    let mut slots: Vec<i64> = Vec::new();
+    for op in ops {
+        match *op {
+            Op::PushConst(value) => stack.push(value),
+            Op::Inc => *stack.last_mut().expect("Inc on an empty stack") = top(&stack).wrapping_add(1),
+            Op::Dec => *stack.last_mut().expect("Dec on an empty stack") = top(&stack).wrapping_sub(1),
+            Op::Load(slot) => stack.push(*slots.get(slot).expect("Load from a slot that was never stored")),
+            Op::Store(slot) => {
+                let value = stack.pop().expect("Store from an empty stack");
+                if slots.len() <= slot {
+                    slots.resize(slot + 1, 0);
+                }
+                slots[slot] = value;
+            }
+        }
+    }
+    top(&stack)
+}
+
+/// Returns the value on top of the stack of `run_bytecode`.
+fn top(stack: &[i64]) -> i64 {
+    *stack.last().expect("bytecode left the stack empty")
+}
+
+/// The state of the compilation of a program into bytecode.
+#[derive(Debug, Default)]
+struct Emitter {
+    /// The bytecode emitted so far, in order.
+    ops: Vec<Op>,
+
+    /// The slot of each variable in scope.
+    bindings: BTreeMap<String, usize>,
+
+    /// The number of slots held by the bindings in scope. A discarded value is stored in the slot
+    /// after them, which the body is free to reuse.
+    slots: usize,
+}
+
+impl Emitter {
+    /// Emits the bytecode that pushes the value of `expression`.
+    fn emit<T>(&mut self, expression: &Expression<T>) -> Result<(), CompileError> {
+        match expression {
+            Expression::Number(value, _) => self.ops.push(Op::PushConst(*value)),
+            Expression::Increment(expr, _) => {
+                self.emit(expr)?;
+                self.ops.push(Op::Inc);
+            }
+            Expression::Decrement(expr, _) => {
+                self.emit(expr)?;
+                self.ops.push(Op::Dec);
+            }
+            Expression::Let(identifier, value, body, _) | Expression::LetMut(identifier, value, body, _) => {
+                // The value is emitted first, so it reads the binding being shadowed (if any)
+                self.emit(value)?;
+                let slot = self.slots;
+                self.ops.push(Op::Store(slot));
+                if identifier == DISCARD {
+                    return self.emit(body);
+                }
+                self.slots += 1;
+                let shadowed = self.bindings.insert(identifier.clone(), slot);
+                let result = self.emit(body);
+                match shadowed {
+                    Some(previous) => self.bindings.insert(identifier.clone(), previous),
+                    None => self.bindings.remove(identifier),
+                };
+                self.slots -= 1;
+                return result;
+            }
+            Expression::Identifier(identifier, _) => {
+                let slot = self.bindings.get(identifier).ok_or_else(|| CompileError::UndefinedVariable {
+                    name: identifier.clone(),
+                    suggestion: closest_name(identifier, self.bindings.keys().map(String::as_str)).map(String::from),
+                })?;
+                self.ops.push(Op::Load(*slot));
+            }
+            _ => return Err(CompileError::Unsupported(construct(expression))),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec;
+    use expectest::prelude::*;
+    use proptest::prelude::*;
+    use crate::ast::arbitrary;
+    use crate::interpreter::eval::{eval, Bindings};
+    use crate::parser::parse::parse;
+    use crate::parser::tokenize::tokenize;
+    use super::*;
+
+    fn compile_source(source: &str) -> Result<Vec<Op>, CompileError> {
+        compile_to_bytecode(&parse(&tokenize(source).unwrap()).unwrap())
+    }
+
+    mod compile_to_bytecode {
+        use super::*;
+
+        #[test]
+        fn pushes_numbers_and_steps_them() {
+            expect!(compile_source("420++--")).to(be_ok().value(vec![Op::PushConst(420), Op::Inc, Op::Dec]));
+        }
+
+        #[test]
+        fn gives_shadowing_bindings_their_own_slot() {
+            expect!(compile_source("let x = 1; let x = x++; x")).to(be_ok().value(vec![
+                Op::PushConst(1),
+                Op::Store(0),
+                Op::Load(0),
+                Op::Inc,
+                Op::Store(1),
+                Op::Load(1),
+            ]));
+        }
+
+        #[test]
+        fn reuses_the_slots_of_bindings_out_of_scope() {
+            expect!(compile_source("let x = (let y = 1; y); let z = x; z")).to(be_ok().value(vec![
+                Op::PushConst(1),
+                Op::Store(0),
+                Op::Load(0),
+                Op::Store(0),
+                Op::Load(0),
+                Op::Store(1),
+                Op::Load(1),
+            ]));
+        }
+
+        #[test]
+        fn stores_discarded_values_without_binding_them() {
+            expect!(compile_source("let x = 5; let _ = x++; x")).to(be_ok().value(vec![
+                Op::PushConst(5),
+                Op::Store(0),
+                Op::Load(0),
+                Op::Inc,
+                Op::Store(1),
+                Op::Load(0),
+            ]));
+        }
+
+        #[test]
+        fn fails_on_undefined_variables() {
+            expect!(compile_source("let value = 1; valu")).to(be_err().value(CompileError::UndefinedVariable {
+                name: "valu".to_string(),
+                suggestion: Some("value".to_string()),
+            }));
+        }
+
+        #[test]
+        fn reports_the_constructs_it_cannot_compile() {
+            expect!(compile_source("1 + 2")).to(be_err().value(CompileError::Unsupported("+")));
+            expect!(compile_source("1; 2")).to(be_err().value(CompileError::Unsupported(";")));
+            expect!(compile_source("[1, 2][0]")).to(be_err().value(CompileError::Unsupported("[]")));
+        }
+    }
+
+    mod run_bytecode {
+        use super::*;
+
+        #[test]
+        fn returns_the_value_on_top_of_the_stack() {
+            expect!(run_bytecode(&[Op::PushConst(1), Op::PushConst(2)])).to(be_equal_to(2));
+        }
+
+        #[test]
+        fn wraps_on_overflow() {
+            expect!(run_bytecode(&[Op::PushConst(i64::MAX), Op::Inc])).to(be_equal_to(i64::MIN));
+            expect!(run_bytecode(&[Op::PushConst(i64::MIN), Op::Dec])).to(be_equal_to(i64::MAX));
+        }
+
+        #[test]
+        #[should_panic(expected = "bytecode left the stack empty")]
+        fn panics_when_nothing_is_left_on_the_stack() {
+            run_bytecode(&[Op::PushConst(1), Op::Store(0)]);
+        }
+
+        proptest!(
+            #[test]
+            fn agrees_with_the_interpreter(expr in arbitrary::straight_line(4)) {
+                let ops = compile_to_bytecode(&expr).unwrap();
+                let expected = eval(&expr, &mut Bindings::new()).unwrap();
+                prop_assert_eq!(run_bytecode(&ops), expected);
+            }
+        );
+    }
+}
@@ -1,15 +1,29 @@
-use crate::asm::arg::Arg;
-use crate::asm::arg::Arg::Constant;
+use alloc::format;
+use alloc::vec;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::asm::arg::{Arg, MemOperand, RAX, RBP, RCX, RSP};
 use crate::asm::instruction::Instruction;
-use crate::asm::instruction::Instruction::{Dec, Inc, Mov};
-use crate::asm::reg::Reg;
-use crate::ast::expression::Expression;
+use crate::asm::program::Data;
+use crate::asm::condition::Condition;
+use crate::asm::instruction::Instruction::{Add, And, Call, Cmov, Cmp, Jmp, Jump, Label, Mov, Or, Pop, Push, Ret, Sar, Set, Shl, Sub, Test, Xor};
+use crate::asm::reg::{Abi, Reg};
+use crate::ast::expression::{Expression, Function, DISCARD};
+use crate::ast::operator::BinaryOperator;
 use crate::compiler::compile::Reg::Rsp;
-use crate::env::{add, Env};
-use Arg::{Registry, RegistryOffset};
-use Reg::Rax;
+use crate::compiler::context::Ctx;
+use crate::compiler::error::CompileError;
+use crate::compiler::initialized::check_initialized;
+use crate::compiler::options::CompileOptions;
+use crate::compiler::regalloc::regalloc;
+use crate::compiler::suggest::closest_name;
+use crate::compiler::unit::CompiledUnit;
+use crate::env::{add, add_temporary, Env};
+use crate::parser::span::SourceSpan;
+use Arg::RegistryOffset;
+use Reg::{Al, Cl, Rax, Rbp, Rcx, Rdi, Rdx, Rsi};
 
-/// Compiles an `Expression` into a sequence of `Instruction`s.
+/// Compiles an `Expression` into a `CompiledUnit`.
 ///
 /// The `compile_expression` function translates a high-level `Expression` into a low-level sequence
 /// of `Instruction`s that can be executed in an assembly-like environment. The function handles
@@ -20,31 +34,44 @@ use Reg::Rax;
 /// # Arguments
 ///
 /// * `expression` - A reference to an `Expression` to be compiled into assembly instructions.
-/// * `env` - A mutable reference to the environment (`Env`), which maps variable names to stack
-///   slots.
+/// * `ctx` - The compilation context (`Ctx`): the environment mapping variable names to stack slots,
+///   the labels already taken, the `CompileOptions` controlling which programs are accepted, and
+///   the errors reported so far, to which a failure is added.
 ///
 /// # Returns
 ///
-/// * `Ok(Vec<Instruction>)` - A vector of `Instruction`s representing the compiled code if the
-///   expression is successfully compiled.
-/// * `Err(())` - An error indicating that the compilation failed. This can occur if an identifier
-///   is not found in the environment or if another compilation step fails.
+/// * `Ok(CompiledUnit)` - The `Instruction`s representing the compiled code, along with the number
+///   of stack slots they use, if the expression is successfully compiled.
+/// * `Err(CompileError)` - An error indicating why the compilation failed, such as
+///   `CompileError::UndefinedVariable` if an identifier is not found in the environment, or
+///   `CompileError::DuplicateBinding` if a `let` shadows a binding of the same scope while
+///   `ctx.options.deny_shadowing` is set.
 ///
 /// # Examples
 ///
 /// ## Compiling a Numeric Expression
 /// ```rust
-/// let expr = Expression::Number(42);
-/// let mut env = Env::new();
-/// let instructions = compile_expression(&expr, &mut env).unwrap();
+/// # use rengo::ast::expression::Expression;
+/// # use rengo::compiler::compile::compile_expression;
+/// # use rengo::compiler::context::Ctx;
+/// # use rengo::asm::instruction::Instruction;
+/// # use rengo::asm::arg::Arg;
+/// # use rengo::asm::reg::Reg;
+/// let expr = Expression::Number(42, ());
+/// let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
 /// assert_eq!(instructions, vec![Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(42))]);
 /// ```
 ///
 /// ## Compiling an Increment Expression
 /// ```rust
-/// let inc_expr = Expression::Increment(Box::new(Expression::Number(42)));
-/// let mut env = Env::new();
-/// let inc_instructions = compile_expression(&inc_expr, &mut env).unwrap();
+/// # use rengo::ast::expression::Expression;
+/// # use rengo::compiler::compile::compile_expression;
+/// # use rengo::compiler::context::Ctx;
+/// # use rengo::asm::instruction::Instruction;
+/// # use rengo::asm::arg::Arg;
+/// # use rengo::asm::reg::Reg;
+/// let inc_expr = Expression::Increment(Box::new(Expression::Number(42, ())), ());
+/// let inc_instructions = compile_expression(&inc_expr, &mut Ctx::default()).unwrap().instructions;
 /// assert_eq!(inc_instructions, vec![
 ///     Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(42)),
 ///     Instruction::Inc(Arg::Registry(Reg::Rax))
@@ -53,9 +80,14 @@ use Reg::Rax;
 ///
 /// ## Compiling a Decrement Expression
 /// ```rust
-/// let dec_expr = Expression::Decrement(Box::new(Expression::Number(42)));
-/// let mut env = Env::new();
-/// let dec_instructions = compile_expression(&dec_expr, &mut env).unwrap();
+/// # use rengo::ast::expression::Expression;
+/// # use rengo::compiler::compile::compile_expression;
+/// # use rengo::compiler::context::Ctx;
+/// # use rengo::asm::instruction::Instruction;
+/// # use rengo::asm::arg::Arg;
+/// # use rengo::asm::reg::Reg;
+/// let dec_expr = Expression::Decrement(Box::new(Expression::Number(42, ())), ());
+/// let dec_instructions = compile_expression(&dec_expr, &mut Ctx::default()).unwrap().instructions;
 /// assert_eq!(dec_instructions, vec![
 ///     Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(42)),
 ///     Instruction::Dec(Arg::Registry(Reg::Rax))
@@ -64,80 +96,927 @@ use Reg::Rax;
 ///
 /// ## Compiling a Let Expression
 /// ```rust
+/// # use rengo::ast::expression::Expression;
+/// # use rengo::compiler::compile::compile_expression;
+/// # use rengo::compiler::context::Ctx;
+/// # use rengo::asm::instruction::Instruction;
+/// # use rengo::asm::arg::Arg;
+/// # use rengo::asm::reg::Reg;
 /// let let_expr = Expression::Let(
 ///     "x".to_string(),
-///     Box::new(Expression::Number(42)),
-///     Box::new(Expression::Identifier("x".to_string()))
+///     Box::new(Expression::Number(42, ())),
+///     Box::new(Expression::Identifier("x".to_string(), ())),
+///     (),
 /// );
-/// let mut env = Env::new();
-/// let let_instructions = compile_expression(&let_expr, &mut env).unwrap();
+/// let let_instructions = compile_expression(&let_expr, &mut Ctx::default()).unwrap().instructions;
 /// assert_eq!(let_instructions, vec![
 ///     Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(42)),
 ///     Instruction::Mov(Arg::RegistryOffset(Reg::Rsp, -1), Arg::Registry(Reg::Rax)),
-///     Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(1))
+///     Instruction::Mov(Arg::Registry(Reg::Rax), Arg::RegistryOffset(Reg::Rsp, -1))
+/// ]);
+/// ```
+///
+/// ## Compiling a Binary Expression
+/// The left operand is spilled to a temporary stack slot while the right operand is computed. The
+/// right operand is then moved to `Rcx` (so shifts can read their count from `Cl`) and the left
+/// operand is reloaded into `Rax` before applying the operator.
+/// ```rust
+/// # use rengo::ast::expression::Expression;
+/// # use rengo::ast::operator::BinaryOperator;
+/// # use rengo::compiler::compile::compile_expression;
+/// # use rengo::compiler::context::Ctx;
+/// # use rengo::asm::instruction::Instruction;
+/// # use rengo::asm::arg::Arg;
+/// # use rengo::asm::reg::Reg;
+/// let expr = Expression::Binary(
+///     BinaryOperator::BitAnd,
+///     Box::new(Expression::Number(6, ())),
+///     Box::new(Expression::Increment(Box::new(Expression::Number(3, ())), ())),
+///     (),
+/// );
+/// let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
+/// assert_eq!(instructions, vec![
+///     Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(6)),
+///     Instruction::Mov(Arg::RegistryOffset(Reg::Rsp, -1), Arg::Registry(Reg::Rax)),
+///     Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(3)),
+///     Instruction::Inc(Arg::Registry(Reg::Rax)),
+///     Instruction::Mov(Arg::Registry(Reg::Rcx), Arg::Registry(Reg::Rax)),
+///     Instruction::Mov(Arg::Registry(Reg::Rax), Arg::RegistryOffset(Reg::Rsp, -1)),
+///     Instruction::And(Arg::Registry(Reg::Rax), Arg::Registry(Reg::Rcx))
+/// ]);
+/// ```
+///
+/// ## Compiling a Sequence
+/// The statements are compiled in order, so the value of the last one is left in `Rax`. Every
+/// statement is compiled, even though the value the others leave in `Rax` is overwritten, as they
+/// can still have effects such as assignments or assertions. With
+/// `options.label_statements` set, each statement of a top-level sequence is placed under its own
+/// label (`stmt0:`, `stmt1:`, ...).
+///
+/// ## Compiling an Assertion
+/// The condition is compiled and tested against zero with `test rax, rax`. If it is zero, the code
+/// calls the runtime's `ASSERT_FAILED_SYMBOL`, which exits the program with `ASSERT_EXIT_CODE`;
+/// otherwise it jumps past the call to a label named after the assertion (`assert0`, `assert1`,
+/// ...), leaving the condition in `Rax`.
+///
+/// ## Compiling a Conditional
+/// The condition is compiled and tested like an assertion's. A nonzero condition jumps to the
+/// `then` branch, placed under a `then<n>` label after the `else` branch, which jumps over it to
+/// `endif<n>`:
+/// ```text
+///     <condition>
+///     test rax, rax
+///     jne then0
+///     <else branch>
+///     jmp endif0
+/// then0:
+///     <then branch>
+/// endif0:
+/// ```
+///
+/// ## Compiling an Array Index
+/// An `Index` into an array literal reserves one slot per element, stores the elements in them in
+/// order, and then loads the element at the index, addressed as a `Memory` operand from the first
+/// slot. A number as the index is checked against the bounds of the array while compiling, failing
+/// with `CompileError::IndexOutOfBounds`. Any other index is computed into `Rax` after the elements,
+/// and the address of its element is computed from it, without checking it unless
+/// `options.sanitize` is set: then it is compared to the length of the array first, and the code
+/// calls the runtime's `INDEX_OUT_OF_BOUNDS_SYMBOL`, which exits with `INDEX_EXIT_CODE`, if it is
+/// out of bounds:
+/// ```text
+///     cmp rax, <length>
+///     jb inbounds0
+///     call rengo_index_out_of_bounds
+/// inbounds0:
+///     shl rax, 3
+///     lea rcx, <first element>
+///     sub rcx, rax
+///     mov rax, [rcx]
+/// ```
+///
+/// An array that is not indexed fails with `CompileError::ArrayValue`.
+///
+/// ## Compiling a Print
+/// The string is placed in the data section of the `CompiledUnit` under a `str<n>` label, shared
+/// by every `print` of the same string. Its address is loaded into `Rdi` to call the runtime's
+/// `PRINT_SYMBOL`, and `Rax` is then set to 0, the value of a `print`. Without a frame, `Rsp` is
+/// moved below the slots in use around the call, so the call does not overwrite them:
+/// ```text
+///     lea rdi, [rel str0]
+///     call print_str
+///     mov rax, 0
+/// ```
+///
+/// ## Compiling a Return
+/// The value is compiled into `Rax`, which is then kept as the result of the program by jumping to
+/// the `RETURN_LABEL` placed at the end of the code, right before the epilogue of the frame. Inside
+/// a function, it jumps to the end of the function instead, returning from it. A `return` in tail
+/// position, the last thing evaluated before that label, falls through to it without a jump.
+///
+/// ## Compiling Functions
+/// The body of each function is compiled into a routine of its own, placed in the `functions` of
+/// the `CompiledUnit` under a label named after the function (`fn0_add`, `fn1_id`, ...). The
+/// routine always sets up a frame, stores its parameters from the `ARGUMENT_REGISTERS` into its
+/// first slots and returns the value of the body in `Rax`:
+/// ```text
+/// fn0_add:
+///     push rbp
+///     mov rbp, rsp
+///     sub rsp, 16
+///     mov [rbp - 8], rdi
+///     mov [rbp - 16], rsi
+///     <body>
+///     mov rsp, rbp
+///     pop rbp
+///     ret
+/// ```
+/// A call stores each argument in a temporary slot as it is computed, then loads them all into the
+/// argument registers and calls the routine like a runtime function.
+///
+/// ## Folding Constants into Immediates
+/// A right operand that is a number fitting in 32 bits is encoded as the immediate of the
+/// operation when the operator has such a form, like `add` and `sub`, so the left operand stays in
+/// `Rax` and the number is never loaded:
+/// ```rust
+/// # use rengo::ast::expression::Expression;
+/// # use rengo::ast::operator::BinaryOperator;
+/// # use rengo::compiler::compile::compile_expression;
+/// # use rengo::compiler::context::Ctx;
+/// # use rengo::asm::instruction::Instruction;
+/// # use rengo::asm::arg::Arg;
+/// # use rengo::asm::reg::Reg;
+/// let expr = Expression::Binary(
+///     BinaryOperator::Add,
+///     Box::new(Expression::Number(5, ())),
+///     Box::new(Expression::Number(3, ())),
+///     (),
+/// );
+/// let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
+/// assert_eq!(instructions, vec![
+///     Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(5)),
+///     Instruction::Add(Arg::Registry(Reg::Rax), Arg::Constant(3)),
+/// ]);
+/// ```
+///
+/// Larger numbers can only be encoded by `mov`, so they are compiled like any other operand.
+///
+/// ## Keeping Operands in Registers
+/// With `options.allocate_registers` set, the right operand of a binary operator that is a number
+/// or a variable, and is not folded into an immediate, is loaded straight into `Rcx`, so the left
+/// operand stays in `Rax` instead of being spilled to a temporary slot. Other right operands still
+/// spill the left one.
+///
+/// ## Skipping Redundant Loads
+/// With `options.cache_loads` set, the compiler remembers which variable `Rax` holds and skips
+/// reloading it, e.g. right after a `let` stores its value:
+/// ```rust
+/// # use rengo::ast::expression::Expression;
+/// # use rengo::compiler::options::CompileOptions;
+/// # use rengo::compiler::compile::compile_expression;
+/// # use rengo::compiler::context::Ctx;
+/// # use rengo::asm::instruction::Instruction;
+/// # use rengo::asm::arg::Arg;
+/// # use rengo::asm::reg::Reg;
+/// let expr = Expression::Let(
+///     "x".to_string(),
+///     Box::new(Expression::Number(42, ())),
+///     Box::new(Expression::Identifier("x".to_string(), ())),
+///     (),
+/// );
+/// let options = CompileOptions { cache_loads: true, ..Default::default() };
+/// let instructions = compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions;
+/// assert_eq!(instructions, vec![
+///     Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(42)),
+///     Instruction::Mov(Arg::RegistryOffset(Reg::Rsp, -1), Arg::Registry(Reg::Rax)),
+/// ]);
+/// ```
+///
+/// ## Allocating Registers to Slots
+/// With `options.linear_scan` set, the code of the program and of each function is passed through
+/// `regalloc` before the frame is added, which moves the slots whose live ranges allow it into
+/// spare caller-saved registers, leaving the others in memory.
+///
+/// ## Compiling With a Stack Frame
+/// With `options.frame` set, a `target_abi` without a red zone, or more slots than fit in the red
+/// zone, the code is wrapped in a prologue that saves `Rbp`, points it at the top of the frame and
+/// reserves room for every slot (rounded up to keep `Rsp` 16-byte aligned), and an epilogue that
+/// releases the frame. Locals are then addressed relative to `Rbp`. A program compiled without a
+/// frame whose slots turn out not to fit is compiled again with one. Each slot takes
+/// `options.slot_bytes()` bytes.
+/// ```rust
+/// # use rengo::ast::expression::Expression;
+/// # use rengo::compiler::options::CompileOptions;
+/// # use rengo::compiler::compile::compile_expression;
+/// # use rengo::compiler::context::Ctx;
+/// # use rengo::asm::instruction::Instruction;
+/// # use rengo::asm::arg::Arg;
+/// # use rengo::asm::reg::Reg;
+/// let expr = Expression::Let(
+///     "x".to_string(),
+///     Box::new(Expression::Number(42, ())),
+///     Box::new(Expression::Identifier("x".to_string(), ())),
+///     (),
+/// );
+/// let options = CompileOptions { frame: true, ..Default::default() };
+/// let instructions = compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions;
+/// assert_eq!(instructions, vec![
+///     Instruction::Push(Arg::Registry(Reg::Rbp)),
+///     Instruction::Mov(Arg::Registry(Reg::Rbp), Arg::Registry(Reg::Rsp)),
+///     Instruction::Sub(Arg::Registry(Reg::Rsp), Arg::Constant(16)),
+///     Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(42)),
+///     Instruction::Mov(Arg::RegistryOffset(Reg::Rbp, -1), Arg::Registry(Reg::Rax)),
+///     Instruction::Mov(Arg::Registry(Reg::Rax), Arg::RegistryOffset(Reg::Rbp, -1)),
+///     Instruction::Mov(Arg::Registry(Reg::Rsp), Arg::Registry(Reg::Rbp)),
+///     Instruction::Pop(Arg::Registry(Reg::Rbp)),
 /// ]);
 /// ```
 ///
 /// # Note:
 /// The `Identifier` variant expects the variable to have been previously defined in the
-/// environment. If the identifier is not found, the function may return an error or panic depending
-/// on the implementation.
-pub(crate) fn compile_expression<T>(expression: &Expression<T>, env: &mut Env) -> Result<Vec<Instruction>, ()> {
+/// environment. If the identifier is not found, the function returns
+/// `CompileError::UndefinedVariable`. A `let` binding is only visible in its body: once the body is
+/// compiled, the binding it shadowed (if any) is restored. Its slot is only allocated once the value
+/// compiled, so a value that fails to compile leaves the environment as it was.
+pub fn compile_expression<T: SourceSpan>(expression: &Expression<T>, ctx: &mut Ctx) -> Result<CompiledUnit, CompileError> {
+    let unframed = (!ctx.options.uses_frame()).then(|| ctx.clone());
+    let unit = compile_unit(expression, ctx)?;
+    match unframed {
+        // The slots below Rsp would spill out of the red zone, so they are given a frame instead
+        Some(mut framed) if !ctx.options.fits_in_red_zone(unit.stack_slots) => {
+            framed.options.frame = true;
+            let unit = compile_unit(expression, &mut framed);
+            framed.options.frame = false;
+            *ctx = framed;
+            unit
+        }
+        _ => Ok(unit),
+    }
+}
+
+/// Compiles a program as `compile_expression` does, setting up a frame only if
+/// `ctx.options.uses_frame()`.
+fn compile_unit<T: SourceSpan>(expression: &Expression<T>, ctx: &mut Ctx) -> Result<CompiledUnit, CompileError> {
+    let mut state = CodegenState { tail: true, ..Default::default() };
+    let compiled = check_initialized(expression).and_then(|()| match expression {
+        Expression::Sequence(statements, _) if ctx.options.label_statements => {
+            compile_labeled_statements(statements, ctx, &mut state)
+        }
+        _ => compile_instructions(expression, ctx, &mut state),
+    });
+    let mut instructions = compiled.inspect_err(|error| ctx.errors.push(error.clone()))?;
+    if state.returns {
+        instructions.push(Label(String::from(RETURN_LABEL)));
+    }
+    if ctx.options.linear_scan {
+        instructions = regalloc(instructions, ctx.options.target_abi);
+    }
+    if ctx.options.uses_frame() {
+        instructions = with_frame(instructions, ctx.env.len(), &ctx.options);
+    }
+    let data = state.strings
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| Data { label: format!("str{}", index), value })
+        .collect();
+    Ok(CompiledUnit { instructions, stack_slots: ctx.env.len(), data, functions: state.routines })
+}
+
+/// The symbol of the runtime function that compiled code calls when an assertion fails.
+pub const ASSERT_FAILED_SYMBOL: &str = "rengo_assert_failed";
+
+/// The exit code of a program stopped by a failed assertion.
+pub const ASSERT_EXIT_CODE: i32 = 101;
+
+/// The symbol of the runtime function that compiled code calls when a bounds check fails, with
+/// `options.sanitize` set.
+pub const INDEX_OUT_OF_BOUNDS_SYMBOL: &str = "rengo_index_out_of_bounds";
+
+/// The exit code of a program stopped by a failed bounds check.
+pub const INDEX_EXIT_CODE: i32 = 102;
+
+/// The symbol of the runtime function that `print` calls with the address of a null-terminated
+/// string in `Rdi`.
+pub const PRINT_SYMBOL: &str = "print_str";
+
+/// The label at the end of the compiled code that `return` jumps to, with its value in `Rax`.
+pub const RETURN_LABEL: &str = "epilogue";
+
+/// The registers that pass the arguments of a call to a function defined by the program, in order:
+/// the first integer argument registers of `RUNTIME_ABI`.
+pub const ARGUMENT_REGISTERS: [Reg; 4] = [Rdi, Rsi, Rdx, Rcx];
+
+/// The most parameters a function can have, one per argument register.
+pub const MAX_PARAMETERS: usize = ARGUMENT_REGISTERS.len();
+
+/// Compiles the statements of a sequence in order, placing each one under a label named after its
+/// position (`stmt0`, `stmt1`, ...).
+fn compile_labeled_statements<T: SourceSpan>(
+    statements: &[Expression<T>],
+    ctx: &mut Ctx,
+    state: &mut CodegenState,
+) -> Result<Vec<Instruction>, CompileError> {
+    let mut instructions = Vec::new();
+    for (index, statement) in statements.iter().enumerate() {
+        instructions.push(Label(format!("stmt{}", index)));
+        state.rax = None;  // Code could jump to the label with anything in Rax
+        state.tail = index + 1 == statements.len();
+        instructions.append(&mut compile_instructions(statement, ctx, state)?);
+    }
+    Ok(instructions)
+}
+
+/// Wraps the compiled code in a prologue that establishes a stack frame with room for `slots`
+/// slots, and an epilogue that tears it down before returning. Without slots, `Rsp` is neither
+/// moved nor restored.
+fn with_frame(body: Vec<Instruction>, slots: usize, options: &CompileOptions) -> Vec<Instruction> {
+    let size = ((slots * options.slot_bytes()) as i64 + 15) / 16 * 16;  // Keep Rsp 16-byte aligned after the push
+    let mut instructions = vec![Push(RBP), Mov(RBP, RSP)];
+    if size > 0 {
+        instructions.push(Sub(RSP, size.into()));
+    }
+    instructions.extend(body);
+    if size > 0 {
+        instructions.push(Mov(RSP, RBP));
+    }
+    instructions.push(Pop(RBP));
+    instructions
+}
+
+/// Returns the stack location of a slot, relative to `Rbp` when a frame is set up and to `Rsp`
+/// otherwise. The offset of a `RegistryOffset` counts 8-byte words, so a slot spans
+/// `options.slot_bytes() / 8` of them.
+fn local(slot: i64, options: &CompileOptions) -> Arg {
+    let base = if options.uses_frame() { Rbp } else { Rsp };
+    RegistryOffset(base, -slot * (options.slot_bytes() / 8) as i64)
+}
+
+/// Returns the elements of the tuple a destructuring `let` binds to `identifiers`, checking that
+/// there is one element per name.
+pub(crate) fn tuple_elements<'a, T>(
+    identifiers: &[String],
+    value: &'a Expression<T>,
+) -> Result<&'a [Expression<T>], CompileError> {
+    let elements = match value {
+        Expression::Tuple(elements, _) => elements.as_slice(),
+        other => core::slice::from_ref(other),
+    };
+    if elements.len() != identifiers.len() {
+        return Err(CompileError::TupleArity { expected: identifiers.len(), found: elements.len() });
+    }
+    Ok(elements)
+}
+
+/// Returns the elements of the array an `Index` indexes, which has to be an array literal for now.
+pub(crate) fn array_elements<T>(array: &Expression<T>) -> Result<&[Expression<T>], CompileError> {
+    match array {
+        Expression::Array(elements, _) => Ok(elements),
+        _ => Err(CompileError::Unsupported("[]")),
+    }
+}
+
+/// Returns the position of the element at `index` in an array of `length` elements, checking
+/// that there is one.
+pub(crate) fn array_position(index: i64, length: usize) -> Result<usize, CompileError> {
+    usize::try_from(index)
+        .ok()
+        .filter(|position| *position < length)
+        .ok_or(CompileError::IndexOutOfBounds { index, length })
+}
+
+/// Returns the memory operand of the element at `position` in an array stored from slot `first`
+/// on. Slots grow downwards, so the element is `position` slots below the first one.
+fn array_element(first: i64, position: usize, options: &CompileOptions) -> Arg {
+    let base = if options.uses_frame() { Rbp } else { Rsp };
+    Arg::Memory(MemOperand::Base(base, -(first + position as i64) * options.slot_bytes() as i64))
+}
+
+/// Returns the first name bound twice among the parameters of a function, ignoring `DISCARD`.
+pub(crate) fn duplicate_parameter(parameters: &[String]) -> Option<&String> {
+    parameters
+        .iter()
+        .enumerate()
+        .find(|(index, parameter)| *parameter != DISCARD && parameters[..*index].contains(parameter))
+        .map(|(_, parameter)| parameter)
+}
+
+/// Tracks what the generated code has left in registers, so redundant loads can be skipped, and
+/// what it refers to besides its own instructions.
+#[derive(Debug, Default)]
+struct CodegenState {
+    /// The slot whose value `Rax` currently holds, if any.
+    rax: Option<i64>,
+    /// Whether a `return` that jumps was compiled, so the code needs a `RETURN_LABEL` to jump to.
+    returns: bool,
+    /// The label `return` jumps to inside the function being compiled, or `None` at the top level,
+    /// where it jumps to `RETURN_LABEL`.
+    return_label: Option<String>,
+    /// Whether the expression about to be compiled is in tail position: nothing runs after it
+    /// before the label `return` jumps to. `compile_instructions` takes it, so subexpressions are
+    /// only in tail position when the arm compiling them sets it again.
+    tail: bool,
+    /// The functions in scope, innermost last: the name, number of parameters and label of each.
+    functions: Vec<(String, usize, String)>,
+    /// The routines of the functions compiled so far.
+    routines: Vec<Instruction>,
+    /// The distinct strings the code refers to, each placed in the data section under the label
+    /// `str<n>`, where `n` is its position.
+    strings: Vec<String>,
+}
+
+impl CodegenState {
+    /// Returns the label of a string in the data section, adding it if it is not there yet, so
+    /// equal strings are only stored once.
+    fn intern(&mut self, text: &str) -> String {
+        let index = match self.strings.iter().position(|string| string == text) {
+            Some(index) => index,
+            None => {
+                self.strings.push(String::from(text));
+                self.strings.len() - 1
+            }
+        };
+        format!("str{}", index)
+    }
+}
+
+/// The calling convention of the runtime functions that take arguments, which `src/main.c`
+/// declares `sysv_abi` on every target.
+pub const RUNTIME_ABI: Abi = Abi::SystemV;
+
+/// Returns the instructions that call a runtime function or a function defined by the program,
+/// keeping `Rsp` 16-byte aligned as the calling convention requires.
+///
+/// With a frame, `Rsp` is already aligned and below every slot. Without one, the slots live below
+/// `Rsp`, where the call would overwrite them, so `Rsp` is first moved past the `slots` in use.
+/// The slots are addressed from `Rsp` or `Rbp`, which the call preserves, while the value of any
+/// caller-saved register under `RUNTIME_ABI` is forgotten.
+fn call(symbol: &str, ctx: &Ctx, state: &mut CodegenState) -> Vec<Instruction> {
+    if Rax.is_caller_saved(RUNTIME_ABI) {
+        state.rax = None;
+    }
+    if ctx.options.uses_frame() {
+        return vec![Call(String::from(symbol))];
+    }
+    // Rsp is 8 bytes past a 16-byte boundary on entry, as the caller pushed its return address
+    let size = ((ctx.env.len() * ctx.options.slot_bytes()) as i64 + 8 + 15) / 16 * 16 - 8;
+    vec![
+        Sub(RSP, size.into()),
+        Call(String::from(symbol)),
+        Add(RSP, size.into()),
+    ]
+}
+
+/// Compiles the routine of a function under `label`. Its body only sees its parameters, which are
+/// stored from the `ARGUMENT_REGISTERS` into slots of a frame of its own.
+fn compile_function<T: SourceSpan>(
+    label: &str,
+    parameters: &[String],
+    body: &Expression<T>,
+    ctx: &mut Ctx,
+    state: &mut CodegenState,
+) -> Result<Vec<Instruction>, CompileError> {
+    if let Some(parameter) = duplicate_parameter(parameters) {
+        return Err(CompileError::DuplicateBinding(parameter.clone()));
+    }
+    // The variables, registers and return label of the code around the definition are not the body's
+    let options = CompileOptions { frame: true, ..ctx.options.clone() };
+    let outer_options = core::mem::replace(&mut ctx.options, options);
+    let outer_env = core::mem::take(&mut ctx.env);
+    let outer_rax = state.rax.take();
+    let outer_returns = core::mem::take(&mut state.returns);
+    let outer_label = state.return_label.replace(format!("{}_epilogue", label));
+    let compiled = store_parameters(parameters, ctx).and_then(|mut instructions| {
+        state.tail = true;
+        instructions.append(&mut compile_instructions(body, ctx, state)?);
+        Ok(instructions)
+    });
+    let slots = ctx.env.len();
+    ctx.options = outer_options;
+    ctx.env = outer_env;
+    state.rax = outer_rax;
+    let returns = core::mem::replace(&mut state.returns, outer_returns);
+    let return_label = core::mem::replace(&mut state.return_label, outer_label);
+    let mut instructions = compiled?;
+    if let (true, Some(return_label)) = (returns, return_label) {
+        instructions.push(Label(return_label));
+    }
+    if ctx.options.linear_scan {
+        instructions = regalloc(instructions, ctx.options.target_abi);
+    }
+    let mut routine = vec![Label(String::from(label))];
+    routine.extend(with_frame(instructions, slots, &ctx.options));
+    routine.push(Ret);
+    Ok(routine)
+}
+
+/// Returns the instructions that store the arguments of a call from the `ARGUMENT_REGISTERS` into
+/// slots of their own, binding each parameter to its slot.
+fn store_parameters(parameters: &[String], ctx: &mut Ctx) -> Result<Vec<Instruction>, CompileError> {
+    let mut instructions = Vec::new();
+    for (parameter, register) in parameters.iter().zip(ARGUMENT_REGISTERS) {
+        let slot = if parameter == DISCARD { add_temporary(&mut ctx.env)? } else { add(parameter.clone(), &mut ctx.env)? };
+        instructions.push(Mov(local(slot, &ctx.options), register.into()));
+    }
+    Ok(instructions)
+}
+
+/// Returns the slot of a variable, or a `CompileError::UndefinedVariable` suggesting a similar
+/// name in scope if it is not bound.
+fn slot_of(identifier: &str, env: &Env) -> Result<i64, CompileError> {
+    env.get(identifier).copied().ok_or_else(|| CompileError::UndefinedVariable {
+        name: String::from(identifier),
+        suggestion: closest_name(identifier, env.names()).map(String::from),
+    })
+}
+
+/// Returns the operand an expression can be read from without computing anything (the constant
+/// of a number, or the slot of a variable), or `None` if it has to be computed into `Rax`.
+fn leaf_operand<T>(expression: &Expression<T>, ctx: &Ctx) -> Result<Option<Arg>, CompileError> {
+    match expression {
+        Expression::Number(value, _) => Ok(Some((*value).into())),
+        Expression::Identifier(identifier, _) => Ok(Some(local(slot_of(identifier, &ctx.env)?, &ctx.options))),
+        _ => Ok(None),
+    }
+}
+
+/// Returns the instruction that applies a binary operator to `Rax` and a constant encoded as an
+/// immediate, or `None` if the operator has no such form or the constant does not fit in it.
+///
+/// `add`, `sub` and the bitwise operators take at most a 32-bit immediate, sign-extended to 64
+/// bits; only `mov` can encode a full 64-bit one.
+fn immediate_operation(operator: &BinaryOperator, value: i64) -> Option<Instruction> {
+    let immediate: Arg = i64::from(i32::try_from(value).ok()?).into();
+    match operator {
+        BinaryOperator::Add => Some(Add(RAX, immediate)),
+        BinaryOperator::Subtract => Some(Sub(RAX, immediate)),
+        BinaryOperator::BitAnd => Some(And(RAX, immediate)),
+        BinaryOperator::BitOr => Some(Or(RAX, immediate)),
+        BinaryOperator::BitXor => Some(Xor(RAX, immediate)),
+        _ => None,
+    }
+}
+
+/// Returns the instructions that apply a binary operator to the left operand in `Rax` and the
+/// right one in `Rcx`, leaving the result in `Rax`.
+fn operation(operator: &BinaryOperator) -> Vec<Instruction> {
+    match operator {
+        BinaryOperator::Add => vec![Add(RAX, RCX)],
+        BinaryOperator::Subtract => vec![Sub(RAX, RCX)],
+        BinaryOperator::BitAnd => vec![And(RAX, RCX)],
+        BinaryOperator::BitOr => vec![Or(RAX, RCX)],
+        BinaryOperator::BitXor => vec![Xor(RAX, RCX)],
+        BinaryOperator::ShiftLeft => vec![Shl(RAX, Cl.into())],
+        BinaryOperator::ShiftRight => vec![Sar(RAX, Cl.into())],
+        BinaryOperator::Less => compare(Condition::Less),
+        BinaryOperator::Greater => compare(Condition::Greater),
+        BinaryOperator::Equal => compare(Condition::Equal),
+        // Without branches: replace the left operand when the right one is smaller (greater)
+        BinaryOperator::Min => vec![Cmp(RAX, RCX), Cmov(Condition::Greater, RAX, RCX)],
+        BinaryOperator::Max => vec![Cmp(RAX, RCX), Cmov(Condition::Less, RAX, RCX)],
+    }
+}
+
+/// Returns the instructions that compare `Rax` to `Rcx`, leaving 1 in `Rax` if the condition holds
+/// and 0 otherwise. `Set` only writes the lowest byte, `Al`, which is then zero-extended into `Rax`.
+fn compare(condition: Condition) -> Vec<Instruction> {
+    vec![
+        Cmp(RAX, RCX),
+        Set(condition, Al.into()),
+        Instruction::Movzx(RAX, Al.into()),
+    ]
+}
+
+/// Compiles a condition, leaving its value in `Rax` and the flags set so that `Condition::NotEqual`
+/// holds if it is true, i.e. nonzero. `test rax, rax` is used rather than comparing `Rax` to 0, as
+/// it has a shorter encoding.
+fn compile_condition<T: SourceSpan>(
+    condition: &Expression<T>,
+    ctx: &mut Ctx,
+    state: &mut CodegenState,
+) -> Result<Vec<Instruction>, CompileError> {
+    let mut instructions = compile_instructions(condition, ctx, state)?;
+    instructions.push(Test(RAX, RAX));
+    Ok(instructions)
+}
+
+/// Compiles an `Expression` into a sequence of `Instruction`s, recording bindings and temporaries in
+/// the environment of the context. See `compile_expression` for how each kind of expression is translated.
+///
+/// The `state` is kept up to date with every instruction emitted that writes to `Rax` or to a
+/// slot, whether or not `ctx.options.cache_loads` is set.
+fn compile_instructions<T: SourceSpan>(
+    expression: &Expression<T>,
+    ctx: &mut Ctx,
+    state: &mut CodegenState,
+) -> Result<Vec<Instruction>, CompileError> {
+    let tail = core::mem::take(&mut state.tail);
     match expression {
-        Expression::Number(value, _) => Ok(vec![Instruction::Mov(Arg::Registry(Reg::Rax), Arg::Constant(*value))]),
+        Expression::Number(value, _) => {
+            state.rax = None;
+            Ok(vec![Instruction::Mov(RAX, (*value).into())])
+        }
         Expression::Increment(expr, _) => {
-            let mut instructions = compile_expression(expr, env)?;
-            instructions.push(Instruction::Inc(Arg::Registry(Reg::Rax)));
+            let mut instructions = compile_instructions(expr, ctx, state)?;
+            instructions.push(Instruction::Inc(RAX));
+            state.rax = None;
             Ok(instructions)
         }
         Expression::Decrement(expr, _) => {
-            let mut instructions = compile_expression(expr, env)?;
-            instructions.push(Instruction::Dec(Arg::Registry(Reg::Rax)));
+            let mut instructions = compile_instructions(expr, ctx, state)?;
+            instructions.push(Instruction::Dec(RAX));
+            state.rax = None;
+            Ok(instructions)
+        }
+        Expression::Let(identifier, value, body, _) | Expression::LetMut(identifier, value, body, _)
+            if identifier == DISCARD =>
+        {
+            // The value is only computed for its effects, so it is never stored
+            let mut instructions = compile_instructions(value, ctx, state)?;
+            state.tail = tail;
+            instructions.append(&mut compile_instructions(body, ctx, state)?);
             Ok(instructions)
         }
-        Expression::Let(identifier, value, body, _) => {
-            let slot = add(identifier.clone(), env);  // Add returns the slot directly, not a new env
-            let mut instructions = compile_expression(value, env)?;  // Continue using the same env
-            instructions.push(Instruction::Mov(
-                Arg::RegistryOffset(Reg::Rsp, -slot),  // Correctly calculate the offset
-                Arg::Registry(Reg::Rax),
-            ));
-            let mut body_instructions = compile_expression(body, env)?;
-            instructions.append(&mut body_instructions);
+        Expression::Let(identifier, value, body, _) | Expression::LetMut(identifier, value, body, _) => {
+            if ctx.options.deny_shadowing && ctx.env.contains(identifier) {
+                return Err(CompileError::DuplicateBinding(identifier.clone()));
+            }
+            let mut scope = ctx.scope();  // The binding goes out of scope when the guard drops
+            // The value is compiled first, so it reads the binding being shadowed (if any)
+            let mut instructions = compile_instructions(value, &mut scope, state)?;
+            let slot = scope.add(identifier.clone())?;
+            if matches!(expression, Expression::LetMut(..)) {
+                scope.env.mark_mutable(slot);
+            }
+            instructions.push(Instruction::Mov(local(slot, &scope.options), RAX));
+            state.rax = Some(slot);
+            state.tail = tail;
+            instructions.append(&mut compile_instructions(body, &mut scope, state)?);
             Ok(instructions)
         }
+        Expression::Declare(identifier, body, _) => {
+            if ctx.options.deny_shadowing && ctx.env.contains(identifier) {
+                return Err(CompileError::DuplicateBinding(identifier.clone()));
+            }
+            // The slot is left as it is: `check_initialized` made sure it is stored before it is read
+            let mut scope = ctx.scope();
+            let slot = scope.add(identifier.clone())?;
+            scope.env.mark_mutable(slot);
+            state.tail = tail;
+            compile_instructions(body, &mut scope, state)
+        }
 
         Expression::Identifier(identifier, _) => {
-            let slot = env.get(identifier).ok_or(())?;
-            Ok(vec![Mov(Registry(Rax), RegistryOffset(Rsp, -*slot))])
+            let slot = slot_of(identifier, &ctx.env)?;
+            if ctx.options.cache_loads && state.rax == Some(slot) {
+                return Ok(Vec::new());  // Rax already holds the value
+            }
+            state.rax = Some(slot);
+            Ok(vec![Mov(RAX, local(slot, &ctx.options))])
+        }
+
+        Expression::Binary(operator, lhs, rhs, _) => {
+            let mut instructions = compile_instructions(lhs, ctx, state)?;
+            // An immediate needs no register at all, so it is used even without allocate_registers
+            let immediate = match &**rhs {
+                Expression::Number(value, _) => immediate_operation(operator, *value),
+                _ => None,
+            };
+            let leaf = match immediate {
+                None if ctx.options.allocate_registers => leaf_operand(rhs, ctx)?,
+                _ => None,
+            };
+            match (immediate, leaf) {
+                (Some(instruction), _) => instructions.push(instruction),
+                // The right operand can be loaded straight into Rcx, leaving the left one in Rax
+                (None, Some(operand)) => {
+                    instructions.push(Mov(RCX, operand));
+                    instructions.append(&mut operation(operator));
+                }
+                (None, None) => {
+                    let slot = add_temporary(&mut ctx.env)?;  // Keep the left operand safe while computing the right one
+                    instructions.push(Mov(local(slot, &ctx.options), RAX));
+                    state.rax = Some(slot);
+                    instructions.append(&mut compile_instructions(rhs, ctx, state)?);
+                    instructions.push(Mov(RCX, RAX));
+                    instructions.push(Mov(RAX, local(slot, &ctx.options)));
+                    instructions.append(&mut operation(operator));
+                }
+            }
+            state.rax = None;
+            Ok(instructions)
+        }
+
+        Expression::Sequence(statements, _) => {
+            let mut instructions = Vec::new();
+            for (index, statement) in statements.iter().enumerate() {
+                // Only the value of the last statement is kept in Rax
+                state.tail = tail && index + 1 == statements.len();
+                instructions.append(&mut compile_instructions(statement, ctx, state)?);
+            }
+            Ok(instructions)
+        }
+
+        Expression::Assign(identifier, value, _) => {
+            let slot = slot_of(identifier, &ctx.env)?;
+            if !ctx.env.is_mutable(identifier) {
+                return Err(CompileError::AssignToImmutable(identifier.clone()));
+            }
+            let mut instructions = compile_instructions(value, ctx, state)?;
+            instructions.push(Mov(local(slot, &ctx.options), RAX));
+            state.rax = Some(slot);
+            Ok(instructions)
+        }
+
+        Expression::Assert(condition, metadata) => {
+            let mut instructions = compile_condition(condition, ctx, state)?;
+            let label = format!("assert{}", ctx.labels.fresh("assert"));
+            instructions.extend([
+                Jump(Condition::NotEqual, label.clone()),
+                Call(String::from(ASSERT_FAILED_SYMBOL)).located(metadata.span()),  // Never returns
+                Label(label),
+            ]);
+            Ok(instructions)
+        }
+
+        Expression::LetTuple(identifiers, value, body, _) => {
+            if ctx.options.deny_shadowing {
+                if let Some(identifier) = identifiers.iter().find(|identifier| ctx.env.contains(identifier)) {
+                    return Err(CompileError::DuplicateBinding(identifier.clone()));
+                }
+            }
+            let mut scope = ctx.scope();
+            let mut instructions = Vec::new();
+            let mut slots = Vec::new();
+            // Every element is stored in its own slot before any name is bound, so the elements
+            // read the bindings being shadowed (if any)
+            for element in tuple_elements(identifiers, value)? {
+                instructions.append(&mut compile_instructions(element, &mut scope, state)?);
+                let slot = add_temporary(&mut scope.env)?;
+                instructions.push(Mov(local(slot, &scope.options), RAX));
+                state.rax = Some(slot);
+                slots.push(slot);
+            }
+            for (identifier, slot) in identifiers.iter().zip(slots) {
+                if identifier != DISCARD {
+                    scope.bind(identifier.clone(), slot);
+                }
+            }
+            state.tail = tail;
+            instructions.append(&mut compile_instructions(body, &mut scope, state)?);
+            Ok(instructions)
+        }
+
+        Expression::Tuple(..) => Err(CompileError::TupleValue),
+
+        Expression::Array(..) => Err(CompileError::ArrayValue),
+
+        Expression::Index(array, index, metadata) => {
+            let elements = array_elements(array)?;
+            let constant = match **index {
+                Expression::Number(index, _) => Some(array_position(index, elements.len())?),
+                _ => None,
+            };
+            // The region is reserved before any element is compiled, so its slots are consecutive
+            // even if the elements need temporaries of their own
+            let slots = elements.iter().map(|_| add_temporary(&mut ctx.env)).collect::<Result<Vec<i64>, _>>()?;
+            let mut instructions = Vec::new();
+            for (element, slot) in elements.iter().zip(&slots) {
+                instructions.append(&mut compile_instructions(element, ctx, state)?);
+                instructions.push(Mov(local(*slot, &ctx.options), RAX));
+            }
+            match (constant, slots.first()) {
+                (Some(position), Some(first)) => {
+                    instructions.push(Mov(RAX, array_element(*first, position, &ctx.options)));
+                }
+                (_, first) => {
+                    instructions.append(&mut compile_instructions(index, ctx, state)?);
+                    // An empty array has no element to read, so the index is always out of bounds
+                    if ctx.options.sanitize || first.is_none() {
+                        let label = format!("inbounds{}", ctx.labels.fresh("inbounds"));
+                        instructions.extend([
+                            Cmp(RAX, (elements.len() as i64).into()),
+                            Jump(Condition::Below, label.clone()),  // Negative indices compare as too large
+                            Call(String::from(INDEX_OUT_OF_BOUNDS_SYMBOL)).located(metadata.span()),  // Never returns
+                            Label(label),
+                        ]);
+                    }
+                    if let Some(first) = first {
+                        // The elements are stored downwards, so the element at the index is below the first one
+                        instructions.extend([
+                            Shl(RAX, i64::from(ctx.options.slot_bytes().trailing_zeros()).into()),
+                            Instruction::Lea(RCX, array_element(*first, 0, &ctx.options)),
+                            Sub(RCX, RAX),
+                            Mov(RAX, Arg::Memory(MemOperand::Base(Rcx, 0))),
+                        ]);
+                    }
+                }
+            }
+            state.rax = None;
+            Ok(instructions)
+        }
+
+        Expression::If(condition, then, otherwise, _) => {
+            let mut instructions = compile_condition(condition, ctx, state)?;
+            let index = ctx.labels.fresh("if");
+            let then_label = format!("then{}", index);
+            let end_label = format!("endif{}", index);
+            instructions.push(Jump(Condition::NotEqual, then_label.clone()));
+            instructions.append(&mut compile_instructions(otherwise, ctx, state)?);
+            instructions.extend([Jmp(end_label.clone()), Label(then_label)]);
+            state.rax = None;  // Jumped to from the condition
+            state.tail = tail;  // Only the end label follows the then branch
+            instructions.append(&mut compile_instructions(then, ctx, state)?);
+            instructions.push(Label(end_label));
+            state.rax = None;  // Reached from either branch
+            Ok(instructions)
         }
 
-        Expression::If(condition, then, else_, _) => {
-            unimplemented!()
+        Expression::Return(value, _) => {
+            state.tail = tail;
+            let mut instructions = compile_instructions(value, ctx, state)?;
+            if !tail {
+                let label = state.return_label.clone().unwrap_or_else(|| String::from(RETURN_LABEL));
+                instructions.push(Jmp(label));
+                state.returns = true;
+            }
+            Ok(instructions)
+        }
+
+        Expression::Func(function, rest, _) => {
+            let Function { name, parameters, body } = function.as_ref();
+            if parameters.len() > MAX_PARAMETERS {
+                return Err(CompileError::TooManyParameters { name: name.clone(), count: parameters.len() });
+            }
+            let label = format!("fn{}_{}", ctx.labels.fresh("fn"), name);
+            // The function is in scope in its own body, so it can call itself
+            state.functions.push((name.clone(), parameters.len(), label.clone()));
+            let mut routine = compile_function(&label, parameters, body, ctx, state)?;
+            state.routines.append(&mut routine);
+            state.tail = tail;
+            let instructions = compile_instructions(rest, ctx, state);
+            state.functions.pop();
+            instructions
+        }
+
+        Expression::Call(name, arguments, _) => {
+            let Some((_, arity, label)) = state.functions.iter().rev().find(|(function, _, _)| function == name).cloned() else {
+                return Err(CompileError::UndefinedFunction(name.clone()));
+            };
+            if arguments.len() != arity {
+                return Err(CompileError::ArgumentCount { name: name.clone(), expected: arity, found: arguments.len() });
+            }
+            let mut instructions = Vec::new();
+            let mut slots = Vec::new();
+            // Arguments can contain calls themselves, so they only go to registers once all are computed
+            for argument in arguments {
+                instructions.append(&mut compile_instructions(argument, ctx, state)?);
+                let slot = add_temporary(&mut ctx.env)?;
+                instructions.push(Mov(local(slot, &ctx.options), RAX));
+                state.rax = Some(slot);
+                slots.push(slot);
+            }
+            for (slot, register) in slots.into_iter().zip(ARGUMENT_REGISTERS) {
+                instructions.push(Mov(register.into(), local(slot, &ctx.options)));
+            }
+            instructions.append(&mut call(&label, ctx, state));
+            state.rax = None;  // Holds the result of the call
+            Ok(instructions)
+        }
+
+        Expression::Print(text, _) => {
+            let mut instructions = vec![Instruction::Lea(Rdi.into(), Arg::Label(state.intern(text)))];
+            instructions.append(&mut call(PRINT_SYMBOL, ctx, state));
+            instructions.push(Mov(RAX, 0.into()));
+            Ok(instructions)
         }
+
+        // Loops are only supported by the interpreter for now
+        Expression::While(..) => Err(CompileError::Unsupported("while")),
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
     use super::*;
     use expectest::prelude::*;
     use proptest::prelude::*;
-    use crate::ast::increment::Increment;
-    use crate::ast::number::Number;
+    use Arg::{Constant, Registry};
+    use Instruction::{Dec, Inc};
+    use Expression::{Binary, Decrement, Identifier, Increment, Let, Number};
 
     proptest!(
         #[test]
         fn test_compile_expression_number(value in any::<i64>()) {
-            let expr = Number::new(value, ());
-            let instructions = compile_expression(&expr, & mut Env::new()).unwrap();
+            let expr = Number(value, ());
+            let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
             expect!(instructions).to(be_equal_to(vec![Mov(Registry(Rax), Constant(value))]));
         }
 
         #[test]
         fn test_compile_expression_increment(value in any::<i64>()) {
-            let expr = Increment::new(Box::new(Number::new(value, ())), ());
-            let instructions = compile_expression(&expr, & mut Env::new()).unwrap();
+            let expr = Increment(Box::new(Number(value, ())), ());
+            let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
             expect!(instructions).to(be_equal_to(vec![
                 Mov(Registry(Rax), Constant(value)),
                 Inc(Registry(Rax)),
@@ -147,7 +1026,7 @@ mod tests {
         #[test]
         fn test_compile_expression_decrement(value in any::<i64>()) {
             let expr = Decrement(Box::new(Expression::Number(value, ())), ());
-            let instructions = compile_expression(&expr, & mut Env::new()).unwrap();
+            let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
             expect!(instructions).to(be_equal_to(vec![
                 Mov(Registry(Rax), Constant(value)),
                 Dec(Registry(Rax)),
@@ -157,7 +1036,7 @@ mod tests {
         #[test]
         fn test_compile_expression_let(value in any::<i64>()) {
             let let_expr = Let("x".to_string(), Box::new(Number(value, ())), Box::new(Identifier("x".to_string(), ())), ());
-            let instructions = compile_expression(&let_expr, &mut Env::new()).unwrap();
+            let instructions = compile_expression(&let_expr, &mut Ctx::default()).unwrap().instructions;
             expect!(instructions).to(be_equal_to(vec![
                 Mov(Registry(Rax), Constant(value)),
                 Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
@@ -168,7 +1047,7 @@ mod tests {
         #[test]
         fn test_compile_expression_identifier(value in any::<i64>()) {
             let expr = Let("x".to_string(), Box::new(Number(value, ())), Box::new(Identifier("x".to_string(), ())), ());
-            let instructions = compile_expression(&expr, &mut Env::new()).unwrap();
+            let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
             expect!(instructions).to(be_equal_to(vec![
                 Mov(Registry(Rax), Constant(value)),                // Move the value into Rax
                 Mov(RegistryOffset(Rsp, -1), Registry(Rax)),        // Store it in the stack slot for 'x'
@@ -178,18 +1057,88 @@ mod tests {
     );
     proptest!(
         #[test]
-        fn test_compile_expression_identifier_not_found(value in any::<i64>()) {
+        fn test_compile_expression_identifier_not_found(_value in any::<i64>()) {
             let expr = Identifier("x".to_string(), ());
-            let result = compile_expression(&expr, & mut Env::new());
-            expect!(result).to(be_err());
+            let result = compile_expression(&expr, &mut Ctx::default());
+            expect!(result).to(be_err().value(CompileError::UndefinedVariable {
+                name: "x".to_string(),
+                suggestion: None,
+            }));
+        }
+    );
+
+    proptest!(
+        #[test]
+        fn test_compile_expression_let_of_undefined_leaks_no_slot(
+            name in "[a-z]{1,4}",
+            bound in proptest::collection::vec("[a-z]{1,4}", 0..4),
+        ) {
+            // let <name> = undefined; <name>
+            let expr = Let(name.clone(), Box::new(Identifier("undefined".to_string(), ())), Box::new(Identifier(name, ())), ());
+            let mut ctx = Ctx::default();
+            expect!(compile_expression(&expr, &mut ctx)).to(be_err());
+            expect!(ctx.env.len()).to(be_equal_to(0));
+
+            for name in bound {
+                crate::env::add(name, &mut ctx.env).unwrap();
+            }
+            let before = ctx.env.snapshot();
+            let names: Vec<String> = ctx.env.names().map(String::from).collect();
+            expect!(compile_expression(&expr, &mut ctx)).to(be_err());
+            expect!(ctx.env.snapshot()).to(be_equal_to(before));
+            expect!(ctx.env.names().map(String::from).collect::<Vec<_>>()).to(be_equal_to(names));
+        }
+    );
+
+    proptest!(
+        #[test]
+        fn test_compile_expression_loads_variables_from_the_slots_they_were_stored_in(
+            names in proptest::collection::vec("[a-e]", 1..8),
+            extra in proptest::collection::vec(any::<proptest::sample::Index>(), 0..8),
+            frame in any::<bool>(),
+        ) {
+            // let <names[0]> = 0; let <names[1]> = 1; ...; <every name>; <more names>
+            let mut references: Vec<&String> = names.iter().collect();
+            references.extend(extra.iter().map(|index| index.get(&names)));
+            let body = Expression::Sequence(references.iter().map(|name| Identifier((*name).clone(), ())).collect(), ());
+            let expr = names.iter().enumerate().rev().fold(body, |body, (value, name)| {
+                Let(name.clone(), Box::new(Number(value as i64, ())), Box::new(body), ())
+            });
+            let options = CompileOptions { frame, ..Default::default() };
+            let instructions = compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions;
+
+            let stores: Vec<&Arg> = instructions.iter().filter_map(|instruction| match instruction {
+                Mov(slot @ RegistryOffset(..), Registry(Rax)) => Some(slot),
+                _ => None,
+            }).collect();
+            let loads: Vec<&Arg> = instructions.iter().filter_map(|instruction| match instruction {
+                Mov(Registry(Rax), slot @ RegistryOffset(..)) => Some(slot),
+                _ => None,
+            }).collect();
+            expect!(stores.len()).to(be_equal_to(names.len()));
+            expect!(loads.len()).to(be_equal_to(references.len()));
+            for (reference, load) in references.into_iter().zip(loads) {
+                // A name refers to its innermost binding, the last one in the program
+                let binding = names.iter().rposition(|name| name == reference).unwrap();
+                expect!(load).to(be_equal_to(stores[binding]));
+            }
         }
     );
 
+    #[test]
+    fn test_compile_expression_suggests_similar_names() {
+        // let food = 1; foo
+        let expr = Let("food".to_string(), Box::new(Number(1, ())), Box::new(Identifier("foo".to_string(), ())), ());
+        let result = compile_expression(&expr, &mut Ctx::default());
+        expect!(result.map_err(|error| error.to_string()))
+            .to(be_err().value("undefined variable 'foo'; did you mean 'food'?".to_string()));
+    }
+
     proptest!(
         #[test]
         fn test_compile_expression_mixed(value in any::<i64>()) {
             let expr = Let("x".to_string(), Box::new(Increment(Box::new(Number(value, ())), ())), Box::new(Decrement(Box::new(Identifier("x".to_string(), ())), ())), ());
-            let instructions = compile_expression(&expr, &mut Env::new()).unwrap();
+            let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
             expect!(instructions).to(be_equal_to(vec![
                 Mov(Registry(Rax), Constant(value)),
                 Inc(Registry(Rax)),
@@ -199,4 +1148,1232 @@ mod tests {
             ]));
         }
     );
+
+    proptest!(
+        #[test]
+        fn test_compile_expression_binary(lhs in any::<i64>(), rhs in any::<i64>()) {
+            let operators = [
+                (BinaryOperator::Add, Add(Registry(Rax), Registry(Rcx))),
+                (BinaryOperator::Subtract, Sub(Registry(Rax), Registry(Rcx))),
+                (BinaryOperator::BitAnd, And(Registry(Rax), Registry(Rcx))),
+                (BinaryOperator::BitOr, Or(Registry(Rax), Registry(Rcx))),
+                (BinaryOperator::BitXor, Xor(Registry(Rax), Registry(Rcx))),
+                (BinaryOperator::ShiftLeft, Shl(Registry(Rax), Registry(Cl))),
+                (BinaryOperator::ShiftRight, Sar(Registry(Rax), Registry(Cl))),
+            ];
+            for (operator, instruction) in operators {
+                let expr = Binary(operator, Box::new(Number(lhs, ())), Box::new(Number(rhs, ())), ());
+                let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
+                expect!(instructions).to(be_equal_to(vec![
+                    Mov(Registry(Rax), Constant(lhs)),
+                    Mov(RegistryOffset(Rsp, -1), Registry(Rax)),        // Spill the left operand
+                    Mov(Registry(Rax), Constant(rhs)),
+                    Mov(Registry(Rcx), Registry(Rax)),                  // Move the right operand aside
+                    Mov(Registry(Rax), RegistryOffset(Rsp, -1)),        // Reload the left operand
+                    instruction,
+                ]));
+            }
+        }
+
+        #[test]
+        fn test_compile_expression_binary_does_not_clobber_bindings(value in any::<i64>()) {
+            let expr = Let(
+                "x".to_string(),
+                Box::new(Number(value, ())),
+                Box::new(Binary(
+                    BinaryOperator::BitXor,
+                    Box::new(Identifier("x".to_string(), ())),
+                    Box::new(Identifier("x".to_string(), ())),
+                    (),
+                )),
+                (),
+            );
+            let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
+            expect!(instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(value)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+                Mov(RegistryOffset(Rsp, -2), Registry(Rax)),        // The temporary gets its own slot
+                Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+                Mov(Registry(Rcx), Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -2)),
+                Xor(Registry(Rax), Registry(Rcx)),
+            ]));
+        }
+    );
+
+    #[test]
+    fn test_compile_expression_stack_slots() {
+        let expr = Let(
+            "x".to_string(),
+            Box::new(Number(1, ())),
+            Box::new(Binary(
+                BinaryOperator::BitOr,
+                Box::new(Identifier("x".to_string(), ())),
+                Box::new(Identifier("x".to_string(), ())),
+                (),
+            )),
+            (),
+        );
+        let unit = compile_expression(&expr, &mut Ctx::default()).unwrap();
+        expect!(unit.stack_slots).to(be_equal_to(2));  // One for `x` and one for the spilled operand
+
+        let unit = compile_expression(&Number(1, ()), &mut Ctx::default()).unwrap();
+        expect!(unit.stack_slots).to(be_equal_to(0));
+    }
+
+    #[test]
+    fn test_compile_expression_restores_a_failed_attempt() {
+        let mut ctx = Ctx::default();
+        crate::env::add("x".to_string(), &mut ctx.env).unwrap();
+        let snapshot = ctx.env.snapshot();
+        // Allocates a slot for `y` and one for the spilled operand before failing on `z`
+        let expr = Let(
+            "y".to_string(),
+            Box::new(Number(1, ())),
+            Box::new(Binary(
+                BinaryOperator::Add,
+                Box::new(Identifier("y".to_string(), ())),
+                Box::new(Identifier("z".to_string(), ())),
+                (),
+            )),
+            (),
+        );
+        expect!(compile_expression(&expr, &mut ctx)).to(be_err());
+        expect!(ctx.env.len()).to(be_equal_to(3));
+        ctx.env.restore(snapshot);
+        expect!(ctx.env.len()).to(be_equal_to(1));
+        expect!(ctx.env.names().collect::<Vec<_>>()).to(be_equal_to(vec!["x"]));
+    }
+
+    mod context {
+        use super::*;
+
+        #[test]
+        fn compiles_a_let_with_the_options_of_the_context() {
+            // let x = 42; x
+            let expr = Let("x".to_string(), Box::new(Number(42, ())), Box::new(Identifier("x".to_string(), ())), ());
+            let mut ctx = Ctx::new(CompileOptions { cache_loads: true, ..Default::default() });
+            let unit = compile_expression(&expr, &mut ctx).unwrap();
+            expect!(unit.instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(42)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+            ]));
+            expect!(ctx.env.len()).to(be_equal_to(1));
+            expect!(ctx.env.contains("x")).to(be_false());
+            expect!(ctx.errors.is_empty()).to(be_true());
+        }
+
+        #[test]
+        fn records_the_error_it_returns() {
+            let mut ctx = Ctx::default();
+            let error = compile_expression(&Identifier("x".to_string(), ()), &mut ctx).unwrap_err();
+            expect!(ctx.errors).to(be_equal_to(vec![error]));
+        }
+
+        #[test]
+        fn keeps_labels_unique_across_expressions() {
+            let assertion = Expression::Assert(Box::new(Number(1, ())), ());
+            let mut ctx = Ctx::default();
+            compile_expression(&assertion, &mut ctx).unwrap();
+            let unit = compile_expression(&assertion, &mut ctx).unwrap();
+            expect!(unit.instructions.contains(&Label("assert1".to_string()))).to(be_true());
+        }
+
+        #[test]
+        fn attaches_the_span_of_an_assertion_to_its_failure_call() {
+            // assert(0), with the span of each node
+            let span = |start, end| crate::parser::span::Span { start, end, line: 1, col: start + 1 };
+            let assertion = Expression::Assert(Box::new(Number(0, span(7, 8))), span(0, 9));
+            let unit = compile_expression(&assertion, &mut Ctx::default()).unwrap();
+            let call = unit.instructions.iter().find(|instruction| matches!(instruction.unlocated(), Call(_))).unwrap();
+            expect!(call.span()).to(be_some().value(span(0, 9)));
+            expect!(call.unlocated()).to(be_equal_to(&Call(ASSERT_FAILED_SYMBOL.to_string())));
+            let unlocated = compile_expression(&Expression::Assert(Box::new(Number(0, ())), ()), &mut Ctx::default());
+            expect!(unlocated.unwrap().instructions.contains(&Call(ASSERT_FAILED_SYMBOL.to_string()))).to(be_true());
+        }
+    }
+
+    mod deny_shadowing {
+        use super::*;
+
+        fn shadowing_program() -> Expression<()> {
+            // let x = 420; let x = 69; x
+            Let(
+                "x".to_string(),
+                Box::new(Number(420, ())),
+                Box::new(Let(
+                    "x".to_string(),
+                    Box::new(Number(69, ())),
+                    Box::new(Identifier("x".to_string(), ())),
+                    (),
+                )),
+                (),
+            )
+        }
+
+        #[test]
+        fn allows_shadowing_by_default() {
+            let instructions = compile_expression(&shadowing_program(), &mut Ctx::default())
+                .unwrap()
+                .instructions;
+            expect!(instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(420)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+                Mov(Registry(Rax), Constant(69)),
+                Mov(RegistryOffset(Rsp, -2), Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -2)),  // The innermost `x` wins
+            ]));
+        }
+
+        #[test]
+        fn rejects_shadowing_in_the_same_scope() {
+            let options = CompileOptions { deny_shadowing: true, ..Default::default() };
+            let result = compile_expression(&shadowing_program(), &mut Ctx::new(options));
+            expect!(result).to(be_err().value(CompileError::DuplicateBinding("x".to_string())));
+        }
+
+        #[test]
+        fn allows_reusing_a_name_after_its_scope_ends() {
+            // (let x = 1; x) | (let x = 2; x)
+            let scoped = |value| Let(
+                "x".to_string(),
+                Box::new(Number(value, ())),
+                Box::new(Identifier("x".to_string(), ())),
+                (),
+            );
+            let expr = Binary(BinaryOperator::BitOr, Box::new(scoped(1)), Box::new(scoped(2)), ());
+            let options = CompileOptions { deny_shadowing: true, ..Default::default() };
+            let result = compile_expression(&expr, &mut Ctx::new(options));
+            expect!(result).to(be_ok());
+        }
+
+        #[test]
+        fn restores_the_environment_after_an_error() {
+            // let x = 1; let x = 2; y
+            let expr = Let(
+                "x".to_string(),
+                Box::new(Number(1, ())),
+                Box::new(Let(
+                    "x".to_string(),
+                    Box::new(Number(2, ())),
+                    Box::new(Identifier("y".to_string(), ())),
+                    (),
+                )),
+                (),
+            );
+            let mut ctx = Ctx::default();
+            let result = compile_expression(&expr, &mut ctx);
+            expect!(result).to(be_err());
+            expect!(ctx.env.in_scope()).to(be_equal_to(0));
+        }
+
+        #[test]
+        fn restores_the_shadowed_binding_after_the_body() {
+            let mut ctx = Ctx::default();
+            compile_expression(&shadowing_program(), &mut ctx).unwrap();
+            expect!(ctx.env.contains("x")).to(be_false());
+            expect!(ctx.env.len()).to(be_equal_to(2));
+        }
+    }
+
+    mod frame {
+        use super::*;
+        use crate::asm::simulate::simulate;
+        use crate::parser::parse::parse;
+        use crate::parser::tokenize::tokenize;
+
+        #[test]
+        fn let_uses_rbp_offsets_once_a_frame_is_requested() {
+            // let x = 420; x++
+            let expr = Let(
+                "x".to_string(),
+                Box::new(Number(420, ())),
+                Box::new(Increment(Box::new(Identifier("x".to_string(), ())), ())),
+                (),
+            );
+            let options = CompileOptions { frame: true, ..Default::default() };
+            let instructions = compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions;
+            expect!(instructions).to(be_equal_to(vec![
+                Push(Registry(Rbp)),
+                Mov(Registry(Rbp), Registry(Rsp)),
+                Sub(Registry(Rsp), Constant(16)),                   // One slot, rounded up to 16 bytes
+                Mov(Registry(Rax), Constant(420)),
+                Mov(RegistryOffset(Rbp, -1), Registry(Rax)),        // Locals live below Rbp
+                Mov(Registry(Rax), RegistryOffset(Rbp, -1)),
+                Inc(Registry(Rax)),
+                Mov(Registry(Rsp), Registry(Rbp)),
+                Pop(Registry(Rbp)),
+            ]));
+        }
+
+        #[test]
+        fn reserves_every_slot_in_the_frame() {
+            // 1 | 2++ | 3++ needs two temporaries, which fit in a 16-byte frame
+            let increment = |value| Box::new(Increment(Box::new(Number(value, ())), ()));
+            let expr = Binary(
+                BinaryOperator::BitOr,
+                Box::new(Binary(BinaryOperator::BitOr, Box::new(Number(1, ())), increment(2), ())),
+                increment(3),
+                (),
+            );
+            let options = CompileOptions { frame: true, ..Default::default() };
+            let unit = compile_expression(&expr, &mut Ctx::new(options)).unwrap();
+            expect!(unit.stack_slots).to(be_equal_to(2));
+            expect!(unit.instructions[2].clone()).to(be_equal_to(Sub(Registry(Rsp), Constant(16))));
+            expect!(unit.instructions.iter().any(|i| matches!(i, Mov(RegistryOffset(Rsp, _), _))))
+                .to(be_false());
+        }
+
+        #[test]
+        fn leaves_rsp_alone_without_slots() {
+            let expr = Binary(BinaryOperator::Add, Box::new(Number(1, ())), Box::new(Number(2, ())), ());
+            let options = CompileOptions { frame: true, ..Default::default() };
+            let instructions = compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions;
+            expect!(instructions).to(be_equal_to(vec![
+                Push(Registry(Rbp)),
+                Mov(Registry(Rbp), Registry(Rsp)),
+                Mov(Registry(Rax), Constant(1)),
+                Add(Registry(Rax), Constant(2)),
+                Pop(Registry(Rbp)),
+            ]));
+        }
+
+        #[test]
+        fn is_set_up_on_targets_without_a_red_zone() {
+            // let x = 1; x
+            let expr = Let("x".to_string(), Box::new(Number(1, ())), Box::new(Identifier("x".to_string(), ())), ());
+            let options = CompileOptions { target_abi: Abi::Win64, ..Default::default() };
+            let instructions = compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions;
+            expect!(instructions[..3].to_vec()).to(be_equal_to(vec![
+                Push(Registry(Rbp)),
+                Mov(Registry(Rbp), Registry(Rsp)),
+                Sub(Registry(Rsp), Constant(16)),
+            ]));
+            expect!(instructions.iter().any(|i| matches!(i, Mov(RegistryOffset(Rsp, _), _)))).to(be_false());
+            let options = CompileOptions { target_abi: Abi::SystemV, ..Default::default() };
+            let instructions = compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions;
+            expect!(instructions[1].clone()).to(be_equal_to(Mov(RegistryOffset(Rsp, -1), Registry(Rax))));
+        }
+
+        /// Builds `let x0 = 0; let x1 = 1; ...; x0`, which takes one slot per binding.
+        fn bindings(count: usize) -> Expression<()> {
+            (0..count).rev().fold(Identifier("x0".to_string(), ()), |body, index| {
+                Let(format!("x{}", index), Box::new(Number(index as i64, ())), Box::new(body), ())
+            })
+        }
+
+        #[test]
+        fn is_set_up_when_the_slots_overflow_the_red_zone() {
+            // The 128 bytes of the System V red zone hold 16 slots of 8 bytes
+            let mut ctx = Ctx::default();
+            let unit = compile_expression(&bindings(16), &mut ctx).unwrap();
+            expect!(unit.instructions[1].clone()).to(be_equal_to(Mov(RegistryOffset(Rsp, -1), Registry(Rax))));
+            let mut ctx = Ctx::default();
+            let unit = compile_expression(&bindings(17), &mut ctx).unwrap();
+            expect!(unit.instructions[..3].to_vec()).to(be_equal_to(vec![
+                Push(Registry(Rbp)),
+                Mov(Registry(Rbp), Registry(Rsp)),
+                Sub(Registry(Rsp), Constant(144)),
+            ]));
+            expect!(unit.instructions.iter().any(|i| matches!(i, Mov(RegistryOffset(Rsp, _), _)))).to(be_false());
+            expect!(ctx.options.frame).to(be_false());
+        }
+
+        #[test]
+        fn makes_room_for_slots_of_the_configured_size() {
+            let options = CompileOptions { slot_size: 16, ..Default::default() };
+            let unit = compile_expression(&bindings(2), &mut Ctx::new(options.clone())).unwrap();
+            expect!(unit.instructions[1].clone()).to(be_equal_to(Mov(RegistryOffset(Rsp, -2), Registry(Rax))));
+            expect!(unit.instructions[3].clone()).to(be_equal_to(Mov(RegistryOffset(Rsp, -4), Registry(Rax))));
+            // 9 slots of 16 bytes no longer fit in the red zone
+            let unit = compile_expression(&bindings(9), &mut Ctx::new(options.clone())).unwrap();
+            expect!(unit.instructions[2].clone()).to(be_equal_to(Sub(Registry(Rsp), Constant(144))));
+            let expr = parse(&tokenize("let i = 2; [10, 20, 30][i]").unwrap()).unwrap();
+            let unit = compile_expression(&expr, &mut Ctx::new(options)).unwrap();
+            expect!(simulate(&unit.instructions)).to(be_ok().value(30));
+        }
+
+        #[test]
+        fn rounds_the_slot_size_up_to_a_power_of_two() {
+            expect!(CompileOptions { slot_size: 0, ..Default::default() }.slot_bytes()).to(be_equal_to(8));
+            expect!(CompileOptions { slot_size: 12, ..Default::default() }.slot_bytes()).to(be_equal_to(16));
+            expect!(CompileOptions::default().slot_bytes()).to(be_equal_to(8));
+        }
+    }
+
+    mod label_statements {
+        use super::*;
+        use crate::asm::to_string::asm_to_string;
+        use crate::parser::parse::parse;
+        use crate::parser::tokenize::tokenize;
+
+        fn compile_source(source: &str, options: &CompileOptions) -> Vec<Instruction> {
+            let expr = parse(&tokenize(source).unwrap()).unwrap();
+            compile_expression(&expr, &mut Ctx::new(options.clone())).unwrap().instructions
+        }
+
+        #[test]
+        fn labels_each_top_level_statement() {
+            let options = CompileOptions { label_statements: true, ..Default::default() };
+            let asm = asm_to_string(compile_source("1; 2 | 3; 4", &options));
+            let statements = ["stmt0:", "stmt1:", "stmt2:"].map(|label| asm.find(label));
+            expect!(statements.iter().all(Option::is_some)).to(be_true());
+            expect!(statements.is_sorted()).to(be_true());
+            expect!(asm.contains("stmt3:")).to(be_false());
+        }
+
+        #[test]
+        fn leaves_sequences_unlabeled_by_default() {
+            let labeled = CompileOptions { label_statements: true, ..Default::default() };
+            let unlabeled = compile_source("1; 2; 3", &CompileOptions::default());
+            let without_labels = compile_source("1; 2; 3", &labeled)
+                .into_iter()
+                .filter(|instruction| !matches!(instruction, Label(_)))
+                .collect::<Vec<Instruction>>();
+            expect!(unlabeled.iter().any(|instruction| matches!(instruction, Label(_)))).to(be_false());
+            expect!(without_labels).to(be_equal_to(unlabeled));
+        }
+    }
+
+    mod discard {
+        use super::*;
+        use crate::parser::parse::parse;
+        use crate::parser::tokenize::tokenize;
+
+        #[test]
+        fn does_not_store_discarded_values() {
+            let expr = parse(&tokenize("let _ = 5; 7").unwrap()).unwrap();
+            let unit = compile_expression(&expr, &mut Ctx::default()).unwrap();
+            expect!(unit.instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(5)),
+                Mov(Registry(Rax), Constant(7)),
+            ]));
+            expect!(unit.stack_slots).to(be_equal_to(0));
+        }
+
+        #[test]
+        fn can_be_repeated_when_shadowing_is_denied() {
+            let expr = parse(&tokenize("let _ = 5; let _ = 6; 7").unwrap()).unwrap();
+            let options = CompileOptions { deny_shadowing: true, ..Default::default() };
+            expect!(compile_expression(&expr, &mut Ctx::new(options))).to(be_ok());
+        }
+    }
+
+    mod assign {
+        use super::*;
+        use crate::parser::parse::parse;
+        use crate::parser::tokenize::tokenize;
+
+        #[test]
+        fn stores_the_value_in_the_slot_of_the_variable() {
+            // let mut x = 1; x = 2
+            let expr = Expression::LetMut(
+                "x".to_string(),
+                Box::new(Number(1, ())),
+                Box::new(Expression::Assign("x".to_string(), Box::new(Number(2, ())), ())),
+                (),
+            );
+            let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
+            expect!(instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(1)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+                Mov(Registry(Rax), Constant(2)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+            ]));
+        }
+
+        #[test]
+        fn fails_on_undefined_variable() {
+            let expr = Expression::Assign("x".to_string(), Box::new(Number(2, ())), ());
+            let result = compile_expression(&expr, &mut Ctx::default());
+            expect!(result).to(be_err().value(CompileError::UndefinedVariable {
+                name: "x".to_string(),
+                suggestion: None,
+            }));
+        }
+
+        fn compile_source(source: &str) -> Result<CompiledUnit, CompileError> {
+            compile_expression(&parse(&tokenize(source).unwrap()).unwrap(), &mut Ctx::default())
+        }
+
+        #[test]
+        fn only_assigns_to_mutable_bindings() {
+            expect!(compile_source("let mut x = 1; x = 2; x")).to(be_ok());
+            let immutable = CompileError::AssignToImmutable("x".to_string());
+            expect!(compile_source("let x = 1; x = 2; x")).to(be_err().value(immutable.clone()));
+            expect!(compile_source("let mut x = 1; let x = 2; x = 3")).to(be_err().value(immutable.clone()));
+            expect!(compile_source("let x = 1; (let mut x = 2; x = 3); x")).to(be_ok());
+            expect!(compile_source("let (x, y) = (1, 2); x = 3")).to(be_err().value(immutable.clone()));
+            expect!(compile_source("fn f(x) = (x = 2; x); f(1)")).to(be_err().value(immutable));
+        }
+
+        #[test]
+        fn stores_a_declared_variable_only_once_it_is_assigned() {
+            let unit = compile_source("let mut x; x = 7; x++").unwrap();
+            expect!(unit.instructions.clone()).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(7)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+                Inc(Registry(Rax)),
+            ]));
+            expect!(crate::asm::simulate::simulate(&unit.instructions)).to(be_ok().value(8));
+        }
+
+        #[test]
+        fn rejects_reads_of_uninitialized_variables() {
+            let uninitialized = CompileError::UseOfUninitialized("x".to_string());
+            expect!(compile_source("let mut x; x")).to(be_err().value(uninitialized.clone()));
+            expect!(compile_source("let mut x; if 0 then x = 1 else 2; x++")).to(be_err().value(uninitialized.clone()));
+            expect!(compile_source("let mut x; if 0 then x = 1 else x = 2; x++")).to(be_ok());
+            let mut ctx = Ctx::default();
+            let program = parse(&tokenize("let mut x; x").unwrap()).unwrap();
+            expect!(compile_expression(&program, &mut ctx)).to(be_err());
+            expect!(ctx.errors).to(be_equal_to(vec![uninitialized]));
+        }
+    }
+
+    mod comparisons {
+        use super::*;
+
+        #[test]
+        fn sets_rax_from_the_flags() {
+            let expr = Binary(BinaryOperator::Less, Box::new(Number(1, ())), Box::new(Number(2, ())), ());
+            let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
+            expect!(instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(1)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+                Mov(Registry(Rax), Constant(2)),
+                Mov(Registry(Rcx), Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+                Cmp(Registry(Rax), Registry(Rcx)),
+                Set(Condition::Less, Registry(Al)),
+                Instruction::Movzx(Registry(Rax), Registry(Al)),
+            ]));
+        }
+    }
+
+    mod tuples {
+        use super::*;
+
+        #[test]
+        fn stores_each_element_in_its_own_slot() {
+            // let (x, y) = (1, 2); y
+            let expr = Expression::LetTuple(
+                vec!["x".to_string(), "y".to_string()],
+                Box::new(Expression::Tuple(vec![Number(1, ()), Number(2, ())], ())),
+                Box::new(Identifier("y".to_string(), ())),
+                (),
+            );
+            let unit = compile_expression(&expr, &mut Ctx::default()).unwrap();
+            expect!(unit.instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(1)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+                Mov(Registry(Rax), Constant(2)),
+                Mov(RegistryOffset(Rsp, -2), Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -2)),
+            ]));
+            expect!(unit.stack_slots).to(be_equal_to(2));
+        }
+
+        #[test]
+        fn rejects_tuples_used_as_values() {
+            let expr = Expression::Tuple(vec![Number(1, ()), Number(2, ())], ());
+            expect!(compile_expression(&expr, &mut Ctx::default()))
+                .to(be_err().value(CompileError::TupleValue));
+        }
+    }
+
+    mod arrays {
+        use super::*;
+        use crate::asm::simulate::simulate;
+        use crate::parser::parse::parse;
+        use crate::parser::tokenize::tokenize;
+
+        fn compile_source(source: &str, options: CompileOptions) -> Result<Vec<Instruction>, CompileError> {
+            let expr = parse(&tokenize(source).unwrap()).unwrap();
+            compile_expression(&expr, &mut Ctx::new(options)).map(|unit| unit.instructions)
+        }
+
+        #[test]
+        fn stores_the_elements_in_consecutive_slots() {
+            let unit = compile_expression(
+                &parse(&tokenize("[10, 20, 30][1]").unwrap()).unwrap(),
+                &mut Ctx::default(),
+            ).unwrap();
+            expect!(unit.instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(10)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+                Mov(Registry(Rax), Constant(20)),
+                Mov(RegistryOffset(Rsp, -2), Registry(Rax)),
+                Mov(Registry(Rax), Constant(30)),
+                Mov(RegistryOffset(Rsp, -3), Registry(Rax)),
+                Mov(Registry(Rax), Arg::Memory(MemOperand::Base(Rsp, -16))),
+            ]));
+            expect!(unit.stack_slots).to(be_equal_to(3));
+        }
+
+        #[test]
+        fn keeps_the_region_together_when_elements_need_temporaries() {
+            for frame in [false, true] {
+                let options = CompileOptions { frame, ..Default::default() };
+                let instructions = compile_source("let x = 5; [x - (1 - x), x, 7][2] + [x][0]", options).unwrap();
+                expect!(simulate(&instructions)).to(be_ok().value(12));
+            }
+        }
+
+        #[test]
+        fn rejects_indices_out_of_bounds() {
+            expect!(compile_source("[10, 20, 30][3]", CompileOptions::default()))
+                .to(be_err().value(CompileError::IndexOutOfBounds { index: 3, length: 3 }));
+            expect!(compile_source("[10][-1]", CompileOptions::default()))
+                .to(be_err().value(CompileError::IndexOutOfBounds { index: -1, length: 1 }));
+            expect!(compile_source("[][0]", CompileOptions::default()))
+                .to(be_err().value(CompileError::IndexOutOfBounds { index: 0, length: 0 }));
+        }
+
+        #[test]
+        fn rejects_arrays_used_as_values() {
+            expect!(compile_source("[1, 2] + 3", CompileOptions::default())).to(be_err().value(CompileError::ArrayValue));
+            expect!(compile_source("[[1], 2][0]", CompileOptions::default())).to(be_err().value(CompileError::ArrayValue));
+        }
+
+        #[test]
+        fn only_indexes_array_literals() {
+            expect!(compile_source("let x = 1; x[0]", CompileOptions::default()))
+                .to(be_err().value(CompileError::Unsupported("[]")));
+        }
+
+        #[test]
+        fn computes_the_address_of_other_indices() {
+            for frame in [false, true] {
+                let options = CompileOptions { frame, ..Default::default() };
+                let instructions = compile_source("let i = 1; [10, 20, 30][i++] - [5, 6][i--]", options).unwrap();
+                expect!(instructions.iter().any(|instruction| matches!(instruction, Call(_)))).to(be_false());
+                expect!(simulate(&instructions)).to(be_ok().value(25));
+            }
+        }
+    }
+
+    mod sanitize {
+        use super::*;
+        use crate::asm::simulate::simulate;
+        use crate::parser::parse::parse;
+        use crate::parser::tokenize::tokenize;
+
+        fn run_sanitized(source: &str) -> Result<i64, alloc::string::String> {
+            let expr = parse(&tokenize(source).unwrap()).unwrap();
+            let options = CompileOptions { sanitize: true, ..Default::default() };
+            simulate(&compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions)
+        }
+
+        #[test]
+        fn checks_the_index_before_reading_the_element() {
+            let expr = parse(&tokenize("let i = 1; [10, 20][i]").unwrap()).unwrap();
+            let options = CompileOptions { sanitize: true, ..Default::default() };
+            let instructions = compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions;
+            expect!(instructions[7..].to_vec()).to(be_equal_to(vec![
+                Cmp(Registry(Rax), Constant(2)),
+                Jump(Condition::Below, "inbounds0".to_string()),
+                Call(INDEX_OUT_OF_BOUNDS_SYMBOL.to_string()),
+                Label("inbounds0".to_string()),
+                Shl(Registry(Rax), Constant(3)),
+                Instruction::Lea(Registry(Rcx), Arg::Memory(MemOperand::Base(Rsp, -16))),
+                Sub(Registry(Rcx), Registry(Rax)),
+                Mov(Registry(Rax), Arg::Memory(MemOperand::Base(Rcx, 0))),
+            ]));
+        }
+
+        #[test]
+        fn reads_indices_in_bounds() {
+            expect!(run_sanitized("let i = 2; [10, 20, 30][i]")).to(be_ok().value(30));
+            expect!(run_sanitized("let i = 0; [10, 20, 30][i] + [7][i]")).to(be_ok().value(17));
+        }
+
+        #[test]
+        fn traps_on_indices_out_of_bounds() {
+            let trap = Err(format!("Call to external function {}", INDEX_OUT_OF_BOUNDS_SYMBOL));
+            expect!(run_sanitized("let i = 3; [10, 20, 30][i]")).to(be_equal_to(trap.clone()));
+            expect!(run_sanitized("let i = -1; [10, 20, 30][i]")).to(be_equal_to(trap.clone()));
+            expect!(run_sanitized("let i = 0; [][i]")).to(be_equal_to(trap));
+        }
+
+        #[test]
+        fn leaves_constant_indices_to_the_compiler() {
+            expect!(run_sanitized("[10, 20, 30][1]")).to(be_ok().value(20));
+            let expr = parse(&tokenize("[10, 20, 30][3]").unwrap()).unwrap();
+            let options = CompileOptions { sanitize: true, ..Default::default() };
+            expect!(compile_expression(&expr, &mut Ctx::new(options)))
+                .to(be_err().value(CompileError::IndexOutOfBounds { index: 3, length: 3 }));
+        }
+    }
+
+    mod builtins {
+        use super::*;
+
+        #[test]
+        fn selects_with_conditional_moves() {
+            for (operator, condition) in [(BinaryOperator::Min, Condition::Greater), (BinaryOperator::Max, Condition::Less)] {
+                let expr = Binary(operator, Box::new(Number(3, ())), Box::new(Number(7, ())), ());
+                let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
+                expect!(instructions).to(be_equal_to(vec![
+                    Mov(Registry(Rax), Constant(3)),
+                    Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+                    Mov(Registry(Rax), Constant(7)),
+                    Mov(Registry(Rcx), Registry(Rax)),
+                    Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+                    Cmp(Registry(Rax), Registry(Rcx)),
+                    Cmov(condition, Registry(Rax), Registry(Rcx)),
+                ]));
+            }
+        }
+    }
+
+    mod assert {
+        use super::*;
+
+        fn assertion(value: i64) -> Expression<()> {
+            Expression::Assert(Box::new(Number(value, ())), ())
+        }
+
+        #[test]
+        fn calls_the_trap_when_the_condition_is_zero() {
+            let instructions = compile_expression(&assertion(7), &mut Ctx::default()).unwrap().instructions;
+            expect!(instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(7)),
+                Test(Registry(Rax), Registry(Rax)),
+                Jump(Condition::NotEqual, "assert0".to_string()),
+                Call(ASSERT_FAILED_SYMBOL.to_string()),
+                Label("assert0".to_string()),
+            ]));
+        }
+
+        #[test]
+        fn gives_each_assertion_its_own_label() {
+            let expr = Expression::Sequence(vec![assertion(1), assertion(2)], ());
+            let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
+            let labels: Vec<_> = instructions.into_iter().filter(|instruction| matches!(instruction, Label(_))).collect();
+            expect!(labels).to(be_equal_to(vec![Label("assert0".to_string()), Label("assert1".to_string())]));
+        }
+    }
+
+    mod control_flow {
+        use super::*;
+        use crate::parser::parse::parse;
+        use crate::parser::tokenize::tokenize;
+
+        #[test]
+        fn loops_are_not_supported_yet() {
+            let while_expr = Expression::While(Box::new(Number(0, ())), Box::new(Number(1, ())), ());
+            expect!(compile_expression(&while_expr, &mut Ctx::default()))
+                .to(be_err().value(CompileError::Unsupported("while")));
+        }
+
+        #[test]
+        fn branches_on_a_nonzero_condition() {
+            let if_expr = Expression::If(Box::new(Number(1, ())), Box::new(Number(2, ())), Box::new(Number(3, ())), ());
+            let instructions = compile_expression(&if_expr, &mut Ctx::default()).unwrap().instructions;
+            expect!(instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(1)),
+                Test(Registry(Rax), Registry(Rax)),
+                Jump(Condition::NotEqual, "then0".to_string()),
+                Mov(Registry(Rax), Constant(3)),
+                Jmp("endif0".to_string()),
+                Label("then0".to_string()),
+                Mov(Registry(Rax), Constant(2)),
+                Label("endif0".to_string()),
+            ]));
+        }
+
+        #[test]
+        fn jumps_to_the_epilogue_on_return() {
+            let expr = Expression::Sequence(vec![
+                Expression::Return(Box::new(Number(5, ())), ()),
+                Number(7, ()),
+            ], ());
+            let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
+            expect!(instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(5)),
+                Jmp(RETURN_LABEL.to_string()),
+                Mov(Registry(Rax), Constant(7)),
+                Label(RETURN_LABEL.to_string()),
+            ]));
+        }
+
+        #[test]
+        fn omits_the_jump_of_a_return_in_tail_position() {
+            let expr = Expression::Sequence(vec![
+                Number(7, ()),
+                Expression::Return(Box::new(Number(5, ())), ()),
+            ], ());
+            let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
+            expect!(instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(7)),
+                Mov(Registry(Rax), Constant(5)),
+            ]));
+        }
+
+        #[test]
+        fn finds_the_tail_position_through_bindings_and_branches() {
+            let compile_source = |source: &str| {
+                let expr = parse(&tokenize(source).unwrap()).unwrap();
+                compile_expression(&expr, &mut Ctx::default()).unwrap()
+            };
+            let jumps = |instructions: Vec<Instruction>| instructions.into_iter().filter(|instruction| matches!(instruction, Jmp(_))).count();
+            // The jump over the then branch is the only one left
+            expect!(jumps(compile_source("let x = 1; if x then return 2 else 3").instructions)).to(be_equal_to(1));
+            expect!(jumps(compile_source("fn f(x) = (x; return x); f(1)").functions)).to(be_equal_to(0));
+            // A return in the else branch is followed by the jump over the then branch
+            expect!(jumps(compile_source("let x = 1; if x then 2 else return 3").instructions)).to(be_equal_to(2));
+            // A return in the value of a let is not in tail position, even when the let is
+            expect!(jumps(compile_source("let x = return 1; x").instructions)).to(be_equal_to(1));
+        }
+
+        #[test]
+        fn omits_the_epilogue_label_without_a_return() {
+            let instructions = compile_expression(&Number(7, ()), &mut Ctx::default()).unwrap().instructions;
+            expect!(instructions.contains(&Label(RETURN_LABEL.to_string()))).to(be_false());
+        }
+    }
+
+    mod print {
+        use super::*;
+
+        fn print(text: &str) -> Expression<()> {
+            Expression::Print(text.to_string(), ())
+        }
+
+        #[test]
+        fn passes_the_address_of_the_string_to_the_runtime() {
+            let options = CompileOptions { frame: true, ..Default::default() };
+            let unit = compile_expression(&print("hi"), &mut Ctx::new(options)).unwrap();
+            expect!(unit.instructions).to(be_equal_to(vec![
+                Push(Registry(Rbp)),
+                Mov(Registry(Rbp), Registry(Rsp)),
+                Instruction::Lea(Registry(Rdi), Arg::Label("str0".to_string())),
+                Call(PRINT_SYMBOL.to_string()),
+                Mov(Registry(Rax), Constant(0)),
+                Pop(Registry(Rbp)),
+            ]));
+            expect!(unit.data).to(be_equal_to(vec![Data { label: "str0".to_string(), value: "hi".to_string() }]));
+        }
+
+        #[test]
+        fn keeps_the_slots_in_use_below_the_call_without_a_frame() {
+            let expr = Let("x".to_string(), Box::new(Number(1, ())), Box::new(print("hi")), ());
+            let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
+            expect!(instructions[2..5].to_vec()).to(be_equal_to(vec![
+                Instruction::Lea(Registry(Rdi), Arg::Label("str0".to_string())),
+                Sub(Registry(Rsp), Constant(8)),
+                Call(PRINT_SYMBOL.to_string()),
+            ]));
+            expect!(instructions[5].clone()).to(be_equal_to(Add(Registry(Rsp), Constant(8))));
+        }
+
+        #[test]
+        fn shares_the_data_of_equal_strings() {
+            let expr = Expression::Sequence(vec![print("hi"), print("bye"), print("hi")], ());
+            let data = compile_expression(&expr, &mut Ctx::default()).unwrap().data;
+            expect!(data).to(be_equal_to(vec![
+                Data { label: "str0".to_string(), value: "hi".to_string() },
+                Data { label: "str1".to_string(), value: "bye".to_string() },
+            ]));
+        }
+    }
+
+    mod functions {
+        use super::*;
+        use crate::parser::parse::parse;
+        use crate::parser::tokenize::tokenize;
+
+        fn compile_source(source: &str) -> Result<CompiledUnit, CompileError> {
+            compile_expression(&parse(&tokenize(source).unwrap()).unwrap(), &mut Ctx::default())
+        }
+
+        #[test]
+        fn compiles_the_body_into_a_routine_of_its_own() {
+            let unit = compile_source("fn add(a, b) = a + b; 0").unwrap();
+            expect!(unit.instructions).to(be_equal_to(vec![Mov(Registry(Rax), Constant(0))]));
+            expect!(unit.functions).to(be_equal_to(vec![
+                Label("fn0_add".to_string()),
+                Push(Registry(Rbp)),
+                Mov(Registry(Rbp), Registry(Rsp)),
+                Sub(Registry(Rsp), Constant(32)),
+                Mov(RegistryOffset(Rbp, -1), Registry(Rdi)),
+                Mov(RegistryOffset(Rbp, -2), Registry(Rsi)),
+                Mov(Registry(Rax), RegistryOffset(Rbp, -1)),
+                Mov(RegistryOffset(Rbp, -3), Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rbp, -2)),
+                Mov(Registry(Rcx), Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rbp, -3)),
+                Add(Registry(Rax), Registry(Rcx)),
+                Mov(Registry(Rsp), Registry(Rbp)),
+                Pop(Registry(Rbp)),
+                Ret,
+            ]));
+        }
+
+        #[test]
+        fn passes_the_arguments_in_registers() {
+            let unit = compile_source("fn add(a, b) = a + b; add(1, 2)").unwrap();
+            expect!(unit.instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(1)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+                Mov(Registry(Rax), Constant(2)),
+                Mov(RegistryOffset(Rsp, -2), Registry(Rax)),
+                Mov(Registry(Rdi), RegistryOffset(Rsp, -1)),
+                Mov(Registry(Rsi), RegistryOffset(Rsp, -2)),
+                Sub(Registry(Rsp), Constant(24)),
+                Call("fn0_add".to_string()),
+                Add(Registry(Rsp), Constant(24)),
+            ]));
+        }
+
+        #[test]
+        fn returns_from_the_function_only() {
+            let unit = compile_source("fn f(x) = (return x; 0); f(1); 2").unwrap();
+            expect!(unit.functions.contains(&Jmp("fn0_f_epilogue".to_string()))).to(be_true());
+            expect!(unit.functions.contains(&Label("fn0_f_epilogue".to_string()))).to(be_true());
+            expect!(unit.instructions.contains(&Label(RETURN_LABEL.to_string()))).to(be_false());
+        }
+
+        #[test]
+        fn rejects_calls_that_do_not_match_a_function() {
+            expect!(compile_source("f(1)")).to(be_err().value(CompileError::UndefinedFunction("f".to_string())));
+            expect!(compile_source("fn f(x) = x; f(1, 2)"))
+                .to(be_err().value(CompileError::ArgumentCount { name: "f".to_string(), expected: 1, found: 2 }));
+            expect!(compile_source("(fn f(x) = x; 0); f(1)"))
+                .to(be_err().value(CompileError::UndefinedFunction("f".to_string())));
+        }
+
+        #[test]
+        fn rejects_what_registers_cannot_pass() {
+            expect!(compile_source("fn f(a, b, c, d, e) = a; 0"))
+                .to(be_err().value(CompileError::TooManyParameters { name: "f".to_string(), count: 5 }));
+            expect!(compile_source("fn f(a, a) = a; 0")).to(be_err().value(CompileError::DuplicateBinding("a".to_string())));
+        }
+
+        #[test]
+        fn hides_the_variables_around_the_definition() {
+            expect!(compile_source("let y = 1; fn f(x) = x + y; f(2)")).to(be_err());
+        }
+    }
+
+    mod semantics {
+        use super::*;
+        use crate::asm::simulate::simulate;
+        use crate::ast::arbitrary;
+        use crate::interpreter::eval::{eval, Bindings};
+        use crate::parser::parse::parse;
+        use crate::parser::tokenize::tokenize;
+
+        proptest!(
+            #[test]
+            fn compiled_code_matches_the_interpreter(
+                expr in arbitrary::expression(4),
+                frame in any::<bool>(),
+                cache_loads in any::<bool>(),
+                allocate_registers in any::<bool>(),
+            ) {
+                let options = CompileOptions { frame, cache_loads, allocate_registers, ..Default::default() };
+                let instructions = compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions;
+                let expected = eval(&expr, &mut Bindings::new()).unwrap();
+                expect!(simulate(&instructions)).to(be_ok().value(expected));
+            }
+        );
+
+        #[test]
+        fn shadowing_reads_the_previous_binding() {
+            let expr = parse(&tokenize("let acc = 0; let acc = acc++; let acc = acc++; acc").unwrap()).unwrap();
+            expect!(eval(&expr, &mut Bindings::new())).to(be_ok().value(2));
+            for frame in [false, true] {
+                let options = CompileOptions { frame, ..Default::default() };
+                let instructions = compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions;
+                expect!(simulate(&instructions)).to(be_ok().value(2));
+            }
+        }
+
+        #[test]
+        fn builtins_match_the_interpreter() {
+            for (source, expected) in [("max(3, 7)", 7), ("min(3, 7)", 3), ("max(-1, -2)", -1), ("min(-1, -2)", -2)] {
+                let expr = parse(&tokenize(source).unwrap()).unwrap();
+                expect!(eval(&expr, &mut Bindings::new())).to(be_ok().value(expected));
+                let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
+                expect!(simulate(&instructions)).to(be_ok().value(expected));
+            }
+        }
+
+        #[test]
+        fn postfix_operators_apply_to_a_parenthesized_let() {
+            let expr = parse(&tokenize("(let x = 5; x)++").unwrap()).unwrap();
+            let instructions = compile_expression(&expr, &mut Ctx::default()).unwrap().instructions;
+            expect!(simulate(&instructions)).to(be_ok().value(6));
+        }
+
+        #[test]
+        fn tuple_destructuring_matches_the_interpreter() {
+            for source in ["let (x, y) = (1, 2); x - y", "let x = 1; let y = 2; let (x, y) = (y, x); x - y"] {
+                let expr = parse(&tokenize(source).unwrap()).unwrap();
+                let expected = eval(&expr, &mut Bindings::new()).unwrap();
+                for frame in [false, true] {
+                    let options = CompileOptions { frame, ..Default::default() };
+                    let instructions = compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions;
+                    expect!(simulate(&instructions)).to(be_ok().value(expected));
+                }
+            }
+        }
+
+        #[test]
+        fn sequences_keep_the_effects_of_every_statement() {
+            for (source, expected) in [
+                ("let x = 5; x++; x--", 4),  // The steps do not mutate `x`, so this is `5 - 1`
+                ("let mut x = 5; x = x++; x = x--; x", 5),
+                ("let mut x = 1; x = x + 9; x = x << 1; 3", 3),
+                ("let mut x = 1; x = x + 9; 0; x", 10),
+            ] {
+                let expr = parse(&tokenize(source).unwrap()).unwrap();
+                expect!(eval(&expr, &mut Bindings::new())).to(be_ok().value(expected));
+                for cache_loads in [false, true] {
+                    let options = CompileOptions { cache_loads, ..Default::default() };
+                    let instructions = compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions;
+                    expect!(simulate(&instructions)).to(be_ok().value(expected));
+                }
+            }
+            let trapping = parse(&tokenize("assert(0); 1; 2").unwrap()).unwrap();
+            let instructions = compile_expression(&trapping, &mut Ctx::default()).unwrap().instructions;
+            expect!(simulate(&instructions)).to(be_err());  // The discarded assertion still runs
+        }
+
+        #[test]
+        fn returns_match_the_interpreter() {
+            for (source, expected) in [
+                ("return 5; 7", 5),
+                ("if 1 then return 3 else 4", 3),
+                ("if 0 then return 3 else 4; 8", 8),
+                ("let x = 2; let y = (if x < 3 then 10 else 20); return y + x; 0", 12),
+            ] {
+                let expr = parse(&tokenize(source).unwrap()).unwrap();
+                expect!(eval(&expr, &mut Bindings::new())).to(be_ok().value(expected));
+                for (frame, linear_scan) in [(false, false), (true, false), (true, true)] {
+                    let options = CompileOptions { frame, cache_loads: true, linear_scan, ..Default::default() };
+                    let instructions = compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions;
+                    expect!(simulate(&instructions)).to(be_ok().value(expected));
+                }
+            }
+        }
+
+        #[test]
+        fn assertions_pass_through_their_condition_or_trap() {
+            let passing = parse(&tokenize("assert(1 < 2); 7").unwrap()).unwrap();
+            let failing = parse(&tokenize("assert(2 < 1); 7").unwrap()).unwrap();
+            expect!(eval(&passing, &mut Bindings::new())).to(be_ok().value(7));
+            expect!(eval(&failing, &mut Bindings::new())).to(be_err().value(CompileError::AssertionFailed));
+            let compile = |expr| compile_expression(expr, &mut Ctx::default()).unwrap().instructions;
+            expect!(simulate(&compile(&passing))).to(be_ok().value(7));
+            expect!(simulate(&compile(&failing))).to(be_err());
+        }
+
+        #[test]
+        fn functions_match_the_interpreter() {
+            for (source, expected) in [
+                ("fn add(a, b) = a + b; add(1, 2)", 3),
+                ("fn add(a, b) = a + b; let x = 10; add(add(x, 1), x) - x", 11),
+                ("fn sum(n) = if n then n + sum(n--) else 0; sum(10)", 55),
+                ("fn f(x) = (if x < 0 then return 0 else 0; x); f(-5) + f(5)", 5),
+                ("fn four() = 4; fn twice(x) = x + x; twice(four()) + 1", 9),
+                ("fn f(a, b, c, d) = a - b + (c << d); f(9, 4, 1, 3)", 13),
+            ] {
+                let expr = parse(&tokenize(source).unwrap()).unwrap();
+                expect!(eval(&expr, &mut Bindings::new())).to(be_ok().value(expected));
+                for (frame, cache_loads, linear_scan) in [(false, false, false), (true, true, false), (false, false, true)] {
+                    let options = CompileOptions { frame, cache_loads, linear_scan, ..Default::default() };
+                    let unit = compile_expression(&expr, &mut Ctx::new(options)).unwrap();
+                    let mut instructions = unit.instructions;
+                    instructions.push(Ret);
+                    instructions.extend(unit.functions);
+                    expect!(simulate(&instructions)).to(be_ok().value(expected));
+                }
+            }
+        }
+    }
+
+    mod immediates {
+        use super::*;
+        use crate::parser::parse::parse;
+        use crate::parser::tokenize::tokenize;
+
+        fn compile_source(source: &str) -> CompiledUnit {
+            compile_expression(&parse(&tokenize(source).unwrap()).unwrap(), &mut Ctx::default()).unwrap()
+        }
+
+        #[test]
+        fn folds_a_small_constant_into_the_operation() {
+            let unit = compile_source("let x = 1; x + 1");
+            expect!(unit.instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(1)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+                Add(Registry(Rax), Constant(1)),
+            ]));
+            expect!(unit.stack_slots).to(be_equal_to(1));  // Only `x`, as nothing is spilled
+            let last = compile_source("let x = 1; x - -7").instructions.last().cloned();
+            expect!(last).to(be_some().value(Sub(Registry(Rax), Constant(-7))));
+        }
+
+        #[test]
+        fn materializes_a_constant_beyond_32_bits() {
+            let unit = compile_source("let x = 1; x + 5000000000");
+            expect!(unit.instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(1)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+                Mov(RegistryOffset(Rsp, -2), Registry(Rax)),
+                Mov(Registry(Rax), Constant(5000000000)),
+                Mov(Registry(Rcx), Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -2)),
+                Add(Registry(Rax), Registry(Rcx)),
+            ]));
+            expect!(unit.stack_slots).to(be_equal_to(2));
+        }
+
+        #[test]
+        fn only_folds_a_constant_right_operand() {
+            let unit = compile_source("let x = 1; 1 + x");
+            expect!(unit.instructions.last().cloned()).to(be_some().value(Add(Registry(Rax), Registry(Rcx))));
+        }
+    }
+
+    mod allocate_registers {
+        use super::*;
+        use crate::parser::parse::parse;
+        use crate::parser::tokenize::tokenize;
+
+        fn compile_source(source: &str) -> CompiledUnit {
+            let expr = parse(&tokenize(source).unwrap()).unwrap();
+            let options = CompileOptions { allocate_registers: true, ..Default::default() };
+            compile_expression(&expr, &mut Ctx::new(options)).unwrap()
+        }
+
+        #[test]
+        fn keeps_the_left_operand_in_rax_for_a_constant() {
+            let unit = compile_source("5 + 3");
+            expect!(unit.instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(5)),
+                Add(Registry(Rax), Constant(3)),
+            ]));
+            expect!(unit.stack_slots).to(be_equal_to(0));
+        }
+
+        #[test]
+        fn moves_constants_beyond_32_bits_into_a_register() {
+            expect!(compile_source("let x = 1; x + 5000000000").instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(1)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+                Mov(Registry(Rcx), Constant(5000000000)),
+                Add(Registry(Rax), Registry(Rcx)),
+            ]));
+        }
+
+        #[test]
+        fn encodes_constants_up_to_the_32_bit_bounds_as_immediates() {
+            let operand = |source: &str| compile_source(source).instructions.last().cloned();
+            expect!(operand("1 - 2147483647")).to(be_some().value(Sub(Registry(Rax), Constant(2147483647))));
+            expect!(operand("1 ^ -2147483648")).to(be_some().value(Xor(Registry(Rax), Constant(-2147483648))));
+            expect!(operand("1 & 2147483648")).to(be_some().value(And(Registry(Rax), Registry(Rcx))));
+            expect!(operand("1 | -2147483649")).to(be_some().value(Or(Registry(Rax), Registry(Rcx))));
+        }
+
+        #[test]
+        fn loads_constants_of_operators_without_an_immediate_form() {
+            expect!(&compile_source("1 < 2").instructions[1]).to(be_equal_to(&Mov(Registry(Rcx), Constant(2))));
+            expect!(&compile_source("1 << 2").instructions[1]).to(be_equal_to(&Mov(Registry(Rcx), Constant(2))));
+        }
+
+        #[test]
+        fn reads_a_variable_operand_from_its_slot() {
+            expect!(compile_source("let x = 3; 5 - x").instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(3)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+                Mov(Registry(Rax), Constant(5)),
+                Mov(Registry(Rcx), RegistryOffset(Rsp, -1)),
+                Sub(Registry(Rax), Registry(Rcx)),
+            ]));
+        }
+
+        #[test]
+        fn spills_the_left_operand_for_a_computed_operand() {
+            let unit = compile_source("let x = 3; 5 + (x++)");
+            expect!(unit.instructions).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(3)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+                Mov(Registry(Rax), Constant(5)),
+                Mov(RegistryOffset(Rsp, -2), Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+                Inc(Registry(Rax)),
+                Mov(Registry(Rcx), Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -2)),
+                Add(Registry(Rax), Registry(Rcx)),
+            ]));
+            expect!(unit.stack_slots).to(be_equal_to(2));
+        }
+
+        #[test]
+        fn reports_undefined_variables() {
+            let expr = parse(&tokenize("let value = 1; 5 + valu").unwrap()).unwrap();
+            let options = CompileOptions { allocate_registers: true, ..Default::default() };
+            expect!(compile_expression(&expr, &mut Ctx::new(options))).to(be_err().value(CompileError::UndefinedVariable {
+                name: "valu".to_string(),
+                suggestion: Some("value".to_string()),
+            }));
+        }
+    }
+
+    mod cache_loads {
+        use super::*;
+        use crate::parser::parse::parse;
+        use crate::parser::tokenize::tokenize;
+
+        fn compile_source(source: &str, cache_loads: bool) -> Vec<Instruction> {
+            let expr = parse(&tokenize(source).unwrap()).unwrap();
+            let options = CompileOptions { cache_loads, ..Default::default() };
+            compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions
+        }
+
+        #[test]
+        fn skips_loads_of_the_value_in_rax() {
+            let source = "let x = 420; x; x++; x--; x";
+            expect!(compile_source(source, false)).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(420)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+                Inc(Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+                Dec(Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+            ]));
+            expect!(compile_source(source, true)).to(be_equal_to(vec![
+                Mov(Registry(Rax), Constant(420)),
+                Mov(RegistryOffset(Rsp, -1), Registry(Rax)),  // Rax still holds `x`...
+                Inc(Registry(Rax)),                           // ...until it is overwritten
+                Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+                Dec(Registry(Rax)),
+                Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+            ]));
+        }
+
+        #[test]
+        fn reloads_after_a_binary_operation() {
+            let instructions = compile_source("let x = 1; (x | 2); x", true);
+            expect!(instructions.last()).to(be_some().value(&Mov(Registry(Rax), RegistryOffset(Rsp, -1))));
+        }
+
+        #[test]
+        fn reloads_after_a_call() {
+            // Rax is caller-saved, so the runtime may have overwritten the value of `x` in it
+            let instructions = compile_source("let x = 1; print(\"a\"); x", true);
+            expect!(instructions.last()).to(be_some().value(&Mov(Registry(Rax), RegistryOffset(Rsp, -1))));
+        }
+    }
 }
@@ -0,0 +1,106 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use crate::compiler::error::CompileError;
+use crate::compiler::options::CompileOptions;
+use crate::env::{Env, ScopeGuard};
+
+/// Everything `compile_expression` carries from one expression to the next: the variables in
+/// scope, the labels handed out so far, the options, and the errors reported.
+///
+/// A context can be reused to compile several expressions, e.g. one line at a time, in which case
+/// the bindings left in `env` and the labels taken stay visible to the next one.
+///
+/// # Example
+///
+/// ```rust
+/// # use rengo::compiler::context::Ctx;
+/// # use rengo::compiler::options::CompileOptions;
+/// # use rengo::compiler::compile::compile_expression;
+/// # use rengo::parse_source;
+/// let mut ctx = Ctx::new(CompileOptions { frame: true, ..Default::default() });
+/// let unit = compile_expression(&parse_source("let x = 1; x")?, &mut ctx)?;
+/// assert_eq!(unit.stack_slots, ctx.env.len());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Ctx {
+    /// The variables in scope and the slots allocated so far.
+    pub env: Env,
+    /// The numbers that keep the labels of the compiled code unique.
+    pub labels: LabelGen,
+    /// How the expressions are translated.
+    pub options: CompileOptions,
+    /// The errors reported so far, oldest first. Compilation still stops at the first error of an
+    /// expression, which `compile_expression` both records here and returns.
+    pub errors: Vec<CompileError>,
+}
+
+impl Ctx {
+    /// Creates a context with an empty environment that compiles with the given options.
+    pub fn new(options: CompileOptions) -> Self {
+        Ctx { options, ..Default::default() }
+    }
+
+    /// Opens a new scope over the environment of the context. See `ScopeGuard`.
+    pub fn scope(&mut self) -> ScopeGuard<'_, Ctx> {
+        ScopeGuard::open(self)
+    }
+}
+
+impl AsMut<Env> for Ctx {
+    fn as_mut(&mut self) -> &mut Env {
+        &mut self.env
+    }
+}
+
+/// Hands out the numbers that make labels unique, counting each kind of label on its own so the
+/// names stay short and predictable (`assert0`, `then0`, `assert1`, ...).
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct LabelGen {
+    counts: BTreeMap<&'static str, usize>,
+}
+
+impl LabelGen {
+    /// Returns the next number for labels of the given kind, starting from 0.
+    pub fn fresh(&mut self, kind: &'static str) -> usize {
+        let count = self.counts.entry(kind).or_default();
+        *count += 1;
+        *count - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expectest::prelude::*;
+    use super::*;
+
+    mod label_gen {
+        use super::*;
+
+        #[test]
+        fn counts_each_kind_of_label_apart() {
+            let mut labels = LabelGen::default();
+            expect!(labels.fresh("assert")).to(be_equal_to(0));
+            expect!(labels.fresh("assert")).to(be_equal_to(1));
+            expect!(labels.fresh("if")).to(be_equal_to(0));
+            expect!(labels.fresh("assert")).to(be_equal_to(2));
+        }
+    }
+
+    mod scope {
+        use alloc::string::ToString;
+        use super::*;
+
+        #[test]
+        fn ends_with_the_guard() {
+            let mut ctx = Ctx::default();
+            {
+                let mut scope = ctx.scope();
+                scope.add("x".to_string()).unwrap();
+                expect!(scope.env.contains("x")).to(be_true());
+            }
+            expect!(ctx.env.contains("x")).to(be_false());
+            expect!(ctx.env.len()).to(be_equal_to(1));
+        }
+    }
+}
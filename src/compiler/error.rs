@@ -0,0 +1,336 @@
+use alloc::string::String;
+use core::fmt;
+use crate::compiler::compile::MAX_PARAMETERS;
+
+/// Represents an error found while compiling an `Expression`.
+///
+/// The `CompileError` enum describes why `compile_expression` could not translate an expression
+/// into instructions. Each variant carries the information needed to report the problem.
+#[derive(Debug, PartialEq, Clone)]
+pub enum CompileError {
+    /// An identifier was used without being bound by an enclosing `let`.
+    ///
+    /// Holds the name of the undefined variable and, if a variable in scope has a similar name,
+    /// that name as a suggestion.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::compiler::error::CompileError;
+    /// let error = CompileError::UndefinedVariable { name: "foo".to_string(), suggestion: Some("food".to_string()) };
+    /// assert_eq!(error.to_string(), "undefined variable 'foo'; did you mean 'food'?");
+    /// ```
+    UndefinedVariable {
+        name: String,
+        suggestion: Option<String>,
+    },
+
+    /// A `let` rebinds a name that is already bound in the same scope while shadowing is denied.
+    ///
+    /// Holds the name of the duplicated binding.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::compiler::error::CompileError;
+    /// let error = CompileError::DuplicateBinding("x".to_string());
+    /// ```
+    DuplicateBinding(String),
+
+    /// The expression is understood by the language but the code generator cannot translate it
+    /// yet.
+    ///
+    /// Holds the keyword of the unsupported construct.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::compiler::error::CompileError;
+    /// let error = CompileError::Unsupported("while");
+    /// ```
+    Unsupported(&'static str),
+
+    /// The interpreter ran more `while` iterations than its step limit allows, which usually means
+    /// the loop never terminates.
+    StepLimitExceeded,
+
+    /// The interpreter evaluated an `assert` whose condition is zero.
+    AssertionFailed,
+
+    /// A destructuring `let` binds a different number of names than its value has elements.
+    ///
+    /// Holds the number of names, and the number of elements of the value (1 if it is not a
+    /// tuple).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::compiler::error::CompileError;
+    /// let error = CompileError::TupleArity { expected: 2, found: 3 };
+    /// assert_eq!(error.to_string(), "expected a tuple of 2 elements, found 3");
+    /// ```
+    TupleArity {
+        expected: usize,
+        found: usize,
+    },
+
+    /// A tuple is used as a value instead of being destructured by a `let`.
+    TupleValue,
+
+    /// The program needs more stack slots for its variables and temporaries than can be numbered.
+    TooManyLocals,
+
+    /// A function was called without being defined by an enclosing `fn`.
+    ///
+    /// Holds the name of the undefined function.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::compiler::error::CompileError;
+    /// let error = CompileError::UndefinedFunction("f".to_string());
+    /// assert_eq!(error.to_string(), "undefined function 'f'");
+    /// ```
+    UndefinedFunction(String),
+
+    /// A function was called with a different number of arguments than it has parameters.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::compiler::error::CompileError;
+    /// let error = CompileError::ArgumentCount { name: "f".to_string(), expected: 2, found: 1 };
+    /// assert_eq!(error.to_string(), "function 'f' takes 2 arguments, found 1");
+    /// ```
+    ArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+
+    /// A function has more parameters than the code generator can pass in registers.
+    ///
+    /// Holds the name of the function and its number of parameters.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::compiler::error::CompileError;
+    /// let error = CompileError::TooManyParameters { name: "f".to_string(), count: 5 };
+    /// assert_eq!(error.to_string(), "function 'f' has 5 parameters, but at most 4 are supported");
+    /// ```
+    TooManyParameters {
+        name: String,
+        count: usize,
+    },
+
+    /// The interpreter nested more function calls than its call depth limit allows, which usually
+    /// means a recursion never ends.
+    CallDepthExceeded,
+
+    /// A variable bound by a plain `let`, by a tuple pattern or as a parameter was assigned to. Only
+    /// `let mut` bindings can be assigned to.
+    ///
+    /// Holds the name of the variable.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::compiler::error::CompileError;
+    /// let error = CompileError::AssignToImmutable("x".to_string());
+    /// assert_eq!(error.to_string(), "cannot assign to immutable variable 'x'; declare it with 'let mut'");
+    /// ```
+    AssignToImmutable(String),
+
+    /// A variable declared without a value (`let mut x; ...`) was read on a path where it was not
+    /// assigned yet, as found by `check_initialized`.
+    ///
+    /// Holds the name of the variable.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::compiler::error::CompileError;
+    /// let error = CompileError::UseOfUninitialized("x".to_string());
+    /// assert_eq!(error.to_string(), "variable 'x' is read before it is assigned a value");
+    /// ```
+    UseOfUninitialized(String),
+
+    /// An array is used as a value instead of being indexed.
+    ArrayValue,
+
+    /// An array was indexed past its end, or with a negative index.
+    ///
+    /// Holds the index and the number of elements of the array.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::compiler::error::CompileError;
+    /// let error = CompileError::IndexOutOfBounds { index: 3, length: 3 };
+    /// assert_eq!(error.to_string(), "index 3 is out of bounds for an array of 3 elements");
+    /// ```
+    IndexOutOfBounds {
+        index: i64,
+        length: usize,
+    },
+}
+
+impl CompileError {
+    /// Returns the code of the error in the registry of `crate::error_codes`, which
+    /// `rengo --explain` describes at length.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::compiler::error::CompileError;
+    /// assert_eq!(CompileError::TupleValue.code(), "E005");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            CompileError::UndefinedVariable { .. } => "E001",
+            CompileError::DuplicateBinding(_) => "E002",
+            CompileError::Unsupported(_) => "E003",
+            CompileError::TupleArity { .. } => "E004",
+            CompileError::TupleValue => "E005",
+            CompileError::TooManyLocals => "E006",
+            CompileError::UndefinedFunction(_) => "E007",
+            CompileError::ArgumentCount { .. } => "E008",
+            CompileError::TooManyParameters { .. } => "E009",
+            CompileError::StepLimitExceeded => "E010",
+            CompileError::AssertionFailed => "E011",
+            CompileError::CallDepthExceeded => "E012",
+            CompileError::AssignToImmutable(_) => "E015",
+            CompileError::UseOfUninitialized(_) => "E016",
+            CompileError::ArrayValue => "E017",
+            CompileError::IndexOutOfBounds { .. } => "E018",
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::UndefinedVariable { name, suggestion } => {
+                write!(f, "undefined variable '{}'", name)?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, "; did you mean '{}'?", suggestion)?;
+                }
+                Ok(())
+            }
+            CompileError::DuplicateBinding(name) => {
+                write!(f, "'{}' is already bound in this scope", name)
+            }
+            CompileError::Unsupported(keyword) => {
+                write!(f, "'{}' is not supported by the code generator yet", keyword)
+            }
+            CompileError::StepLimitExceeded => write!(f, "step limit exceeded"),
+            CompileError::AssertionFailed => write!(f, "assertion failed"),
+            CompileError::TupleArity { expected, found } => {
+                write!(f, "expected a tuple of {} elements, found {}", expected, found)
+            }
+            CompileError::TupleValue => write!(f, "a tuple can only be destructured by 'let', not used as a value"),
+            CompileError::TooManyLocals => write!(f, "too many local variables"),
+            CompileError::UndefinedFunction(name) => write!(f, "undefined function '{}'", name),
+            CompileError::ArgumentCount { name, expected, found } => {
+                write!(f, "function '{}' takes {} arguments, found {}", name, expected, found)
+            }
+            CompileError::TooManyParameters { name, count } => write!(
+                f,
+                "function '{}' has {} parameters, but at most {} are supported",
+                name, count, MAX_PARAMETERS
+            ),
+            CompileError::CallDepthExceeded => write!(f, "call depth exceeded"),
+            CompileError::AssignToImmutable(name) => {
+                write!(f, "cannot assign to immutable variable '{}'; declare it with 'let mut'", name)
+            }
+            CompileError::UseOfUninitialized(name) => {
+                write!(f, "variable '{}' is read before it is assigned a value", name)
+            }
+            CompileError::ArrayValue => write!(f, "an array can only be indexed, not used as a value"),
+            CompileError::IndexOutOfBounds { index, length } => {
+                write!(f, "index {} is out of bounds for an array of {} elements", index, length)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CompileError {}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use expectest::prelude::*;
+    use super::*;
+
+    #[test]
+    fn it_displays_undefined_variables() {
+        let error = CompileError::UndefinedVariable { name: "x".to_string(), suggestion: None };
+        expect!(error.to_string()).to(be_equal_to("undefined variable 'x'"));
+        let error = CompileError::UndefinedVariable { name: "foo".to_string(), suggestion: Some("food".to_string()) };
+        expect!(error.to_string()).to(be_equal_to("undefined variable 'foo'; did you mean 'food'?"));
+    }
+
+    #[test]
+    fn it_displays_duplicate_bindings() {
+        let error = CompileError::DuplicateBinding("x".to_string());
+        expect!(error.to_string()).to(be_equal_to("'x' is already bound in this scope"));
+    }
+
+    #[test]
+    fn it_displays_interpreter_and_backend_limits() {
+        expect!(CompileError::Unsupported("if").to_string())
+            .to(be_equal_to("'if' is not supported by the code generator yet"));
+        expect!(CompileError::StepLimitExceeded.to_string()).to(be_equal_to("step limit exceeded"));
+        expect!(CompileError::TooManyLocals.to_string()).to(be_equal_to("too many local variables"));
+        expect!(CompileError::CallDepthExceeded.to_string()).to(be_equal_to("call depth exceeded"));
+    }
+
+    #[test]
+    fn it_has_a_documented_code() {
+        let errors = [
+            CompileError::UndefinedVariable { name: "x".to_string(), suggestion: None },
+            CompileError::DuplicateBinding("x".to_string()),
+            CompileError::Unsupported("while"),
+            CompileError::TupleArity { expected: 2, found: 3 },
+            CompileError::TupleValue,
+            CompileError::TooManyLocals,
+            CompileError::UndefinedFunction("f".to_string()),
+            CompileError::ArgumentCount { name: "f".to_string(), expected: 2, found: 1 },
+            CompileError::TooManyParameters { name: "f".to_string(), count: 5 },
+            CompileError::StepLimitExceeded,
+            CompileError::AssertionFailed,
+            CompileError::CallDepthExceeded,
+            CompileError::AssignToImmutable("x".to_string()),
+            CompileError::ArrayValue,
+            CompileError::IndexOutOfBounds { index: 3, length: 3 },
+        ];
+        for error in errors {
+            expect!(crate::error_codes::explain(error.code())).to(be_some());
+        }
+        expect!(CompileError::UndefinedVariable { name: "x".to_string(), suggestion: None }.code()).to(be_equal_to("E001"));
+    }
+
+    #[test]
+    fn it_displays_assignments_to_immutable_variables() {
+        expect!(CompileError::AssignToImmutable("x".to_string()).to_string())
+            .to(be_equal_to("cannot assign to immutable variable 'x'; declare it with 'let mut'"));
+    }
+
+    #[test]
+    fn it_displays_array_errors() {
+        expect!(CompileError::ArrayValue.to_string()).to(be_equal_to("an array can only be indexed, not used as a value"));
+        expect!(CompileError::IndexOutOfBounds { index: -1, length: 2 }.to_string())
+            .to(be_equal_to("index -1 is out of bounds for an array of 2 elements"));
+    }
+
+    #[test]
+    fn it_displays_function_errors() {
+        expect!(CompileError::UndefinedFunction("f".to_string()).to_string()).to(be_equal_to("undefined function 'f'"));
+        let error = CompileError::ArgumentCount { name: "f".to_string(), expected: 2, found: 1 };
+        expect!(error.to_string()).to(be_equal_to("function 'f' takes 2 arguments, found 1"));
+        let error = CompileError::TooManyParameters { name: "f".to_string(), count: 5 };
+        expect!(error.to_string()).to(be_equal_to("function 'f' has 5 parameters, but at most 4 are supported"));
+    }
+}
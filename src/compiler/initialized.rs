@@ -0,0 +1,214 @@
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::ast::expression::Expression;
+use crate::compiler::error::CompileError;
+
+/// Checks that no variable declared without a value (`let mut x; ...`) is read on a path of the
+/// program where it was not assigned yet.
+///
+/// This is a definite-assignment analysis, following the program in evaluation order. After an
+/// `if`, a variable only counts as assigned if both branches assign it, and after a `while` only if
+/// its condition does, since the body may not run at all. The code after a `return` is never
+/// reached, so it may read anything. Variables bound with a value, like those of a `let`, are
+/// always assigned.
+///
+/// ## Errors:
+/// `CompileError::UseOfUninitialized` with the name of the first variable, in evaluation order,
+/// that is read before it is assigned.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parser::parse::parse;
+/// # use rengo::parser::tokenize::tokenize;
+/// # use rengo::compiler::initialized::check_initialized;
+/// # use rengo::compiler::error::CompileError;
+/// let program = parse(&tokenize("let mut x; if 1 then x = 2 else 3; x").unwrap()).unwrap();
+/// assert_eq!(check_initialized(&program), Err(CompileError::UseOfUninitialized("x".to_string())));
+/// ```
+pub fn check_initialized<T>(expression: &Expression<T>) -> Result<(), CompileError> {
+    Flow::default().visit(expression)
+}
+
+/// What is known about the variables at a point of the program.
+#[derive(Debug, Clone, Default)]
+struct Flow {
+    /// The variables in scope that were declared without a value and may not be assigned yet.
+    unassigned: BTreeSet<String>,
+
+    /// Whether a `return` was taken on every path to the point, so it is never reached.
+    returned: bool,
+}
+
+impl Flow {
+    fn visit<T>(&mut self, expression: &Expression<T>) -> Result<(), CompileError> {
+        match expression {
+            Expression::Identifier(name, _) if !self.returned && self.unassigned.contains(name) => {
+                Err(CompileError::UseOfUninitialized(name.clone()))
+            }
+            Expression::Number(..) | Expression::Identifier(..) | Expression::Print(..) => Ok(()),
+            Expression::Increment(expr, _) | Expression::Decrement(expr, _) | Expression::Assert(expr, _) => {
+                self.visit(expr)
+            }
+            Expression::Let(name, value, body, _) | Expression::LetMut(name, value, body, _) => {
+                self.visit(value)?;  // The value reads the variable being shadowed, if any
+                self.visit_scope(core::slice::from_ref(name), false, body)
+            }
+            Expression::LetTuple(names, value, body, _) => {
+                self.visit(value)?;
+                self.visit_scope(names, false, body)
+            }
+            Expression::Declare(name, body, _) => self.visit_scope(core::slice::from_ref(name), true, body),
+            Expression::Binary(_, lhs, rhs, _) | Expression::Index(lhs, rhs, _) => {
+                self.visit(lhs)?;
+                self.visit(rhs)
+            }
+            Expression::Sequence(expressions, _)
+            | Expression::Tuple(expressions, _)
+            | Expression::Array(expressions, _)
+            | Expression::Call(_, expressions, _) => {
+                expressions.iter().try_for_each(|expression| self.visit(expression))
+            }
+            Expression::Assign(name, value, _) => {
+                self.visit(value)?;
+                self.unassigned.remove(name);
+                Ok(())
+            }
+            Expression::Return(value, _) => {
+                self.visit(value)?;
+                self.returned = true;
+                Ok(())
+            }
+            Expression::If(condition, then, otherwise, _) => {
+                self.visit(condition)?;
+                let mut other_branch = self.clone();
+                self.visit(then)?;
+                other_branch.visit(otherwise)?;
+                self.join(other_branch);
+                Ok(())
+            }
+            Expression::While(condition, body, _) => {
+                self.visit(condition)?;
+                // Reads in later iterations see more assigned variables than in the first one
+                let skipped = self.clone();
+                self.visit(body)?;
+                *self = skipped;
+                Ok(())
+            }
+            Expression::Func(function, rest, _) => {
+                // The body only sees its parameters, which the call assigns
+                Flow::default().visit(&function.body)?;
+                self.visit(rest)
+            }
+        }
+    }
+
+    /// Visits `body` with `names` in scope, unassigned if they were `declared` without a value,
+    /// then gives back the variables they shadowed the state they had.
+    fn visit_scope<T>(&mut self, names: &[String], declared: bool, body: &Expression<T>) -> Result<(), CompileError> {
+        let shadowed: Vec<bool> = names.iter().map(|name| self.unassigned.contains(name)).collect();
+        for name in names {
+            if declared {
+                self.unassigned.insert(name.clone());
+            } else {
+                self.unassigned.remove(name);
+            }
+        }
+        self.visit(body)?;
+        for (name, unassigned) in names.iter().zip(shadowed).rev() {
+            if unassigned {
+                self.unassigned.insert(name.clone());
+            } else {
+                self.unassigned.remove(name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges the state at the end of another path into this one, as where two branches meet.
+    fn join(&mut self, other: Flow) {
+        if self.returned {
+            *self = other;
+        } else if !other.returned {
+            self.unassigned.extend(other.unassigned);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use expectest::prelude::*;
+    use crate::parser::parse::parse;
+    use crate::parser::tokenize::tokenize;
+    use super::*;
+
+    fn check_source(source: &str) -> Result<(), CompileError> {
+        check_initialized(&parse(&tokenize(source).unwrap()).unwrap())
+    }
+
+    fn uninitialized(name: &str) -> CompileError {
+        CompileError::UseOfUninitialized(name.to_string())
+    }
+
+    mod check_initialized {
+        use super::*;
+
+        #[test]
+        fn rejects_a_read_before_the_assignment() {
+            expect!(check_source("let mut x; x")).to(be_err().value(uninitialized("x")));
+            expect!(check_source("let mut x; x = x + 1; x")).to(be_err().value(uninitialized("x")));
+        }
+
+        #[test]
+        fn accepts_a_read_after_the_assignment() {
+            expect!(check_source("let mut x; x = 5; x")).to(be_ok());
+            expect!(check_source("let mut x; let mut y; y = x = 1; x + y")).to(be_ok());
+        }
+
+        #[test]
+        fn needs_both_branches_of_an_if_to_assign() {
+            expect!(check_source("let mut x; if 1 then x = 1 else 0; x")).to(be_err().value(uninitialized("x")));
+            expect!(check_source("let mut x; if 1 then x = 1 else x = 2; x")).to(be_ok());
+        }
+
+        #[test]
+        fn does_not_count_assignments_in_a_loop_after_it() {
+            // let mut x; while <condition> <body>; x
+            let declare = |condition, body| Expression::Declare(
+                "x".to_string(),
+                Box::new(Expression::Sequence(vec![
+                    Expression::While(Box::new(condition), Box::new(body), ()),
+                    Expression::Identifier("x".to_string(), ()),
+                ], ())),
+                (),
+            );
+            let assign = || Expression::Assign("x".to_string(), Box::new(Expression::Number(0, ())), ());
+            let in_body = declare(Expression::Number(0, ()), assign());
+            expect!(check_initialized(&in_body)).to(be_err().value(uninitialized("x")));
+            let in_condition = declare(assign(), Expression::Number(1, ()));
+            expect!(check_initialized(&in_condition)).to(be_ok());
+        }
+
+        #[test]
+        fn ignores_the_paths_that_return() {
+            expect!(check_source("let mut x; if 1 then return 0 else x = 1; x")).to(be_ok());
+            expect!(check_source("let mut x; return 0; x")).to(be_ok());
+        }
+
+        #[test]
+        fn gives_back_the_state_of_shadowed_variables() {
+            expect!(check_source("let mut x; let x = 1; x")).to(be_ok());
+            expect!(check_source("let mut x; (let x = 1; x); x")).to(be_err().value(uninitialized("x")));
+            expect!(check_source("let x = 1; (let mut x; x = 2); x")).to(be_ok());
+        }
+
+        #[test]
+        fn checks_function_bodies_on_their_own() {
+            expect!(check_source("let mut x; fn f(x) = x; f(1)")).to(be_ok());
+            expect!(check_source("fn f(a) = (let mut y; y); f(1)")).to(be_err().value(uninitialized("y")));
+        }
+    }
+}
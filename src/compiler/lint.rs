@@ -0,0 +1,332 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use crate::ast::expression::Expression;
+use crate::ast::visit::{walk_expression, Visitor};
+use crate::parser::span::{SourceSpan, Span};
+
+/// A problem found in a program that does not stop it from being compiled.
+///
+/// Unlike a `CompileError`, a warning is only reported, unless the caller chooses to treat
+/// warnings as errors (`--strict`).
+#[derive(Debug, PartialEq, Clone)]
+pub enum Warning {
+    /// A `let` binds a variable that is never read.
+    ///
+    /// Holds the name of the variable. Assigning to a variable does not count as reading it.
+    /// Names starting with `_`, like `_` itself or `_unused`, are meant to be left unread and are
+    /// never reported.
+    UnusedBinding(String),
+
+    /// The program nests deeper than `LintOptions::max_depth`, as measured by
+    /// `Expression::depth`, which makes it hard to read and deepens the recursion of the compiler.
+    TooDeep { depth: usize, limit: usize },
+}
+
+/// The deepest nesting `lint` accepts without a `Warning::TooDeep`.
+pub const DEFAULT_MAX_DEPTH: usize = 32;
+
+/// Options that change what `lint_with_options` reports.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LintOptions {
+    /// The deepest nesting allowed before `Warning::TooDeep` is reported.
+    pub max_depth: usize,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        LintOptions { max_depth: DEFAULT_MAX_DEPTH }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::UnusedBinding(name) => write!(f, "unused variable '{}'", name),
+            Warning::TooDeep { depth, limit } => {
+                write!(f, "expression nested {} levels deep (more than {}); consider splitting it with let bindings", depth, limit)
+            }
+        }
+    }
+}
+
+/// Checks a program for code that compiles but is likely a mistake.
+///
+/// ## Parameters:
+/// - `expression`: The program to check.
+///
+/// ## Returns:
+/// The warnings found: the unused bindings in the order they are introduced, then whether the
+/// program nests deeper than `DEFAULT_MAX_DEPTH`.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parse_source;
+/// # use rengo::compiler::lint::lint;
+/// # use rengo::compiler::lint::Warning;
+/// let program = parse_source("let x = 5; 7").unwrap();
+/// assert_eq!(lint(&program), vec![Warning::UnusedBinding("x".to_string())]);
+/// ```
+pub fn lint<T: SourceSpan>(expression: &Expression<T>) -> Vec<Warning> {
+    lint_with_options(expression, &LintOptions::default())
+}
+
+/// Checks a program like `lint`, with the limits selected by `options`.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parse_source;
+/// # use rengo::compiler::lint::lint_with_options;
+/// # use rengo::compiler::lint::LintOptions;
+/// # use rengo::compiler::lint::Warning;
+/// let program = parse_source("1++++").unwrap();
+/// let warnings = lint_with_options(&program, &LintOptions { max_depth: 2 });
+/// assert_eq!(warnings, vec![Warning::TooDeep { depth: 3, limit: 2 }]);
+/// ```
+pub fn lint_with_options<T: SourceSpan>(expression: &Expression<T>, options: &LintOptions) -> Vec<Warning> {
+    lint_with_spans(expression, options).into_iter().map(|(warning, _)| warning).collect()
+}
+
+/// Checks a program like `lint_with_options`, pairing each warning with the span of the binding it
+/// is about, if the metadata of the binding has one. A `Warning::TooDeep` is about the whole
+/// program, and has no span.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parser::parse::parse_with_spans;
+/// # use rengo::parser::tokenize::tokenize_with_spans;
+/// # use rengo::parser::tokenize::TokenizeOptions;
+/// # use rengo::compiler::lint::lint_with_spans;
+/// # use rengo::compiler::lint::LintOptions;
+/// # use rengo::compiler::lint::Warning;
+/// # use rengo::parser::span::Span;
+/// let program = parse_with_spans(&tokenize_with_spans("let x = 5; 7", &TokenizeOptions::default()).unwrap()).unwrap();
+/// let warnings = lint_with_spans(&program, &LintOptions::default());
+/// assert_eq!(warnings, vec![(Warning::UnusedBinding("x".to_string()), Some(Span { start: 4, end: 5, line: 1, col: 5 }))]);
+/// ```
+pub fn lint_with_spans<T: SourceSpan>(expression: &Expression<T>, options: &LintOptions) -> Vec<(Warning, Option<Span>)> {
+    let mut linter = Linter::default();
+    linter.visit_expression(expression);
+    linter.warnings.sort_by_key(|(order, _, _)| *order);
+    let mut warnings: Vec<_> = linter.warnings.into_iter().map(|(_, warning, span)| (warning, span)).collect();
+    let depth = expression.depth();
+    if depth > options.max_depth {
+        warnings.push((Warning::TooDeep { depth, limit: options.max_depth }, None));
+    }
+    warnings
+}
+
+/// The state of the lint pass while it walks a program.
+#[derive(Default)]
+struct Linter {
+    /// The bindings in scope, innermost last: their name, the order they were introduced in,
+    /// whether they have been read, and the span of the node that introduced them.
+    scopes: Vec<(String, usize, bool, Option<Span>)>,
+
+    /// The number of bindings introduced so far.
+    introduced: usize,
+
+    /// The warnings found, with the order and the span of the binding they are about.
+    warnings: Vec<(usize, Warning, Option<Span>)>,
+}
+
+impl Linter {
+    /// Visits `body` with `names` bound by a node with the given `span`, then reports the ones it
+    /// never read.
+    fn visit_scope<T: SourceSpan>(&mut self, names: &[String], body: &Expression<T>, span: Option<Span>) {
+        for name in names {
+            self.scopes.push((name.clone(), self.introduced, false, span));
+            self.introduced += 1;
+        }
+        self.visit_expression(body);
+        for _ in names {
+            if let Some((name, order, false, span)) = self.scopes.pop() {
+                if !name.starts_with('_') {
+                    self.warnings.push((order, Warning::UnusedBinding(name), span));
+                }
+            }
+        }
+    }
+}
+
+impl<T: SourceSpan> Visitor<T> for Linter {
+    fn visit_expression(&mut self, expression: &Expression<T>) {
+        match expression {
+            Expression::Let(name, value, body, metadata) | Expression::LetMut(name, value, body, metadata) => {
+                self.visit_expression(value);  // The value reads the binding being shadowed, if any
+                self.visit_scope(core::slice::from_ref(name), body, metadata.span());
+            }
+            Expression::LetTuple(names, value, body, metadata) => {
+                self.visit_expression(value);
+                self.visit_scope(names, body, metadata.span());
+            }
+            Expression::Declare(name, body, metadata) => {
+                self.visit_scope(core::slice::from_ref(name), body, metadata.span())
+            }
+            Expression::Func(function, rest, metadata) => {
+                // The body only sees the parameters, not the bindings around the definition
+                let outer = core::mem::take(&mut self.scopes);
+                self.visit_scope(&function.parameters, &function.body, metadata.span());
+                self.scopes = outer;
+                self.visit_expression(rest);
+            }
+            Expression::Identifier(name, _) => {
+                if let Some(binding) = self.scopes.iter_mut().rev().find(|(bound, ..)| bound == name) {
+                    binding.2 = true;
+                }
+            }
+            _ => walk_expression(self, expression),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use expectest::prelude::*;
+    use crate::parser::parse::parse;
+    use crate::parser::tokenize::tokenize;
+    use super::*;
+
+    fn lint_source(source: &str) -> Vec<Warning> {
+        lint(&parse(&tokenize(source).unwrap()).unwrap())
+    }
+
+    mod lint {
+        use super::*;
+
+        #[test]
+        fn warns_about_unused_bindings() {
+            expect!(lint_source("let x = 5; 7")).to(be_equal_to(vec![Warning::UnusedBinding("x".to_string())]));
+            expect!(lint_source("let x = 5; x")).to(be_equal_to(vec![]));
+        }
+
+        #[test]
+        fn does_not_count_assignments_as_reads() {
+            expect!(lint_source("let x = 5; x = 6; 7")).to(be_equal_to(vec![Warning::UnusedBinding("x".to_string())]));
+        }
+
+        #[test]
+        fn tells_shadowed_bindings_apart() {
+            // The value of the second `x` reads the first one, but the second one is never read
+            expect!(lint_source("let x = 1; let x = x++; 7")).to(be_equal_to(vec![Warning::UnusedBinding("x".to_string())]));
+            expect!(lint_source("let x = 1; let y = 2; x")).to(be_equal_to(vec![Warning::UnusedBinding("y".to_string())]));
+        }
+
+        #[test]
+        fn reports_warnings_in_binding_order() {
+            expect!(lint_source("let (a, b) = (1, 2); let c = 3; 0")).to(be_equal_to(vec![
+                Warning::UnusedBinding("a".to_string()),
+                Warning::UnusedBinding("b".to_string()),
+                Warning::UnusedBinding("c".to_string()),
+            ]));
+        }
+
+        #[test]
+        fn warns_about_unused_parameters() {
+            expect!(lint_source("fn f(a, b) = a; f(1, 2)")).to(be_equal_to(vec![Warning::UnusedBinding("b".to_string())]));
+        }
+
+        #[test]
+        fn does_not_let_function_bodies_read_outer_bindings() {
+            expect!(lint_source("let x = 1; fn f(y) = y + x; f(2)")).to(be_equal_to(vec![Warning::UnusedBinding("x".to_string())]));
+        }
+
+        #[test]
+        fn ignores_discarded_values() {
+            expect!(lint_source("let _ = 5; 7")).to(be_equal_to(vec![]));
+        }
+
+        #[test]
+        fn ignores_names_starting_with_an_underscore() {
+            expect!(lint_source("let _x = 5; 7")).to(be_equal_to(vec![]));
+            expect!(lint_source("let (_a, b) = (1, 2); fn f(_y) = 3; f(b)")).to(be_equal_to(vec![]));
+            expect!(lint_source("let x = 5; 7")).to(be_equal_to(vec![Warning::UnusedBinding("x".to_string())]));
+        }
+
+        #[test]
+        fn accepts_the_nesting_of_ordinary_programs() {
+            expect!(lint_source("let x = 1; let y = x + 2 << 3; (x++)-- - y")).to(be_equal_to(vec![]));
+        }
+    }
+
+    mod lint_with_options {
+        use super::*;
+
+        fn lint_deep(source: &str, max_depth: usize) -> Vec<Warning> {
+            lint_with_options(&parse(&tokenize(source).unwrap()).unwrap(), &LintOptions { max_depth })
+        }
+
+        #[test]
+        fn warns_when_the_program_nests_past_the_limit() {
+            expect!(lint_deep("1++++", 2)).to(be_equal_to(vec![Warning::TooDeep { depth: 3, limit: 2 }]));
+            expect!(lint_deep("1++++", 3)).to(be_equal_to(vec![]));
+        }
+
+        #[test]
+        fn does_not_warn_about_a_long_flat_program() {
+            // let x0 = 0; let x1 = x0 + 1; ...; let x40 = x39 + 1; x40
+            let lets: String = (1..=40).map(|index| format!("let x{} = x{} + 1; ", index, index - 1)).collect();
+            let program = format!("let x0 = 0; {}x40", lets);
+            expect!(lint_deep(&program, DEFAULT_MAX_DEPTH)).to(be_equal_to(vec![]));
+        }
+
+        #[test]
+        fn reports_the_depth_after_the_unused_bindings() {
+            expect!(lint_deep("let x = 5; 7", 1)).to(be_equal_to(vec![
+                Warning::UnusedBinding("x".to_string()),
+                Warning::TooDeep { depth: 2, limit: 1 },
+            ]));
+        }
+    }
+
+    mod lint_with_spans {
+        use super::*;
+        use crate::parser::parse::parse_with_spans;
+        use crate::parser::tokenize::{tokenize_with_spans, TokenizeOptions};
+
+        /// Returns the warnings about `source` with the text their spans cover.
+        fn lint_located(source: &str, max_depth: usize) -> Vec<(Warning, Option<&str>)> {
+            let program = parse_with_spans(&tokenize_with_spans(source, &TokenizeOptions::default()).unwrap()).unwrap();
+            lint_with_spans(&program, &LintOptions { max_depth })
+                .into_iter()
+                .map(|(warning, span)| (warning, span.map(|span| span.text(source))))
+                .collect()
+        }
+
+        #[test]
+        fn locates_unused_bindings_at_their_names() {
+            expect!(lint_located("let x = 1; let (a, _b) = (x, 2); fn f(n) = 0; 7", DEFAULT_MAX_DEPTH)).to(be_equal_to(vec![
+                (Warning::UnusedBinding("a".to_string()), Some("a")),
+                (Warning::UnusedBinding("n".to_string()), Some("f")),
+            ]));
+        }
+
+        #[test]
+        fn does_not_locate_the_depth_of_the_program() {
+            expect!(lint_located("let x = 5; 7", 1)).to(be_equal_to(vec![
+                (Warning::UnusedBinding("x".to_string()), Some("x")),
+                (Warning::TooDeep { depth: 2, limit: 1 }, None),
+            ]));
+        }
+    }
+
+    mod display {
+        use super::*;
+
+        #[test]
+        fn names_the_unused_variable() {
+            expect!(Warning::UnusedBinding("x".to_string()).to_string()).to(be_equal_to("unused variable 'x'"));
+        }
+
+        #[test]
+        fn gives_the_depth_and_the_limit() {
+            expect!(Warning::TooDeep { depth: 40, limit: 32 }.to_string()).to(be_equal_to(
+                "expression nested 40 levels deep (more than 32); consider splitting it with let bindings"
+            ));
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::ast::expression::{Expression, DISCARD};
+use crate::ast::operator::BinaryOperator;
+use crate::compiler::error::CompileError;
+use crate::compiler::suggest::closest_name;
+
+/// Lowers a program straight from its AST into textual LLVM IR, as an alternative to the `nasm`
+/// backend of `compile_expression`.
+///
+/// The IR defines `i64 @main()`, which returns the value of the program, so `clang` or `llc` can
+/// compile it on any target LLVM supports. Values are kept in SSA registers: a `let` binds its name
+/// to the register (or constant) of its value, so no memory is used. `add` and `sub` are emitted
+/// without `nsw`, so the arithmetic wraps like in the generated assembly.
+///
+/// Only numbers, increments, decrements, `let` bindings and identifiers are lowered so far.
+///
+/// ## Errors:
+/// - `CompileError::UndefinedVariable` if an identifier is not bound by an enclosing `let`.
+/// - `CompileError::Unsupported` for any other construct.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parser::parse::parse;
+/// # use rengo::parser::tokenize::tokenize;
+/// # use rengo::compiler::llvm::compile_llvm_ir;
+/// # use rengo::env::add;
+/// let program = parse(&tokenize("let x = 41; x++").unwrap()).unwrap();
+/// assert_eq!(
+///     compile_llvm_ir(&program).unwrap(),
+///     "define i64 @main() {\nentry:\n  %0 = add i64 41, 1\n  ret i64 %0\n}\n"
+/// );
+/// ```
+pub fn compile_llvm_ir<T>(expression: &Expression<T>) -> Result<String, CompileError> {
+    let mut lowering = Lowering::default();
+    let result = lowering.lower(expression)?;
+    let mut ir = String::from("define i64 @main() {\nentry:\n");
+    for instruction in &lowering.instructions {
+        ir.push_str(&format!("  {}\n", instruction));
+    }
+    ir.push_str(&format!("  ret i64 {}\n}}\n", result));
+    Ok(ir)
+}
+
+/// The state of the lowering of the body of `@main`.
+#[derive(Debug, Default)]
+struct Lowering {
+    /// The instructions emitted so far, in order.
+    instructions: Vec<String>,
+
+    /// The operand each variable in scope is bound to.
+    bindings: BTreeMap<String, String>,
+
+    /// The number of registers defined so far, which LLVM requires to be numbered in order.
+    registers: usize,
+}
+
+impl Lowering {
+    /// Emits the instructions that compute `expression`, returning the operand that holds its
+    /// value: a constant or a register.
+    fn lower<T>(&mut self, expression: &Expression<T>) -> Result<String, CompileError> {
+        match expression {
+            Expression::Number(value, _) => Ok(format!("{}", value)),
+            Expression::Increment(expr, _) => {
+                let operand = self.lower(expr)?;
+                Ok(self.step("add", &operand))
+            }
+            Expression::Decrement(expr, _) => {
+                let operand = self.lower(expr)?;
+                Ok(self.step("sub", &operand))
+            }
+            Expression::Let(identifier, value, body, _) | Expression::LetMut(identifier, value, body, _) => {
+                // The value is lowered first, so it reads the binding being shadowed (if any)
+                let value = self.lower(value)?;
+                if identifier == DISCARD {
+                    return self.lower(body);
+                }
+                let shadowed = self.bindings.insert(identifier.clone(), value);
+                let result = self.lower(body);
+                match shadowed {
+                    Some(previous) => self.bindings.insert(identifier.clone(), previous),
+                    None => self.bindings.remove(identifier),
+                };
+                result
+            }
+            Expression::Identifier(identifier, _) => {
+                self.bindings.get(identifier).cloned().ok_or_else(|| CompileError::UndefinedVariable {
+                    name: identifier.clone(),
+                    suggestion: closest_name(identifier, self.bindings.keys().map(String::as_str)).map(String::from),
+                })
+            }
+            _ => Err(CompileError::Unsupported(construct(expression))),
+        }
+    }
+
+    /// Emits `<register> = <operation> i64 <operand>, 1` into a new register, which is returned.
+    fn step(&mut self, operation: &str, operand: &str) -> String {
+        let register = format!("%{}", self.registers);
+        self.registers += 1;
+        self.instructions.push(format!("{} = {} i64 {}, 1", register, operation, operand));
+        register
+    }
+}
+
+/// Returns the keyword or operator of a construct `compile_llvm_ir` cannot lower, for the
+/// `CompileError::Unsupported` it reports. `compile_to_bytecode` compiles the same constructs, so
+/// it reports the rest the same way.
+pub(crate) fn construct<T>(expression: &Expression<T>) -> &'static str {
+    match expression {
+        Expression::Binary(operator, ..) => match operator {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::BitAnd => "&",
+            BinaryOperator::BitOr => "|",
+            BinaryOperator::BitXor => "^",
+            BinaryOperator::ShiftLeft => "<<",
+            BinaryOperator::ShiftRight => ">>",
+            BinaryOperator::Less => "<",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::Min => "min",
+            BinaryOperator::Max => "max",
+        },
+        Expression::Sequence(..) => ";",
+        Expression::Assign(..) => "=",
+        Expression::Declare(..) => "let mut",
+        Expression::If(..) => "if",
+        Expression::While(..) => "while",
+        Expression::Assert(..) => "assert",
+        Expression::Return(..) => "return",
+        Expression::Tuple(..) | Expression::LetTuple(..) => "(,)",
+        Expression::Array(..) | Expression::Index(..) => "[]",
+        Expression::Print(..) => "print",
+        Expression::Func(..) | Expression::Call(..) => "fn",
+        Expression::Number(..)
+        | Expression::Increment(..)
+        | Expression::Decrement(..)
+        | Expression::Let(..)
+        | Expression::LetMut(..)
+        | Expression::Identifier(..) => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use expectest::prelude::*;
+    use proptest::prelude::*;
+    use crate::parser::parse::parse;
+    use crate::parser::tokenize::tokenize;
+    use super::*;
+
+    fn lower_source(source: &str) -> Result<String, CompileError> {
+        compile_llvm_ir(&parse(&tokenize(source).unwrap()).unwrap())
+    }
+
+    mod compile_llvm_ir {
+        use super::*;
+
+        #[test]
+        fn defines_main_returning_the_value_of_the_program() {
+            let ir = lower_source("420++--").unwrap();
+            expect!(ir.starts_with("define i64 @main() {\n")).to(be_true());
+            expect!(ir.contains("  %0 = add i64 420, 1\n")).to(be_true());
+            expect!(ir.contains("  %1 = sub i64 %0, 1\n")).to(be_true());
+            expect!(ir.ends_with("  ret i64 %1\n}\n")).to(be_true());
+        }
+
+        #[test]
+        fn returns_a_constant_without_instructions() {
+            expect!(lower_source("-7")).to(be_ok().value("define i64 @main() {\nentry:\n  ret i64 -7\n}\n".to_string()));
+        }
+
+        #[test]
+        fn binds_names_to_the_operands_of_their_values() {
+            let ir = lower_source("let x = 1; let y = x++; let x = y--; x++").unwrap();
+            expect!(ir).to(be_equal_to(
+                "define i64 @main() {\nentry:\n  %0 = add i64 1, 1\n  %1 = sub i64 %0, 1\n  %2 = add i64 %1, 1\n  ret i64 %2\n}\n"
+                    .to_string(),
+            ));
+        }
+
+        #[test]
+        fn restores_shadowed_bindings() {
+            let ir = lower_source("let x = 5; let _ = (let x = x++; x); x").unwrap();
+            expect!(ir.ends_with("  ret i64 5\n}\n")).to(be_true());
+        }
+
+        #[test]
+        fn fails_on_undefined_variables() {
+            expect!(lower_source("let value = 1; valu")).to(be_err().value(CompileError::UndefinedVariable {
+                name: "valu".to_string(),
+                suggestion: Some("value".to_string()),
+            }));
+        }
+
+        #[test]
+        fn reports_the_constructs_it_cannot_lower() {
+            expect!(lower_source("1 + 2")).to(be_err().value(CompileError::Unsupported("+")));
+            expect!(lower_source("let x = 1; if x then 2 else 3")).to(be_err().value(CompileError::Unsupported("if")));
+            expect!(lower_source("1; 2")).to(be_err().value(CompileError::Unsupported(";")));
+            expect!(lower_source("[1, 2][0]")).to(be_err().value(CompileError::Unsupported("[]")));
+        }
+
+        proptest!(
+            #[test]
+            fn numbers_every_register_in_order(value in any::<i64>(), steps in prop::collection::vec(any::<bool>(), 0..16)) {
+                let source = steps.iter().fold(format!("({})", value), |source, increment| {
+                    format!("{}{}", source, if *increment { "++" } else { "--" })
+                });
+                let ir = lower_source(&source).unwrap();
+                for register in 0..steps.len() {
+                    let definition = format!("  %{} = ", register);
+                    expect!(ir.contains(&definition)).to(be_true());
+                }
+                let returned = if steps.is_empty() { format!("{}", value) } else { format!("%{}", steps.len() - 1) };
+                expect!(ir.ends_with(&format!("  ret i64 {}\n}}\n", returned))).to(be_true());
+            }
+        );
+    }
+}
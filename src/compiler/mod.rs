@@ -1 +1,11 @@
-pub(crate) mod compile;
\ No newline at end of file
+pub mod bytecode;
+pub mod compile;
+pub mod context;
+pub mod error;
+pub mod initialized;
+pub mod lint;
+pub mod llvm;
+pub mod options;
+pub mod regalloc;
+mod suggest;
+pub mod unit;
@@ -0,0 +1,138 @@
+use crate::asm::reg::Abi;
+use crate::ast::expression::Expression;
+use crate::ast::fold::{fold_constants, simplify};
+
+/// How much a program is optimized (`-O0`, `-O1`, `-O2`), selecting the passes over its AST that
+/// `optimize` runs and the optimizations of the code generator that `with_opt_level` turns on.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub enum OptLevel {
+    /// No optimization: the code follows the program as written.
+    #[default]
+    O0,
+
+    /// Operations on constants are folded with `fold_constants`. There is no peephole pass over
+    /// the instructions yet to run alongside it.
+    O1,
+
+    /// Every optimization: `simplify` cancels the steps that undo each other before folding, and
+    /// the code generator caches loads, allocates registers to operands and runs `regalloc`.
+    O2,
+}
+
+impl OptLevel {
+    /// Runs the passes of the level over a program, in order: `simplify` at `O2`, then
+    /// `fold_constants` from `O1` up.
+    ///
+    /// ## Example:
+    /// ```rust
+    /// # use rengo::parser::parse::parse;
+    /// # use rengo::parser::tokenize::tokenize;
+    /// # use rengo::compiler::options::OptLevel;
+    /// # use rengo::ast::expression::Expression;
+    /// let program = parse(&tokenize("420++--++").unwrap()).unwrap();
+    /// assert_eq!(OptLevel::O2.optimize(program), Expression::Number(421, ()));
+    /// ```
+    pub fn optimize<T>(self, program: Expression<T>) -> Expression<T> {
+        let program = if self >= OptLevel::O2 { simplify(program) } else { program };
+        if self >= OptLevel::O1 { fold_constants(program) } else { program }
+    }
+}
+
+/// The size in bytes of a stack slot by default: that of a 64-bit value.
+pub const SLOT_SIZE: usize = 8;
+
+/// Options that change how `compile_expression` translates a program.
+///
+/// The default options accept every valid program and produce the plainest code.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CompileOptions {
+    /// Reject `let` bindings that reuse a name already bound in the current scope, instead of
+    /// silently shadowing it.
+    pub deny_shadowing: bool,
+
+    /// Set up a stack frame around the compiled code and address locals relative to `Rbp` instead
+    /// of `Rsp`. The prologue reserves the stack area for every slot, so locals are never stored
+    /// below `Rsp`, which is only safe on targets with a red zone.
+    pub frame: bool,
+
+    /// The calling convention of the target. Without a red zone, as on Win64, a frame is set up
+    /// even if `frame` is not set, since the locals would otherwise be stored below `Rsp`. The same
+    /// goes for a program whose slots do not all fit in the red zone.
+    pub target_abi: Abi,
+
+    /// The size in bytes of each stack slot, `SLOT_SIZE` by default. A slot holds a 64-bit value at
+    /// its top, so the size is rounded up to a power of two of at least 8 bytes.
+    pub slot_size: usize,
+
+    /// Emit each statement of a top-level sequence under its own label (`stmt0:`, `stmt1:`, ...),
+    /// to make the statements easy to find in a disassembler.
+    pub label_statements: bool,
+
+    /// Skip reloading a variable into `Rax` when `Rax` is known to already hold its value.
+    pub cache_loads: bool,
+
+    /// Keep the left operand of a binary operator in `Rax` while loading the right one straight
+    /// into `Rcx`, instead of spilling the left one to the stack, when the right operand is a
+    /// number or a variable.
+    pub allocate_registers: bool,
+
+    /// Move the stack slots of locals and temporaries into spare registers where their live ranges
+    /// permit, with `regalloc`, so fewer values go through memory.
+    pub linear_scan: bool,
+
+    /// Check every index that is not a number against the bounds of its array at runtime, calling
+    /// the runtime's `INDEX_OUT_OF_BOUNDS_SYMBOL` if it is out of them. Without it, such an index
+    /// is not checked, and reads whatever is at the address it computes.
+    pub sanitize: bool,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions {
+            deny_shadowing: false,
+            frame: false,
+            target_abi: Abi::default(),
+            slot_size: SLOT_SIZE,
+            label_statements: false,
+            cache_loads: false,
+            allocate_registers: false,
+            linear_scan: false,
+            sanitize: false,
+        }
+    }
+}
+
+impl CompileOptions {
+    /// Returns `true` if the compiled code sets up a stack frame whatever its number of slots: when
+    /// `frame` is set, or when the target has no red zone to store the locals in below `Rsp`.
+    /// `compile_expression` also sets one up when the slots do not `fit_in_red_zone`.
+    pub fn uses_frame(&self) -> bool {
+        self.frame || self.target_abi.red_zone() == 0
+    }
+
+    /// Returns the size in bytes of each stack slot: `slot_size` rounded up to a power of two of at
+    /// least 8 bytes.
+    pub fn slot_bytes(&self) -> usize {
+        self.slot_size.max(SLOT_SIZE).next_power_of_two()
+    }
+
+    /// Returns `true` if `slots` stack slots fit in the red zone of the target, so they can be
+    /// stored below `Rsp` without a frame.
+    pub fn fits_in_red_zone(&self, slots: usize) -> bool {
+        slots * self.slot_bytes() <= self.target_abi.red_zone()
+    }
+
+    /// Returns the options with the optimizations of the code generator that `opt_level` includes
+    /// turned on. Those already on stay on, so a level only adds to the options selected one by
+    /// one. The level itself is not kept: the passes over the AST are run by `OptLevel::optimize`
+    /// before the program is compiled.
+    pub fn with_opt_level(self, opt_level: OptLevel) -> CompileOptions {
+        let all = opt_level >= OptLevel::O2;
+        CompileOptions {
+            cache_loads: self.cache_loads || all,
+            allocate_registers: self.allocate_registers || all,
+            linear_scan: self.linear_scan || all,
+            ..self
+        }
+    }
+}
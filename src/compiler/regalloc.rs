@@ -0,0 +1,398 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::asm::arg::Arg;
+use crate::asm::instruction::Instruction;
+use crate::asm::reg::{Abi, Reg};
+
+/// The registers stack slots can be moved to, in the order they are handed out. Only those the
+/// calling convention lets the code overwrite without saving them are used.
+const CANDIDATES: [Reg; 3] = [Reg::Rdi, Reg::Rsi, Reg::Rdx];
+
+/// Moves the values of stack slots into registers where their live ranges permit, with a linear
+/// scan over the instructions.
+///
+/// Each slot, a `RegistryOffset` from `Rsp` or `Rbp`, is treated as a virtual register that is
+/// live from the first instruction that accesses it to the last one. The registers of `abi` that a
+/// function may overwrite and that the instructions never name are handed out to the live ranges
+/// in order, and given back when a range ends. When all of them are taken, the range that ends
+/// last keeps its slot, which is the classic spilling choice of linear scan. Every access to a slot
+/// that gets a register then accesses the register instead, so values stay out of memory.
+///
+/// A slot keeps living in memory if moving it could change what the program computes: when its
+/// address is taken with `lea`, when a single byte of it is accessed, or when its live range
+/// contains a call or a `ret`, which may overwrite the register, or an instruction that moves
+/// the register it is addressed from. If the code jumps backwards, live ranges are not just spans
+/// of instructions, and if it uses a `Memory` operand, that could alias a slot, so it is returned
+/// unchanged.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::compiler::compile::compile_expression;
+/// # use rengo::compiler::context::Ctx;
+/// # use rengo::compiler::regalloc::regalloc;
+/// # use rengo::asm::reg::Abi;
+/// let expression = rengo::parse_source("let x = 5; x + x++")?;
+/// let instructions = compile_expression(&expression, &mut Ctx::default())?.instructions;
+/// let allocated = regalloc(instructions, Abi::SystemV);
+/// assert!(!allocated.iter().any(|instruction| format!("{:?}", instruction).contains("RegistryOffset")));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn regalloc(instructions: Vec<Instruction>, abi: Abi) -> Vec<Instruction> {
+    if jumps_backwards(&instructions) || instructions.iter().flat_map(operands).any(|arg| matches!(arg, Arg::Memory(_))) {
+        return instructions;
+    }
+    let named: BTreeSet<Reg> = instructions.iter().flat_map(operands).filter_map(named_register).collect();
+    let registers: Vec<Reg> = CANDIDATES
+        .into_iter()
+        .filter(|register| register.is_caller_saved(abi) && !named.contains(register))
+        .collect();
+    let assigned = linear_scan(&live_ranges(&instructions), &registers);
+    if assigned.is_empty() {
+        return instructions;
+    }
+    instructions
+        .into_iter()
+        .map(|instruction| map_operands(instruction, &|arg| match slot(&arg).and_then(|slot| assigned.get(&slot)) {
+            Some(register) => Arg::Registry(register.clone()),
+            None => arg,
+        }))
+        .collect()
+}
+
+/// A stack slot, as addressed from `Rsp` or `Rbp`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+struct Slot {
+    base: Reg,
+    offset: i64,
+}
+
+/// The instructions from the first to the last access of a slot, both included.
+#[derive(Debug, PartialEq, Clone)]
+struct LiveRange {
+    slot: Slot,
+    start: usize,
+    end: usize,
+}
+
+/// Returns the slot an operand accesses, if it is one.
+fn slot(arg: &Arg) -> Option<Slot> {
+    match arg {
+        Arg::RegistryOffset(base @ (Reg::Rsp | Reg::Rbp), offset) => Some(Slot { base: base.clone(), offset: *offset }),
+        _ => None,
+    }
+}
+
+/// Returns the live ranges of the slots that can be moved to a register, ordered by their start.
+fn live_ranges(instructions: &[Instruction]) -> Vec<LiveRange> {
+    let mut ranges: BTreeMap<Slot, LiveRange> = BTreeMap::new();
+    let mut pinned = BTreeSet::new();
+    for (index, instruction) in instructions.iter().enumerate() {
+        for arg in operands(instruction) {
+            if let Some(slot) = slot(arg) {
+                ranges.entry(slot.clone()).or_insert(LiveRange { slot, start: index, end: index }).end = index;
+            }
+        }
+        // These need the slot to be in memory, as an address or as a byte
+        if let Instruction::Lea(_, arg) | Instruction::Movzx(_, arg) | Instruction::Movsx(_, arg) | Instruction::Set(_, arg) =
+            instruction.unlocated()
+        {
+            pinned.extend(slot(arg));
+        }
+    }
+    let rsp_barriers = barriers(instructions, &Reg::Rsp);
+    let rbp_barriers = barriers(instructions, &Reg::Rbp);
+    let mut ranges: Vec<LiveRange> = ranges
+        .into_values()
+        .filter(|range| !pinned.contains(&range.slot))
+        .filter(|range| {
+            let barriers = if range.slot.base == Reg::Rsp { &rsp_barriers } else { &rbp_barriers };
+            barriers[range.end + 1] == barriers[range.start]
+        })
+        .collect();
+    ranges.sort_by_key(|range| range.start);
+    ranges
+}
+
+/// Counts, for every index, the instructions before it that a value cannot be kept in a register
+/// across when its slot is addressed from `base`: calls and returns, and writes to `base`.
+fn barriers(instructions: &[Instruction], base: &Reg) -> Vec<usize> {
+    let mut counts = vec![0];
+    for instruction in instructions {
+        let barrier = match instruction.unlocated() {
+            Instruction::Call(_) | Instruction::Ret => true,
+            Instruction::Push(_) | Instruction::Pop(_) if *base == Reg::Rsp => true,
+            instruction => destination(instruction) == Some(&Arg::Registry(base.clone())),
+        };
+        counts.push(counts[counts.len() - 1] + usize::from(barrier));
+    }
+    counts
+}
+
+/// Hands out `registers` to the live ranges, ordered by their start, returning the register of
+/// each slot that got one.
+fn linear_scan(ranges: &[LiveRange], registers: &[Reg]) -> BTreeMap<Slot, Reg> {
+    let mut assigned = BTreeMap::new();
+    let mut free: Vec<Reg> = registers.iter().rev().cloned().collect();
+    let mut active: Vec<(LiveRange, Reg)> = Vec::new();
+    for range in ranges {
+        active.retain(|(active, register)| {
+            let expired = active.end < range.start;
+            if expired {
+                free.push(register.clone());
+            }
+            !expired
+        });
+        if let Some(register) = free.pop() {
+            assigned.insert(range.slot.clone(), register.clone());
+            active.push((range.clone(), register));
+            continue;
+        }
+        // Out of registers: the range that ends last stays in memory
+        let last = active.iter().enumerate().max_by_key(|(_, (active, _))| active.end).map(|(index, _)| index);
+        if let Some(index) = last.filter(|index| active[*index].0.end > range.end) {
+            let (spilled, register) = active.swap_remove(index);
+            assigned.remove(&spilled.slot);
+            assigned.insert(range.slot.clone(), register.clone());
+            active.push((range.clone(), register));
+        }
+    }
+    assigned
+}
+
+/// Returns `true` if an instruction jumps to a label placed before it.
+fn jumps_backwards(instructions: &[Instruction]) -> bool {
+    let labels: BTreeMap<&str, usize> = instructions
+        .iter()
+        .enumerate()
+        .filter_map(|(index, instruction)| match instruction {
+            Instruction::Label(label) => Some((label.as_str(), index)),
+            _ => None,
+        })
+        .collect();
+    instructions.iter().enumerate().any(|(index, instruction)| match instruction.unlocated() {
+        Instruction::Jump(_, label) | Instruction::Jmp(label) => labels.get(label.as_str()).is_some_and(|target| *target <= index),
+        _ => false,
+    })
+}
+
+/// Returns the whole register an operand names, as a register or as the base of a memory operand.
+fn named_register(arg: &Arg) -> Option<Reg> {
+    let register = match arg {
+        Arg::Registry(register) | Arg::RegistryOffset(register, _) => register,
+        _ => return None,
+    };
+    match register {
+        Reg::Al => Some(Reg::Rax),
+        Reg::Cl => Some(Reg::Rcx),
+        register => Some(register.clone()),
+    }
+}
+
+/// Returns the operand an instruction writes to, if it writes one.
+fn destination(instruction: &Instruction) -> Option<&Arg> {
+    match instruction.unlocated() {
+        Instruction::Inc(dest)
+        | Instruction::Dec(dest)
+        | Instruction::Pop(dest)
+        | Instruction::Set(_, dest)
+        | Instruction::Mov(dest, _)
+        | Instruction::Movzx(dest, _)
+        | Instruction::Movsx(dest, _)
+        | Instruction::Add(dest, _)
+        | Instruction::Sub(dest, _)
+        | Instruction::And(dest, _)
+        | Instruction::Or(dest, _)
+        | Instruction::Xor(dest, _)
+        | Instruction::Shl(dest, _)
+        | Instruction::Sar(dest, _)
+        | Instruction::Lea(dest, _)
+        | Instruction::Cmov(_, dest, _) => Some(dest),
+        _ => None,
+    }
+}
+
+/// Returns the operands of an instruction.
+fn operands(instruction: &Instruction) -> Vec<&Arg> {
+    match instruction {
+        Instruction::Inc(arg) | Instruction::Dec(arg) | Instruction::Push(arg) | Instruction::Pop(arg) | Instruction::Set(_, arg) => {
+            vec![arg]
+        }
+        Instruction::Mov(dest, src)
+        | Instruction::Movzx(dest, src)
+        | Instruction::Movsx(dest, src)
+        | Instruction::Add(dest, src)
+        | Instruction::Sub(dest, src)
+        | Instruction::And(dest, src)
+        | Instruction::Or(dest, src)
+        | Instruction::Xor(dest, src)
+        | Instruction::Shl(dest, src)
+        | Instruction::Sar(dest, src)
+        | Instruction::Lea(dest, src)
+        | Instruction::Cmp(dest, src)
+        | Instruction::Test(dest, src)
+        | Instruction::Cmov(_, dest, src) => vec![dest, src],
+        Instruction::Located(_, instruction) => operands(instruction),
+        Instruction::Label(_)
+        | Instruction::Nop
+        | Instruction::Jump(..)
+        | Instruction::Jmp(_)
+        | Instruction::Call(_)
+        | Instruction::Ret => vec![],
+    }
+}
+
+/// Replaces every operand of an instruction with the result of `map` on it.
+fn map_operands(instruction: Instruction, map: &impl Fn(Arg) -> Arg) -> Instruction {
+    match instruction {
+        Instruction::Inc(arg) => Instruction::Inc(map(arg)),
+        Instruction::Dec(arg) => Instruction::Dec(map(arg)),
+        Instruction::Push(arg) => Instruction::Push(map(arg)),
+        Instruction::Pop(arg) => Instruction::Pop(map(arg)),
+        Instruction::Set(condition, arg) => Instruction::Set(condition, map(arg)),
+        Instruction::Mov(dest, src) => Instruction::Mov(map(dest), map(src)),
+        Instruction::Movzx(dest, src) => Instruction::Movzx(map(dest), map(src)),
+        Instruction::Movsx(dest, src) => Instruction::Movsx(map(dest), map(src)),
+        Instruction::Add(dest, src) => Instruction::Add(map(dest), map(src)),
+        Instruction::Sub(dest, src) => Instruction::Sub(map(dest), map(src)),
+        Instruction::And(dest, src) => Instruction::And(map(dest), map(src)),
+        Instruction::Or(dest, src) => Instruction::Or(map(dest), map(src)),
+        Instruction::Xor(dest, src) => Instruction::Xor(map(dest), map(src)),
+        Instruction::Shl(dest, src) => Instruction::Shl(map(dest), map(src)),
+        Instruction::Sar(dest, src) => Instruction::Sar(map(dest), map(src)),
+        Instruction::Lea(dest, src) => Instruction::Lea(map(dest), map(src)),
+        Instruction::Cmp(lhs, rhs) => Instruction::Cmp(map(lhs), map(rhs)),
+        Instruction::Test(lhs, rhs) => Instruction::Test(map(lhs), map(rhs)),
+        Instruction::Cmov(condition, dest, src) => Instruction::Cmov(condition, map(dest), map(src)),
+        Instruction::Located(span, instruction) => Instruction::Located(span, alloc::boxed::Box::new(map_operands(*instruction, map))),
+        instruction @ (Instruction::Label(_)
+        | Instruction::Nop
+        | Instruction::Jump(..)
+        | Instruction::Jmp(_)
+        | Instruction::Call(_)
+        | Instruction::Ret) => instruction,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use expectest::prelude::*;
+    use proptest::prelude::*;
+    use crate::asm::simulate::simulate;
+    use crate::ast::arbitrary;
+    use crate::compiler::compile::compile_expression;
+    use crate::compiler::context::Ctx;
+    use crate::compiler::options::CompileOptions;
+    use crate::parser::parse::parse;
+    use crate::parser::tokenize::tokenize;
+    use super::*;
+
+    fn compile_source(source: &str, options: CompileOptions) -> Vec<Instruction> {
+        let expression = parse(&tokenize(source).unwrap()).unwrap();
+        compile_expression(&expression, &mut Ctx::new(options)).unwrap().instructions
+    }
+
+    /// Counts the instructions that access a stack slot.
+    fn memory_accesses(instructions: &[Instruction]) -> usize {
+        instructions.iter().filter(|instruction| operands(instruction).into_iter().any(|arg| slot(arg).is_some())).count()
+    }
+
+    /// Runs the instructions of the whole program, ended by the `ret` that leaves it.
+    fn run(mut instructions: Vec<Instruction>) -> Result<i64, alloc::string::String> {
+        instructions.push(Instruction::Ret);
+        simulate(&instructions)
+    }
+
+    mod regalloc {
+        use super::*;
+
+        /// Nests subtractions to the right, so each left operand is spilled while the right one
+        /// is computed.
+        const NESTED: &str = "1++ - (2++ - (3++ - (4++ - (5++ - 6++))))";
+
+        #[test]
+        fn keeps_the_operands_of_nested_arithmetic_in_registers() {
+            let naive = compile_source(NESTED, CompileOptions::default());
+            let allocated = regalloc(naive.clone(), Abi::SystemV);
+            expect!(memory_accesses(&naive)).to(be_equal_to(10));
+            // Five spills are live at once, so the two outermost stay in memory
+            expect!(memory_accesses(&allocated)).to(be_equal_to(4));
+            expect!(run(allocated)).to(be_equal_to(run(naive)));
+        }
+
+        #[test]
+        fn spills_the_range_that_ends_last_when_out_of_registers() {
+            // Win64 only lets the code overwrite Rdx, as Rax and Rcx are taken
+            let naive = compile_source(NESTED, CompileOptions { frame: true, target_abi: Abi::Win64, ..Default::default() });
+            let allocated = regalloc(naive.clone(), Abi::Win64);
+            expect!(memory_accesses(&allocated)).to(be_equal_to(8));  // Only the innermost spill moves
+            expect!(allocated.iter().any(|instruction| operands(instruction).contains(&&Arg::Registry(Reg::Rdx))))
+                .to(be_true());
+            expect!(run(allocated)).to(be_equal_to(run(naive)));
+        }
+
+        #[test]
+        fn does_not_use_the_registers_the_code_names() {
+            let naive = compile_source("fn f(a, b) = a - b; let x = 3; f(x, 1) + x++", CompileOptions::default());
+            let allocated = regalloc(naive.clone(), Abi::SystemV);
+            let named = |instructions: &[Instruction], register: Reg| {
+                instructions.iter().flat_map(operands).filter(|arg| **arg == Arg::Registry(register.clone())).count()
+            };
+            expect!(named(&allocated, Reg::Rdi)).to(be_equal_to(named(&naive, Reg::Rdi)));
+            expect!(named(&allocated, Reg::Rsi)).to(be_equal_to(named(&naive, Reg::Rsi)));
+        }
+
+        #[test]
+        fn never_hands_out_a_callee_saved_register() {
+            for abi in [Abi::SystemV, Abi::Win64] {
+                let naive = compile_source(NESTED, CompileOptions { target_abi: abi, ..Default::default() });
+                let callee_saved = |instructions: &[Instruction]| -> BTreeSet<Reg> {
+                    instructions.iter().flat_map(operands).filter_map(named_register).filter(|register| register.is_callee_saved(abi)).collect()
+                };
+                expect!(callee_saved(&regalloc(naive.clone(), abi))).to(be_equal_to(callee_saved(&naive)));
+            }
+        }
+
+        #[test]
+        fn keeps_values_live_across_a_call_in_memory() {
+            // x is read after print, which may overwrite any caller-saved register
+            let naive = compile_source("let x = 5; print(\"hi\"); x", CompileOptions::default());
+            expect!(regalloc(naive.clone(), Abi::SystemV)).to(be_equal_to(naive));
+        }
+
+        #[test]
+        fn keeps_slots_read_as_a_byte_in_memory() {
+            let slot = Arg::RegistryOffset(Reg::Rsp, -1);
+            let instructions = vec![
+                Instruction::Mov(slot.clone(), Arg::Registry(Reg::Rax)),
+                Instruction::Movzx(Arg::Registry(Reg::Rax), slot),
+            ];
+            expect!(regalloc(instructions.clone(), Abi::SystemV)).to(be_equal_to(instructions));
+        }
+
+        #[test]
+        fn leaves_code_that_jumps_backwards_unchanged() {
+            let slot = Arg::RegistryOffset(Reg::Rsp, -1);
+            let instructions = vec![
+                Instruction::Label("loop0".to_string()),
+                Instruction::Mov(slot.clone(), Arg::Registry(Reg::Rax)),
+                Instruction::Mov(Arg::Registry(Reg::Rax), slot),
+                Instruction::Jmp("loop0".to_string()),
+            ];
+            expect!(regalloc(instructions.clone(), Abi::SystemV)).to(be_equal_to(instructions));
+        }
+
+        proptest!(
+            #[test]
+            fn preserves_the_result_of_any_program(expr in arbitrary::expression(4), frame in any::<bool>(), win64 in any::<bool>()) {
+                let abi = if win64 { Abi::Win64 } else { Abi::SystemV };
+                let options = CompileOptions { frame, target_abi: abi, ..Default::default() };
+                let naive = compile_expression(&expr, &mut Ctx::new(options)).unwrap().instructions;
+                let allocated = regalloc(naive.clone(), abi);
+                prop_assert!(memory_accesses(&allocated) <= memory_accesses(&naive));
+                prop_assert_eq!(run(allocated), run(naive));
+            }
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use alloc::vec::Vec;
+
+/// Computes the Levenshtein distance between two strings: the smallest number of single-character
+/// insertions, deletions and substitutions that turn one into the other.
+///
+/// ## Example:
+/// ```ignore
+/// assert_eq!(edit_distance("foo", "food"), 1);
+/// assert_eq!(edit_distance("kitten", "sitting"), 3);
+/// ```
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    // `previous[j]` is the distance between the prefix of `a` seen so far and the first `j`
+    // characters of `b`
+    let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = Vec::with_capacity(b.len() + 1);
+        current.push(i + 1);
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            let deletion = previous[j + 1] + 1;
+            let insertion = current[j] + 1;
+            current.push(substitution.min(deletion).min(insertion));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Returns the candidate closest to `name`, if any is close enough to be a likely typo.
+///
+/// A candidate qualifies if it is at most a third of the length of `name` away from it (and at
+/// least one edit). Ties are broken in favor of the candidate that comes first.
+pub(crate) fn closest_name<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let threshold = (name.chars().count() / 3).max(1);
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use expectest::prelude::*;
+    use super::*;
+
+    mod edit_distance {
+        use super::*;
+
+        #[test]
+        fn counts_single_character_edits() {
+            expect!(edit_distance("foo", "foo")).to(be_equal_to(0));
+            expect!(edit_distance("foo", "food")).to(be_equal_to(1));
+            expect!(edit_distance("food", "foo")).to(be_equal_to(1));
+            expect!(edit_distance("foo", "fob")).to(be_equal_to(1));
+            expect!(edit_distance("kitten", "sitting")).to(be_equal_to(3));
+            expect!(edit_distance("", "abc")).to(be_equal_to(3));
+        }
+    }
+
+    mod closest_name {
+        use super::*;
+
+        #[test]
+        fn suggests_the_closest_candidate() {
+            let candidates = ["bar", "food", "fo"];
+            expect!(closest_name("foo", candidates.into_iter())).to(be_some().value("food"));
+        }
+
+        #[test]
+        fn ignores_distant_candidates() {
+            let candidates = ["x", "counter"];
+            expect!(closest_name("foo", candidates.into_iter())).to(be_none());
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use alloc::vec::Vec;
+use crate::asm::instruction::Instruction;
+use crate::asm::program::Data;
+
+/// The result of compiling an `Expression`.
+///
+/// The `CompiledUnit` struct bundles the generated instructions with the information callers need
+/// to run them, so they don't have to inspect the `Env` used during compilation.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CompiledUnit {
+    /// The generated instructions, leaving the value of the expression in `Rax`.
+    pub instructions: Vec<Instruction>,
+
+    /// The number of 8-byte stack slots the instructions use, for both bindings and temporaries.
+    pub stack_slots: usize,
+
+    /// The strings the instructions refer to by label, to be placed in the data section.
+    pub data: Vec<Data>,
+
+    /// The routines of the functions the program defines, each under its own label and ending
+    /// with a `ret`, to be placed after the instructions and the `ret` that ends them.
+    pub functions: Vec<Instruction>,
+}
@@ -0,0 +1,276 @@
+use std::fmt::Display;
+use std::io::{self, IsTerminal, Write};
+use rengo::error_codes::ErrorCode;
+use rengo::parser::span::Span;
+
+/// When to colorize diagnostics with ANSI escape codes (`--color always|never|auto`).
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub(crate) enum ColorChoice {
+    /// Always colorize, even when the output is redirected.
+    Always,
+
+    /// Never colorize.
+    Never,
+
+    /// Colorize only when writing to a terminal.
+    #[default]
+    Auto,
+}
+
+impl ColorChoice {
+    /// Returns whether to colorize output written to a stream, given whether that stream is a
+    /// terminal.
+    pub(crate) fn enabled(self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => is_terminal,
+        }
+    }
+}
+
+/// A stream diagnostics are written to, which knows whether it is a terminal, so that
+/// `ColorChoice::Auto` is decided by the stream the output actually goes to.
+pub(crate) trait Output: Write {
+    /// Returns `true` if the stream is a terminal.
+    fn is_terminal(&self) -> bool;
+}
+
+impl Output for io::Stdout {
+    fn is_terminal(&self) -> bool {
+        IsTerminal::is_terminal(self)
+    }
+}
+
+impl Output for io::Stderr {
+    fn is_terminal(&self) -> bool {
+        IsTerminal::is_terminal(self)
+    }
+}
+
+/// An in-memory buffer is never a terminal.
+impl Output for Vec<u8> {
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+/// The escape code that starts bold red text.
+const RED: &str = "\x1b[1;31m";
+/// The escape code that starts bold yellow text.
+const YELLOW: &str = "\x1b[1;33m";
+/// The escape code that starts bold text.
+const BOLD: &str = "\x1b[1m";
+/// The escape code that goes back to plain text.
+const RESET: &str = "\x1b[0m";
+
+/// Renders an error as a diagnostic for the user: `error: <message>`, or `error[<code>]: <message>`
+/// if it has a code, with the `error` keyword and the code in bold red if `color` is set.
+///
+/// ## Example:
+/// ```ignore
+/// assert_eq!(render_error("Invalid character: $", Some("E013"), false), "error[E013]: Invalid character: $");
+/// ```
+pub(crate) fn render_error(error: impl Display, code: Option<&str>, color: bool) -> String {
+    let keyword = match code {
+        Some(code) => format!("error[{}]", code),
+        None => "error".to_string(),
+    };
+    if color {
+        format!("{}{}{}: {}", RED, keyword, RESET, error)
+    } else {
+        format!("{}: {}", keyword, error)
+    }
+}
+
+/// Renders the hint that follows an error with a code, to look the code up with `--explain`.
+///
+/// ## Example:
+/// ```ignore
+/// assert_eq!(render_hint("E013"), "For more information about this error, try `rengo --explain E013`.");
+/// ```
+pub(crate) fn render_hint(code: &str) -> String {
+    format!("For more information about this error, try `rengo --explain {}`.", code)
+}
+
+/// Renders the description of an error code for `--explain`: the code and its title, in bold if
+/// `color` is set, then the explanation after a blank line.
+///
+/// ## Example:
+/// ```ignore
+/// let entry = rengo::error_codes::explain("E001").unwrap();
+/// assert!(render_explanation(entry, false).starts_with("E001: undefined variable\n\n"));
+/// ```
+pub(crate) fn render_explanation(entry: &ErrorCode, color: bool) -> String {
+    let heading = format!("{}: {}", entry.code, entry.title);
+    if color {
+        format!("{}{}{}\n\n{}", BOLD, heading, RESET, entry.explanation)
+    } else {
+        format!("{}\n\n{}", heading, entry.explanation)
+    }
+}
+
+/// Renders a warning as a diagnostic for the user: `warning: <message>`, with the `warning` keyword
+/// in bold yellow if `color` is set.
+///
+/// ## Example:
+/// ```ignore
+/// assert_eq!(render_warning("unused variable 'x'", false), "warning: unused variable 'x'");
+/// ```
+pub(crate) fn render_warning(warning: impl Display, color: bool) -> String {
+    if color {
+        format!("{}warning{}: {}", YELLOW, RESET, warning)
+    } else {
+        format!("warning: {}", warning)
+    }
+}
+
+/// A diagnostic rendered for the user, along with where in the source it points to.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Diagnostic {
+    /// The span the diagnostic is about, or `None` if the pass that found it does not know it.
+    pub(crate) span: Option<Span>,
+
+    /// The diagnostic as `render_error` or `render_warning` rendered it.
+    pub(crate) rendered: String,
+}
+
+/// Orders diagnostics by their position in the source, the byte offset their span starts at, so
+/// that the output does not depend on which pass found each of them. With several input files, the
+/// offsets are those `tokenize_sources` counts across the files, so a file's diagnostics all come
+/// before those of the files after it. Diagnostics without a span go last, in the order they were
+/// found.
+///
+/// ## Example:
+/// ```ignore
+/// let mut diagnostics = vec![at_offset(30), at_offset(2)];
+/// sort(&mut diagnostics);
+/// assert_eq!(diagnostics, vec![at_offset(2), at_offset(30)]);
+/// ```
+pub(crate) fn sort(diagnostics: &mut [Diagnostic]) {
+    diagnostics.sort_by_key(|diagnostic| match diagnostic.span {
+        Some(span) => (false, span.start),
+        None => (true, 0),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use expectest::prelude::*;
+    use super::*;
+
+    mod enabled {
+        use super::*;
+
+        #[test]
+        fn only_detects_terminals_in_auto_mode() {
+            expect!(ColorChoice::Always.enabled(false)).to(be_true());
+            expect!(ColorChoice::Never.enabled(true)).to(be_false());
+            expect!(ColorChoice::Auto.enabled(true)).to(be_true());
+            expect!(ColorChoice::Auto.enabled(false)).to(be_false());
+        }
+    }
+
+    mod render_error {
+        use super::*;
+
+        #[test]
+        fn never_emits_escapes_without_color() {
+            let rendered = render_error("undefined variable 'x'", None, ColorChoice::Never.enabled(true));
+            expect!(rendered.as_str()).to(be_equal_to("error: undefined variable 'x'"));
+            expect!(rendered.contains('\x1b')).to(be_false());
+        }
+
+        #[test]
+        fn colorizes_the_error_keyword() {
+            let rendered = render_error("undefined variable 'x'", None, ColorChoice::Always.enabled(false));
+            expect!(rendered.as_str()).to(be_equal_to("\x1b[1;31merror\x1b[0m: undefined variable 'x'"));
+        }
+
+        #[test]
+        fn shows_the_code_of_the_error() {
+            expect!(render_error("undefined variable 'x'", Some("E001"), false).as_str())
+                .to(be_equal_to("error[E001]: undefined variable 'x'"));
+            expect!(render_error("undefined variable 'x'", Some("E001"), true).as_str())
+                .to(be_equal_to("\x1b[1;31merror[E001]\x1b[0m: undefined variable 'x'"));
+        }
+    }
+
+    mod render_explanation {
+        use super::*;
+
+        #[test]
+        fn only_emboldens_the_heading_with_color() {
+            let entry = rengo::error_codes::explain("E001").unwrap();
+            let plain = render_explanation(entry, false);
+            expect!(plain.starts_with("E001: undefined variable\n\n")).to(be_true());
+            expect!(plain.contains('\x1b')).to(be_false());
+            expect!(render_explanation(entry, true).starts_with("\x1b[1mE001: undefined variable\x1b[0m\n\n")).to(be_true());
+        }
+    }
+
+    mod render_warning {
+        use super::*;
+
+        #[test]
+        fn colorizes_the_warning_keyword() {
+            expect!(render_warning("unused variable 'x'", false).as_str()).to(be_equal_to("warning: unused variable 'x'"));
+            expect!(render_warning("unused variable 'x'", true).as_str())
+                .to(be_equal_to("\x1b[1;33mwarning\x1b[0m: unused variable 'x'"));
+        }
+    }
+
+    mod sort {
+        use super::*;
+
+        /// A diagnostic in a source whose lines are all 80 bytes long.
+        fn located(line: usize, col: usize, rendered: &str) -> Diagnostic {
+            let start = (line - 1) * 80 + col - 1;
+            Diagnostic { span: Some(Span { start, end: start + 1, line, col }), rendered: rendered.to_string() }
+        }
+
+        fn unlocated(rendered: &str) -> Diagnostic {
+            Diagnostic { span: None, rendered: rendered.to_string() }
+        }
+
+        #[test]
+        fn puts_earlier_lines_first_whichever_pass_found_them() {
+            let parse_error = located(1, 9, &render_error("Expected closing parenthesis", Some("E014"), false));
+            let warning = located(3, 5, &render_warning("unused variable 'x'", false));
+            for mut diagnostics in [vec![warning.clone(), parse_error.clone()], vec![parse_error.clone(), warning.clone()]] {
+                sort(&mut diagnostics);
+                expect!(diagnostics).to(be_equal_to(vec![parse_error.clone(), warning.clone()]));
+            }
+        }
+
+        #[test]
+        fn orders_a_line_by_column() {
+            let mut diagnostics = vec![located(2, 7, "b"), located(2, 3, "a"), located(1, 20, "first")];
+            sort(&mut diagnostics);
+            expect!(diagnostics).to(be_equal_to(vec![located(1, 20, "first"), located(2, 3, "a"), located(2, 7, "b")]));
+        }
+
+        #[test]
+        fn puts_the_diagnostics_of_earlier_files_first() {
+            // The error is on line 3 of the first file, the warning on line 1 of the second one
+            let parse_error = Diagnostic {
+                span: Some(Span { start: 30, end: 31, line: 3, col: 5 }),
+                rendered: render_error("Unexpected token: LineEnd", Some("E014"), false),
+            };
+            let warning = Diagnostic {
+                span: Some(Span { start: 45, end: 46, line: 1, col: 5 }),
+                rendered: render_warning("unused variable 'y'", false),
+            };
+            let mut diagnostics = vec![warning.clone(), parse_error.clone()];
+            sort(&mut diagnostics);
+            expect!(diagnostics).to(be_equal_to(vec![parse_error, warning]));
+        }
+
+        #[test]
+        fn keeps_unlocated_diagnostics_last_in_their_order() {
+            let mut diagnostics = vec![unlocated("x"), located(4, 1, "located"), unlocated("y")];
+            sort(&mut diagnostics);
+            expect!(diagnostics).to(be_equal_to(vec![located(4, 1, "located"), unlocated("x"), unlocated("y")]));
+        }
+    }
+}
@@ -1,33 +1,363 @@
-use std::collections::HashMap;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+use crate::compiler::error::CompileError;
 
-/// Environment is a map of variable names to their values.
-pub(crate) type Env = HashMap<String, i64>;
+/// Environment is a map of the variable names in scope to their stack slots.
+///
+/// Slots are numbered from `1` and are never reused, even after the binding that owns them goes
+/// out of scope, so the number of allocated slots is also the size of the stack area the compiled
+/// code needs. They are counted apart from the bindings, which only hold the names in scope, and
+/// allocating one fails with `CompileError::TooManyLocals` rather than overflowing the count.
+///
+/// The names are kept in a `BTreeMap`, so anything listing them (such as the locals of a frame)
+/// sees them in the same order on every run.
+///
+/// A slot is immutable unless it is marked as mutable with `mark_mutable`, as the slot of a
+/// `let mut` binding is, so only the names bound to such slots can be assigned to.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Env {
+    bindings: BTreeMap<String, i64>,
+    slots: i64,
+    mutable: BTreeSet<i64>,
+}
+
+impl Env {
+    /// Creates an empty environment.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the slot of a name in scope, or `None` if the name is not bound.
+    pub fn get(&self, name: &str) -> Option<&i64> {
+        self.bindings.get(name)
+    }
+
+    /// Returns `true` if the name is bound in the current scope.
+    pub fn contains(&self, name: &str) -> bool {
+        self.bindings.contains_key(name)
+    }
+
+    /// Returns the number of slots allocated so far, including those of bindings that are no longer
+    /// in scope and of temporaries.
+    pub fn len(&self) -> usize {
+        self.slots as usize
+    }
+
+    /// Returns `true` if no slots have been allocated.
+    pub fn is_empty(&self) -> bool {
+        self.slots == 0
+    }
+
+    /// Returns the names currently in scope, in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.bindings.keys().map(String::as_str)
+    }
+
+    /// Returns `true` if the name is bound to a slot marked as mutable.
+    pub fn is_mutable(&self, name: &str) -> bool {
+        self.get(name).is_some_and(|slot| self.mutable.contains(slot))
+    }
+
+    /// Marks a slot as mutable, so the names bound to it can be assigned to.
+    pub fn mark_mutable(&mut self, slot: i64) {
+        self.mutable.insert(slot);
+    }
+
+    /// Returns the number of names currently in scope.
+    pub fn in_scope(&self) -> usize {
+        self.bindings.len()
+    }
+
+    /// Opens a new scope over the environment. See `ScopeGuard`.
+    pub fn scope(&mut self) -> ScopeGuard<'_> {
+        ScopeGuard::open(self)
+    }
+
+    /// Records the slots allocated so far, so an attempt at compiling something can be undone with
+    /// `restore` if it fails.
+    pub fn snapshot(&self) -> EnvSnapshot {
+        EnvSnapshot { slots: self.slots }
+    }
+
+    /// Releases the slots allocated since `snapshot` was taken, so they can be allocated again.
+    ///
+    /// Only the slots are recorded by a snapshot: the names bound since then are expected to be out
+    /// of scope again, as they are once the `ScopeGuard`s opened by the failed attempt are dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::env::Env;
+    /// # use rengo::env::add_temporary;
+    /// let mut env = Env::new();
+    /// let snapshot = env.snapshot();
+    /// add_temporary(&mut env).unwrap();
+    /// env.restore(snapshot);
+    /// assert!(env.is_empty());
+    /// ```
+    pub fn restore(&mut self, snapshot: EnvSnapshot) {
+        debug_assert!(
+            self.bindings.values().all(|slot| *slot <= snapshot.slots),
+            "a name is still bound to a slot allocated after the snapshot"
+        );
+        self.slots = snapshot.slots;
+        self.mutable.retain(|slot| *slot <= snapshot.slots);
+    }
+}
+
+/// The state of an `Env` recorded by `Env::snapshot`, to be given back to `Env::restore`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct EnvSnapshot {
+    slots: i64,
+}
+
+impl AsMut<Env> for Env {
+    fn as_mut(&mut self) -> &mut Env {
+        self
+    }
+}
+
+/// A scope opened over an `Env`, or over something that owns one (such as a compilation `Ctx`),
+/// which ends when the guard is dropped.
+///
+/// Names added through the guard are removed from the environment when the scope ends, and the
+/// bindings they shadowed are restored, even if compilation of the scope bails out early with an
+/// error. The guard dereferences to its owner, so it can be passed wherever the owner is expected
+/// while the scope is open. As with `remove`, the slots of the bindings stay allocated.
+///
+/// # Example
+///
+/// ```rust
+/// # use rengo::env::Env;
+/// let mut env = Env::new();
+/// {
+///     let mut scope = env.scope();
+///     scope.add("x".to_string()).unwrap();
+///     assert!(scope.contains("x"));
+/// }
+/// assert!(!env.contains("x"));
+/// ```
+pub struct ScopeGuard<'a, S: AsMut<Env> = Env> {
+    owner: &'a mut S,
+    shadowed: Vec<(String, Option<i64>)>,
+}
+
+impl<'a, S: AsMut<Env>> ScopeGuard<'a, S> {
+    /// Opens a new scope over the environment of `owner`.
+    pub fn open(owner: &'a mut S) -> Self {
+        ScopeGuard { owner, shadowed: Vec::new() }
+    }
+
+    /// Adds a name to the scope, assigning it a new slot number.
+    ///
+    /// ## Parameters:
+    /// - `name`: The name to add to the scope.
+    ///
+    /// ## Returns:
+    /// The assigned slot number, or `CompileError::TooManyLocals` if no slot is left.
+    pub fn add(&mut self, name: String) -> Result<i64, CompileError> {
+        let slot = add_temporary(self.owner.as_mut())?;
+        self.bind(name, slot);
+        Ok(slot)
+    }
+
+    /// Binds a name in the scope to a slot that is already allocated, such as a temporary holding
+    /// the value the name stands for.
+    ///
+    /// ## Parameters:
+    /// - `name`: The name to bind.
+    /// - `slot`: The slot the name refers to from now on.
+    pub fn bind(&mut self, name: String, slot: i64) {
+        let env = self.owner.as_mut();
+        self.shadowed.push((name.clone(), env.get(&name).copied()));
+        env.bindings.insert(name, slot);
+    }
+}
+
+impl<S: AsMut<Env>> Deref for ScopeGuard<'_, S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        self.owner
+    }
+}
+
+impl<S: AsMut<Env>> DerefMut for ScopeGuard<'_, S> {
+    fn deref_mut(&mut self) -> &mut S {
+        self.owner
+    }
+}
+
+impl<S: AsMut<Env>> Drop for ScopeGuard<'_, S> {
+    fn drop(&mut self) {
+        // Undo the additions from the last to the first, so a name added twice ends up bound to
+        // what it was before the scope
+        while let Some((name, shadowed)) = self.shadowed.pop() {
+            remove(&name, shadowed, self.owner.as_mut());
+        }
+    }
+}
 
 /// Adds a name to the environment, assigning it a new slot number.
 ///
 /// ## Parameters:
 /// - `name`: The name to add to the environment.
-/// - `env`: A mutable reference to the environment, a `HashMap` of `(String, i64)` pairs.
+/// - `env`: A mutable reference to the environment.
 ///
 /// ## Returns:
-/// A tuple containing the updated environment and the assigned slot number.
-pub(crate) fn add(name: String, env: &mut Env) -> i64 {
-    let slot = (env.len() as i64) + 1;  // Calculate the new slot number
-    env.insert(name, slot);             // Insert the name with the slot into the environment
-    slot                                // Return the slot
+/// The assigned slot number, or `CompileError::TooManyLocals` if no slot is left.
+pub fn add(name: String, env: &mut Env) -> Result<i64, CompileError> {
+    let slot = add_temporary(env)?;  // Reserve a new slot
+    env.bindings.insert(name, slot); // Bind the name to it
+    Ok(slot)                         // Return the slot
+}
+
+/// Reserves an anonymous slot in the environment for a temporary value.
+///
+/// Temporaries hold intermediate results, such as the left operand of a binary operation while the
+/// right operand is being computed. They have no name, so they can never be referenced by the
+/// program.
+///
+/// ## Parameters:
+/// - `env`: A mutable reference to the environment.
+///
+/// ## Returns:
+/// The slot number assigned to the temporary, or `CompileError::TooManyLocals` if every slot
+/// number up to `i64::MAX` is taken.
+pub fn add_temporary(env: &mut Env) -> Result<i64, CompileError> {
+    env.slots = env.slots.checked_add(1).ok_or(CompileError::TooManyLocals)?;
+    Ok(env.slots)
+}
+
+/// Removes a name from the environment when its scope ends.
+///
+/// If the binding shadowed an outer one, the outer binding is restored. The slot of the removed
+/// binding stays allocated.
+///
+/// ## Parameters:
+/// - `name`: The name whose scope ends.
+/// - `shadowed`: The slot the name was bound to before entering the scope, if any.
+/// - `env`: A mutable reference to the environment.
+pub fn remove(name: &str, shadowed: Option<i64>, env: &mut Env) {
+    match shadowed {
+        Some(slot) => env.bindings.insert(String::from(name), slot),
+        None => env.bindings.remove(name),
+    };
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::string::ToString;
     use expectest::prelude::*;
     use super::*;
 
     #[test]
     fn test_add() {
         let mut env = Env::new();  // Create a new environment
-        let slot = add("x".to_string(), &mut env);  // Add the variable "x" to the environment
+        let slot = add("x".to_string(), &mut env).unwrap();  // Add the variable "x" to the environment
         expect!(slot).to(be_equal_to(1));  // The first variable should have slot 1
-        let slot = add("y".to_string(), &mut env);  // Add the variable "y" to the environment
+        let slot = add("y".to_string(), &mut env).unwrap();  // Add the variable "y" to the environment
         expect!(slot).to(be_equal_to(2));  // The second variable should have slot 2
     }
+
+    #[test]
+    fn test_add_temporary() {
+        let mut env = Env::new();
+        add("x".to_string(), &mut env).unwrap();
+        let slot = add_temporary(&mut env).unwrap();  // Temporaries take the next free slot
+        expect!(slot).to(be_equal_to(2));
+        let slot = add("y".to_string(), &mut env).unwrap();  // ...and are never reused by later bindings
+        expect!(slot).to(be_equal_to(3));
+        expect!(env.len()).to(be_equal_to(3));
+    }
+
+    #[test]
+    fn test_slots_are_counted_apart_from_bindings() {
+        let mut env = Env::new();
+        add("x".to_string(), &mut env).unwrap();
+        add("x".to_string(), &mut env).unwrap();  // Shadowing replaces the binding...
+        expect!(env.in_scope()).to(be_equal_to(1));
+        expect!(env.len()).to(be_equal_to(2));  // ...but still takes a new slot
+        {
+            let mut scope = env.scope();
+            scope.add("x".to_string()).unwrap();
+        }
+        expect!(env.in_scope()).to(be_equal_to(1));
+        expect!(add("x".to_string(), &mut env)).to(be_ok().value(4));
+    }
+
+    #[test]
+    fn test_add_temporary_fails_instead_of_overflowing() {
+        let mut env = Env { slots: i64::MAX - 1, ..Env::new() };
+        expect!(add_temporary(&mut env)).to(be_ok().value(i64::MAX));
+        expect!(add_temporary(&mut env)).to(be_err().value(CompileError::TooManyLocals));
+        expect!(add("x".to_string(), &mut env)).to(be_err().value(CompileError::TooManyLocals));
+        expect!(env.contains("x")).to(be_false());
+        expect!(env.len()).to(be_equal_to(i64::MAX as usize));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut env = Env::new();
+        add("x".to_string(), &mut env).unwrap();
+        let shadowed = env.get("x").copied();
+        add("x".to_string(), &mut env).unwrap();  // Shadow "x" with a new slot
+        expect!(env.get("x")).to(be_some().value(&2));
+        remove("x", shadowed, &mut env);  // The outer "x" is visible again
+        expect!(env.get("x")).to(be_some().value(&1));
+        remove("x", None, &mut env);
+        expect!(env.contains("x")).to(be_false());
+        expect!(env.len()).to(be_equal_to(2));  // Slots are never reclaimed
+    }
+
+    #[test]
+    fn test_scope_guard() {
+        let mut env = Env::new();
+        add("x".to_string(), &mut env).unwrap();
+        let in_scope = env.in_scope();
+        {
+            let mut scope = env.scope();
+            scope.add("y".to_string()).unwrap();
+            scope.add("x".to_string()).unwrap();  // Shadow the outer "x"
+            scope.add("x".to_string()).unwrap();  // ...twice
+            expect!(scope.in_scope()).to(be_equal_to(2));
+            expect!(scope.get("x")).to(be_some().value(&4));
+        }
+        expect!(env.in_scope()).to(be_equal_to(in_scope));  // The scope ended with the guard
+        expect!(env.get("x")).to(be_some().value(&1));
+        expect!(env.contains("y")).to(be_false());
+        expect!(env.len()).to(be_equal_to(4));
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut env = Env::new();
+        add("x".to_string(), &mut env).unwrap();
+        let snapshot = env.snapshot();
+        {
+            let mut scope = env.scope();
+            scope.add("y".to_string()).unwrap();
+            add_temporary(&mut scope).unwrap();
+        }
+        env.restore(snapshot);
+        expect!(env.len()).to(be_equal_to(1));
+        expect!(add_temporary(&mut env)).to(be_ok().value(2));  // The released slots are allocated again
+        expect!(env.get("x")).to(be_some().value(&1));
+    }
+
+    #[test]
+    fn test_scope_guard_bind() {
+        let mut env = Env::new();
+        add("x".to_string(), &mut env).unwrap();
+        let slot = add_temporary(&mut env).unwrap();
+        {
+            let mut scope = env.scope();
+            scope.bind("x".to_string(), slot);  // Takes over the temporary without a new slot
+            expect!(scope.get("x")).to(be_some().value(&slot));
+            expect!(scope.len()).to(be_equal_to(2));
+        }
+        expect!(env.get("x")).to(be_some().value(&1));
+    }
 }
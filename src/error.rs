@@ -0,0 +1,86 @@
+use alloc::string::String;
+use core::fmt;
+use crate::compiler::error::CompileError;
+use crate::parser::error::{ParseError, TokenizeError};
+
+/// Represents any error found while turning source code into an executable.
+///
+/// The `RengoError` enum has a variant per stage of the pipeline, so callers can tell where a
+/// program was rejected. The errors of the front end and the code generator convert into it with
+/// `?`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum RengoError {
+    /// The source code could not be split into tokens.
+    Tokenize(TokenizeError),
+
+    /// The tokens do not form a valid expression.
+    Parse(ParseError),
+
+    /// The expression could not be compiled.
+    Compile(CompileError),
+
+    /// The assembler could not be run, or rejected the generated assembly.
+    ///
+    /// Holds a message describing the problem.
+    Assemble(String),
+
+    /// The linker could not be run, or failed to produce the executable.
+    ///
+    /// Holds a message describing the problem.
+    Link(String),
+}
+
+impl RengoError {
+    /// Returns the code of the error in the registry of `crate::error_codes`, or `None` for the
+    /// errors of the assembler and the linker, which come from outside the compiler.
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            RengoError::Tokenize(error) => Some(error.code()),
+            RengoError::Parse(error) => Some(error.code()),
+            RengoError::Compile(error) => Some(error.code()),
+            RengoError::Assemble(_) | RengoError::Link(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for RengoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RengoError::Tokenize(error) => write!(f, "{}", error),
+            RengoError::Parse(error) => write!(f, "{}", error),
+            RengoError::Compile(error) => write!(f, "{}", error),
+            RengoError::Assemble(message) => write!(f, "Failed to assemble: {}", message),
+            RengoError::Link(message) => write!(f, "Failed to link: {}", message),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RengoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RengoError::Tokenize(error) => Some(error),
+            RengoError::Parse(error) => Some(error),
+            RengoError::Compile(error) => Some(error),
+            RengoError::Assemble(_) | RengoError::Link(_) => None,
+        }
+    }
+}
+
+impl From<TokenizeError> for RengoError {
+    fn from(error: TokenizeError) -> Self {
+        RengoError::Tokenize(error)
+    }
+}
+
+impl From<ParseError> for RengoError {
+    fn from(error: ParseError) -> Self {
+        RengoError::Parse(error)
+    }
+}
+
+impl From<CompileError> for RengoError {
+    fn from(error: CompileError) -> Self {
+        RengoError::Compile(error)
+    }
+}
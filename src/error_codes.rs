@@ -0,0 +1,371 @@
+//! The registry of error codes, which `rengo --explain <code>` looks up.
+//!
+//! Every error a program can be rejected with has a stable code, returned by the `code` method of
+//! `TokenizeError`, `ParseError` and `CompileError`. Codes are never reused: an error that goes
+//! away keeps its code in the registry, so the explanation can still be found.
+
+/// A documented error code.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ErrorCode {
+    /// The code, like `E001`.
+    pub code: &'static str,
+
+    /// A short description of the error, as it would start a sentence.
+    pub title: &'static str,
+
+    /// A longer description of the error and how to fix it, usually with an example.
+    pub explanation: &'static str,
+}
+
+/// The documented error codes, in order.
+pub const ERROR_CODES: &[ErrorCode] = &[
+    ErrorCode {
+        code: "E001",
+        title: "undefined variable",
+        explanation: "\
+A variable was used without being bound by an enclosing `let`.
+
+A `let` binding is only visible in its body, the part after the `;`:
+
+    (let x = 1; x) + x
+
+The second `x` is outside the parentheses, so it is undefined. If a variable in scope has a
+similar name, the error suggests it. To fix the error, bind the variable before using it:
+
+    let x = 1; x + x
+",
+    },
+    ErrorCode {
+        code: "E002",
+        title: "duplicate binding",
+        explanation: "\
+A `let` rebinds a name that is already bound in the same scope, while shadowing is denied with
+`--deny-shadowing`:
+
+    let x = 1; let x = 2; x
+
+To fix the error, give the second binding another name, or assign to the first one:
+
+    let mut x = 1; x = 2; x
+",
+    },
+    ErrorCode {
+        code: "E003",
+        title: "unsupported by the code generator",
+        explanation: "\
+The program uses a construct the language has, but that the code generator cannot translate to
+assembly yet. `Expression::While` loops, which have no syntax yet and can only be built through
+the library, are the only such construct for now.
+
+Such programs can still be run with the interpreter, `rengo::interpreter::eval`.
+",
+    },
+    ErrorCode {
+        code: "E004",
+        title: "tuple arity mismatch",
+        explanation: "\
+A destructuring `let` binds a different number of names than its value has elements.
+
+The parser only accepts pairs on both sides, so this can only happen to an `Expression::LetTuple`
+built through the library, like one binding `a` and `b` to the three elements of `(1, 2, 3)`. To
+fix the error, bind one name per element, using `_` for the elements that are not needed.
+",
+    },
+    ErrorCode {
+        code: "E005",
+        title: "tuple used as a value",
+        explanation: "\
+A tuple was used where a single value is expected. Tuples can only be destructured by a `let`:
+
+    (1, 2) + 3
+
+To fix the error, destructure the tuple and use its elements:
+
+    let (a, b) = (1, 2); a + 3
+",
+    },
+    ErrorCode {
+        code: "E006",
+        title: "too many local variables",
+        explanation: "\
+The program needs more stack slots for its variables and temporaries than can be numbered. Each
+`let` and each operand kept aside while another one is computed takes a slot of its own.
+
+To fix the error, split the program into smaller functions.
+",
+    },
+    ErrorCode {
+        code: "E007",
+        title: "undefined function",
+        explanation: "\
+A function was called without being defined by an enclosing `fn`:
+
+    f(1)
+
+Like a `let`, a function is only visible after its definition. To fix the error, define the
+function first:
+
+    fn f(x) = x + 1; f(1)
+",
+    },
+    ErrorCode {
+        code: "E008",
+        title: "wrong number of arguments",
+        explanation: "\
+A function was called with a different number of arguments than it has parameters:
+
+    fn add(a, b) = a + b; add(1)
+
+To fix the error, pass one argument per parameter:
+
+    fn add(a, b) = a + b; add(1, 2)
+",
+    },
+    ErrorCode {
+        code: "E009",
+        title: "too many parameters",
+        explanation: "\
+A function has more parameters than the code generator can pass in registers, which is 4:
+
+    fn f(a, b, c, d, e) = a; f(1, 2, 3, 4, 5)
+
+To fix the error, split the function, or combine some of the values before the call.
+",
+    },
+    ErrorCode {
+        code: "E010",
+        title: "step limit exceeded",
+        explanation: "\
+The interpreter ran more `while` iterations than its step limit allows, which usually means the
+loop never ends, like one whose condition is the number `1`. Loops have no syntax yet and can only
+be built through the library, as `Expression::While`.
+
+To fix the error, make sure the condition of the loop eventually becomes zero.
+",
+    },
+    ErrorCode {
+        code: "E011",
+        title: "assertion failed",
+        explanation: "\
+The interpreter evaluated an `assert` whose condition is zero:
+
+    let x = 0; assert(x); x
+
+Compiled programs stop with exit code 101 instead. To fix the error, find out why the condition
+does not hold.
+",
+    },
+    ErrorCode {
+        code: "E012",
+        title: "call depth exceeded",
+        explanation: "\
+The interpreter nested more function calls than its call depth limit allows, which usually means
+a recursion never ends:
+
+    fn f(x) = f(x); f(1)
+
+To fix the error, make sure every recursion reaches a case that does not call the function again.
+",
+    },
+    ErrorCode {
+        code: "E013",
+        title: "invalid character",
+        explanation: "\
+The source code contains a character that starts no token of the language, like one that no
+operator uses:
+
+    1 $ 2
+
+To fix the error, remove the character.
+",
+    },
+    ErrorCode {
+        code: "E014",
+        title: "syntax error",
+        explanation: "\
+The tokens do not form a valid program, like an operator missing its right operand or a
+parenthesis that is never closed:
+
+    (1 +
+
+To fix the error, complete the expression. `rengo --grammar` prints the grammar of the language.
+Programs nested too deeply are reported as E022 instead.
+",
+    },
+    ErrorCode {
+        code: "E015",
+        title: "assignment to an immutable variable",
+        explanation: "\
+A variable was assigned to, but it was not bound by a `let mut`. Variables bound by a plain `let`,
+by a tuple pattern, or as the parameter of a function cannot be changed:
+
+    let x = 1; x = 2; x
+
+To fix the error, declare the variable as mutable:
+
+    let mut x = 1; x = 2; x
+",
+    },
+    ErrorCode {
+        code: "E016",
+        title: "use of an uninitialized variable",
+        explanation: "\
+A variable declared without a value was read on a path of the program where it was not assigned
+yet. After an `if`, a variable only counts as assigned if both branches assign it:
+
+    let mut x; if 1 then x = 2 else 3; x
+
+To fix the error, assign the variable on every path before reading it, or give it a value where
+it is declared:
+
+    let mut x; if 1 then x = 2 else x = 3; x
+",
+    },
+    ErrorCode {
+        code: "E017",
+        title: "array used as a value",
+        explanation: "\
+An array was used where a single value is expected. Arrays can only be indexed:
+
+    [1, 2, 3] + 4
+
+To fix the error, index the array to get one of its elements, counting from 0:
+
+    [1, 2, 3][0] + 4
+",
+    },
+    ErrorCode {
+        code: "E018",
+        title: "index out of bounds",
+        explanation: "\
+An array was indexed with a negative index, or one that is not less than its number of elements.
+The elements are counted from 0, so the last element of an array of 3 is at index 2:
+
+    [10, 20, 30][3]
+
+To fix the error, use an index from 0 up to the number of elements minus one:
+
+    [10, 20, 30][2]
+
+An index that is not a number is only known when the program runs; compiled with `--sanitize`, the
+program checks it then, and exits with code 102 if it is out of bounds.
+",
+    },
+    ErrorCode {
+        code: "E019",
+        title: "invalid number literal",
+        explanation: "\
+A number literal is too large to fit in 64 bits, or runs straight into an identifier:
+
+    9223372036854775808
+    5x
+
+To fix the error, write a number from -9223372036854775808 to 9223372036854775807, and separate
+it from the name that follows with an operator.
+",
+    },
+    ErrorCode {
+        code: "E020",
+        title: "unterminated string literal",
+        explanation: "\
+A string literal has no closing quote, so it runs to the end of the source code:
+
+    print(\"hi)
+
+To fix the error, close the string with a `\"`:
+
+    print(\"hi\")
+",
+    },
+    ErrorCode {
+        code: "E021",
+        title: "missing operator between numbers",
+        explanation: "\
+Two numbers follow each other with no operator in between, while the source code is tokenized
+strictly with `--strict-lexing`:
+
+    5 5
+
+To fix the error, put an operator between the numbers, or remove one of them.
+",
+    },
+    ErrorCode {
+        code: "E022",
+        title: "program nested too deeply",
+        explanation: "\
+The program nests more parentheses and operators inside each other than the parser accepts, which
+is 256 levels, like a number inside 257 pairs of parentheses. Each level takes room on the stack
+of the compiler. Statements that follow each other do not nest; see E024 for those after `let`
+bindings.
+
+To fix the error, bind the inner parts to variables with `let` and use them instead.
+",
+    },
+    ErrorCode {
+        code: "E023",
+        title: "plus sign with no operand",
+        explanation: "\
+The source code ends with a `+` that nothing follows, so it is neither an increment `++` nor an
+addition or a unary plus, which need an operand after them:
+
+    let x = 5; x +
+
+To fix the error, write the operand after the `+`, or write `++` to increment.
+",
+    },
+    ErrorCode {
+        code: "E024",
+        title: "too many bindings in scope",
+        explanation: "\
+The program has more `let` bindings and function definitions in scope at once than the parser
+accepts, which is 1024. The statements after a binding are its body, so each binding in scope
+takes room on the stack of the compiler, like each level of nesting does.
+
+To fix the error, put the bindings only needed for part of the program in parentheses, which end
+their scope:
+
+    let total = (let a = 1; let b = 2; a + b); total
+",
+    },
+];
+
+/// Returns the documentation of an error code, ignoring case, or `None` if there is no such code.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::error_codes::explain;
+/// assert_eq!(explain("e001").map(|entry| entry.title), Some("undefined variable"));
+/// assert_eq!(explain("E999"), None);
+/// ```
+pub fn explain(code: &str) -> Option<&'static ErrorCode> {
+    ERROR_CODES.iter().find(|entry| entry.code.eq_ignore_ascii_case(code))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use expectest::prelude::*;
+    use super::*;
+
+    mod explain {
+        use super::*;
+
+        #[test]
+        fn finds_codes_in_any_case() {
+            expect!(explain("E001").map(|entry| entry.title)).to(be_some().value("undefined variable"));
+            expect!(explain("e014").map(|entry| entry.title)).to(be_some().value("syntax error"));
+        }
+
+        #[test]
+        fn rejects_unknown_codes() {
+            expect!(explain("E999")).to(be_none());
+            expect!(explain("")).to(be_none());
+        }
+    }
+
+    #[test]
+    fn codes_are_unique_and_in_order() {
+        let codes: Vec<&str> = ERROR_CODES.iter().map(|entry| entry.code).collect();
+        expect!(codes.windows(2).all(|pair| pair[0] < pair[1])).to(be_true());
+    }
+}
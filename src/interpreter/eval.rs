@@ -0,0 +1,723 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::ast::expression::{Expression, Function, DISCARD};
+use crate::ast::operator::BinaryOperator;
+use crate::compiler::compile::{array_elements, array_position, duplicate_parameter, tuple_elements};
+use crate::compiler::error::CompileError;
+use crate::compiler::initialized::check_initialized;
+
+/// Bindings is a map of variable names to their current values.
+pub type Bindings = BTreeMap<String, i64>;
+
+/// The number of `while` iterations `eval` runs before giving up on a program.
+pub const DEFAULT_STEP_LIMIT: u64 = 1_000_000;
+
+/// How many function calls the interpreter nests before giving up on a program, which usually
+/// means that a recursion never ends.
+pub const MAX_CALL_DEPTH: usize = 1_000;
+
+/// How deep the interpreter lets the evaluation of nested calls recurse, well before it would run
+/// out of the stack the CLI evaluates programs on. Each call being evaluated counts with how deep
+/// evaluating the body recurses, as `Expression::height` measures it, so that a few calls to a
+/// deeply nested body are stopped like many calls to a shallow one.
+const MAX_EVAL_DEPTH: usize = 8_192;
+
+/// Evaluates an `Expression` directly, without compiling it.
+///
+/// The `eval` function is a reference interpreter for the language. It defines the expected result
+/// of every program, so the output of the compiled code can be checked against it. Arithmetic wraps
+/// on overflow and shifts only use the lowest six bits of their count, matching the x86-64
+/// instructions emitted by the compiler. Conditions treat any nonzero value as true. A `return`
+/// ends the program with its value, wherever it is evaluated, except inside a function, which it
+/// returns from. The body of a function only sees its parameters.
+///
+/// # Arguments
+///
+/// * `expression` - A reference to the `Expression` to evaluate.
+/// * `bindings` - A mutable reference to the variables in scope. A `let` binding is only visible in
+///   its body; the previous value of a shadowed name is restored afterwards.
+///
+/// # Returns
+///
+/// * `Ok(i64)` - The value of the expression.
+/// * `Err(CompileError)` - If an identifier is not bound, or if the program runs more than
+///   `DEFAULT_STEP_LIMIT` loop iterations or nests more than `MAX_CALL_DEPTH` calls.
+///
+/// # Example
+///
+/// ```rust
+/// # use rengo::ast::expression::Expression;
+/// # use rengo::interpreter::eval::eval;
+/// # use rengo::interpreter::eval::Bindings;
+/// let expr = Expression::Increment(Box::new(Expression::Number(41, ())), ());
+/// assert_eq!(eval(&expr, &mut Bindings::new()), Ok(42));
+/// ```
+pub fn eval<T>(expression: &Expression<T>, bindings: &mut Bindings) -> Result<i64, CompileError> {
+    eval_with_limit(expression, bindings, DEFAULT_STEP_LIMIT)
+}
+
+/// Evaluates an `Expression` like `eval`, but allowing at most `step_limit` iterations of `while`
+/// loops in total. Running out of steps is a `CompileError::StepLimitExceeded`.
+pub fn eval_with_limit<T>(
+    expression: &Expression<T>,
+    bindings: &mut Bindings,
+    step_limit: u64,
+) -> Result<i64, CompileError> {
+    run(expression, bindings, &mut State::new(step_limit))
+}
+
+/// Evaluates an `Expression` like `eval`, also returning the text its `print`s wrote, each
+/// followed by a newline as the runtime writes them.
+///
+/// # Example
+///
+/// ```rust
+/// # use rengo::parse_source;
+/// # use rengo::interpreter::eval::eval_with_output;
+/// # use rengo::interpreter::eval::Bindings;
+/// let program = parse_source("print(\"hi\"); 7").unwrap();
+/// assert_eq!(eval_with_output(&program, &mut Bindings::new()), Ok(("hi\n".to_string(), 7)));
+/// ```
+pub fn eval_with_output<T>(expression: &Expression<T>, bindings: &mut Bindings) -> Result<(String, i64), CompileError> {
+    let mut state = State::new(DEFAULT_STEP_LIMIT);
+    let value = run(expression, bindings, &mut state)?;
+    Ok((state.printed, value))
+}
+
+/// Evaluates a whole program, ending it with the value of the first `return` it reaches, if any.
+fn run<'a, T>(expression: &'a Expression<T>, bindings: &mut Bindings, state: &mut State<'a, T>) -> Result<i64, CompileError> {
+    check_initialized(expression)?;
+    match eval_counted(expression, bindings, state) {
+        Ok(value) | Err(Exit::Return(value)) => Ok(value),
+        Err(Exit::Error(error)) => Err(error),
+    }
+}
+
+/// What the evaluation of a program keeps track of besides its bindings.
+struct State<'a, T> {
+    /// The loop iterations left before the program is given up on.
+    steps: u64,
+
+    /// The text written by the `print`s evaluated so far.
+    printed: String,
+
+    /// The functions in scope, innermost last.
+    functions: Vec<&'a Function<T>>,
+
+    /// The number of calls being evaluated.
+    calls: usize,
+
+    /// The sum of the depths of the bodies of the calls being evaluated.
+    depth: usize,
+
+    /// The names in scope that cannot be assigned to: those bound by a plain `let`, a tuple pattern
+    /// or as a parameter. The bindings the caller passes in are not among them.
+    immutable: BTreeSet<String>,
+}
+
+impl<T> State<'_, T> {
+    fn new(steps: u64) -> Self {
+        State { steps, printed: String::new(), functions: Vec::new(), calls: 0, depth: 0, immutable: BTreeSet::new() }
+    }
+
+    /// Records whether a name that is being bound is immutable, returning whether the binding it
+    /// shadows (if any) was, so that can be restored when the scope ends.
+    fn set_immutable(&mut self, name: &str, immutable: bool) -> bool {
+        if immutable {
+            !self.immutable.insert(String::from(name))
+        } else {
+            self.immutable.remove(name)
+        }
+    }
+}
+
+/// Why the evaluation of an expression stopped without producing its value.
+#[derive(Debug, PartialEq)]
+enum Exit {
+    /// A `return` ended the program with this value.
+    Return(i64),
+    /// The program failed.
+    Error(CompileError),
+}
+
+impl From<CompileError> for Exit {
+    fn from(error: CompileError) -> Self {
+        Exit::Error(error)
+    }
+}
+
+/// Evaluates `body` with `identifier` bound to `value`, then restores the binding it shadowed, if
+/// any.
+fn eval_scoped<'a, T>(
+    identifier: &str,
+    value: i64,
+    mutable: bool,
+    body: &'a Expression<T>,
+    bindings: &mut Bindings,
+    state: &mut State<'a, T>,
+) -> Result<i64, Exit> {
+    let shadowed = bindings.insert(String::from(identifier), value);
+    let was_immutable = state.set_immutable(identifier, !mutable);
+    let result = eval_counted(body, bindings, state);
+    state.set_immutable(identifier, was_immutable);
+    match shadowed {
+        Some(previous) => bindings.insert(String::from(identifier), previous),
+        None => bindings.remove(identifier),
+    };
+    result
+}
+
+/// Evaluates an `Expression`, taking a step out of `state.steps` for every loop iteration.
+///
+/// A `return` unwinds the evaluation as an `Exit::Return`, restoring the bindings of the `let`s
+/// it leaves on the way, so it can be told apart from an error until it reaches `eval_with_limit`.
+fn eval_counted<'a, T>(expression: &'a Expression<T>, bindings: &mut Bindings, state: &mut State<'a, T>) -> Result<i64, Exit> {
+    match expression {
+        Expression::Number(value, _) => Ok(*value),
+        Expression::Increment(expr, _) => Ok(eval_counted(expr, bindings, state)?.wrapping_add(1)),
+        Expression::Decrement(expr, _) => Ok(eval_counted(expr, bindings, state)?.wrapping_sub(1)),
+        Expression::Let(identifier, value, body, _) | Expression::LetMut(identifier, value, body, _)
+            if identifier == DISCARD =>
+        {
+            eval_counted(value, bindings, state)?;
+            eval_counted(body, bindings, state)
+        }
+        Expression::Let(identifier, value, body, _) | Expression::LetMut(identifier, value, body, _) => {
+            let mutable = matches!(expression, Expression::LetMut(..));
+            let value = eval_counted(value, bindings, state)?;
+            eval_scoped(identifier, value, mutable, body, bindings, state)
+        }
+        // `check_initialized` made sure the 0 is never read
+        Expression::Declare(identifier, body, _) => eval_scoped(identifier, 0, true, body, bindings, state),
+        Expression::Identifier(identifier, _) => Ok(bindings
+            .get(identifier)
+            .copied()
+            .ok_or_else(|| undefined_variable(identifier))?),
+        Expression::Binary(operator, lhs, rhs, _) => {
+            let lhs = eval_counted(lhs, bindings, state)?;
+            let rhs = eval_counted(rhs, bindings, state)?;
+            Ok(match operator {
+                BinaryOperator::Add => lhs.wrapping_add(rhs),
+                BinaryOperator::Subtract => lhs.wrapping_sub(rhs),
+                BinaryOperator::BitAnd => lhs & rhs,
+                BinaryOperator::BitOr => lhs | rhs,
+                BinaryOperator::BitXor => lhs ^ rhs,
+                BinaryOperator::ShiftLeft => lhs.wrapping_shl(rhs as u32),
+                BinaryOperator::ShiftRight => lhs.wrapping_shr(rhs as u32),
+                BinaryOperator::Less => i64::from(lhs < rhs),
+                BinaryOperator::Greater => i64::from(lhs > rhs),
+                BinaryOperator::Equal => i64::from(lhs == rhs),
+                BinaryOperator::Min => lhs.min(rhs),
+                BinaryOperator::Max => lhs.max(rhs),
+            })
+        }
+        Expression::Sequence(statements, _) => {
+            let mut result = 0;
+            for statement in statements {
+                result = eval_counted(statement, bindings, state)?;
+            }
+            Ok(result)
+        }
+        Expression::Assign(identifier, value, _) => {
+            if !bindings.contains_key(identifier) {
+                return Err(undefined_variable(identifier).into());
+            }
+            if state.immutable.contains(identifier) {
+                return Err(CompileError::AssignToImmutable(identifier.clone()).into());
+            }
+            let value = eval_counted(value, bindings, state)?;
+            bindings.insert(identifier.clone(), value);
+            Ok(value)
+        }
+        Expression::If(condition, then, otherwise, _) => {
+            if eval_counted(condition, bindings, state)? != 0 {
+                eval_counted(then, bindings, state)
+            } else {
+                eval_counted(otherwise, bindings, state)
+            }
+        }
+        Expression::While(condition, body, _) => {
+            while eval_counted(condition, bindings, state)? != 0 {
+                state.steps = state.steps.checked_sub(1).ok_or(CompileError::StepLimitExceeded)?;
+                eval_counted(body, bindings, state)?;
+            }
+            Ok(0)
+        }
+        Expression::LetTuple(identifiers, value, body, _) => {
+            let mut values = Vec::new();
+            for element in tuple_elements(identifiers, value)? {
+                values.push(eval_counted(element, bindings, state)?);
+            }
+            let mut shadowed = Vec::new();
+            for (identifier, value) in identifiers.iter().zip(values) {
+                if identifier != DISCARD {
+                    let previous = bindings.insert(identifier.clone(), value);
+                    shadowed.push((identifier, previous, state.set_immutable(identifier, true)));
+                }
+            }
+            let result = eval_counted(body, bindings, state);
+            // Restore from the last binding to the first, in case a name is bound twice
+            while let Some((identifier, previous, was_immutable)) = shadowed.pop() {
+                state.set_immutable(identifier, was_immutable);
+                match previous {
+                    Some(previous) => bindings.insert(identifier.clone(), previous),
+                    None => bindings.remove(identifier),
+                };
+            }
+            result
+        }
+        Expression::Tuple(..) => Err(CompileError::TupleValue.into()),
+        Expression::Array(..) => Err(CompileError::ArrayValue.into()),
+        Expression::Index(array, index, _) => {
+            let mut values = Vec::new();
+            for element in array_elements(array)? {
+                values.push(eval_counted(element, bindings, state)?);
+            }
+            let index = eval_counted(index, bindings, state)?;
+            Ok(values[array_position(index, values.len())?])
+        }
+        Expression::Print(text, _) => {
+            state.printed.push_str(text);
+            state.printed.push('\n');
+            Ok(0)
+        }
+        Expression::Assert(condition, _) => match eval_counted(condition, bindings, state)? {
+            0 => Err(CompileError::AssertionFailed.into()),
+            value => Ok(value),
+        },
+        Expression::Return(value, _) => Err(Exit::Return(eval_counted(value, bindings, state)?)),
+        Expression::Func(function, rest, _) => {
+            if let Some(parameter) = duplicate_parameter(&function.parameters) {
+                return Err(CompileError::DuplicateBinding(parameter.clone()).into());
+            }
+            state.functions.push(function);
+            let result = eval_counted(rest, bindings, state);
+            state.functions.pop();
+            result
+        }
+        Expression::Call(name, arguments, _) => call(name, arguments, bindings, state),
+    }
+}
+
+/// Evaluates a call to the innermost function in scope named `name`, with the body only seeing
+/// its parameters and the functions defined up to it, as where it was defined.
+fn call<'a, T>(
+    name: &str,
+    arguments: &'a [Expression<T>],
+    bindings: &mut Bindings,
+    state: &mut State<'a, T>,
+) -> Result<i64, Exit> {
+    let index = state.functions
+        .iter()
+        .rposition(|function| function.name == name)
+        .ok_or_else(|| CompileError::UndefinedFunction(String::from(name)))?;
+    let Function { parameters, body, .. } = state.functions[index];
+    if arguments.len() != parameters.len() {
+        let error = CompileError::ArgumentCount { name: String::from(name), expected: parameters.len(), found: arguments.len() };
+        return Err(error.into());
+    }
+    let mut locals = Bindings::new();
+    for (parameter, argument) in parameters.iter().zip(arguments) {
+        let value = eval_counted(argument, bindings, state)?;
+        if parameter != DISCARD {
+            locals.insert(parameter.clone(), value);
+        }
+    }
+    let depth = body.height();
+    if state.calls == MAX_CALL_DEPTH || state.depth + depth > MAX_EVAL_DEPTH {
+        return Err(CompileError::CallDepthExceeded.into());
+    }
+    let hidden = state.functions.split_off(index + 1);
+    let outer_immutable = core::mem::replace(&mut state.immutable, locals.keys().cloned().collect());
+    (state.calls, state.depth) = (state.calls + 1, state.depth + depth);
+    let result = eval_counted(body, &mut locals, state);
+    (state.calls, state.depth) = (state.calls - 1, state.depth - depth);
+    state.immutable = outer_immutable;
+    state.functions.extend(hidden);
+    match result {
+        Ok(value) | Err(Exit::Return(value)) => Ok(value),
+        Err(error) => Err(error),
+    }
+}
+
+/// Builds the error for a read of or an assignment to an unbound variable.
+fn undefined_variable(identifier: &str) -> CompileError {
+    CompileError::UndefinedVariable { name: String::from(identifier), suggestion: None }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use expectest::prelude::*;
+    use proptest::prelude::*;
+    use super::*;
+    use crate::parser::parse::parse;
+    use crate::parser::tokenize::tokenize;
+
+    fn eval_source(program: &str) -> Result<i64, CompileError> {
+        let ast = parse(&tokenize(program).unwrap()).unwrap();
+        eval(&ast, &mut Bindings::new())
+    }
+
+    /// Evaluates `program` on a thread with as much stack as the CLI gives the interpreter, which
+    /// the deepest recursions it accepts need.
+    fn eval_source_on_a_large_stack(program: &'static str) -> Result<i64, CompileError> {
+        std::thread::Builder::new()
+            .stack_size(64 << 20)
+            .spawn(move || eval_source(program))
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    fn identifier(name: &str) -> Box<Expression<()>> {
+        Box::new(Expression::Identifier(name.to_string(), ()))
+    }
+
+    fn number(value: i64) -> Box<Expression<()>> {
+        Box::new(Expression::Number(value, ()))
+    }
+
+    /// Builds `let mut n = <start>; let mut steps = 0; while n { n = n--; steps = steps++ }; steps`,
+    /// whose loop ends because the assignments change `n`.
+    fn countdown(start: i64) -> Expression<()> {
+        let body = Expression::Sequence(vec![
+            Expression::Assign("n".to_string(), Box::new(Expression::Decrement(identifier("n"), ())), ()),
+            Expression::Assign("steps".to_string(), Box::new(Expression::Increment(identifier("steps"), ())), ()),
+        ], ());
+        let program = Expression::Sequence(vec![
+            Expression::While(identifier("n"), Box::new(body), ()),
+            *identifier("steps"),
+        ], ());
+        Expression::LetMut(
+            "n".to_string(),
+            number(start),
+            Box::new(Expression::LetMut("steps".to_string(), number(0), Box::new(program), ())),
+            (),
+        )
+    }
+
+    mod eval {
+        use super::*;
+
+        proptest!(
+            #[test]
+            fn evaluates_numbers(value in any::<i64>()) {
+                let expr = Expression::Number(value, ());
+                expect!(eval(&expr, &mut Bindings::new())).to(be_ok().value(value));
+            }
+
+            #[test]
+            fn wraps_on_increment(value in any::<i64>()) {
+                let expr = Expression::Increment(Box::new(Expression::Number(value, ())), ());
+                expect!(eval(&expr, &mut Bindings::new())).to(be_ok().value(value.wrapping_add(1)));
+            }
+        );
+
+        #[test]
+        fn evaluates_let_bindings() {
+            expect!(eval_source("let x = 420; let y = x++; y")).to(be_ok().value(421));
+            expect!(eval_source("let x = 420; let x = 69; x")).to(be_ok().value(69));
+        }
+
+        #[test]
+        fn discards_values_bound_to_underscore() {
+            expect!(eval_source("let _ = 5; 7")).to(be_ok().value(7));
+            expect!(eval_source("let _ = y; 7")).to(be_err());  // The value is still evaluated
+        }
+
+        #[test]
+        fn scopes_let_bindings_to_their_body() {
+            let mut bindings = Bindings::new();
+            let expr = parse(&tokenize("let x = 1; x").unwrap()).unwrap();
+            eval(&expr, &mut bindings).unwrap();
+            expect!(bindings.is_empty()).to(be_true());
+        }
+
+        #[test]
+        fn fails_on_undefined_variable() {
+            expect!(eval_source("x")).to(be_err());
+        }
+
+        #[test]
+        fn evaluates_bitwise_operators() {
+            expect!(eval_source("6 & 3")).to(be_ok().value(2));
+            expect!(eval_source("6 | 3")).to(be_ok().value(7));
+            expect!(eval_source("6 ^ 3")).to(be_ok().value(5));
+            expect!(eval_source("1 | 2 & 3")).to(be_ok().value(3));
+        }
+
+        #[test]
+        fn evaluates_addition_and_subtraction() {
+            expect!(eval_source("1 + 2")).to(be_ok().value(3));
+            expect!(eval_source("3-1")).to(be_ok().value(2));
+            expect!(eval_source("10 - 4 - 3")).to(be_ok().value(3));  // Left-associative
+            expect!(eval_source("1 << 2 + 1")).to(be_ok().value(8));  // Tighter than shifts
+            expect!(eval_source("1+++2")).to(be_ok().value(4));  // `1++ + 2`
+        }
+
+        #[test]
+        fn evaluates_comparisons() {
+            expect!(eval_source("1 < 2")).to(be_ok().value(1));
+            expect!(eval_source("2 < 1")).to(be_ok().value(0));
+            expect!(eval_source("-1 > 0")).to(be_ok().value(0));  // Signed
+            expect!(eval_source("1 < 1 << 1")).to(be_ok().value(1));  // Looser than shifts
+            expect!(eval_source("3 == 3")).to(be_ok().value(1));
+            expect!(eval_source("3 == -3")).to(be_ok().value(0));
+            expect!(eval_source("1 < 2 == 1")).to(be_ok().value(1));  // Looser than comparisons
+        }
+
+        #[test]
+        fn evaluates_postfix_operators_on_a_parenthesized_let() {
+            expect!(eval_source("(let x = 5; x)++")).to(be_ok().value(6));
+            expect!(eval_source("(let x = 5; x)--")).to(be_ok().value(4));
+            expect!(eval_source("let x = 1; (let x = 5; x)++; x")).to(be_ok().value(1));  // The binding ends with the group
+        }
+
+        #[test]
+        fn evaluates_builtins() {
+            expect!(eval_source("max(3, 7)")).to(be_ok().value(7));
+            expect!(eval_source("min(3, 7)")).to(be_ok().value(3));
+            expect!(eval_source("let min = 1; min(min, 0)")).to(be_ok().value(0));  // Names stay usable
+        }
+
+        #[test]
+        fn evaluates_tuple_destructuring() {
+            expect!(eval_source("let (x, y) = (1, 2); x + y")).to(be_ok().value(3));
+            expect!(eval_source("let (x, y) = (1, 2); x - y")).to(be_ok().value(-1));  // In order
+            expect!(eval_source("let x = 1; let y = 2; let (x, y) = (y, x); x - y")).to(be_ok().value(1));  // Swap
+            expect!(eval_source("let (x, _) = (1, 2); x")).to(be_ok().value(1));
+        }
+
+        #[test]
+        fn fails_on_tuple_arity_mismatch() {
+            let three = Expression::Tuple(vec![*number(1), *number(2), *number(3)], ());
+            let expr = Expression::LetTuple(vec!["x".to_string(), "y".to_string()], Box::new(three), identifier("x"), ());
+            expect!(eval(&expr, &mut Bindings::new())).to(be_err().value(CompileError::TupleArity { expected: 2, found: 3 }));
+            expect!(eval_source("(1, 2)")).to(be_err().value(CompileError::TupleValue));
+        }
+
+        #[test]
+        fn evaluates_array_indexing() {
+            expect!(eval_source("[10, 20, 30][1]")).to(be_ok().value(20));
+            expect!(eval_source("let i = 1; [10, 20, 30][i++] + [5][0]")).to(be_ok().value(35));
+            expect!(eval_source("[10, 20, 30][3]")).to(be_err().value(CompileError::IndexOutOfBounds { index: 3, length: 3 }));
+            expect!(eval_source("[10][-1]")).to(be_err().value(CompileError::IndexOutOfBounds { index: -1, length: 1 }));
+            expect!(eval_source("[1, 2]")).to(be_err().value(CompileError::ArrayValue));
+        }
+
+        #[test]
+        fn evaluates_every_element_of_an_indexed_array() {
+            let program = parse(&tokenize("[print(\"a\"), print(\"b\")][0]").unwrap()).unwrap();
+            expect!(eval_with_output(&program, &mut Bindings::new())).to(be_ok().value(("a\nb\n".to_string(), 0)));
+        }
+
+        #[test]
+        fn evaluates_assertions() {
+            expect!(eval_source("assert(3 > 2)")).to(be_ok().value(1));
+            expect!(eval_source("assert(5)")).to(be_ok().value(5));
+            expect!(eval_source("assert(0); 1")).to(be_err().value(CompileError::AssertionFailed));
+        }
+
+        #[test]
+        fn evaluates_shifts() {
+            expect!(eval_source("1 << 4")).to(be_ok().value(16));
+            expect!(eval_source("1 << 64")).to(be_ok().value(1));  // Counts are masked to 6 bits
+            expect!(eval_source("-1 >> 1")).to(be_ok().value(-1));  // The sign bit is preserved
+            expect!(eval_source("-16 >> 2")).to(be_ok().value(-4));
+        }
+
+        #[test]
+        fn evaluates_sequences_to_their_last_statement() {
+            expect!(eval_source("1; 2; 3")).to(be_ok().value(3));
+            // Not 5: `x++` and `x--` are one above and one below `x` without changing it, so the
+            // last statement is `5 - 1`. Assigning the steps back carries them across statements
+            expect!(eval_source("let x = 5; x++; x--")).to(be_ok().value(4));
+            expect!(eval_source("let mut x = 5; x = x++; x = x--; x")).to(be_ok().value(5));
+            expect!(eval_source("1; y")).to(be_err());  // Earlier statements are still evaluated
+        }
+
+        #[test]
+        fn keeps_the_effects_of_discarded_statements() {
+            expect!(eval_source("let mut x = 5; x = x++; x = x--; x")).to(be_ok().value(5));
+            expect!(eval_source("let mut x = 1; x = x + 9; 0; x")).to(be_ok().value(10));
+            expect!(eval_source("assert(0); 1; 2")).to(be_err().value(CompileError::AssertionFailed));
+        }
+
+        #[test]
+        fn assigns_to_bound_variables() {
+            // let mut x = 1; x = 5; x
+            let expr = Expression::LetMut(
+                "x".to_string(),
+                number(1),
+                Box::new(Expression::Sequence(vec![
+                    Expression::Assign("x".to_string(), number(5), ()),
+                    *identifier("x"),
+                ], ())),
+                (),
+            );
+            expect!(eval(&expr, &mut Bindings::new())).to(be_ok().value(5));
+            let unbound = Expression::Assign("x".to_string(), number(5), ());
+            expect!(eval(&unbound, &mut Bindings::new())).to(be_err());
+        }
+
+        #[test]
+        fn only_assigns_to_mutable_bindings() {
+            expect!(eval_source("let mut x = 1; x = 2; x")).to(be_ok().value(2));
+            let immutable = CompileError::AssignToImmutable("x".to_string());
+            expect!(eval_source("let x = 1; x = 2; x")).to(be_err().value(immutable.clone()));
+            expect!(eval_source("let mut x = 1; let x = 2; x = 3")).to(be_err().value(immutable.clone()));
+            expect!(eval_source("let x = 1; (let mut x = 2; x = 3); x")).to(be_ok().value(1));
+            expect!(eval_source("let (x, y) = (1, 2); x = 3")).to(be_err().value(immutable.clone()));
+            expect!(eval_source("fn f(x) = (x = 2; x); f(1)")).to(be_err().value(immutable));
+        }
+
+        #[test]
+        fn reads_declared_variables_once_they_are_assigned() {
+            expect!(eval_source("let mut x; x = 41; x++")).to(be_ok().value(42));
+            expect!(eval_source("let x = 1; (let mut x; x = 2); x")).to(be_ok().value(1));
+            let uninitialized = CompileError::UseOfUninitialized("x".to_string());
+            expect!(eval_source("let mut x; x")).to(be_err().value(uninitialized.clone()));
+            // The check is static, so a branch that is never taken still counts
+            expect!(eval_source("let mut x; if 1 then x = 1 else 0; x")).to(be_err().value(uninitialized));
+        }
+
+        #[test]
+        fn lets_the_bindings_of_the_caller_be_assigned() {
+            let mut bindings = Bindings::new();
+            bindings.insert("x".to_string(), 1);
+            expect!(eval(&parse(&tokenize("x = 2").unwrap()).unwrap(), &mut bindings)).to(be_ok().value(2));
+            expect!(bindings.get("x")).to(be_some().value(&2));
+        }
+
+        #[test]
+        fn chains_assignments() {
+            expect!(eval_source("let mut x = 1; let mut y = 2; x = y = 5; x")).to(be_ok().value(5));
+            expect!(eval_source("let mut x = 1; let mut y = 2; x = y = 5; y")).to(be_ok().value(5));
+            expect!(eval_source("let mut x = 1; x = y = 5; x")).to(be_err());  // `y` is not bound
+        }
+
+        #[test]
+        fn evaluates_only_the_taken_branch_of_an_if() {
+            // Evaluating `y` would fail, as it is not bound
+            let taken = Expression::If(number(2), number(10), identifier("y"), ());
+            expect!(eval(&taken, &mut Bindings::new())).to(be_ok().value(10));
+            let not_taken = Expression::If(number(0), identifier("y"), number(20), ());
+            expect!(eval(&not_taken, &mut Bindings::new())).to(be_ok().value(20));
+        }
+
+        #[test]
+        fn runs_a_countdown_loop() {
+            expect!(eval(&countdown(5), &mut Bindings::new())).to(be_ok().value(5));
+            expect!(eval(&countdown(0), &mut Bindings::new())).to(be_ok().value(0));
+        }
+
+        #[test]
+        fn stops_loops_at_the_step_limit() {
+            expect!(eval_with_limit(&countdown(5), &mut Bindings::new(), 5)).to(be_ok().value(5));
+            expect!(eval_with_limit(&countdown(5), &mut Bindings::new(), 4))
+                .to(be_err().value(CompileError::StepLimitExceeded));
+            let forever = Expression::While(number(1), number(0), ());
+            expect!(eval(&forever, &mut Bindings::new())).to(be_err().value(CompileError::StepLimitExceeded));
+        }
+    }
+
+    mod returns {
+        use super::*;
+
+        #[test]
+        fn skips_the_rest_of_the_sequence() {
+            expect!(eval_source("return 5; 7")).to(be_ok().value(5));
+        }
+
+        #[test]
+        fn returns_from_a_branch() {
+            expect!(eval_source("if 1 then return 3 else 4")).to(be_ok().value(3));
+            expect!(eval_source("if 0 then return 3 else 4; 8")).to(be_ok().value(8));
+        }
+
+        #[test]
+        fn unwinds_the_bindings() {
+            let mut bindings = Bindings::new();
+            let ast = parse(&tokenize("let x = 1; let y = 2; return x + y; 0").unwrap()).unwrap();
+            expect!(eval(&ast, &mut bindings)).to(be_ok().value(3));
+            expect!(bindings.is_empty()).to(be_true());
+        }
+    }
+
+    mod functions {
+        use super::*;
+
+        #[test]
+        fn calls_a_function_with_two_arguments() {
+            expect!(eval_source("fn add(a, b) = a + b; add(1, 2)")).to(be_ok().value(3));
+        }
+
+        #[test]
+        fn evaluates_recursive_calls() {
+            expect!(eval_source("fn sum(n) = if n then n + sum(n--) else 0; sum(10)")).to(be_ok().value(55));
+        }
+
+        #[test]
+        fn returns_from_the_function_only() {
+            expect!(eval_source("fn f(x) = (return x; 0); f(4) + 1")).to(be_ok().value(5));
+        }
+
+        #[test]
+        fn hides_the_variables_around_the_call() {
+            expect!(eval_source("let y = 1; fn f(x) = x + y; f(2)")).to(be_err());
+            expect!(eval_source("let x = 1; fn f(x) = x; f(2) + x")).to(be_ok().value(3));
+        }
+
+        #[test]
+        fn hides_the_functions_defined_later() {
+            expect!(eval_source("fn f() = g(); fn g() = 1; f()"))
+                .to(be_err().value(CompileError::UndefinedFunction("g".to_string())));
+        }
+
+        #[test]
+        fn fails_on_mismatched_calls() {
+            expect!(eval_source("f(1)")).to(be_err().value(CompileError::UndefinedFunction("f".to_string())));
+            expect!(eval_source("fn f(x) = x; f()"))
+                .to(be_err().value(CompileError::ArgumentCount { name: "f".to_string(), expected: 1, found: 0 }));
+            expect!(eval_source("fn f(a, a) = a; 0")).to(be_err().value(CompileError::DuplicateBinding("a".to_string())));
+        }
+
+        #[test]
+        fn recurses_up_to_the_call_depth_limit() {
+            let countdown = "fn f(n) = if n then f(n - 1) + 1 else 0; f(60)";
+            expect!(eval_source_on_a_large_stack(countdown)).to(be_ok().value(60));
+            let deepest = "fn f(n) = if n then f(n - 1) + 1 else 0; f(999)";
+            expect!(eval_source_on_a_large_stack(deepest)).to(be_ok().value(999));
+            let too_deep = "fn f(n) = if n then f(n - 1) + 1 else 0; f(1000)";
+            expect!(eval_source_on_a_large_stack(too_deep)).to(be_err().value(CompileError::CallDepthExceeded));
+        }
+
+        #[test]
+        fn stops_endless_recursion() {
+            let endless = "fn f(x) = f(x + 1) + 1; f(0)";
+            expect!(eval_source_on_a_large_stack(endless)).to(be_err().value(CompileError::CallDepthExceeded));
+            let nested = "fn f(n) = (let m = n + 1; if m then (let k = m; (k + f(m))--)++ else 0); f(1)";
+            expect!(eval_source_on_a_large_stack(nested)).to(be_err().value(CompileError::CallDepthExceeded));
+        }
+    }
+
+    mod print {
+        use super::*;
+
+        #[test]
+        fn evaluates_to_zero() {
+            expect!(eval_source("print(\"hi\")")).to(be_ok().value(0));
+            expect!(eval_source("print(\"hi\"); 3")).to(be_ok().value(3));
+        }
+
+        #[test]
+        fn collects_the_printed_text() {
+            let ast = parse(&tokenize("print(\"a\"); if 0 then print(\"b\") else print(\"c\"); 3").unwrap()).unwrap();
+            expect!(eval_with_output(&ast, &mut Bindings::new())).to(be_ok().value(("a\nc\n".to_string(), 3)));
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! The `rengo` compiler as a library.
+//!
+//! The front end (`parser`, `ast`), the code generator (`compiler`, `env`), the assembly model
+//! (`asm`) and the reference interpreter (`interpreter`) only rely on `alloc`, so they can be used
+//! from `no_std` environments by disabling the default `std` feature. Anything that touches the
+//! file system or spawns processes (like `assemble` and `runner`) is only available with `std`.
+//!
+//! The `no_std` build can be checked with:
+//!
+//! ```text
+//! cargo test --lib --no-default-features
+//! ```
+//!
+//! The front end is also fuzzed, without `std`, by the target in `fuzz/fuzz_targets/parse.rs`,
+//! which explains how to run it.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
+pub mod asm;
+pub mod ast;
+pub mod compiler;
+pub mod env;
+pub mod error;
+pub mod error_codes;
+pub mod parser;
+pub mod radix;
+#[cfg(feature = "std")]
+pub mod assemble;
+#[cfg(feature = "std")]
+pub mod runner;
+pub mod interpreter;
+
+use crate::ast::expression::Expression;
+use crate::compiler::compile::compile_expression;
+use crate::compiler::context::Ctx;
+use crate::compiler::options::CompileOptions;
+use crate::compiler::unit::CompiledUnit;
+use crate::error::RengoError;
+
+/// Tokenizes and parses source code into an `Expression`.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parse_source;
+/// # use rengo::ast::expression::Expression;
+/// # use rengo::error::RengoError;
+/// assert_eq!(parse_source("42"), Ok(Expression::Number(42, ())));
+/// assert!(matches!(parse_source("1 &"), Err(RengoError::Parse(_))));
+/// ```
+pub fn parse_source(source: &str) -> Result<Expression<()>, RengoError> {
+    let tokens = parser::tokenize::tokenize(source)?;
+    Ok(parser::parse::parse(&tokens)?)
+}
+
+/// Compiles source code into instructions, reporting the stage that rejected the program if it
+/// fails.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::compile_source;
+/// # use rengo::compiler::options::CompileOptions;
+/// # use rengo::error::RengoError;
+/// let unit = compile_source("let x = 41; x++", &CompileOptions::default())?;
+/// assert!(matches!(compile_source("x", &CompileOptions::default()), Err(RengoError::Compile(_))));
+/// # Ok::<(), RengoError>(())
+/// ```
+pub fn compile_source(source: &str, options: &CompileOptions) -> Result<CompiledUnit, RengoError> {
+    let expression = parse_source(source)?;
+    Ok(compile_expression(&expression, &mut Ctx::new(options.clone()))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use expectest::prelude::*;
+    use super::*;
+    use crate::compiler::error::CompileError;
+
+    mod compile_source {
+        use super::*;
+
+        #[test]
+        fn compiles_valid_programs() {
+            expect!(compile_source("let x = 41; x++", &CompileOptions::default())).to(be_ok());
+        }
+
+        #[test]
+        fn reports_the_failing_stage() {
+            let options = CompileOptions::default();
+            expect!(matches!(compile_source("4 $ 2", &options), Err(RengoError::Tokenize(_)))).to(be_true());
+            expect!(matches!(compile_source("(4", &options), Err(RengoError::Parse(_)))).to(be_true());
+            expect!(matches!(
+                compile_source("y", &options),
+                Err(RengoError::Compile(CompileError::UndefinedVariable { .. }))
+            )).to(be_true());
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "std")))]
+mod no_std_tests {
+    use alloc::vec;
+    use expectest::prelude::*;
+    use crate::asm::arg::Arg::{Constant, Registry, RegistryOffset};
+    use crate::asm::instruction::Instruction::{Inc, Mov};
+    use crate::asm::reg::Reg::{Rax, Rsp};
+    use crate::compiler::compile::compile_expression;
+    use crate::compiler::context::Ctx;
+    use crate::parser::parse::parse;
+    use crate::parser::tokenize::tokenize;
+
+    #[test]
+    fn parses_and_compiles_without_std() {
+        let ast = parse(&tokenize("let x = 41; x++").unwrap()).unwrap();
+        let instructions = compile_expression(&ast, &mut Ctx::default()).unwrap().instructions;
+        expect!(instructions).to(be_equal_to(vec![
+            Mov(Registry(Rax), Constant(41)),
+            Mov(RegistryOffset(Rsp, -1), Registry(Rax)),
+            Mov(Registry(Rax), RegistryOffset(Rsp, -1)),
+            Inc(Registry(Rax)),
+        ]));
+    }
+}
@@ -1,10 +1,71 @@
 #include <stdio.h>
+#include <stdlib.h>
 #include <stdint.h>
 
 extern int64_t _start() asm("_start");
 
+/* The exit code of a failed assertion; keep it in sync with ASSERT_EXIT_CODE in src/compiler/compile.rs. */
+#define RENGO_ASSERT_EXIT_CODE 101
+
+/* Called by the compiled code when an assertion fails. */
+void rengo_assert_failed(void) asm("rengo_assert_failed");
+
+void rengo_assert_failed(void) {
+  fprintf(stderr, "assertion failed\n");
+  exit(RENGO_ASSERT_EXIT_CODE);
+}
+
+/* The exit code of an index out of bounds; keep it in sync with INDEX_EXIT_CODE in src/compiler/compile.rs. */
+#define RENGO_INDEX_EXIT_CODE 102
+
+/* Called by the compiled code with `--sanitize` when an index is out of the bounds of its array. */
+void rengo_index_out_of_bounds(void) asm("rengo_index_out_of_bounds");
+
+void rengo_index_out_of_bounds(void) {
+  fprintf(stderr, "index out of bounds\n");
+  exit(RENGO_INDEX_EXIT_CODE);
+}
+
+/* Called by the compiled code for `print`, with the System V calling convention on every target. */
+void print_str(const char* text) asm("print_str") __attribute__((sysv_abi));
+
+void print_str(const char* text) {
+  puts(text);
+}
+
+/* The exit code of a result outside the exit code range, with RENGO_EXIT_CODE_ERROR; keep it in
+   sync with OUT_OF_RANGE_EXIT_CODE in src/runner.rs. */
+#define RENGO_OUT_OF_RANGE_EXIT_CODE 255
+
+/* Returns the exit code for the result: 0 by default, its low 8 bits with RENGO_EXIT_CODE_TRUNCATE,
+   or the result itself with RENGO_EXIT_CODE_ERROR, which exits with RENGO_OUT_OF_RANGE_EXIT_CODE
+   instead if the result does not fit below it. */
+static int exit_code(int64_t result) {
+#if defined(RENGO_EXIT_CODE_TRUNCATE)
+  return (int) (result & 0xff);
+#elif defined(RENGO_EXIT_CODE_ERROR)
+  if (result < 0 || result >= RENGO_OUT_OF_RANGE_EXIT_CODE) {
+    fprintf(stderr, "result out of the exit code range: %lld\n", (long long) result);
+    return RENGO_OUT_OF_RANGE_EXIT_CODE;
+  }
+  return (int) result;
+#else
+  (void) result;
+  return 0;
+#endif
+}
+
+/* Prints the result in the radix selected when linking: define RENGO_RADIX_HEX for hexadecimal. */
+static void print_result(int64_t result) {
+#ifdef RENGO_RADIX_HEX
+  printf("0x%llx\n", (unsigned long long) result);
+#else
+  printf("%lld\n", (long long) result);
+#endif
+}
+
 int main(int argc, char** argv) {
   int64_t result = _start();
-  printf("%lld\n", result);
-  return 0;
+  print_result(result);
+  return exit_code(result);
 }
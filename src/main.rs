@@ -1,105 +1,454 @@
-mod asm;
-mod ast;
-mod compiler;
-mod parser;
-mod env;
-mod assemble;
+mod cli;
+mod diagnostics;
+#[cfg(test)]
 mod tests;
+mod timing;
 
 use std::fs;
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use crate::asm::instruction::Instruction;
-use crate::asm::to_string::asm_to_string;
-use crate::assemble::assemble;
-use crate::ast::expression::Expression;
-use crate::compiler::compile::compile_expression;
-use crate::parser::parse::parse;
-use crate::parser::tokenize;
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = parse_args()?;
-    let program = read_program(&args[1])?;
-    let assembly = compile(program);
-
-    let asm_output_path = create_output_paths("build/s/", "out.asm")?;
-    let obj_output_path = create_output_paths("build/obj/", "out.obj")?;
-    let exe_output_path = create_output_paths("build/", "out.exe")?;
-
-    let prelude = "section .text\n\
-                   global _start\n\
-                   _start:\n";
-    let asm = format!("{}\n{}", prelude, asm_to_string(assembly.unwrap()));
-    let ret = "ret\n";
-    let compiled_asm = format!("{}\n{}", asm, ret);
-    write_assembly(&asm_output_path, &compiled_asm)?;
-    assemble(&asm_output_path, &obj_output_path)?;
-    link(&obj_output_path, &exe_output_path)?;
+use std::process::{Command, ExitCode};
+use std::thread;
+use crate::cli::{Args, Emit, ExitCodeMode, LinkOptions, Stage};
+use crate::diagnostics::{render_error, render_explanation, render_hint, render_warning, sort, ColorChoice, Diagnostic, Output};
+use crate::timing::PhaseTimer;
+use rengo::asm::align::align_loops;
+use rengo::asm::instruction::Instruction;
+use rengo::asm::program::Program;
+use rengo::asm::style::{AsmStyle, LineEnding};
+use rengo::asm::to_string::asm_to_string_with_style;
+use rengo::assemble::assemble;
+use rengo::ast::expression::Expression;
+use rengo::ast::json::to_json;
+use rengo::compiler::compile::compile_expression;
+use rengo::compiler::context::Ctx;
+use rengo::compiler::lint::lint_with_spans;
+use rengo::compiler::llvm::compile_llvm_ir;
+use rengo::compiler::options::CompileOptions;
+use rengo::compiler::unit::CompiledUnit;
+use rengo::error::RengoError;
+use rengo::error_codes::explain;
+use rengo::interpreter::eval::{eval_with_output, Bindings};
+use rengo::parser::doc::split_doc;
+use rengo::parser::grammar::grammar;
+use rengo::parser::parse::{parse, parse_recovering};
+use rengo::parser::error::TokenizeError;
+use rengo::parser::span::{SourceSpan, Span};
+use rengo::parser::token::Token;
+use rengo::parser::json::tokens_to_json;
+use rengo::parser::tokenize::{tokenize_with_options, tokenize_with_spans, TokenizeOptions};
+use rengo::radix::{format_result, Radix};
+use rengo::runner::OUT_OF_RANGE_EXIT_CODE;
 
+/// The size of the stack the compiler runs on. Its passes recurse once per level of nesting and
+/// once per binding in scope, which `parse` bounds by `MAX_NESTING` and `MAX_BINDINGS`, and a
+/// program at both bounds needs more than the stack of the main thread.
+const STACK_SIZE: usize = 64 << 20;
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(error) => return report(error, ColorChoice::Auto),
+    };
+    let compiler = thread::Builder::new().stack_size(STACK_SIZE).spawn(move || {
+        match run(&args, Path::new("build"), &mut io::stdout(), &mut io::stderr()) {
+            Ok(status) => ExitCode::from(status),
+            Err(error) => report(error, args.color),
+        }
+    });
+    match compiler {
+        Ok(compiler) => compiler.join().unwrap_or(ExitCode::FAILURE),
+        Err(error) => report(error.into(), ColorChoice::Auto),
+    }
+}
+
+/// Prints an error as a diagnostic on standard error and returns the exit code for a failure. An
+/// error with a code is followed by a hint to look it up with `--explain`.
+fn report(error: Box<dyn std::error::Error>, color: ColorChoice) -> ExitCode {
+    let color = color.enabled(Output::is_terminal(&io::stderr()));
+    let code = error.downcast_ref::<RengoError>().and_then(RengoError::code);
+    eprintln!("{}", render_error(&error, code, color));
+    if let Some(code) = code {
+        eprintln!("{}", render_hint(code));
+    }
+    ExitCode::FAILURE
+}
+
+/// Runs the compiler as requested by the command-line arguments.
+///
+/// The programs of the input files are joined with `;` into a single program, in order.
+/// Intermediate and output files are written under `build_dir`, while anything the user asked to
+/// see (`--grammar`, `--explain`, `--emit-tokens-json`, `--emit-ast-json`, `--emit-asm`,
+/// `--dump-ir`) is written to `out`. Those modes, like `--stop-after=ir`, stop early and never
+/// touch `build_dir`; `--dump-ir` alone carries on. With `--emit=obj`, the object file is the final
+/// output and nothing is linked. With `--clean`, the intermediate files are removed once linking
+/// succeeds; they are kept if it fails, for debugging. With `--emit=run-interpreted`, nothing is
+/// compiled: the interpreter runs the program and what it prints, then its result, are written to
+/// `out` as the executable would print them, and the result becomes the exit status, fitted into it
+/// by `exit_status`. With `--emit=llvm-ir`, the program is lowered to LLVM IR, which is written to
+/// `out`. With `--time`, the duration of each phase that runs is reported to `err`. With `--doc`,
+/// the comment lines at the start of the input are printed to `out` before compiling. The syntax
+/// errors and the warnings about the program are collected, whichever pass found them, and written
+/// to `err` together in the order they appear in the input files; the warnings are about what could
+/// be parsed. A syntax error stops the compilation once they are written, as does any warning with
+/// `--strict`. With `--check`, the program is compiled in memory to find its errors, and nothing
+/// else is done: no output is written and `build_dir` is never touched. The passes of the
+/// optimization level (`-O0`, `-O1`, `-O2`) run over the program once it is linted, before any
+/// backend or the interpreter sees it. With `rengo fmt`, the program is printed back to `out` in
+/// its canonical layout instead: the documentation and the lines after the program are kept as they
+/// are.
+///
+/// Returns the exit status of the process, which is `0` unless the program was interpreted.
+pub(crate) fn run(args: &Args, build_dir: &Path, out: &mut dyn Output, err: &mut dyn Output) -> Result<u8, Box<dyn std::error::Error>> {
+    if args.grammar {
+        write!(out, "{}", grammar())?;
+        return Ok(0);
+    }
+    if let Some(code) = &args.explain {
+        let entry = explain(code).ok_or_else(|| format!("{} is not a valid error code", code))?;
+        write!(out, "{}", render_explanation(entry, args.color.enabled(out.is_terminal())))?;
+        return Ok(0);
+    }
+    let color = args.color.enabled(err.is_terminal());
+    let mut timer = PhaseTimer::new(err, args.time);
+    let inputs = args.inputs.iter()
+        .map(|path| read_input(path, args.max_program_size()))
+        .collect::<Result<Vec<_>, _>>()?;
+    if args.fmt {
+        let (_, rest) = split_doc(&inputs[0]);
+        format_source(&inputs[0], rest, &args.tokenize_options(), out)?;
+        return Ok(0);
+    }
+    let mut sources = Vec::new();
+    for input in &inputs {
+        let (doc, rest) = split_doc(input);
+        if args.doc {
+            for line in doc {
+                writeln!(out, "{}", line)?;
+            }
+        }
+        let prefix = &input[..input.len() - rest.len()];
+        sources.push((prefix, rest.lines().next().ok_or("Error: empty input file")?));
+    }
+    if args.emit_tokens_json {
+        let tokens = tokenize_sources(&sources[..1], &args.tokenize_options()).map_err(RengoError::from)?;
+        writeln!(out, "{}", tokens_to_json(&inputs[0], &tokens))?;
+        return Ok(0);
+    }
+    let tokens = timer.time("tokenize", || tokenize_sources(&sources, &args.tokenize_options()))?
+        .map_err(RengoError::from)?;
+    let (program, errors) = timer.time("parse", || parse_recovering(&tokens))?;
+    if let Some(program) = program.as_ref().filter(|_| args.emit_ast_json && errors.is_empty()) {
+        writeln!(out, "{}", to_json(program))?;
+        return Ok(0);
+    }
+    // The program the parser recovered from its errors, if any, is linted as well
+    let warnings = program.as_ref().map(|program| lint_with_spans(program, &args.lint_options())).unwrap_or_default();
+    // Every diagnostic is collected before any is printed, so they come out in source order
+    let mut diagnostics: Vec<Diagnostic> = errors.iter()
+        .map(|(error, span)| Diagnostic { span: *span, rendered: render_error(error, Some(error.code()), color) })
+        .chain(warnings.iter().map(|(warning, span)| Diagnostic { span: *span, rendered: render_warning(warning, color) }))
+        .collect();
+    sort(&mut diagnostics);
+    for diagnostic in &diagnostics {
+        writeln!(timer.stream(), "{}", diagnostic.rendered)?;
+    }
+    let Some(program) = program.filter(|_| errors.is_empty()) else {
+        let mut codes: Vec<&str> = errors.iter().map(|(error, _)| error.code()).collect();
+        codes.sort();
+        codes.dedup();
+        for code in codes {
+            writeln!(timer.stream(), "{}", render_hint(code))?;
+        }
+        return Err(format!("aborting due to {} parse error(s)", errors.len()).into());
+    };
+    if args.strict && !warnings.is_empty() {
+        return Err(format!("{} warning(s) treated as errors (--strict)", warnings.len()).into());
+    }
+    let program = args.opt_level.optimize(define(program, &args.defines));
+    if args.check {
+        timer.time("compile", || compile(program, &args.compile_options()))??;
+        return Ok(0);
+    }
+    if args.emit == Emit::RunInterpreted {
+        let (printed, result) = timer.time("interpret", || eval_with_output(&program, &mut Bindings::new()))?
+            .map_err(RengoError::from)?;
+        write!(out, "{}", printed)?;
+        writeln!(out, "{}", format_result(result, args.radix))?;
+        return exit_status(result, args.exit_code_mode, timer.stream());
+    }
+    if args.emit == Emit::LlvmIr {
+        let ir = timer.time("compile", || compile_llvm_ir(&program))?.map_err(RengoError::from)?;
+        write!(out, "{}", ir)?;
+        return Ok(0);
+    }
+    let mut unit = timer.time("compile", || compile(program, &args.compile_options()))??;
+    if let Some(alignment) = args.align_loops {
+        unit.instructions = align_loops(unit.instructions, alignment);
+    }
+    if args.dump_ir {
+        for instruction in &unit.instructions {
+            writeln!(out, "{:?}", instruction)?;
+        }
+    }
+    if args.stop_after == Some(Stage::Ir) {
+        return Ok(0);
+    }
+    let asm = generate_asm(unit, args.entry_symbol(), args.pie == Some(true), args.line_ending);
+    if args.emit_asm {
+        write!(out, "{}", asm)?;
+        return Ok(0);
+    }
+
+    let (asm_output_path, obj_output_path) = timer.time("assemble", || write_and_assemble(&asm, build_dir, args.target_os()))??;
+    if args.emit == Emit::Obj {
+        return Ok(0);
+    }
+    let exe_output_path = create_output_paths(build_dir, "out.exe")?;
+    timer.time("link", || link(&obj_output_path, &exe_output_path, &args.link_options()))??;
+    if args.clean {
+        remove_intermediates(&[&asm_output_path, &obj_output_path])?;
+    }
+
+    Ok(0)
+}
+
+/// Returns the exit status for the result of an interpreted program, fitted into 8 bits as the
+/// runtime does with `mode`: its low 8 bits, unless `ExitCodeMode::Error` is selected, which
+/// reports a result outside `0..OUT_OF_RANGE_EXIT_CODE` to `err` and gives
+/// `OUT_OF_RANGE_EXIT_CODE` instead. Unlike an executable, which exits with `0` without a mode,
+/// the interpreter always exits with its result.
+fn exit_status(result: i64, mode: Option<ExitCodeMode>, err: &mut dyn Write) -> Result<u8, Box<dyn std::error::Error>> {
+    let out_of_range = OUT_OF_RANGE_EXIT_CODE as u8;
+    match mode {
+        Some(ExitCodeMode::Error) if !(0..i64::from(out_of_range)).contains(&result) => {
+            writeln!(err, "result out of the exit code range: {}", result)?;
+            Ok(out_of_range)
+        }
+        _ => Ok(result as u8),
+    }
+}
+
+/// Tokenizes the programs of the input files and joins them with `;` into the tokens of a single
+/// program, so that the `let` bindings of a file are in scope in the files after it, and the value
+/// of the program is that of the last file. Each file is tokenized on its own, so none of them can
+/// change how the next one is read.
+///
+/// Each source comes with the text of its file before it, so that the lines and columns of the
+/// spans are located in the whole file, documentation included, as editors show it. Their byte
+/// offsets are counted as if the sources were written one after the other, each on a line of its
+/// own, so that they tell the files apart and follow the token stream. The `;` joining two files is
+/// located at the end of the first.
+fn tokenize_sources(sources: &[(&str, &str)], options: &TokenizeOptions) -> Result<Vec<(Token, Span)>, TokenizeError> {
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    for (index, (prefix, source)) in sources.iter().enumerate() {
+        if index > 0 {
+            let (previous_prefix, previous) = sources[index - 1];
+            let end = Span { start: previous.len(), end: previous.len(), line: 1, col: previous.chars().count() + 1 };
+            tokens.push((Token::LineEnd, shifted(end.after(previous_prefix), offset)));
+            offset += previous_prefix.len() + previous.len() + 1;
+        }
+        let located = tokenize_with_spans(source, options)?;
+        tokens.extend(located.into_iter().map(|(token, span)| (token, shifted(span.after(prefix), offset))));
+    }
+    Ok(tokens)
+}
+
+/// Returns `span` with its byte offsets moved `offset` bytes further, its line and column kept.
+fn shifted(span: Span, offset: usize) -> Span {
+    Span { start: span.start + offset, end: span.end + offset, ..span }
+}
+
+/// Writes a source file back with its program in the canonical layout of `Expression`'s `Display`
+/// impl, for `rengo fmt`. `rest` is what follows the documentation of `input`.
+///
+/// Only the program is reformatted: the documentation before it and the lines after it, which the
+/// compiler does not read, are written unchanged, so formatting a file twice gives the same text.
+fn format_source(input: &str, rest: &str, options: &TokenizeOptions, out: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+    let (source, after) = rest.split_once('\n').unwrap_or((rest, ""));
+    if rest.is_empty() {
+        return Err("Error: empty input file".into());
+    }
+    let tokens = tokenize_with_options(source, options).map_err(RengoError::from)?;
+    let program = parse(&tokens).map_err(RengoError::from)?;
+    write!(out, "{}{}\n{}", &input[..input.len() - rest.len()], program, after)?;
     Ok(())
 }
 
-fn parse_args() -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
-        eprintln!("Usage: {} <program>", args[0]);
-        return Err("Invalid number of arguments".into());
+/// Binds the names given with `--define` around the program, the first one outermost, as `let`
+/// bindings would: each gets a stack slot initialized with its value before the program runs, and
+/// the interpreter sees them the same way. A later definition of a name shadows an earlier one.
+///
+/// This happens after linting, so that a definition the program does not use is not reported as an
+/// unused variable, and the lints only ever see the program as written. The compiler does see the
+/// definitions: with `--deny-shadowing`, a program that binds a defined name again is rejected with
+/// `CompileError::DuplicateBinding`, as if the definition were part of it.
+fn define<T: Default>(program: Expression<T>, defines: &[(String, i64)]) -> Expression<T> {
+    defines.iter().rev().fold(program, |body, (name, value)| {
+        Expression::Let(name.clone(), Box::new(Expression::Number(*value, T::default())), Box::new(body), T::default())
+    })
+}
+
+/// Renders a compiled unit as a complete NASM source file, exporting the code as `entry` and
+/// declaring the runtime functions it calls as external. The strings the code refers to are
+/// placed in a data section before the code. For position-independent code, memory
+/// references default to being relative to `Rip`. Every line ends with `line_ending`.
+pub(crate) fn generate_asm(unit: CompiledUnit, entry: &str, position_independent: bool, line_ending: LineEnding) -> String {
+    let nl = line_ending.as_str();
+    let program = Program { data: unit.data, text: unit.instructions };
+    let functions = unit.functions;
+    let style = AsmStyle { indent: true, line_ending };
+    let default_rel = if position_independent { format!("default rel{nl}") } else { String::new() };
+    let code: Vec<Instruction> = program.text.iter().chain(&functions).cloned().collect();
+    let externs = extern_declarations(&code, nl);
+    let data = program.data_to_string(&style);
+    let prelude = format!("{default_rel}{externs}{data}section .text{nl}global {entry}{nl}{entry}:{nl}");
+    let asm = format!("{}{nl}{}", prelude, asm_to_string_with_style(program.text, &style));
+    let ret = format!("ret{nl}");
+    if functions.is_empty() {
+        return format!("{}{nl}{}", asm, ret);
+    }
+    // The routines of the functions follow the `ret` that ends the program, so they only run when called
+    format!("{}{nl}{}{}{nl}", asm, ret, asm_to_string_with_style(functions, &style))
+}
+
+/// Returns an `extern` line for each function called by the instructions that they do not define
+/// themselves, in order of first call, each ended by `nl`.
+fn extern_declarations(instructions: &[Instruction], nl: &str) -> String {
+    let defined = |symbol: &str| instructions.iter().any(|instruction| matches!(instruction, Instruction::Label(label) if label == symbol));
+    let mut symbols: Vec<&str> = Vec::new();
+    for instruction in instructions {
+        if let Instruction::Call(symbol) = instruction.unlocated() {
+            if !symbols.contains(&symbol.as_str()) && !defined(symbol) {
+                symbols.push(symbol);
+            }
+        }
     }
-    Ok(args)
+    symbols.iter().map(|symbol| format!("extern {}{}", symbol, nl)).collect()
+}
+
+/// Writes the assembly under `build_dir` and assembles it, returning the paths of the assembly and
+/// object files.
+fn write_and_assemble(asm: &str, build_dir: &Path, os: &str) -> Result<(PathBuf, PathBuf), Box<dyn std::error::Error>> {
+    let asm_output_path = create_output_paths(build_dir.join("s"), "out.asm")?;
+    let obj_output_path = create_output_paths(build_dir.join("obj"), "out.obj")?;
+    write_assembly(&asm_output_path, asm)?;
+    assemble(&asm_output_path, &obj_output_path, os)?;
+    Ok((asm_output_path, obj_output_path))
+}
+
+/// Removes intermediate files that are no longer needed once the executable is linked.
+pub(crate) fn remove_intermediates(paths: &[&Path]) -> Result<(), io::Error> {
+    for path in paths {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn parse_args() -> Result<Args, Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+    cli::parse_args(&args).map_err(|error| {
+        eprintln!("Usage: {} [options] <program>...", args[0]);
+        error.into()
+    })
 }
 
-fn read_program<T>(input_path: &str) -> Result<Expression<T>, Box<dyn std::error::Error>> {
-    let input_file = File::open(input_path).expect("Failed to open input file");
-    let reader = io::BufReader::new(input_file);
-    let input_program = reader.lines().next().ok_or("Error: empty input file")??;
-    let program = tokenize::tokenize(&input_program)?;
-    let ast = parse(&program)?;
-    Ok(ast)
+/// Reads the input file: its documentation comments, if any, followed by the program on a single
+/// line. A file larger than `max_size` bytes is refused, and one that cannot be opened is an error
+/// too.
+fn read_input(input_path: &str, max_size: u64) -> Result<String, io::Error> {
+    read_limited(File::open(input_path)?, max_size)
 }
 
-pub(crate) fn create_output_paths(dir: &str, file_name: &str) -> Result<PathBuf, io::Error> {
-    let output_dir = Path::new(dir);
+/// Reads `reader` to the end as text, failing as soon as more than `max_size` bytes are read, so
+/// that a huge input cannot exhaust the memory before it is even tokenized.
+pub(crate) fn read_limited(reader: impl Read, max_size: u64) -> Result<String, io::Error> {
+    let mut bytes = Vec::new();
+    reader.take(max_size.saturating_add(1)).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > max_size {
+        let message = format!("the input is larger than {} bytes (see --max-program-size)", max_size);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+    String::from_utf8(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+pub(crate) fn create_output_paths(dir: impl AsRef<Path>, file_name: &str) -> Result<PathBuf, io::Error> {
+    let output_dir = dir.as_ref();
     fs::create_dir_all(output_dir)?;
     Ok(output_dir.join(file_name))
 }
 
 fn write_assembly(asm_output_path: &Path, assembly: &str) -> Result<(), io::Error> {
-    fs::write(asm_output_path, assembly).expect("Failed to write assembly file");
-    Ok(())
+    write_atomically(asm_output_path, |file| file.write_all(assembly.as_bytes()))
 }
 
-fn link(obj_output_path: &Path, exe_output_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    let mut cmd = Command::new("clang");
-
-    if std::env::consts::OS == "windows" {
-        // Use the correct format for passing the /subsystem:console option to the MSVC linker
-        cmd.args(&[
-            "-g",
-            "-m64",
-            "-o",
-            exe_output_path.to_str().unwrap(),
-            "src/main.c",
-            obj_output_path.to_str().unwrap(),
-            "-Xlinker", "/subsystem:console",
-        ]);
-    } else {
-        cmd.args(&[
-            "-g",
-            "-m64",
-            "-o",
-            exe_output_path.to_str().unwrap(),
-            "src/main.c",
-            obj_output_path.to_str().unwrap(),
-        ]);
-    }
-
-    cmd.status().expect("Failed to link .obj to .exe");
+/// Writes a file through `write`, so that it is either written in full or not at all.
+///
+/// The content goes to a temporary file next to `path`, which is renamed to `path` once `write`
+/// succeeds and removed if it fails, instead of leaving part of the content behind to be
+/// assembled later.
+pub(crate) fn write_atomically(path: &Path, write: impl FnOnce(&mut dyn Write) -> Result<(), io::Error>) -> Result<(), io::Error> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+    let written = File::create(&temp_path).and_then(|mut file| {
+        write(&mut file)?;
+        file.flush()
+    });
+    match written.and_then(|()| fs::rename(&temp_path, path)) {
+        Ok(()) => Ok(()),
+        Err(error) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(error)
+        }
+    }
+}
 
+fn link(obj_output_path: &Path, exe_output_path: &Path, options: &LinkOptions) -> Result<(), RengoError> {
+    let status = Command::new("clang")
+        .args(link_args(obj_output_path, exe_output_path, options))
+        .status()
+        .map_err(|error| RengoError::Link(format!("could not run clang: {}", error)))?;
+    if !status.success() {
+        return Err(RengoError::Link(format!("clang exited with {}", status)));
+    }
     Ok(())
 }
 
-fn compile<T>(program: Expression<T>) -> Result<Vec<Instruction>, ()> {
-    compile_expression(&program, &mut env::Env::new())
+/// Returns the arguments `link` passes to `clang` for the given options. The radix selects how the
+/// runtime in `src/main.c` prints the result, and the exit code mode what it exits with.
+fn link_args(obj_output_path: &Path, exe_output_path: &Path, options: &LinkOptions) -> Vec<String> {
+    let mut args = vec![
+        "-g".to_string(),
+        "-m64".to_string(),
+        "-o".to_string(),
+        exe_output_path.to_str().unwrap().to_string(),
+        "src/main.c".to_string(),
+        obj_output_path.to_str().unwrap().to_string(),
+    ];
+    if options.radix == Radix::Hex {
+        args.push("-DRENGO_RADIX_HEX".to_string());
+    }
+    match options.exit_code_mode {
+        Some(ExitCodeMode::Truncate) => args.push("-DRENGO_EXIT_CODE_TRUNCATE".to_string()),
+        Some(ExitCodeMode::Error) => args.push("-DRENGO_EXIT_CODE_ERROR".to_string()),
+        None => {}
+    }
+    match (options.pie, options.os.as_str()) {
+        (_, "windows") => {}  // The MSVC linker does not take these flags
+        (Some(true), _) => args.extend(["-fPIE".to_string(), "-pie".to_string()]),
+        (Some(false), _) => args.push("-no-pie".to_string()),
+        (None, _) => {}
+    }
+    if options.os == "windows" {
+        // Use the correct format for passing the /subsystem:console option to the MSVC linker
+        args.extend(["-Xlinker".to_string(), "/subsystem:console".to_string()]);
+    }
+    args
+}
+
+fn compile<T: SourceSpan>(program: Expression<T>, options: &CompileOptions) -> Result<CompiledUnit, RengoError> {
+    Ok(compile_expression(&program, &mut Ctx::new(options.clone()))?)
 }
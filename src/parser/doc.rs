@@ -0,0 +1,87 @@
+use alloc::vec::Vec;
+
+/// The markers that start a comment line, longest first so `//!` is not read as `//` followed by
+/// `!`.
+const COMMENT_MARKERS: &[&str] = &["//!", "//", "#"];
+
+/// Splits the documentation off the start of a source file.
+///
+/// The documentation is made of the comment lines, starting with `#`, `//` or `//!`, that come
+/// before the program. Each one is returned without its marker and the space that follows it.
+/// Blank lines between them are skipped, as is a leading UTF-8 byte order mark.
+///
+/// ## Parameters:
+/// - `source`: The contents of a source file.
+///
+/// ## Returns:
+/// The lines of documentation, and the rest of the source, starting at the first line that is
+/// neither a comment nor blank.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parser::doc::split_doc;
+/// let (doc, rest) = split_doc("# doubles the input\n\n//! by shifting it\nlet x = 21; x << 1\n");
+/// assert_eq!(doc, vec!["doubles the input", "by shifting it"]);
+/// assert_eq!(rest, "let x = 21; x << 1\n");
+/// ```
+pub fn split_doc(source: &str) -> (Vec<&str>, &str) {
+    let mut doc = Vec::new();
+    let mut rest = source.strip_prefix('\u{feff}').unwrap_or(source);
+    while !rest.is_empty() {
+        let (line, next) = rest.split_once('\n').unwrap_or((rest, ""));
+        let line = line.trim();
+        match comment_text(line) {
+            Some(text) => doc.push(text),
+            None if line.is_empty() => {}
+            None => break,
+        }
+        rest = next;
+    }
+    (doc, rest)
+}
+
+/// Returns the text of a comment line, without its marker and the space that follows it, or
+/// `None` if the line is not a comment.
+fn comment_text(line: &str) -> Option<&str> {
+    let text = COMMENT_MARKERS.iter().find_map(|marker| line.strip_prefix(marker))?;
+    Some(text.strip_prefix(' ').unwrap_or(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use expectest::prelude::*;
+    use super::*;
+
+    mod split_doc {
+        use super::*;
+
+        #[test]
+        fn strips_the_comment_markers() {
+            let (doc, rest) = split_doc("# doubles the input\n// twice\n//!  indented\n#\n420");
+            expect!(doc).to(be_equal_to(vec!["doubles the input", "twice", " indented", ""]));
+            expect!(rest).to(be_equal_to("420"));
+        }
+
+        #[test]
+        fn stops_at_the_program() {
+            let (doc, rest) = split_doc("420\n# not documentation\n");
+            expect!(doc.is_empty()).to(be_true());
+            expect!(rest).to(be_equal_to("420\n# not documentation\n"));
+        }
+
+        #[test]
+        fn skips_blank_lines_and_carriage_returns() {
+            let (doc, rest) = split_doc("\u{feff}\r\n# first\r\n\r\n# second\r\n420\r\n");
+            expect!(doc).to(be_equal_to(vec!["first", "second"]));
+            expect!(rest).to(be_equal_to("420\r\n"));
+        }
+
+        #[test]
+        fn accepts_a_file_with_only_documentation() {
+            let (doc, rest) = split_doc("# nothing else");
+            expect!(doc).to(be_equal_to(vec!["nothing else"]));
+            expect!(rest).to(be_equal_to(""));
+        }
+    }
+}
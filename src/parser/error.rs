@@ -0,0 +1,147 @@
+use alloc::string::String;
+use core::fmt;
+
+/// An error found by `tokenize` while splitting the source code into tokens, like an invalid
+/// character.
+///
+/// # Example
+///
+/// ```rust
+/// # use rengo::parser::error::TokenizeError;
+/// let error = TokenizeError::InvalidCharacter('$');
+/// assert_eq!(error.to_string(), "Invalid character: $");
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub enum TokenizeError {
+    /// A character that starts no token of the language.
+    InvalidCharacter(char),
+
+    /// A number literal that does not fit in an `i64`, or that runs into an identifier. Holds the
+    /// message describing the problem.
+    InvalidNumber(String),
+
+    /// A string literal whose closing quote is missing. Holds its contents up to the end of the
+    /// input.
+    UnterminatedString(String),
+
+    /// Two numbers with nothing in between, like `5 5`, which strict lexing rejects.
+    MissingOperator(i64, i64),
+
+    /// A `+` with nothing after it, which is neither `++` nor the sign or operator before an
+    /// operand.
+    DanglingPlus,
+}
+
+/// An error found by `parse` while building an `Expression` from tokens, like a missing closing
+/// parenthesis.
+///
+/// # Example
+///
+/// ```rust
+/// # use rengo::parser::error::ParseError;
+/// let error = ParseError::Syntax("Expected closing parenthesis".to_string());
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseError {
+    /// The tokens do not follow the grammar. Holds the message describing the problem.
+    Syntax(String),
+
+    /// The program nests deeper than `parse` accepts, past `limit` levels.
+    TooDeep { limit: usize },
+
+    /// The program has more than `limit` bindings in scope at once, more than `parse` accepts.
+    TooManyBindings { limit: usize },
+}
+
+impl TokenizeError {
+    /// Returns the code of the error in the registry of `crate::error_codes`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TokenizeError::InvalidCharacter(_) => "E013",
+            TokenizeError::InvalidNumber(_) => "E019",
+            TokenizeError::UnterminatedString(_) => "E020",
+            TokenizeError::MissingOperator(..) => "E021",
+            TokenizeError::DanglingPlus => "E023",
+        }
+    }
+}
+
+impl ParseError {
+    /// Returns the code of the error in the registry of `crate::error_codes`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::Syntax(_) => "E014",
+            ParseError::TooDeep { .. } => "E022",
+            ParseError::TooManyBindings { .. } => "E024",
+        }
+    }
+}
+
+impl fmt::Display for TokenizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenizeError::InvalidCharacter(c) => write!(f, "Invalid character: {}", c),
+            TokenizeError::InvalidNumber(message) => f.write_str(message),
+            TokenizeError::UnterminatedString(contents) => write!(f, "Unterminated string literal: \"{}", contents),
+            TokenizeError::MissingOperator(previous, value) => {
+                write!(f, "Missing operator between numbers {} and {}", previous, value)
+            }
+            TokenizeError::DanglingPlus => f.write_str("Invalid token: Expected '++' or an operand after '+'"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Syntax(message) => f.write_str(message),
+            ParseError::TooDeep { limit } => write!(f, "Program nested too deeply (more than {} levels)", limit),
+            ParseError::TooManyBindings { limit } => write!(f, "Too many bindings in scope (more than {})", limit),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TokenizeError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use expectest::prelude::*;
+    use crate::error_codes::explain;
+    use super::*;
+
+    mod code {
+        use super::*;
+
+        #[test]
+        fn tells_the_kinds_of_errors_apart() {
+            let tokenize_errors = [
+                TokenizeError::InvalidCharacter('$'),
+                TokenizeError::InvalidNumber("Number literal out of range: 9223372036854775808".to_string()),
+                TokenizeError::UnterminatedString("hi".to_string()),
+                TokenizeError::MissingOperator(5, 5),
+                TokenizeError::DanglingPlus,
+            ];
+            let parse_errors = [
+                ParseError::Syntax("Expected closing parenthesis".to_string()),
+                ParseError::TooDeep { limit: 256 },
+                ParseError::TooManyBindings { limit: 1024 },
+            ];
+            let codes: Vec<&str> = tokenize_errors
+                .iter()
+                .map(TokenizeError::code)
+                .chain(parse_errors.iter().map(ParseError::code))
+                .collect();
+            expect!(codes.clone()).to(be_equal_to(vec!["E013", "E019", "E020", "E021", "E023", "E014", "E022", "E024"]));
+            for code in codes {
+                expect!(explain(code)).to(be_some());
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+/// The grammar returned by `grammar`. Keep it in sync with the functions in `parse` and
+/// `tokenize` when the syntax changes.
+const GRAMMAR: &str = r##"(* The grammar of the language accepted by the parser, in EBNF. *)
+
+(* A file can start with comment lines, introduced by "#", "//" or "//!", which document the
+   program. They are not part of the grammar below. *)
+program    = expression ;
+expression = { ";" } , statement , { ";" , { ";" } , statement } , { ";" } ;
+statement  = let | fn | assign | return | if | binary ;
+let        = "let" , [ "mut" ] , identifier , "=" , statement , ";" , expression
+           | "let" , "mut" , identifier , ";" , expression
+           | "let" , pattern , "=" , tuple , ";" , expression ;
+pattern    = "(" , identifier , "," , identifier , ")" ;
+fn         = "fn" , identifier , "(" , [ identifier , { "," , identifier } ] , ")" , "=" , statement ,
+             ";" , expression ;
+assign     = identifier , "=" , ( assign | binary ) ;
+return     = "return" , binary ;
+if         = "if" , binary , "then" , statement , "else" , statement ;
+
+(* Operators are listed from the loosest to the tightest binding. Operators of the same
+   precedence associate to the left. *)
+binary     = term , { operator , term } ;
+operator   = "|"
+           | "^"
+           | "&"
+           | "=="
+           | "<" | ">"
+           | "<<" | ">>"
+           | "+" | "-" ;
+
+term       = factor , { "++" | "--" | "[" , expression , "]" } ;
+factor     = "+" , factor
+           | number
+           | identifier
+           | "(" , expression , ")"
+           | tuple
+           | "[" , [ expression , { "," , expression } ] , "]"
+           | "assert" , "(" , expression , ")"
+           | "print" , "(" , string , ")"
+           | builtin , "(" , expression , "," , expression , ")"
+           | identifier , "(" , [ expression , { "," , expression } ] , ")" ;
+tuple      = "(" , expression , "," , expression , ")" ;
+builtin    = "min" | "max" ;
+
+(* A "-" right before the digits is the sign of the number unless it follows an operand, in
+   which case it is a subtraction. "_" can only be used as a binding name, to discard a value. *)
+number     = [ "-" ] , digit , { digit } ;
+identifier = ( letter | "_" ) , { letter | digit | "_" } ;
+string     = '"' , { character - '"' } , '"' ;
+"##;
+
+/// Returns the grammar of the language accepted by the parser, in EBNF.
+///
+/// The grammar describes the syntax only: a program that matches it can still be rejected while
+/// compiling it, e.g. for using an undefined variable.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parser::grammar::grammar;
+/// assert!(grammar().contains("\"let\""));
+/// ```
+pub fn grammar() -> &'static str {
+    GRAMMAR
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use expectest::prelude::*;
+    use crate::parser::parse::BUILTINS;
+    use crate::parser::tokenize::{KEYWORDS, OPERATORS};
+    use super::*;
+
+    /// Returns whether the grammar contains a spelling as a terminal symbol.
+    fn mentions(spelling: &str) -> bool {
+        grammar().contains(&format!("\"{}\"", spelling))
+    }
+
+    mod grammar {
+        use super::*;
+
+        #[test]
+        fn it_mentions_every_keyword() {
+            for (spelling, _) in KEYWORDS {
+                expect!(mentions(spelling)).to(be_true());
+            }
+        }
+
+        #[test]
+        fn it_mentions_every_operator() {
+            for (spelling, _) in OPERATORS {
+                expect!(mentions(spelling)).to(be_true());
+            }
+        }
+
+        #[test]
+        fn it_mentions_every_builtin() {
+            for (name, _) in BUILTINS {
+                expect!(mentions(name)).to(be_true());
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::ast::json::string_to_json;
+use crate::parser::span::Span;
+use crate::parser::token::Token;
+
+/// Serializes tokens and their spans into a JSON array, for editors to highlight the source with.
+///
+/// Each token is an object with the following keys, always in this order:
+///
+/// - `token`: the `Token::name` of the token (`"Let"`, `"Identifier"`, `"Number"`, ...);
+/// - `line` and `col`: where the token starts, counting lines and characters from 1;
+/// - `len`: the number of characters the token spans.
+///
+/// ## Parameters:
+/// - `source`: The source the tokens were read from, which their spans refer to.
+/// - `tokens`: The tokens, as returned by `tokenize_with_spans`.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parser::tokenize::tokenize_with_spans;
+/// # use rengo::parser::tokenize::TokenizeOptions;
+/// # use rengo::parser::json::tokens_to_json;
+/// let tokens = tokenize_with_spans("x++", &TokenizeOptions::default()).unwrap();
+/// assert_eq!(
+///     tokens_to_json("x++", &tokens),
+///     r#"[{"token":"Identifier","line":1,"col":1,"len":1},{"token":"Increment","line":1,"col":2,"len":2}]"#
+/// );
+/// ```
+pub fn tokens_to_json(source: &str, tokens: &[(Token, Span)]) -> String {
+    let objects: Vec<String> = tokens
+        .iter()
+        .map(|(token, span)| format!(
+            "{{\"token\":{},\"line\":{},\"col\":{},\"len\":{}}}",
+            string_to_json(token.name()),
+            span.line,
+            span.col,
+            span.text(source).chars().count()
+        ))
+        .collect();
+    format!("[{}]", objects.join(","))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use expectest::prelude::*;
+    use super::*;
+    use crate::parser::tokenize::{tokenize_with_spans, TokenizeOptions};
+
+    mod tokens_to_json {
+        use super::*;
+
+        fn json(source: &str) -> String {
+            tokens_to_json(source, &tokenize_with_spans(source, &TokenizeOptions::default()).unwrap())
+        }
+
+        #[test]
+        fn serializes_a_let_binding() {
+            expect!(json("let x = 5")).to(be_equal_to(String::from(concat!(
+                r#"[{"token":"Let","line":1,"col":1,"len":3},"#,
+                r#"{"token":"Identifier","line":1,"col":5,"len":1},"#,
+                r#"{"token":"Assign","line":1,"col":7,"len":1},"#,
+                r#"{"token":"Number","line":1,"col":9,"len":1}]"#,
+            ))));
+        }
+
+        #[test]
+        fn counts_characters_rather_than_bytes() {
+            expect!(json("\u{feff}ñu << -12")).to(be_equal_to(String::from(concat!(
+                r#"[{"token":"Identifier","line":1,"col":1,"len":2},"#,
+                r#"{"token":"ShiftLeft","line":1,"col":4,"len":2},"#,
+                r#"{"token":"Number","line":1,"col":7,"len":3}]"#,
+            ))));
+        }
+
+        #[test]
+        fn serializes_no_tokens_as_an_empty_array() {
+            expect!(json("  ")).to(be_equal_to(String::from("[]")));
+        }
+    }
+}
@@ -1,3 +1,9 @@
-pub(crate) mod token;
-pub(crate) mod tokenize;
-pub(crate) mod parse;
\ No newline at end of file
+pub mod doc;
+pub mod error;
+pub mod grammar;
+pub mod json;
+pub mod span;
+pub mod token;
+pub mod tokenize;
+pub mod parse;
+pub mod retokenize;
\ No newline at end of file
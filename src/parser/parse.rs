@@ -1,5 +1,16 @@
-use crate::ast::expression::Expression;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+use crate::ast::expression::{Expression, Function, DISCARD};
+use crate::ast::node_id::{number_nodes, NodeId};
+use crate::ast::operator::BinaryOperator;
+use crate::parser::error::ParseError;
+use crate::parser::span::Span;
 use crate::parser::token::Token;
+use crate::parser::tokenize::{keyword_spelling, OPERATORS};
 
 /// Parses a complete expression from the provided token stream and returns the corresponding
 /// abstract syntax tree (AST) representation.
@@ -11,20 +22,28 @@ use crate::parser::token::Token;
 ///
 /// ### Example 1: Parsing a simple expression
 /// ```rust
+/// # use rengo::parser::token::Token;
+/// # use rengo::parser::parse::parse;
+/// # use rengo::ast::expression::Expression;
 /// let tokens = vec![Token::Number(5)];
 /// let result = parse(&tokens);
-/// assert_eq!(result, Ok(Expression::Number(5)));
+/// assert_eq!(result, Ok(Expression::Number(5, ())));
 /// ```
 ///
 /// ### Example 2: Parsing an expression with increment
 /// ```rust
+/// # use rengo::parser::token::Token;
+/// # use rengo::parser::parse::parse;
+/// # use rengo::ast::expression::Expression;
 /// let tokens = vec![Token::Number(5), Token::Increment];
 /// let result = parse(&tokens);
-/// assert_eq!(result, Ok(Expression::Increment(Box::new(Expression::Number(5)))));
+/// assert_eq!(result, Ok(Expression::Increment(Box::new(Expression::Number(5, ())), ())));
 /// ```
 ///
 /// ### Example 3: Handling an invalid expression
 /// ```rust
+/// # use rengo::parser::token::Token;
+/// # use rengo::parser::parse::parse;
 /// let tokens = vec![Token::LParen, Token::Number(5)];
 /// let result = parse(&tokens);
 /// assert!(result.is_err());
@@ -34,46 +53,244 @@ use crate::parser::token::Token;
 /// - `tokens`: A slice of tokens representing the input expression to parse.
 ///
 /// ## Returns:
-/// A `Result` containing the parsed `Expression` if successful, or a `String` error message
-/// if parsing fails.
+/// A `Result` containing the parsed `Expression` if successful, or a `ParseError` if parsing
+/// fails.
 ///
 /// ## Errors:
 /// - Returns an error if the token stream does not form a valid expression.
 /// - Returns an error if the token stream contains unmatched parentheses or other syntax issues.
-pub fn parse<T>(tokens: &[Token]) -> Result<Expression<T>, String> {
-    let (expression, _) = parse_expression(tokens, 0)?;
+/// - Returns an error if the program could nest deeper than `MAX_NESTING`, or chain more than
+///   `MAX_BINDINGS` bindings, rather than running out of stack while parsing it.
+pub fn parse(tokens: &[Token]) -> Result<Expression<()>, ParseError> {
+    parse_positioned(tokens)
+}
+
+/// Parses a complete expression like `parse`, giving its nodes the metadata `M` records.
+fn parse_positioned<M: Position>(tokens: &[Token]) -> Result<Expression<M>, ParseError> {
+    check_nesting(tokens)?;
+    let (expression, _) = parse_expression(tokens, 0).map_err(|error| ParseError::Syntax(error.message))?;
     Ok(expression)
 }
 
+/// The metadata the parser gives the nodes it builds, which may record where in the token stream
+/// a node was read.
+trait Position {
+    /// Returns the metadata of a node that is not located.
+    fn none() -> Self;
+
+    /// Returns the metadata of a node located at the token at `index`: the operator of a step, the
+    /// `assert` keyword of an assertion, or the first name a binding introduces.
+    fn at(index: usize) -> Self;
+}
+
+impl Position for () {
+    fn none() -> Self {}
+
+    fn at(_: usize) -> Self {}
+}
+
+/// The index of the token a node was read at, if it is located.
+impl Position for Option<usize> {
+    fn none() -> Self {
+        None
+    }
+
+    fn at(index: usize) -> Self {
+        Some(index)
+    }
+}
+
+/// The deepest nesting `parse` accepts, as counted by `check_nesting`.
+pub const MAX_NESTING: usize = 256;
+
+/// The most `let` bindings and function definitions `parse` accepts in scope at once, as counted
+/// by `check_nesting`.
+pub const MAX_BINDINGS: usize = 1024;
+
+/// Checks, before parsing, that the program cannot nest deeper than `MAX_NESTING` nor chain more
+/// than `MAX_BINDINGS` bindings.
+///
+/// The parser and the passes after it recurse once per level of the tree, so a deep enough
+/// program would overflow the stack. The count of levels is an upper bound on its nesting: every
+/// parenthesis opens a level that lasts until it is closed, and every operator, keyword or `=` in
+/// a statement opens one that lasts until the statement ends. The statements after a `let` or a
+/// `fn` follow it rather than nest in it, but as its body they are still one recursion deeper, so
+/// the bindings in scope are counted apart, until the enclosing parenthesis is closed.
+///
+/// ## Returns:
+/// `Ok(())`, `ParseError::TooDeep` if the nesting is above `MAX_NESTING`, or
+/// `ParseError::TooManyBindings` if the bindings are more than `MAX_BINDINGS`.
+fn check_nesting(tokens: &[Token]) -> Result<(), ParseError> {
+    // The levels opened by the enclosing parentheses and by the current statement, and the
+    // bindings in scope
+    let (mut depth, mut statement, mut bindings) = (0, 0, 0);
+    let mut enclosing = Vec::new();
+    for token in tokens {
+        match token {
+            Token::LParen | Token::LBracket => {
+                enclosing.push((depth, statement, bindings));
+                (depth, statement) = (depth + statement + 1, 0);
+            }
+            Token::RParen | Token::RBracket => {
+                (depth, statement, bindings) = enclosing.pop().unwrap_or((depth, statement, bindings))
+            }
+            Token::LineEnd | Token::Comma => statement = 0,
+            Token::Let | Token::Fn => bindings += 1,
+            Token::Number(_) | Token::Identifier(_) | Token::Str(_) | Token::Eof => {}
+            _ => statement += 1,
+        }
+        if depth + statement > MAX_NESTING {
+            return Err(ParseError::TooDeep { limit: MAX_NESTING });
+        }
+        if bindings > MAX_BINDINGS {
+            return Err(ParseError::TooManyBindings { limit: MAX_BINDINGS });
+        }
+    }
+    Ok(())
+}
+
+/// Parses a complete expression like `parse`, numbering its nodes with `number_nodes` so passes
+/// can refer to each of them by `NodeId`.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parser::parse::parse_with_ids;
+/// # use rengo::parser::token::Token;
+/// # use rengo::ast::node_id::NodeId;
+/// let expr = parse_with_ids(&[Token::Number(5), Token::Increment]).unwrap();
+/// assert_eq!(expr.metadata(), &NodeId(0));
+/// ```
+pub fn parse_with_ids(tokens: &[Token]) -> Result<Expression<NodeId>, ParseError> {
+    parse(tokens).map(number_nodes)
+}
+
+/// Parses a complete expression like `parse` from tokens located by `tokenize_with_spans`,
+/// recording in each `Increment` and `Decrement` the span of its `++` or `--`, so that an error
+/// about the step can point at the operator rather than at its operand, and in each `Assert` the
+/// span of its `assert` keyword, so that a failed assertion can be reported at its line. Each
+/// binding (a `let`, a function, or a declaration) records the span of the first name it
+/// introduces, so that a warning about it can point there. The other nodes have no span yet.
+///
+/// The parser records the index of that token as it builds each node, which is then replaced by the
+/// span of the token.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parser::parse::parse_with_spans;
+/// # use rengo::parser::tokenize::tokenize_with_spans;
+/// # use rengo::parser::tokenize::TokenizeOptions;
+/// # use rengo::parser::span::Span;
+/// let expr = parse_with_spans(&tokenize_with_spans("5++", &TokenizeOptions::default()).unwrap()).unwrap();
+/// assert_eq!(expr.metadata(), &Some(Span { start: 1, end: 3, line: 1, col: 2 }));
+/// ```
+pub fn parse_with_spans(tokens: &[(Token, Span)]) -> Result<Expression<Option<Span>>, ParseError> {
+    let bare: Vec<Token> = tokens.iter().map(|(token, _)| token.clone()).collect();
+    let expression: Expression<Option<usize>> = parse_positioned(&bare)?;
+    Ok(expression.map_metadata(&mut |index| index.map(|index| tokens[index].1)))
+}
+
+/// Parses a complete expression like `parse_with_spans`, going on past its syntax errors to find
+/// the ones after them, so that they can all be reported at once.
+///
+/// When a syntax error is found, the top-level statement it is in, outside of any parentheses or
+/// brackets, is replaced by a `0` and the tokens are parsed again. Each error is returned with the
+/// span of the token it was found at, or of the last token if the input ended first, in the order
+/// they were found.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parser::tokenize::tokenize_with_spans;
+/// # use rengo::parser::tokenize::TokenizeOptions;
+/// # use rengo::parser::parse::parse_recovering;
+/// # use rengo::parser::parse::parse;
+/// # use rengo::parser::tokenize::tokenize;
+/// let tokens = tokenize_with_spans("let x = ; x + (1", &TokenizeOptions::default()).unwrap();
+/// let (program, errors) = parse_recovering(&tokens);
+/// assert_eq!(program.map(|program| program.map_metadata(&mut |_| ())), parse(&tokenize("0; 0").unwrap()).ok());
+/// assert_eq!(errors.len(), 2);
+/// ```
+///
+/// ## Returns:
+/// The expression with the statements that failed to parse replaced, and the errors found. The
+/// expression is `None` if the errors could not be recovered from: if the program nests deeper
+/// than `MAX_NESTING` or has more than `MAX_BINDINGS` bindings, which is then the only error
+/// returned, without a span, or if the statement of an error is already a `0`.
+pub fn parse_recovering(tokens: &[(Token, Span)]) -> (Option<Expression<Option<Span>>>, Vec<LocatedError>) {
+    let mut tokens = tokens.to_vec();
+    let mut errors = Vec::new();
+    loop {
+        let bare: Vec<Token> = tokens.iter().map(|(token, _)| token.clone()).collect();
+        if let Err(error) = check_nesting(&bare) {
+            return (None, vec![(error, None)]);
+        }
+        let error = match parse_expression::<Option<usize>>(&bare, 0) {
+            Ok((expression, _)) => {
+                return (Some(expression.map_metadata(&mut |index| index.map(|index| tokens[index].1))), errors)
+            }
+            Err(error) => error,
+        };
+        let span = tokens.get(error.index).or(tokens.last()).map(|(_, span)| *span);
+        errors.push((ParseError::Syntax(error.message), span));
+        // A `0` is inserted in place of an empty statement, like a missing `let` body
+        let statement = statement_around(&bare, error.index);
+        if bare[statement.clone()] == [Token::Number(0)] {
+            return (None, errors);  // Replacing it would change nothing
+        }
+        tokens.splice(statement, [(Token::Number(0), span.unwrap_or_default())]);
+    }
+}
+
+/// A parse error with the span of the token it was found at, as `parse_recovering` reports it.
+pub type LocatedError = (ParseError, Option<Span>);
+
+/// Returns the range of the tokens of the top-level statement that the token at `index` is in, or
+/// that starts at `index`, for `parse_recovering` to replace. Statements in parentheses or brackets
+/// are part of the statement around them.
+fn statement_around(tokens: &[Token], index: usize) -> Range<usize> {
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (position, token) in tokens.iter().enumerate() {
+        match token {
+            Token::LParen | Token::LBracket => depth += 1,
+            Token::RParen | Token::RBracket => depth = depth.saturating_sub(1),
+            Token::LineEnd if depth == 0 && position >= index => return start..position,
+            Token::LineEnd if depth == 0 => start = position + 1,
+            _ => {}
+        }
+    }
+    start..tokens.len()
+}
+
 /// Parses an expression from the token stream.
 ///
 /// The `parse_expression` function is a core component of a recursive descent parser, responsible
 /// for interpreting a sequence of tokens as an abstract syntax tree (AST). This function supports
-/// both simple arithmetic expressions and more complex constructs like `let` bindings.
+/// both simple arithmetic expressions and more complex constructs like `let` bindings and sequences
+/// of statements.
 ///
 /// ## Usage:
-/// This function identifies the type of expression starting at the given index in the token stream
-/// and delegates the parsing to the appropriate helper function. If the expression begins with a
-/// `let` keyword, it calls `parse_let` to handle the `let` binding. Otherwise, it processes the
-/// expression as a term (a factor potentially followed by increment (`++`) or decrement (`--`)
-/// operations).
+/// This function parses one or more statements separated by line-ends (`;`) using
+/// `parse_statement`. A single statement is returned as is; several statements are wrapped in an
+/// `Expression::Sequence`. A trailing `;` is not treated as the start of another statement, and
+/// an empty statement, like the one between the `;`s of `5;; 6` or before the `;` of `;5`, is
+/// skipped.
 ///
 /// ### Example 1: Parsing a simple numeric expression
-/// ```rust
+/// ```ignore
 /// let tokens = vec![Token::Number(5)];
 /// let result = parse_expression(&tokens, 0);
-/// assert_eq!(result, Ok((Expression::Number(5), 1)));
+/// assert_eq!(result, Ok((Expression::Number(5, ()), 1)));
 /// ```
 ///
 /// ### Example 2: Parsing an expression with increment
-/// ```rust
+/// ```ignore
 /// let tokens = vec![Token::Number(5), Token::Increment];
 /// let result = parse_expression(&tokens, 0);
-/// assert_eq!(result, Ok((Expression::Increment(Box::new(Expression::Number(5))), 2)));
+/// assert_eq!(result, Ok((Expression::Increment(Box::new(Expression::Number(5, ())), ()), 2)));
 /// ```
 ///
 /// ### Example 3: Parsing a `let` binding expression
-/// ```rust
+/// ```ignore
 /// let tokens = vec![
 ///     Token::Let,
 ///     Token::Identifier("x".to_string()),
@@ -88,8 +305,9 @@ pub fn parse<T>(tokens: &[Token]) -> Result<Expression<T>, String> {
 ///     Ok((
 ///         Expression::Let(
 ///             "x".to_string(),
-///             Box::new(Expression::Number(5)),
-///             Box::new(Expression::Identifier("x".to_string()))
+///             Box::new(Expression::Number(5, ())),
+///             Box::new(Expression::Identifier("x".to_string(), ())),
+///             ()
 ///         ),
 ///         6
 ///     ))
@@ -103,20 +321,120 @@ pub fn parse<T>(tokens: &[Token]) -> Result<Expression<T>, String> {
 /// ## Returns:
 /// A `Result` containing:
 /// - A tuple with the parsed `Expression` and the index of the next token to parse, if successful.
-/// - A `String` error message if parsing fails.
+/// - The `SyntaxError` found if parsing fails.
 ///
 /// ## Errors:
 /// - Returns an error if the token stream does not form a valid expression.
 /// - Returns an error if the token stream contains syntax issues like missing `;`, `=`, or
-///     parentheses.
-fn parse_expression<T>(tokens: &[Token], index: usize) -> Result<(Expression<T>, usize), String> {
-    if let Some(Token::Let) = tokens.get(index) {
-        parse_let(tokens, index + 1)
+///   parentheses.
+fn parse_expression<M: Position>(tokens: &[Token], index: usize) -> Result<(Expression<M>, usize), SyntaxError> {
+    let (first, mut index) = parse_statement(tokens, skip_empty_statements(tokens, index))?;
+    let mut statements = vec![first];
+    while let Some(Token::LineEnd) = tokens.get(index) {
+        let start = skip_empty_statements(tokens, index + 1);
+        if is_end(tokens.get(start)) {
+            break; // A trailing `;` does not start a new statement
+        }
+        let (statement, next_index) = parse_statement(tokens, start)?;
+        statements.push(statement);
+        index = next_index;
+    }
+    if statements.len() == 1 {
+        Ok((statements.remove(0), index))
     } else {
-        parse_term(tokens, index)
+        Ok((Expression::Sequence(statements, M::none()), index))
+    }
+}
+
+/// Returns the index of the first token from `index` on that is not a `;`, skipping the empty
+/// statements there.
+fn skip_empty_statements(tokens: &[Token], index: usize) -> usize {
+    let empty = tokens[index.min(tokens.len())..].iter().take_while(|token| **token == Token::LineEnd).count();
+    index + empty
+}
+
+/// Parses a single statement of a sequence from the token stream.
+///
+/// A statement is either a `let` binding or a function definition, whose body extends to the end
+/// of the enclosing sequence, an assignment to a variable (`x = <expression>`, where the
+/// expression may be another assignment), a `return` of a value, a conditional
+/// (`if <condition> then <statement> else <statement>`), or a chain of terms joined by binary
+/// operators. An `=` anywhere else, like after `5` or `x + 1`, is reported as such rather than as
+/// an unexpected token.
+///
+/// ## Parameters:
+/// - `tokens`: A slice of tokens representing the input to parse.
+/// - `index`: The index in the token stream where the statement starts.
+///
+/// ## Returns:
+/// A `Result` containing a tuple with the parsed `Expression` and the index of the next token to
+/// parse, or the `SyntaxError` found if parsing fails.
+fn parse_statement<M: Position>(tokens: &[Token], index: usize) -> Result<(Expression<M>, usize), SyntaxError> {
+    let (statement, next_index) = match (tokens.get(index), tokens.get(index + 1)) {
+        (Some(Token::Let), _) => return parse_let(tokens, index + 1),
+        (Some(Token::Fn), _) => return parse_fn(tokens, index + 1),
+        (Some(Token::Return), _) => {
+            let (value, next_index) = parse_binary(tokens, index + 1, 0)?;
+            (Expression::Return(Box::new(value), M::none()), next_index)
+        }
+        (Some(Token::If), _) => parse_if(tokens, index + 1)?,
+        (Some(Token::Identifier(_)), Some(Token::Assign)) => parse_assignment(tokens, index)?,
+        _ => parse_binary(tokens, index, 0)?,
+    };
+    if tokens.get(next_index) == Some(&Token::Assign) {
+        return Err(SyntaxError::new(next_index, STRAY_ASSIGN));
+    }
+    Ok((statement, next_index))
+}
+
+/// Parses an assignment, or a chain of terms joined by binary operators if the tokens at `index`
+/// are not a variable followed by `=`.
+///
+/// The value of an assignment is parsed the same way, so assignments chain to the right:
+/// `x = y = 5` parses as `x = (y = 5)`, assigning 5 to both variables.
+///
+/// ## Parameters:
+/// - `tokens`: A slice of tokens representing the input to parse.
+/// - `index`: The index of the first token of the assignment.
+///
+/// ## Returns:
+/// A `Result` containing a tuple with the parsed `Expression` and the index of the next token to
+/// parse, or the `SyntaxError` found if parsing fails.
+fn parse_assignment<M: Position>(tokens: &[Token], index: usize) -> Result<(Expression<M>, usize), SyntaxError> {
+    match (tokens.get(index), tokens.get(index + 1)) {
+        (Some(Token::Identifier(name)), Some(Token::Assign)) if name != DISCARD => {
+            let (value, next_index) = parse_assignment(tokens, index + 2)?;
+            Ok((Expression::Assign(name.clone(), Box::new(value), M::none()), next_index))
+        }
+        _ => parse_binary(tokens, index, 0),
     }
 }
 
+/// Parses the rest of a conditional, right after its `if` keyword.
+///
+/// Both branches are single statements, so `if c then return 3 else 4` returns from the `then`
+/// branch only, and a `;` after the `else` branch ends the conditional.
+///
+/// ## Parameters:
+/// - `tokens`: A slice of tokens representing the input to parse.
+/// - `index`: The index of the first token of the condition.
+///
+/// ## Returns:
+/// A `Result` containing a tuple with the `Expression::If` and the index of the next token to
+/// parse, or the `SyntaxError` found if parsing fails.
+fn parse_if<M: Position>(tokens: &[Token], index: usize) -> Result<(Expression<M>, usize), SyntaxError> {
+    let (condition, then_start) = parse_binary(tokens, index, 0)?;
+    expect_token(tokens, then_start, &Token::Then, "Expected 'then' after the condition")?;
+    let (then, else_start) = parse_statement(tokens, then_start + 1)?;
+    expect_token(tokens, else_start, &Token::Else, "Expected 'else' after the 'then' branch")?;
+    let (otherwise, next_index) = parse_statement(tokens, else_start + 1)?;
+    Ok((Expression::If(Box::new(condition), Box::new(then), Box::new(otherwise), M::none()), next_index))
+}
+
+/// The error reported for an `=` that is neither part of a `let` binding nor preceded by the
+/// variable it assigns to.
+const STRAY_ASSIGN: &str = "unexpected '='; assignment requires a variable on the left";
+
 /// Parses a `let` expression from the token stream.
 ///
 /// The `parse_let` function is responsible for parsing `let` bindings in the form of:
@@ -135,7 +453,7 @@ fn parse_expression<T>(tokens: &[Token], index: usize) -> Result<(Expression<T>,
 /// assignment operator (`=`), an expression, a line-end (`;`), and then the body expression.
 ///
 /// ### Example:
-/// ```rust
+/// ```ignore
 /// let tokens = vec![
 ///     Token::Let,
 ///     Token::Identifier("x".to_string()),
@@ -150,85 +468,296 @@ fn parse_expression<T>(tokens: &[Token], index: usize) -> Result<(Expression<T>,
 ///     Ok((
 ///         Expression::Let(
 ///             "x".to_string(),
-///             Box::new(Expression::Number(5)),
-///             Box::new(Expression::Identifier("x".to_string()))
+///             Box::new(Expression::Number(5, ())),
+///             Box::new(Expression::Identifier("x".to_string(), ())),
+///             ()
 ///         ),
 ///         6
 ///     ))
 /// );
 /// ```
 ///
+/// A `(` in place of the identifier starts a destructuring pattern, parsed by `parse_let_tuple`.
+/// A `mut` before the identifier makes the binding mutable, so it can be assigned to.
+///
 /// ## Parameters:
 /// - `tokens`: A slice of tokens representing the input to parse.
 /// - `index`: The index in the token stream where the `let` expression starts.
 ///
 /// ## Returns:
 /// A `Result` containing a tuple with the parsed `Expression::Let` and the index of the next token
-/// to be parsed, or a `String` error message if parsing fails.
+/// to be parsed, or the `SyntaxError` found if parsing fails.
 ///
 /// ## Errors:
-/// - Returns an error if the expected identifier is missing after the `let` keyword.
+/// - Returns an error if the expected identifier is missing after the `let` keyword, or if a
+///   reserved keyword is used in its place.
 /// - Returns an error if the assignment operator (`=`) is missing after the identifier.
 /// - Returns an error if the line-end (`;`) is missing after the assigned expression.
+/// - Returns an end-of-input error, with the position where a token was expected, if the input
+///   ends before the binding is complete.
 /// - Returns an error if there are issues parsing the expression or the body of the `let` binding.
-fn parse_let<T>(tokens: &[Token], index: usize) -> Result<(Expression<T>, usize), String> {
-    if let Some(Token::Identifier(ref name)) = tokens.get(index) {
-        let next_index = index + 1;
-        if let Some(Token::Assign) = tokens.get(next_index) {
-            let (value_expr, body_start) =
-                parse_expression(tokens, next_index + 1)?;
-            if let Some(Token::LineEnd) = tokens.get(body_start) {
-                let (body_expr, final_index) =
-                    parse_expression(tokens, body_start + 1)?;
-                Ok((
-                    Expression::Let(name.clone(), Box::new(value_expr), Box::new(body_expr), ()),
-                    final_index
-                ))
-            } else {
-                Err("Expected ';' at the end of let binding".to_string())
+fn parse_let<M: Position>(tokens: &[Token], index: usize) -> Result<(Expression<M>, usize), SyntaxError> {
+    if tokens.get(index) == Some(&Token::LParen) {
+        return parse_let_tuple(tokens, index + 1);
+    }
+    // In `let mut = 1` the `mut` is the name, so it is reported as a reserved keyword
+    let mutable = tokens.get(index) == Some(&Token::Mut) && tokens.get(index + 1) != Some(&Token::Assign);
+    let index = if mutable { index + 1 } else { index };
+    let name = match tokens.get(index) {
+        Some(Token::Identifier(name)) => name.clone(),
+        Some(Token::Eof) | None => return Err(end_of_input(index)),
+        Some(token) => {
+            return match keyword_spelling(token) {
+                Some(keyword) => Err(SyntaxError::new(index, format!(
+                    "'{}' is a reserved keyword and cannot be used as a variable name",
+                    keyword
+                ))),
+                None => Err(SyntaxError::new(index, "Expected identifier after 'let'")),
             }
-        } else {
-            Err("Expected '=' in let binding".to_string())
         }
-    } else {
-        Err("Expected identifier after 'let'".to_string())
+    };
+    if mutable && tokens.get(index + 1) == Some(&Token::LineEnd) {
+        let (body_expr, final_index) = parse_expression(tokens, index + 2)?;
+        return Ok((Expression::Declare(name, Box::new(body_expr), M::at(index)), final_index));
+    }
+    expect_token(tokens, index + 1, &Token::Assign, "Expected '=' in let binding")?;
+    let (value_expr, body_start) = parse_statement(tokens, index + 2)?;
+    expect_token(tokens, body_start, &Token::LineEnd, "Expected ';' at the end of let binding")?;
+    let (body_expr, final_index) = parse_expression(tokens, body_start + 1)?;
+    let expression = match mutable {
+        true => Expression::LetMut(name, Box::new(value_expr), Box::new(body_expr), M::at(index)),
+        false => Expression::Let(name, Box::new(value_expr), Box::new(body_expr), M::at(index)),
+    };
+    Ok((expression, final_index))
+}
+
+/// Parses a function definition, starting right after its `fn` keyword.
+///
+/// The name is followed by a parenthesized, comma-separated list of parameter names, which may be
+/// empty, an `=`, the body of the function as a single statement, and a line-end. What follows is
+/// the expression the function is in scope for, as in `fn add(a, b) = a + b; add(1, 2)`.
+///
+/// ## Returns:
+/// A tuple with the parsed `Expression::Func` and the index of the next token to be parsed, or the
+/// `SyntaxError` found if parsing fails.
+fn parse_fn<M: Position>(tokens: &[Token], index: usize) -> Result<(Expression<M>, usize), SyntaxError> {
+    let name = match tokens.get(index) {
+        Some(Token::Identifier(name)) if name != DISCARD && builtin(name).is_none() => name.clone(),
+        token if is_end(token) => return Err(end_of_input(index)),
+        _ => return Err(SyntaxError::new(index, "Expected function name after 'fn'")),
+    };
+    expect_token(tokens, index + 1, &Token::LParen, "Expected '(' after the function name")?;
+    let name_index = index;
+    let mut parameters = Vec::new();
+    let mut index = index + 2;
+    if tokens.get(index) != Some(&Token::RParen) {
+        loop {
+            match tokens.get(index) {
+                Some(Token::Identifier(parameter)) => parameters.push(parameter.clone()),
+                token if is_end(token) => return Err(end_of_input(index)),
+                _ => return Err(SyntaxError::new(index, "Expected parameter name")),
+            }
+            index += 1;
+            if tokens.get(index) != Some(&Token::Comma) {
+                break;
+            }
+            index += 1;
+        }
+    }
+    expect_token(tokens, index, &Token::RParen, "Expected closing parenthesis after the parameters")?;
+    expect_token(tokens, index + 1, &Token::Assign, "Expected '=' in function definition")?;
+    let (body, rest_start) = parse_statement(tokens, index + 2)?;
+    expect_token(tokens, rest_start, &Token::LineEnd, "Expected ';' at the end of function definition")?;
+    let (rest, final_index) = parse_expression(tokens, rest_start + 1)?;
+    let function = Function { name, parameters, body };
+    Ok((Expression::Func(Box::new(function), Box::new(rest), M::at(name_index)), final_index))
+}
+
+/// The number of elements of a tuple, which is the only arity supported so far.
+const TUPLE_ARITY: usize = 2;
+
+/// Parses a destructuring `let`, starting right after the `(` that opens its pattern.
+///
+/// The pattern is a parenthesized, comma-separated list of `TUPLE_ARITY` names, and the value must
+/// be a tuple literal, as in `let (x, y) = (1, 2); x + y`.
+///
+/// ## Returns:
+/// A tuple with the parsed `Expression::LetTuple` and the index of the next token to be parsed, or
+/// the `SyntaxError` found if parsing fails.
+fn parse_let_tuple<M: Position>(tokens: &[Token], index: usize) -> Result<(Expression<M>, usize), SyntaxError> {
+    let first_name = index;
+    let mut names = Vec::new();
+    let mut index = index;
+    loop {
+        match tokens.get(index) {
+            Some(Token::Identifier(name)) => names.push(name.clone()),
+            token if is_end(token) => return Err(end_of_input(index)),
+            _ => return Err(SyntaxError::new(index, "Expected identifier in tuple pattern")),
+        }
+        match tokens.get(index + 1) {
+            Some(Token::Comma) => index += 2,
+            _ => break,
+        }
+    }
+    expect_token(tokens, index + 1, &Token::RParen, "Expected closing parenthesis in tuple pattern")?;
+    if names.len() != TUPLE_ARITY {
+        let message = format!("Tuple patterns must bind exactly {} names, found {}", TUPLE_ARITY, names.len());
+        return Err(SyntaxError::new(index + 1, message));
+    }
+    expect_token(tokens, index + 2, &Token::Assign, "Expected '=' in let binding")?;
+    let (value_expr, body_start) = parse_statement(tokens, index + 3)?;
+    if !matches!(value_expr, Expression::Tuple(..)) {
+        return Err(SyntaxError::new(index + 3, "Expected a tuple literal to destructure"));
+    }
+    expect_token(tokens, body_start, &Token::LineEnd, "Expected ';' at the end of let binding")?;
+    let (body_expr, final_index) = parse_expression(tokens, body_start + 1)?;
+    Ok((
+        Expression::LetTuple(names, Box::new(value_expr), Box::new(body_expr), M::at(first_name)),
+        final_index
+    ))
+}
+
+/// Parses a chain of terms joined by binary operators from the token stream.
+///
+/// The `parse_binary` function implements precedence climbing: it parses a term, then keeps
+/// consuming operators whose precedence is at least `min_precedence`, parsing each right-hand side
+/// with a higher minimum precedence so that tighter-binding operators are grouped first. Operators
+/// of equal precedence associate to the left.
+///
+/// ## Usage:
+/// This function is called by `parse_expression` with a `min_precedence` of `0`. Operator
+/// precedences are given by `binary_operator`.
+///
+/// ### Example 1: Parsing a single operator
+/// ```ignore
+/// let tokens = vec![Token::Number(6), Token::BitAnd, Token::Number(3)];
+/// let result = parse_binary(&tokens, 0, 0);
+/// assert_eq!(
+///     result,
+///     Ok((
+///         Expression::Binary(
+///             BinaryOperator::BitAnd,
+///             Box::new(Expression::Number(6, ())),
+///             Box::new(Expression::Number(3, ())),
+///             ()
+///         ),
+///         3
+///     ))
+/// );
+/// ```
+///
+/// ### Example 2: Precedence
+/// ```ignore
+/// // Parsed as `1 | (2 & 3)`, since `&` binds tighter than `|`.
+/// let tokens = vec![
+///     Token::Number(1),
+///     Token::BitOr,
+///     Token::Number(2),
+///     Token::BitAnd,
+///     Token::Number(3)
+/// ];
+/// let result = parse_binary(&tokens, 0, 0);
+/// ```
+///
+/// ## Parameters:
+/// - `tokens`: A slice of tokens representing the input to parse.
+/// - `index`: The index in the token stream to start parsing from.
+/// - `min_precedence`: The lowest operator precedence this call is allowed to consume.
+///
+/// ## Returns:
+/// A `Result` containing a tuple with the parsed `Expression` and the index of the next token to
+/// parse, or the `SyntaxError` found if parsing fails.
+fn parse_binary<M: Position>(
+    tokens: &[Token],
+    index: usize,
+    min_precedence: u8,
+) -> Result<(Expression<M>, usize), SyntaxError> {
+    let (mut expression, mut index) = parse_term(tokens, index)?;
+
+    while let Some((operator, precedence)) = tokens.get(index).and_then(binary_operator) {
+        if precedence < min_precedence {
+            break;
+        }
+        let (rhs, next_index) = parse_binary(tokens, index + 1, precedence + 1)?;
+        expression = Expression::Binary(operator, Box::new(expression), Box::new(rhs), M::none());
+        index = next_index;
+    }
+
+    Ok((expression, index))
+}
+
+/// Returns the binary operator represented by a token, along with its precedence.
+///
+/// Higher precedences bind tighter. The levels follow C: `+` and `-` bind tighter than shifts,
+/// which bind tighter than comparisons, which bind tighter than `==`, which binds tighter than `&`,
+/// which binds tighter than `^`, which binds tighter than `|`. Gaps are left between levels so
+/// logical operators (below `|`) and multiplicative operators (above `+` and `-`) can be slotted
+/// in.
+///
+/// ## Parameters:
+/// - `token`: The token to classify.
+///
+/// ## Returns:
+/// `Some((operator, precedence))` if the token is a binary operator, or `None` otherwise.
+fn binary_operator(token: &Token) -> Option<(BinaryOperator, u8)> {
+    match token {
+        Token::BitOr => Some((BinaryOperator::BitOr, 3)),
+        Token::BitXor => Some((BinaryOperator::BitXor, 4)),
+        Token::BitAnd => Some((BinaryOperator::BitAnd, 5)),
+        Token::Eq => Some((BinaryOperator::Equal, 6)),
+        Token::Less => Some((BinaryOperator::Less, 7)),
+        Token::Greater => Some((BinaryOperator::Greater, 7)),
+        Token::ShiftLeft => Some((BinaryOperator::ShiftLeft, 8)),
+        Token::ShiftRight => Some((BinaryOperator::ShiftRight, 8)),
+        Token::Plus => Some((BinaryOperator::Add, 9)),
+        Token::Minus => Some((BinaryOperator::Subtract, 9)),
+        _ => None,
     }
 }
 
+/// Returns the spelling and the precedence of a binary operator written between its operands, or
+/// `None` for the operators only computed by built-in functions, like `min`.
+pub(crate) fn infix_operator(operator: BinaryOperator) -> Option<(&'static str, u8)> {
+    OPERATORS.iter().find_map(|(spelling, token)| match binary_operator(token) {
+        Some((infix, precedence)) if infix == operator => Some((*spelling, precedence)),
+        _ => None,
+    })
+}
+
 /// Parses a term from the token stream, which can consist of a factor followed by increment (`++`)
 /// or decrement (`--`) operations.
 ///
 /// ## Usage:
 /// This function is typically used as part of a recursive descent parser to parse terms within a
 /// mathematical expression. A term is a factor that can be followed by zero or more increment or
-/// decrement operations.
+/// decrement operations and indices, like `[1, 2][0]++`.
 ///
 /// ### Example 1: Parsing a simple factor
-/// ```rust
+/// ```ignore
 /// let tokens = vec![Token::Number(5)];
 /// let result = parse_term(&tokens, 0);
-/// assert_eq!(result, Ok((Expression::Number(5), 1)));
+/// assert_eq!(result, Ok((Expression::Number(5, ()), 1)));
 /// ```
 ///
 /// ### Example 2: Parsing a factor with increment
-/// ```rust
+/// ```ignore
 /// let tokens = vec![Token::Number(5), Token::Increment];
 /// let result = parse_term(&tokens, 0);
-/// assert_eq!(result, Ok((Expression::Increment(Box::new(Expression::Number(5))), 2)));
+/// assert_eq!(result, Ok((Expression::Increment(Box::new(Expression::Number(5, ())), ()), 2)));
 /// ```
 ///
 /// ### Example 3: Parsing a factor with decrement
-/// ```rust
+/// ```ignore
 /// let tokens = vec![Token::Number(5), Token::Decrement];
 /// let result = parse_term(&tokens, 0);
-/// assert_eq!(result, Ok((Expression::Decrement(Box::new(Expression::Number(5))), 2)));
+/// assert_eq!(result, Ok((Expression::Decrement(Box::new(Expression::Number(5, ())), ()), 2)));
 /// ```
 ///
 /// ### Example 4: Handling multiple increments and decrements
-/// ```rust
+/// ```ignore
 /// let tokens = vec![Token::Number(5), Token::Increment, Token::Increment, Token::Decrement];
 /// let result = parse_term(&tokens, 0);
-/// assert_eq!(result, Ok((Expression::Decrement(Box::new(Expression::Increment(Box::new(Expression::Increment(Box::new(Expression::Number(5))))))), 4)));
+/// assert_eq!(result, Ok((Expression::Decrement(Box::new(Expression::Increment(Box::new(Expression::Increment(Box::new(Expression::Number(5, ())), ())), ())), ()), 4)));
 /// ```
 ///
 /// ## Parameters:
@@ -237,20 +766,21 @@ fn parse_let<T>(tokens: &[Token], index: usize) -> Result<(Expression<T>, usize)
 ///
 /// ## Returns:
 /// A `Result` containing a tuple with the parsed `Expression` and the index of the next token to parse,
-/// or a `String` error message if parsing fails.
-fn parse_term<T>(tokens: &[Token], index: usize) -> Result<(Expression<T>, usize), String> {
+/// or the `SyntaxError` found if parsing fails.
+fn parse_term<M: Position>(tokens: &[Token], index: usize) -> Result<(Expression<M>, usize), SyntaxError> {
     let (mut expression, mut index) = parse_factor(tokens, index)?;
 
     while index < tokens.len() {
         match tokens[index] {
             Token::Increment => {
+                expression = Expression::Increment(Box::new(expression), M::at(index));
                 index += 1; // consume '++'
-                expression = Expression::Increment(Box::new(expression), ());
             }
             Token::Decrement => {
+                expression = Expression::Decrement(Box::new(expression), M::at(index));
                 index += 1; // consume '--'
-                expression = Expression::Decrement(Box::new(expression), ());
             }
+            Token::LBracket => index = parse_index(tokens, index + 1, &mut expression)?,
             _ => break,
         }
     }
@@ -263,26 +793,30 @@ fn parse_term<T>(tokens: &[Token], index: usize) -> Result<(Expression<T>, usize
 ///
 /// ## Usage:
 /// This function is typically used as part of a recursive descent parser to parse individual
-/// factors within a mathematical expression. A factor is either a standalone number or an
-/// expression enclosed in parentheses.
+/// factors within a mathematical expression. A factor is either a standalone number, an expression
+/// enclosed in parentheses, a tuple literal like `(a, b)`, an array literal like `[1, 2, 3]`, an
+/// assertion, `assert(condition)`, a `print("...")` of a string literal, or a call to a built-in
+/// function like `min(a, b)`. A built-in name that is not followed by `(` is an ordinary
+/// identifier. A factor can also be preceded by a unary `+`, which leaves it unchanged, so `+5` is
+/// just `5`.
 ///
 /// ### Example 1: Parsing a number
 /// ```kotlin
 /// let tokens = vec![Token::Number(5)];
 /// let result = parse_factor(&tokens, 0);
-/// assert_eq!(result, Ok((Expression::Number(5), 1)));
+/// assert_eq!(result, Ok((Expression::Number(5, ()), 1)));
 /// ```
 ///
 /// ### Example 2: Parsing a parenthesized expression
 /// ```kotlin
 /// let tokens = vec![Token::LParen, Token::Number(5), Token::RParen];
 /// let result = parse_factor(&tokens, 0);
-/// assert_eq!(result, Ok((Expression::Number(5), 3)));
+/// assert_eq!(result, Ok((Expression::Number(5, ()), 3)));
 /// ```
 ///
 /// ### Example 3: Handling unexpected tokens
 /// ```kotlin
-/// let tokens = vec![Token::Plus];
+/// let tokens = vec![Token::RParen];
 /// let result = parse_factor(&tokens, 0);
 /// assert!(result.is_err());
 /// ```
@@ -293,26 +827,205 @@ fn parse_term<T>(tokens: &[Token], index: usize) -> Result<(Expression<T>, usize
 ///
 /// ## Returns:
 /// A tuple containing the parsed expression and the index of the next token to parse.
-fn parse_factor<T>(tokens: &[Token], index: usize) -> Result<(Expression<T>, usize), String> {
+fn parse_factor<M: Position>(tokens: &[Token], index: usize) -> Result<(Expression<M>, usize), SyntaxError> {
     match tokens.get(index) {
-        Some(Token::Number(value)) => Ok((Expression::Number(*value, ()), index + 1)),
-        Some(Token::Identifier(ref name)) =>
-            Ok((Expression::Identifier(name.clone(), ()), index + 1)),
+        Some(Token::Number(value)) => Ok((Expression::Number(*value, M::none()), index + 1)),
+        Some(Token::Plus) => parse_factor(tokens, index + 1),  // A unary plus does nothing
+        Some(Token::Identifier(name)) if name == DISCARD => {
+            Err(SyntaxError::new(index, format!("'{}' can only be used as a binding name, not as a value", DISCARD)))
+        }
+        Some(Token::Identifier(ref name)) => match (builtin(name), tokens.get(index + 1)) {
+            (Some(operator), Some(Token::LParen)) => parse_builtin_call(tokens, index + 2, operator),
+            (None, Some(Token::LParen)) => parse_call(tokens, index + 2, name),
+            _ => Ok((Expression::Identifier(name.clone(), M::none()), index + 1)),
+        },
         Some(Token::LParen) => {
-            let (expression, next_index) =
-                parse_expression(tokens, index + 1)?;
-            match tokens.get(next_index) {
-                Some(Token::RParen) => Ok((expression, next_index + 1)),
-                _ => Err("Expected closing parenthesis".to_string()),
+            let (expression, next_index) = parse_expression(tokens, index + 1)?;
+            if tokens.get(next_index) == Some(&Token::Comma) {
+                // A comma makes a tuple rather than a parenthesized expression
+                return parse_tuple(tokens, next_index, expression);
+            }
+            expect_token(tokens, next_index, &Token::RParen, "Expected closing parenthesis")?;
+            Ok((expression, next_index + 1))
+        }
+        Some(Token::LBracket) => parse_array(tokens, index + 1),
+        Some(Token::Assert) => {
+            expect_token(tokens, index + 1, &Token::LParen, "Expected '(' after 'assert'")?;
+            let (condition, next_index) = parse_expression(tokens, index + 2)?;
+            expect_token(tokens, next_index, &Token::RParen, "Expected closing parenthesis")?;
+            Ok((Expression::Assert(Box::new(condition), M::at(index)), next_index + 1))
+        }
+        Some(Token::Print) => {
+            expect_token(tokens, index + 1, &Token::LParen, "Expected '(' after 'print'")?;
+            let Some(Token::Str(text)) = tokens.get(index + 2) else {
+                return Err(SyntaxError::new(index + 2, "Expected a string literal in 'print'"));
+            };
+            expect_token(tokens, index + 3, &Token::RParen, "Expected closing parenthesis")?;
+            Ok((Expression::Print(text.clone(), M::none()), index + 4))
+        }
+        Some(Token::Assign) => Err(SyntaxError::new(index, STRAY_ASSIGN)),
+        Some(Token::Eof) | None => Err(end_of_input(index)),
+        Some(token) => Err(SyntaxError::new(index, format!("Unexpected token: {:?}", token))),
+    }
+}
+
+/// Every built-in function, with the operator it computes.
+pub(crate) const BUILTINS: &[(&str, BinaryOperator)] = &[
+    ("min", BinaryOperator::Min),
+    ("max", BinaryOperator::Max),
+];
+
+/// Returns the operator computed by the built-in function with the given name, if there is one.
+fn builtin(name: &str) -> Option<BinaryOperator> {
+    BUILTINS
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, operator)| *operator)
+}
+
+/// Parses the two comma-separated arguments of a built-in call, starting right after its `(`, and
+/// the closing parenthesis.
+///
+/// ## Returns:
+/// A `Binary` expression applying `operator` to the arguments, and the index of the next token.
+fn parse_builtin_call<M: Position>(
+    tokens: &[Token],
+    index: usize,
+    operator: BinaryOperator,
+) -> Result<(Expression<M>, usize), SyntaxError> {
+    let (lhs, next_index) = parse_expression(tokens, index)?;
+    expect_token(tokens, next_index, &Token::Comma, "Expected ',' between arguments")?;
+    let (rhs, next_index) = parse_expression(tokens, next_index + 1)?;
+    expect_token(tokens, next_index, &Token::RParen, "Expected closing parenthesis")?;
+    Ok((Expression::Binary(operator, Box::new(lhs), Box::new(rhs), M::none()), next_index + 1))
+}
+
+/// Parses the comma-separated arguments of a call to a function, starting right after its `(`,
+/// and the closing parenthesis.
+///
+/// ## Returns:
+/// An `Expression::Call` of the function `name`, and the index of the next token.
+fn parse_call<M: Position>(tokens: &[Token], index: usize, name: &str) -> Result<(Expression<M>, usize), SyntaxError> {
+    let mut arguments = Vec::new();
+    let mut index = index;
+    if tokens.get(index) != Some(&Token::RParen) {
+        loop {
+            let (argument, next_index) = parse_expression(tokens, index)?;
+            arguments.push(argument);
+            if tokens.get(next_index) != Some(&Token::Comma) {
+                index = next_index;
+                break;
+            }
+            index = next_index + 1;
+        }
+    }
+    expect_token(tokens, index, &Token::RParen, "Expected closing parenthesis")?;
+    Ok((Expression::Call(name.to_string(), arguments, M::none()), index + 1))
+}
+
+/// Parses the index that follows `array`, starting right after its `[`, and the closing bracket,
+/// replacing `array` with an `Expression::Index` into it.
+///
+/// It is kept apart from `parse_term`, and updates the term in place, so the frame of `parse_term`,
+/// which every level of nesting goes through, does not grow.
+///
+/// ## Returns:
+/// The index of the next token.
+fn parse_index<M: Position>(tokens: &[Token], index: usize, array: &mut Expression<M>) -> Result<usize, SyntaxError> {
+    let (position, next_index) = parse_expression(tokens, index)?;
+    expect_token(tokens, next_index, &Token::RBracket, "Expected closing bracket")?;
+    let indexed = core::mem::replace(array, Expression::Number(0, M::none()));
+    *array = Expression::Index(Box::new(indexed), Box::new(position), M::none());
+    Ok(next_index + 1)
+}
+
+/// Parses the rest of the elements of a tuple, starting at the comma after its `first` element,
+/// and the closing parenthesis.
+///
+/// ## Returns:
+/// An `Expression::Tuple` of the elements, and the index of the next token.
+fn parse_tuple<M: Position>(
+    tokens: &[Token],
+    mut index: usize,
+    first: Expression<M>,
+) -> Result<(Expression<M>, usize), SyntaxError> {
+    let mut elements = vec![first];
+    while tokens.get(index) == Some(&Token::Comma) {
+        let (element, next_index) = parse_expression(tokens, index + 1)?;
+        elements.push(element);
+        index = next_index;
+    }
+    expect_token(tokens, index, &Token::RParen, "Expected closing parenthesis")?;
+    if elements.len() != TUPLE_ARITY {
+        let message = format!("Tuples must have exactly {} elements, found {}", TUPLE_ARITY, elements.len());
+        return Err(SyntaxError::new(index, message));
+    }
+    Ok((Expression::Tuple(elements, M::none()), index + 1))
+}
+
+/// Parses the comma-separated elements of an array literal, starting right after its `[`, and the
+/// closing bracket.
+///
+/// ## Returns:
+/// An `Expression::Array` of the elements, and the index of the next token.
+fn parse_array<M: Position>(tokens: &[Token], index: usize) -> Result<(Expression<M>, usize), SyntaxError> {
+    let mut elements = Vec::new();
+    let mut index = index;
+    if tokens.get(index) != Some(&Token::RBracket) {
+        loop {
+            let (element, next_index) = parse_expression(tokens, index)?;
+            elements.push(element);
+            if tokens.get(next_index) != Some(&Token::Comma) {
+                index = next_index;
+                break;
             }
+            index = next_index + 1;
         }
-        Some(token) => Err(format!("Unexpected token: {:?}", token)),
-        None => Err("Unexpected end of input".to_string()),
+    }
+    expect_token(tokens, index, &Token::RBracket, "Expected closing bracket")?;
+    Ok((Expression::Array(elements, M::none()), index + 1))
+}
+
+/// Checks that the token at `index` is `expected`.
+///
+/// ## Returns:
+/// `Ok(())` if it is, an end-of-input error if the input ends at `index`, or `message` otherwise.
+fn expect_token(tokens: &[Token], index: usize, expected: &Token, message: &str) -> Result<(), SyntaxError> {
+    match tokens.get(index) {
+        Some(token) if token == expected => Ok(()),
+        token if is_end(token) => Err(end_of_input(index)),
+        _ => Err(SyntaxError::new(index, message)),
+    }
+}
+
+/// Returns `true` if the input ends at a token: the token stream is exhausted, or the token is the
+/// `Token::Eof` sentinel.
+fn is_end(token: Option<&Token>) -> bool {
+    matches!(token, None | Some(Token::Eof))
+}
+
+/// Builds the error reported when the input ends at `index` while a token was expected.
+fn end_of_input(index: usize) -> SyntaxError {
+    SyntaxError::new(index, format!("Unexpected end of input at token {}", index))
+}
+
+/// An error found while parsing, at the token at `index`, before it is turned into a
+/// `ParseError::Syntax` that only keeps the `message`.
+#[derive(Debug, PartialEq)]
+struct SyntaxError {
+    index: usize,
+    message: String,
+}
+
+impl SyntaxError {
+    fn new(index: usize, message: impl Into<String>) -> SyntaxError {
+        SyntaxError { index, message: message.into() }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec;
     use expectest::prelude::*;
     use super::*;
 
@@ -338,15 +1051,15 @@ mod tests {
         #[test]
         fn unexpected_token() {
             let tokens = vec![Token::Increment];
-            let result = parse_factor(&tokens, 0);
-            expect!(result).to(be_err().value("Unexpected token: Increment"));
+            let result = parse_factor::<()>(&tokens, 0);
+            expect!(result).to(be_err().value(SyntaxError::new(0, "Unexpected token: Increment")));
         }
 
         #[test]
         fn unexpected_end_of_input() {
             let tokens = vec![];
-            let result = parse_factor(&tokens, 0);
-            expect!(result).to(be_err().value("Unexpected end of input"));
+            let result = parse_factor::<()>(&tokens, 0);
+            expect!(result).to(be_err().value(SyntaxError::new(0, "Unexpected end of input at token 0")));
         }
 
         #[test]
@@ -356,59 +1069,362 @@ mod tests {
             expect!(expression).to(be_equal_to(Expression::Identifier("x".to_string(), ())));
             expect!(next_index).to(be_equal_to(1));
         }
-    }
 
-    mod parse_term {
-        use super::*;
+        #[test]
+        fn rejects_the_discard_name() {
+            let tokens = vec![Token::Identifier("_".to_string())];
+            expect!(parse_factor::<()>(&tokens, 0))
+                .to(be_err().value(SyntaxError::new(0, "'_' can only be used as a binding name, not as a value".to_string())));
+        }
 
         #[test]
-        fn increment() {
-            let tokens = vec![Token::Number(42), Token::Increment];
-            let (expression, next_index) = parse_term(&tokens, 0).unwrap();
-            expect!(expression).to(be_equal_to(Expression::Increment(Box::new(Expression::Number(42, ())), ())));
-            expect!(next_index).to(be_equal_to(2));
+        fn assertion() {
+            let tokens = vec![Token::Assert, Token::LParen, Token::Number(1), Token::RParen];
+            let (expression, next_index) = parse_factor(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Assert(Box::new(Expression::Number(1, ())), ())));
+            expect!(next_index).to(be_equal_to(4));
         }
 
         #[test]
-        fn decrement() {
-            let tokens = vec![Token::Number(42), Token::Decrement];
-            let (expression, next_index) = parse_term(&tokens, 0).unwrap();
-            expect!(expression).to(be_equal_to(Expression::Decrement(Box::new(Expression::Number(42, ())), ())));
-            expect!(next_index).to(be_equal_to(2));
+        fn builtin_call() {
+            let tokens = vec![
+                Token::Identifier("max".to_string()),
+                Token::LParen,
+                Token::Number(3),
+                Token::Comma,
+                Token::Identifier("x".to_string()),
+                Token::RParen,
+            ];
+            let (expression, next_index) = parse_factor(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Binary(
+                BinaryOperator::Max,
+                Box::new(Expression::Number(3, ())),
+                Box::new(Expression::Identifier("x".to_string(), ())),
+                (),
+            )));
+            expect!(next_index).to(be_equal_to(6));
         }
-    }
 
-    mod parse_expression {
-        use super::*;
+        #[test]
+        fn ignores_a_unary_plus() {
+            // +5
+            let tokens = vec![Token::Plus, Token::Number(5)];
+            expect!(parse_factor(&tokens, 0)).to(be_ok().value((Expression::Number(5, ()), 2)));
+            // +(3++)
+            let tokens = vec![Token::Plus, Token::LParen, Token::Number(3), Token::Increment, Token::RParen];
+            expect!(parse_factor(&tokens, 0))
+                .to(be_ok().value((Expression::Increment(Box::new(Expression::Number(3, ())), ()), 5)));
+        }
 
         #[test]
-        fn number() {
-            let tokens = vec![Token::Number(42)];
-            let (expression, next_index) = parse_expression(&tokens, 0).unwrap();
-            expect!(expression).to(be_equal_to(Expression::Number(42, ())));
-            expect!(next_index).to(be_equal_to(1));
+        fn reads_a_unary_plus_after_a_binary_operator() {
+            let expression = parse(&crate::parser::tokenize::tokenize("1 - +2 + +-3").unwrap()).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Binary(
+                BinaryOperator::Add,
+                Box::new(Expression::Binary(
+                    BinaryOperator::Subtract,
+                    Box::new(Expression::Number(1, ())),
+                    Box::new(Expression::Number(2, ())),
+                    (),
+                )),
+                Box::new(Expression::Number(-3, ())),
+                (),
+            )));
+            expect!(parse(&[Token::Plus])).to(be_err());
         }
 
         #[test]
-        fn increment() {
-            let tokens = vec![Token::Number(42), Token::Increment];
-            let (expression, next_index) = parse_expression(&tokens, 0).unwrap();
-            expect!(expression).to(be_equal_to(Expression::Increment(Box::new(Expression::Number(42, ())), ())));
-            expect!(next_index).to(be_equal_to(2));
+        fn builtin_name_as_identifier() {
+            let tokens = vec![Token::Identifier("min".to_string()), Token::Plus];
+            let (expression, next_index) = parse_factor(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Identifier("min".to_string(), ())));
+            expect!(next_index).to(be_equal_to(1));
         }
 
         #[test]
-        fn decrement() {
-            let tokens = vec![Token::Number(42), Token::Decrement];
-            let (expression, next_index) = parse_expression(&tokens, 0).unwrap();
-            expect!(expression).to(be_equal_to(Expression::Decrement(Box::new(Expression::Number(42, ())), ())));
-            expect!(next_index).to(be_equal_to(2));
+        fn builtin_call_with_one_argument() {
+            let tokens = vec![Token::Identifier("min".to_string()), Token::LParen, Token::Number(3), Token::RParen];
+            expect!(parse_factor::<()>(&tokens, 0)).to(be_err().value(SyntaxError::new(3, "Expected ',' between arguments".to_string())));
         }
 
         #[test]
-        fn let_binding() {
-            let tokens = vec![
-                Token::Let,
+        fn assertion_without_parentheses() {
+            let tokens = vec![Token::Assert, Token::Number(1)];
+            expect!(parse_factor::<()>(&tokens, 0)).to(be_err().value(SyntaxError::new(1, "Expected '(' after 'assert'".to_string())));
+        }
+    }
+
+    mod parse_term {
+        use super::*;
+
+        #[test]
+        fn postfix_on_a_parenthesized_let() {
+            // (let x = 5; x)++--++
+            let tokens = vec![
+                Token::LParen,
+                Token::Let,
+                Token::Identifier("x".to_string()),
+                Token::Assign,
+                Token::Number(5),
+                Token::LineEnd,
+                Token::Identifier("x".to_string()),
+                Token::RParen,
+                Token::Increment,
+                Token::Decrement,
+                Token::Increment,
+            ];
+            let binding = Expression::Let(
+                "x".to_string(),
+                Box::new(Expression::Number(5, ())),
+                Box::new(Expression::Identifier("x".to_string(), ())),
+                (),
+            );
+            let (expression, next_index) = parse_term(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Increment(
+                Box::new(Expression::Decrement(Box::new(Expression::Increment(Box::new(binding), ())), ())),
+                (),
+            )));
+            expect!(next_index).to(be_equal_to(11));
+        }
+
+        #[test]
+        fn increment() {
+            let tokens = vec![Token::Number(42), Token::Increment];
+            let (expression, next_index) = parse_term(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Increment(Box::new(Expression::Number(42, ())), ())));
+            expect!(next_index).to(be_equal_to(2));
+        }
+
+        #[test]
+        fn decrement() {
+            let tokens = vec![Token::Number(42), Token::Decrement];
+            let (expression, next_index) = parse_term(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Decrement(Box::new(Expression::Number(42, ())), ())));
+            expect!(next_index).to(be_equal_to(2));
+        }
+
+        fn parse_term_source(source: &str) -> Expression<()> {
+            let tokens = crate::parser::tokenize::tokenize(source).unwrap();
+            let (expression, next_index) = parse_term(&tokens, 0).unwrap();
+            expect!(next_index).to(be_equal_to(tokens.len()));
+            expression
+        }
+
+        #[test]
+        fn grouped_and_chained_increments_are_the_same() {
+            let expected = Expression::Increment(
+                Box::new(Expression::Increment(Box::new(Expression::Number(5, ())), ())),
+                (),
+            );
+            expect!(parse_term_source("(5++)++")).to(be_equal_to(expected.clone()));
+            expect!(parse_term_source("5++ ++")).to(be_equal_to(expected.clone()));
+            expect!(parse_term_source("5++++")).to(be_equal_to(expected));
+        }
+
+        #[test]
+        fn postfix_operators_apply_innermost_first() {
+            let expected = Expression::Decrement(
+                Box::new(Expression::Increment(Box::new(Expression::Number(5, ())), ())),
+                (),
+            );
+            expect!(parse_term_source("(5++)--")).to(be_equal_to(expected.clone()));
+            expect!(parse_term_source("5++ --")).to(be_equal_to(expected));
+        }
+
+        #[test]
+        fn parentheses_around_a_factor_are_transparent() {
+            expect!(parse_term_source("(5)++")).to(be_equal_to(parse_term_source("5++")));
+            expect!(parse_term_source("((5))")).to(be_equal_to(Expression::Number(5, ())));
+        }
+    }
+
+    mod parse_binary {
+        use super::*;
+
+        fn binary(
+            operator: BinaryOperator,
+            lhs: Expression<()>,
+            rhs: Expression<()>,
+        ) -> Expression<()> {
+            Expression::Binary(operator, Box::new(lhs), Box::new(rhs), ())
+        }
+
+        #[test]
+        fn comparisons_bind_between_shifts_and_bitwise_and() {
+            // 1 & 2 < 3 << 4 == 1 & (2 < (3 << 4))
+            let tokens = vec![
+                Token::Number(1),
+                Token::BitAnd,
+                Token::Number(2),
+                Token::Less,
+                Token::Number(3),
+                Token::ShiftLeft,
+                Token::Number(4),
+            ];
+            let (expression, _) = parse_binary(&tokens, 0, 0).unwrap();
+            expect!(expression).to(be_equal_to(binary(
+                BinaryOperator::BitAnd,
+                Expression::Number(1, ()),
+                binary(
+                    BinaryOperator::Less,
+                    Expression::Number(2, ()),
+                    binary(BinaryOperator::ShiftLeft, Expression::Number(3, ()), Expression::Number(4, ())),
+                ),
+            )));
+        }
+
+        #[test]
+        fn equality_binds_between_comparisons_and_bitwise_and() {
+            // 1 & 2 == 3 < 4 == 1 & ((2 == (3 < 4))
+            let tokens = vec![
+                Token::Number(1),
+                Token::BitAnd,
+                Token::Number(2),
+                Token::Eq,
+                Token::Number(3),
+                Token::Less,
+                Token::Number(4),
+            ];
+            let (expression, _) = parse_binary(&tokens, 0, 0).unwrap();
+            expect!(expression).to(be_equal_to(binary(
+                BinaryOperator::BitAnd,
+                Expression::Number(1, ()),
+                binary(
+                    BinaryOperator::Equal,
+                    Expression::Number(2, ()),
+                    binary(BinaryOperator::Less, Expression::Number(3, ()), Expression::Number(4, ())),
+                ),
+            )));
+        }
+
+        #[test]
+        fn single_operator() {
+            let tokens = vec![Token::Number(6), Token::BitAnd, Token::Number(3)];
+            let (expression, next_index) = parse_binary(&tokens, 0, 0).unwrap();
+            expect!(expression).to(be_equal_to(binary(
+                BinaryOperator::BitAnd,
+                Expression::Number(6, ()),
+                Expression::Number(3, ()),
+            )));
+            expect!(next_index).to(be_equal_to(3));
+        }
+
+        #[test]
+        fn left_associative() {
+            let tokens = vec![
+                Token::Number(1),
+                Token::ShiftLeft,
+                Token::Number(2),
+                Token::ShiftRight,
+                Token::Number(3),
+            ];
+            let (expression, _) = parse_binary(&tokens, 0, 0).unwrap();
+            expect!(expression).to(be_equal_to(binary(
+                BinaryOperator::ShiftRight,
+                binary(BinaryOperator::ShiftLeft, Expression::Number(1, ()), Expression::Number(2, ())),
+                Expression::Number(3, ()),
+            )));
+        }
+
+        #[test]
+        fn precedence() {
+            // 1 | 2 ^ 3 & 4 << 5 == 1 | (2 ^ (3 & (4 << 5)))
+            let tokens = vec![
+                Token::Number(1),
+                Token::BitOr,
+                Token::Number(2),
+                Token::BitXor,
+                Token::Number(3),
+                Token::BitAnd,
+                Token::Number(4),
+                Token::ShiftLeft,
+                Token::Number(5),
+            ];
+            let (expression, next_index) = parse_binary(&tokens, 0, 0).unwrap();
+            expect!(expression).to(be_equal_to(binary(
+                BinaryOperator::BitOr,
+                Expression::Number(1, ()),
+                binary(
+                    BinaryOperator::BitXor,
+                    Expression::Number(2, ()),
+                    binary(
+                        BinaryOperator::BitAnd,
+                        Expression::Number(3, ()),
+                        binary(BinaryOperator::ShiftLeft, Expression::Number(4, ()), Expression::Number(5, ())),
+                    ),
+                ),
+            )));
+            expect!(next_index).to(be_equal_to(9));
+        }
+
+        #[test]
+        fn additive_binds_tighter_than_shifts() {
+            // 1 << 2 - 1 == 1 << (2 - 1)
+            let tokens = vec![
+                Token::Number(1),
+                Token::ShiftLeft,
+                Token::Number(2),
+                Token::Minus,
+                Token::Number(1),
+            ];
+            let (expression, _) = parse_binary(&tokens, 0, 0).unwrap();
+            expect!(expression).to(be_equal_to(binary(
+                BinaryOperator::ShiftLeft,
+                Expression::Number(1, ()),
+                binary(BinaryOperator::Subtract, Expression::Number(2, ()), Expression::Number(1, ())),
+            )));
+        }
+
+        #[test]
+        fn binds_looser_than_postfix() {
+            let tokens = vec![Token::Number(1), Token::BitAnd, Token::Number(2), Token::Increment];
+            let (expression, _) = parse_binary(&tokens, 0, 0).unwrap();
+            expect!(expression).to(be_equal_to(binary(
+                BinaryOperator::BitAnd,
+                Expression::Number(1, ()),
+                Expression::Increment(Box::new(Expression::Number(2, ())), ()),
+            )));
+        }
+
+        #[test]
+        fn missing_right_operand() {
+            let tokens = vec![Token::Number(1), Token::BitAnd];
+            let result = parse_binary::<()>(&tokens, 0, 0);
+            expect!(result).to(be_err().value(SyntaxError::new(2, "Unexpected end of input at token 2".to_string())));
+        }
+    }
+
+    mod parse_expression {
+        use super::*;
+
+        #[test]
+        fn number() {
+            let tokens = vec![Token::Number(42)];
+            let (expression, next_index) = parse_expression(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Number(42, ())));
+            expect!(next_index).to(be_equal_to(1));
+        }
+
+        #[test]
+        fn increment() {
+            let tokens = vec![Token::Number(42), Token::Increment];
+            let (expression, next_index) = parse_expression(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Increment(Box::new(Expression::Number(42, ())), ())));
+            expect!(next_index).to(be_equal_to(2));
+        }
+
+        #[test]
+        fn decrement() {
+            let tokens = vec![Token::Number(42), Token::Decrement];
+            let (expression, next_index) = parse_expression(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Decrement(Box::new(Expression::Number(42, ())), ())));
+            expect!(next_index).to(be_equal_to(2));
+        }
+
+        #[test]
+        fn let_binding() {
+            let tokens = vec![
+                Token::Let,
                 Token::Identifier("x".to_string()),
                 Token::Assign,
                 Token::Number(5),
@@ -424,6 +1440,178 @@ mod tests {
             )));
             expect!(next_index).to(be_equal_to(6));
         }
+
+        #[test]
+        fn parses_a_mutable_let_binding() {
+            // let mut x = 5; x
+            let tokens = vec![
+                Token::Let,
+                Token::Mut,
+                Token::Identifier("x".to_string()),
+                Token::Assign,
+                Token::Number(5),
+                Token::LineEnd,
+                Token::Identifier("x".to_string()),
+            ];
+            let (expression, next_index) = parse_expression(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::LetMut(
+                "x".to_string(),
+                Box::new(Expression::Number(5, ())),
+                Box::new(Expression::Identifier("x".to_string(), ())),
+                (),
+            )));
+            expect!(next_index).to(be_equal_to(7));
+        }
+
+        #[test]
+        fn parses_a_declaration_without_a_value() {
+            // let mut x; x = 5
+            let tokens = vec![
+                Token::Let,
+                Token::Mut,
+                Token::Identifier("x".to_string()),
+                Token::LineEnd,
+                Token::Identifier("x".to_string()),
+                Token::Assign,
+                Token::Number(5),
+            ];
+            let (expression, next_index) = parse_expression(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Declare(
+                "x".to_string(),
+                Box::new(Expression::Assign("x".to_string(), Box::new(Expression::Number(5, ())), ())),
+                (),
+            )));
+            expect!(next_index).to(be_equal_to(7));
+            // Only a mutable variable can be declared without a value
+            let tokens = vec![Token::Let, Token::Identifier("x".to_string()), Token::LineEnd, Token::Number(5)];
+            expect!(parse_expression::<()>(&tokens, 0)).to(be_err().value(SyntaxError::new(2, "Expected '=' in let binding".to_string())));
+        }
+
+        #[test]
+        fn accepts_the_discard_name_as_binding_name() {
+            // let _ = 5; 7
+            let tokens = vec![
+                Token::Let,
+                Token::Identifier("_".to_string()),
+                Token::Assign,
+                Token::Number(5),
+                Token::LineEnd,
+                Token::Number(7),
+            ];
+            let (expression, _) = parse_expression(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Let(
+                "_".to_string(),
+                Box::new(Expression::Number(5, ())),
+                Box::new(Expression::Number(7, ())),
+                (),
+            )));
+        }
+
+        #[test]
+        fn rejects_keywords_as_binding_names() {
+            for (spelling, keyword) in crate::parser::tokenize::KEYWORDS {
+                // let <keyword> = 5; 1
+                let tokens = vec![
+                    Token::Let,
+                    keyword.clone(),
+                    Token::Assign,
+                    Token::Number(5),
+                    Token::LineEnd,
+                    Token::Number(1),
+                ];
+                expect!(parse_expression::<()>(&tokens, 0)).to(be_err().value(SyntaxError::new(1, format!(
+                    "'{}' is a reserved keyword and cannot be used as a variable name",
+                    spelling
+                ))));
+            }
+        }
+
+        #[test]
+        fn sequence() {
+            // 1; 2 | 3; 4
+            let tokens = vec![
+                Token::Number(1),
+                Token::LineEnd,
+                Token::Number(2),
+                Token::BitOr,
+                Token::Number(3),
+                Token::LineEnd,
+                Token::Number(4),
+            ];
+            let (expression, next_index) = parse_expression(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Sequence(vec![
+                Expression::Number(1, ()),
+                Expression::Binary(
+                    BinaryOperator::BitOr,
+                    Box::new(Expression::Number(2, ())),
+                    Box::new(Expression::Number(3, ())),
+                    ()
+                ),
+                Expression::Number(4, ()),
+            ], ())));
+            expect!(next_index).to(be_equal_to(7));
+        }
+
+        #[test]
+        fn let_body_extends_to_the_end_of_the_sequence() {
+            // 1; let x = 2; x; x
+            let tokens = vec![
+                Token::Number(1),
+                Token::LineEnd,
+                Token::Let,
+                Token::Identifier("x".to_string()),
+                Token::Assign,
+                Token::Number(2),
+                Token::LineEnd,
+                Token::Identifier("x".to_string()),
+                Token::LineEnd,
+                Token::Identifier("x".to_string()),
+            ];
+            let (expression, _) = parse_expression(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Sequence(vec![
+                Expression::Number(1, ()),
+                Expression::Let(
+                    "x".to_string(),
+                    Box::new(Expression::Number(2, ())),
+                    Box::new(Expression::Sequence(vec![
+                        Expression::Identifier("x".to_string(), ()),
+                        Expression::Identifier("x".to_string(), ()),
+                    ], ())),
+                    ()
+                ),
+            ], ())));
+        }
+
+        #[test]
+        fn trailing_line_end() {
+            let tokens = vec![Token::Number(1), Token::LineEnd];
+            let (expression, next_index) = parse_expression(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Number(1, ())));
+            expect!(next_index).to(be_equal_to(1));
+        }
+
+        #[test]
+        fn skips_empty_statements() {
+            // 5;; 6
+            let tokens = vec![Token::Number(5), Token::LineEnd, Token::LineEnd, Token::Number(6)];
+            let (expression, next_index) = parse_expression(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Sequence(vec![
+                Expression::Number(5, ()),
+                Expression::Number(6, ()),
+            ], ())));
+            expect!(next_index).to(be_equal_to(4));
+            // ;5
+            let tokens = vec![Token::LineEnd, Token::Number(5)];
+            let (expression, next_index) = parse_expression(&tokens, 0).unwrap();
+            expect!(expression).to(be_equal_to(Expression::Number(5, ())));
+            expect!(next_index).to(be_equal_to(2));
+        }
+
+        #[test]
+        fn rejects_a_sequence_of_empty_statements_only() {
+            let tokens = vec![Token::LineEnd, Token::LineEnd];
+            expect!(parse_expression::<()>(&tokens, 0)).to(be_err().value(SyntaxError::new(2, "Unexpected end of input at token 2".to_string())));
+        }
     }
 
     mod parse {
@@ -450,4 +1638,540 @@ mod tests {
             expect!(expression).to(be_equal_to(Expression::Decrement(Box::new(Expression::Number(42, ())), ())));
         }
     }
+
+    mod parse_with_spans {
+        use super::*;
+        use crate::ast::visit::{walk_expression, Visitor};
+        use crate::parser::tokenize::{tokenize, tokenize_with_spans, TokenizeOptions};
+
+        /// Collects the spelling of the operator each step node is located at, in the order of
+        /// the operators.
+        #[derive(Default)]
+        struct Located<'a> {
+            source: &'a str,
+            steps: Vec<(usize, &'static str, &'a str)>,
+        }
+
+        impl<'a> Visitor<Option<Span>> for Located<'a> {
+            fn visit_expression(&mut self, expression: &Expression<Option<Span>>) {
+                let kind = match expression {
+                    Expression::Increment(..) => "++",
+                    Expression::Decrement(..) => "--",
+                    _ => "",
+                };
+                if let Some(span) = expression.metadata() {
+                    self.steps.push((span.start, kind, span.text(self.source)));
+                }
+                walk_expression(self, expression);
+            }
+        }
+
+        fn parse_source(source: &str) -> Expression<Option<Span>> {
+            parse_with_spans(&tokenize_with_spans(source, &TokenizeOptions::default()).unwrap()).unwrap()
+        }
+
+        fn operator_span(source: &str, operator: &str, occurrence: usize) -> Option<Span> {
+            let start = source.match_indices(operator).nth(occurrence).unwrap().0;
+            Some(Span { start, end: start + 2, line: 1, col: start + 1 })
+        }
+
+        #[test]
+        fn locates_an_increment_at_its_operator() {
+            let source = "let x = 1; (x)++";
+            let Expression::Let(_, _, body, _) = parse_source(source) else { panic!("expected a let") };
+            let Expression::Increment(operand, span) = *body else { panic!("expected an increment") };
+            expect!(span).to(be_equal_to(operator_span(source, "++", 0)));
+            expect!(*operand.metadata()).to(be_none());
+        }
+
+        #[test]
+        fn locates_an_assertion_at_its_keyword() {
+            let source = "let x = 1; assert(x > 0)";
+            let Expression::Let(_, _, body, _) = parse_source(source) else { panic!("expected a let") };
+            let Expression::Assert(condition, span) = *body else { panic!("expected an assertion") };
+            expect!(span).to(be_some().value(Span { start: 11, end: 17, line: 1, col: 12 }));
+            expect!(*condition.metadata()).to(be_none());
+        }
+
+        #[test]
+        fn locates_chained_steps_at_their_own_operators() {
+            let source = "5++ -- ++";
+            let Expression::Increment(inner, outer) = parse_source(source) else { panic!("expected an increment") };
+            let Expression::Decrement(innermost, middle) = *inner else { panic!("expected a decrement") };
+            expect!(innermost.metadata()).to(be_equal_to(&operator_span(source, "++", 0)));
+            expect!(middle).to(be_equal_to(operator_span(source, "--", 0)));
+            expect!(outer).to(be_equal_to(operator_span(source, "++", 1)));
+        }
+
+        #[test]
+        fn follows_the_operators_across_nested_steps() {
+            let source = "(1++ + 2--)++ - [3++][0]";
+            let expression = parse_source(source);
+            let mut located = Located { source, ..Default::default() };
+            located.visit_expression(&expression);
+            located.steps.sort();
+            expect!(located.steps).to(be_equal_to(vec![(2, "++", "++"), (8, "--", "--"), (11, "++", "++"), (18, "++", "++")]));
+            expect!(expression.map_metadata(&mut |_| ())).to(be_equal_to(parse(&tokenize(source).unwrap()).unwrap()));
+        }
+
+        #[test]
+        fn locates_each_step_in_bindings_functions_and_branches() {
+            let source = "fn f(n) = n--; let x = f(1++); if x++ > 0 then x-- else f(x)++";
+            let mut located = Located { source, ..Default::default() };
+            located.visit_expression(&parse_source(source));
+            located.steps.sort();
+            let mut operators: Vec<_> = source
+                .match_indices("++")
+                .chain(source.match_indices("--"))
+                .map(|(start, operator)| (start, operator, operator))
+                .collect();
+            // The function and the binding are located at their names
+            operators.extend([(3, "", "f"), (19, "", "x")]);
+            operators.sort();
+            expect!(located.steps.len()).to(be_equal_to(7));
+            expect!(located.steps).to(be_equal_to(operators));
+        }
+
+        #[test]
+        fn locates_each_binding_at_its_first_name() {
+            let source = "let mut a = 1; let (b, c) = (a, 2); let mut d; fn e(f) = f; 0";
+            let mut located = Located { source, ..Default::default() };
+            located.visit_expression(&parse_source(source));
+            let names: Vec<_> = located.steps.iter().map(|(_, _, text)| *text).collect();
+            expect!(names).to(be_equal_to(vec!["a", "b", "d", "e"]));
+        }
+    }
+
+    mod parse_recovering {
+        use super::*;
+        use crate::parser::tokenize::{tokenize, tokenize_with_spans, TokenizeOptions};
+
+        /// The recovered program and the errors found, each with the offset it was found at.
+        type Recovered = (Option<Expression<()>>, Vec<(String, Option<usize>)>);
+
+        fn parse_source(source: &str) -> Recovered {
+            let (program, errors) = parse_recovering(&tokenize_with_spans(source, &TokenizeOptions::default()).unwrap());
+            let errors = errors.into_iter().map(|(error, span)| (error.to_string(), span.map(|span| span.start))).collect();
+            (program.map(|program| program.map_metadata(&mut |_| ())), errors)
+        }
+
+        #[test]
+        fn parses_a_valid_program_as_parse_does() {
+            let source = "let x = 1; fn f(y) = y++; f(x)";
+            expect!(parse_source(source)).to(be_equal_to((parse(&tokenize(source).unwrap()).ok(), vec![])));
+        }
+
+        #[test]
+        fn reports_every_statement_that_fails_to_parse() {
+            let (program, errors) = parse_source("let x = ; let y = 1; y + & 2; y");
+            expect!(program).to(be_equal_to(parse(&tokenize("0; let y = 1; 0; y").unwrap()).ok()));
+            expect!(errors).to(be_equal_to(vec![
+                ("Unexpected token: LineEnd".to_string(), Some(8)),
+                ("Unexpected token: BitAnd".to_string(), Some(25)),
+            ]));
+        }
+
+        #[test]
+        fn replaces_the_statement_around_parentheses() {
+            let (program, errors) = parse_source("1; (2 + ; 3); 4");
+            expect!(program).to(be_equal_to(parse(&tokenize("1; 0; 4").unwrap()).ok()));
+            expect!(errors).to(be_equal_to(vec![("Unexpected token: LineEnd".to_string(), Some(8))]));
+        }
+
+        #[test]
+        fn reports_the_first_error_as_parse_does() {
+            let source = "let x = 1; x = ; (1 2";
+            let first = parse_source(source).1.remove(0).0;
+            expect!(parse(&tokenize(source).unwrap()).map_err(|error| error.to_string())).to(be_err().value(first));
+        }
+
+        #[test]
+        fn locates_the_end_of_the_input_at_the_last_token() {
+            expect!(parse_source("let x = 1;")).to(be_equal_to((
+                parse(&tokenize("let x = 1; 0").unwrap()).ok(),
+                vec![("Unexpected end of input at token 5".to_string(), Some(9))],
+            )));
+        }
+
+        #[test]
+        fn gives_up_on_programs_nested_too_deeply() {
+            let nested = format!("{}1{}", "(".repeat(MAX_NESTING + 1), ")".repeat(MAX_NESTING + 1));
+            let (program, errors) = parse_source(&nested);
+            expect!(program).to(be_none());
+            expect!(errors).to(be_equal_to(vec![(ParseError::TooDeep { limit: MAX_NESTING }.to_string(), None)]));
+        }
+    }
+
+    mod assignments {
+        use super::*;
+        use crate::parser::tokenize::tokenize;
+
+        fn parse_source(source: &str) -> Result<Expression<()>, String> {
+            parse(&tokenize(source).unwrap()).map_err(|error| error.to_string())
+        }
+
+        #[test]
+        fn skips_empty_statements_around_a_binding() {
+            expect!(parse_source("let mut x = 5;;; x = 6;; x;;")).to(be_equal_to(parse_source("let mut x = 5; x = 6; x")));
+        }
+
+        #[test]
+        fn assigns_to_a_variable() {
+            expect!(parse_source("let x = 1; x = x + 2; x")).to(be_ok().value(Expression::Let(
+                "x".to_string(),
+                Box::new(Expression::Number(1, ())),
+                Box::new(Expression::Sequence(vec![
+                    Expression::Assign(
+                        "x".to_string(),
+                        Box::new(Expression::Binary(
+                            BinaryOperator::Add,
+                            Box::new(Expression::Identifier("x".to_string(), ())),
+                            Box::new(Expression::Number(2, ())),
+                            (),
+                        )),
+                        (),
+                    ),
+                    Expression::Identifier("x".to_string(), ()),
+                ], ())),
+                (),
+            )));
+        }
+
+        #[test]
+        fn rejects_a_number_on_the_left() {
+            expect!(parse_source("5 = 6")).to(be_err().value(STRAY_ASSIGN.to_string()));
+        }
+
+        #[test]
+        fn rejects_a_missing_left_side() {
+            expect!(parse_source("= 5")).to(be_err().value(STRAY_ASSIGN.to_string()));
+        }
+
+        #[test]
+        fn rejects_an_expression_on_the_left() {
+            expect!(parse_source("let x = 1; x + 1 = 5")).to(be_err().value(STRAY_ASSIGN.to_string()));
+            expect!(parse_source("let x = 1; (x++ = 5)")).to(be_err().value(STRAY_ASSIGN.to_string()));
+            expect!(parse_source("let x = 5 = 6; x")).to(be_err().value(STRAY_ASSIGN.to_string()));
+        }
+
+        #[test]
+        fn chains_assignments_to_the_right() {
+            let parsed = parse_source("x = y = 5").unwrap();
+            expect!(parsed).to(be_equal_to(Expression::Assign(
+                "x".to_string(),
+                Box::new(Expression::Assign("y".to_string(), Box::new(Expression::Number(5, ())), ())),
+                (),
+            )));
+            expect!(parse_source("x = 1 + y = 5")).to(be_err().value(STRAY_ASSIGN.to_string()));
+        }
+
+        #[test]
+        fn reports_the_message_for_stray_assignments() {
+            expect!(parse_source("5 = 6"))
+                .to(be_err().value("unexpected '='; assignment requires a variable on the left".to_string()));
+        }
+    }
+
+    mod control_flow {
+        use super::*;
+        use crate::parser::tokenize::tokenize;
+
+        fn parse_source(source: &str) -> Result<Expression<()>, String> {
+            parse(&tokenize(source).unwrap()).map_err(|error| error.to_string())
+        }
+
+        #[test]
+        fn returns_a_value() {
+            expect!(parse_source("return 5; 7")).to(be_ok().value(Expression::Sequence(vec![
+                Expression::Return(Box::new(Expression::Number(5, ())), ()),
+                Expression::Number(7, ()),
+            ], ())));
+        }
+
+        #[test]
+        fn parses_a_conditional_with_a_return() {
+            expect!(parse_source("if 1 then return 3 else 4")).to(be_ok().value(Expression::If(
+                Box::new(Expression::Number(1, ())),
+                Box::new(Expression::Return(Box::new(Expression::Number(3, ())), ())),
+                Box::new(Expression::Number(4, ())),
+                (),
+            )));
+        }
+
+        #[test]
+        fn ends_the_conditional_at_the_line_end() {
+            let parsed = parse_source("if 0 then 1 else 2; 3").unwrap();
+            expect!(matches!(parsed, Expression::Sequence(ref statements, ()) if statements.len() == 2)).to(be_true());
+        }
+
+        #[test]
+        fn requires_both_branches() {
+            expect!(parse_source("if 1 3 else 4")).to(be_err().value("Expected 'then' after the condition".to_string()));
+            expect!(parse_source("if 1 then 3 4")).to(be_err().value("Expected 'else' after the 'then' branch".to_string()));
+        }
+    }
+
+    mod print {
+        use super::*;
+        use crate::parser::tokenize::tokenize;
+
+        fn parse_source(source: &str) -> Result<Expression<()>, String> {
+            parse(&tokenize(source).unwrap()).map_err(|error| error.to_string())
+        }
+
+        #[test]
+        fn parses_a_string_argument() {
+            expect!(parse_source("print(\"hi\")")).to(be_ok().value(Expression::Print("hi".to_string(), ())));
+        }
+
+        #[test]
+        fn requires_a_string_between_parentheses() {
+            expect!(parse_source("print \"hi\"")).to(be_err().value("Expected '(' after 'print'".to_string()));
+            expect!(parse_source("print(5)")).to(be_err().value("Expected a string literal in 'print'".to_string()));
+        }
+    }
+
+    mod functions {
+        use super::*;
+        use crate::parser::tokenize::tokenize;
+
+        fn parse_source(source: &str) -> Result<Expression<()>, String> {
+            parse(&tokenize(source).unwrap()).map_err(|error| error.to_string())
+        }
+
+        fn identifier(name: &str) -> Expression<()> {
+            Expression::Identifier(name.to_string(), ())
+        }
+
+        #[test]
+        fn defines_and_calls_a_function() {
+            let add = Function {
+                name: "add".to_string(),
+                parameters: vec!["a".to_string(), "b".to_string()],
+                body: Expression::Binary(BinaryOperator::Add, Box::new(identifier("a")), Box::new(identifier("b")), ()),
+            };
+            expect!(parse_source("fn add(a, b) = a + b; add(1, 2)")).to(be_ok().value(Expression::Func(
+                Box::new(add),
+                Box::new(Expression::Call(
+                    "add".to_string(),
+                    vec![Expression::Number(1, ()), Expression::Number(2, ())],
+                    (),
+                )),
+                (),
+            )));
+        }
+
+        #[test]
+        fn accepts_no_parameters_and_no_arguments() {
+            let four = Function { name: "four".to_string(), parameters: vec![], body: Expression::Number(4, ()) };
+            expect!(parse_source("fn four() = 4; four()")).to(be_ok().value(Expression::Func(
+                Box::new(four),
+                Box::new(Expression::Call("four".to_string(), vec![], ())),
+                (),
+            )));
+        }
+
+        #[test]
+        fn keeps_builtins_apart_from_calls() {
+            expect!(parse_source("min(1, 2)")).to(be_ok().value(Expression::Binary(
+                BinaryOperator::Min,
+                Box::new(Expression::Number(1, ())),
+                Box::new(Expression::Number(2, ())),
+                (),
+            )));
+            expect!(parse_source("fn min(a, b) = a; 0")).to(be_err());
+        }
+
+        #[test]
+        fn rejects_malformed_definitions() {
+            expect!(parse_source("fn (a) = a; 0")).to(be_err().value("Expected function name after 'fn'".to_string()));
+            expect!(parse_source("fn f a = a; 0")).to(be_err().value("Expected '(' after the function name".to_string()));
+            expect!(parse_source("fn f(1) = 1; 0")).to(be_err().value("Expected parameter name".to_string()));
+            expect!(parse_source("fn f(a) a; 0")).to(be_err().value("Expected '=' in function definition".to_string()));
+            expect!(parse_source("fn f(a) = a")).to(be_err());
+        }
+
+        #[test]
+        fn rejects_unclosed_calls() {
+            expect!(parse_source("f(1, 2")).to(be_err());
+            expect!(parse_source("f(1 2)")).to(be_err().value("Expected closing parenthesis".to_string()));
+        }
+    }
+
+    mod tuples {
+        use super::*;
+        use crate::parser::tokenize::tokenize;
+
+        fn parse_source(source: &str) -> Result<Expression<()>, String> {
+            parse(&tokenize(source).unwrap()).map_err(|error| error.to_string())
+        }
+
+        #[test]
+        fn destructures_a_pair() {
+            expect!(parse_source("let (x, y) = (1, 2); x + y")).to(be_ok().value(Expression::LetTuple(
+                vec!["x".to_string(), "y".to_string()],
+                Box::new(Expression::Tuple(vec![Expression::Number(1, ()), Expression::Number(2, ())], ())),
+                Box::new(Expression::Binary(
+                    BinaryOperator::Add,
+                    Box::new(Expression::Identifier("x".to_string(), ())),
+                    Box::new(Expression::Identifier("y".to_string(), ())),
+                    (),
+                )),
+                (),
+            )));
+        }
+
+        #[test]
+        fn keeps_parenthesized_expressions_apart_from_tuples() {
+            expect!(parse_source("(1)")).to(be_ok().value(Expression::Number(1, ())));
+        }
+
+        #[test]
+        fn rejects_other_arities() {
+            expect!(parse_source("let (x, y) = (1, 2, 3); x"))
+                .to(be_err().value("Tuples must have exactly 2 elements, found 3".to_string()));
+            expect!(parse_source("let (x, y, z) = (1, 2); x"))
+                .to(be_err().value("Tuple patterns must bind exactly 2 names, found 3".to_string()));
+            expect!(parse_source("let (x) = (1, 2); x"))
+                .to(be_err().value("Tuple patterns must bind exactly 2 names, found 1".to_string()));
+        }
+
+        #[test]
+        fn rejects_values_that_are_not_tuple_literals() {
+            expect!(parse_source("let (x, y) = 5; x"))
+                .to(be_err().value("Expected a tuple literal to destructure".to_string()));
+        }
+    }
+
+    mod arrays {
+        use super::*;
+        use crate::parser::tokenize::tokenize;
+
+        fn parse_source(source: &str) -> Result<Expression<()>, String> {
+            parse(&tokenize(source).unwrap()).map_err(|error| error.to_string())
+        }
+
+        fn numbers(values: &[i64]) -> Vec<Expression<()>> {
+            values.iter().map(|value| Expression::Number(*value, ())).collect()
+        }
+
+        #[test]
+        fn parses_array_literals() {
+            expect!(parse_source("[1, 2, 3]")).to(be_ok().value(Expression::Array(numbers(&[1, 2, 3]), ())));
+            expect!(parse_source("[7]")).to(be_ok().value(Expression::Array(numbers(&[7]), ())));
+            expect!(parse_source("[]")).to(be_ok().value(Expression::Array(vec![], ())));
+        }
+
+        #[test]
+        fn parses_indices_like_postfix_operators() {
+            let indexed = Expression::Index(
+                Box::new(Expression::Array(numbers(&[10, 20, 30]), ())),
+                Box::new(Expression::Number(1, ())),
+                (),
+            );
+            expect!(parse_source("[10, 20, 30][1]")).to(be_ok().value(indexed.clone()));
+            expect!(parse_source("[10, 20, 30][1]++ + 1")).to(be_ok().value(Expression::Binary(
+                BinaryOperator::Add,
+                Box::new(Expression::Increment(Box::new(indexed), ())),
+                Box::new(Expression::Number(1, ())),
+                (),
+            )));
+        }
+
+        #[test]
+        fn rejects_unclosed_brackets() {
+            expect!(parse_source("[1, 2")).to(be_err());
+            expect!(parse_source("[1 2]")).to(be_err().value("Expected closing bracket".to_string()));
+            expect!(parse_source("[1][0")).to(be_err());
+            expect!(parse_source("[1][]")).to(be_err());
+        }
+    }
+
+    mod eof {
+        use super::*;
+        use crate::parser::tokenize::{tokenize, tokenize_with_eof};
+
+        #[test]
+        fn parses_like_the_plain_token_stream() {
+            for source in ["42", "let x = 5; x++", "(1 | 2) << 3; 4;", "1; let y = 2; y - 1"] {
+                let plain = parse(&tokenize(source).unwrap());
+                expect!(parse(&tokenize_with_eof(source).unwrap())).to(be_equal_to(plain));
+            }
+        }
+
+        #[test]
+        fn reports_the_position_of_the_end_of_input() {
+            let errors = ["let x =", "let x", "(1", "1 &", "let x = 5"]
+                .map(|source| parse(&tokenize_with_eof(source).unwrap()).unwrap_err().to_string());
+            expect!(errors).to(be_equal_to([
+                "Unexpected end of input at token 3",
+                "Unexpected end of input at token 2",
+                "Unexpected end of input at token 2",
+                "Unexpected end of input at token 2",
+                "Unexpected end of input at token 4",
+            ].map(String::from)));
+        }
+    }
+
+    mod nesting {
+        use super::*;
+        use proptest::prelude::*;
+        use crate::parser::tokenize::tokenize;
+
+        fn parse_source(source: &str) -> Result<Expression<()>, String> {
+            parse(&tokenize(source).unwrap()).map_err(|error| error.to_string())
+        }
+
+        #[test]
+        fn rejects_programs_nested_too_deeply() {
+            let too_deep = format!("{}1{}", "(".repeat(MAX_NESTING * 4), ")".repeat(MAX_NESTING * 4));
+            expect!(parse_source(&too_deep)).to(be_err().value(format!("Program nested too deeply (more than {} levels)", MAX_NESTING)));
+            expect!(parse_source(&format!("1{}", "++".repeat(MAX_NESTING + 1)))).to(be_err());
+        }
+
+        #[test]
+        fn accepts_programs_up_to_the_limit() {
+            let nested = format!("{}1{}", "(".repeat(MAX_NESTING - 1), ")".repeat(MAX_NESTING - 1));
+            expect!(parse_source(&nested)).to(be_ok().value(Expression::Number(1, ())));
+        }
+
+        #[test]
+        fn does_not_count_the_statements_after_a_binding_as_nesting() {
+            // let x0 = 0; let x1 = x0 + 1; ...; let x257 = x256 + 1; x257
+            let lets: String = (1..=MAX_NESTING + 1).map(|index| format!("let x{} = x{} + 1; ", index, index - 1)).collect();
+            expect!(parse_source(&format!("let x0 = 0; {}x{}", lets, MAX_NESTING + 1))).to(be_ok());
+            let functions = format!("{}0", "fn f() = 1; ".repeat(MAX_NESTING + 1));
+            expect!(parse_source(&functions)).to(be_ok());
+        }
+
+        #[test]
+        fn rejects_too_many_bindings_in_scope() {
+            // let x = 1; let x = 1; ...; 0
+            let binding = vec![Token::Let, Token::Identifier("x".to_string()), Token::Assign, Token::Number(1), Token::LineEnd];
+            let repeat = |tokens: Vec<Token>| -> Vec<Token> {
+                (0..=MAX_BINDINGS).flat_map(|_| tokens.clone()).chain([Token::Number(0)]).collect()
+            };
+            expect!(parse(&repeat(binding.clone()))).to(be_err().value(ParseError::TooManyBindings { limit: MAX_BINDINGS }));
+            // (let x = 1; x); (let x = 1; x); ...; 0, as the bindings go out of scope with their parentheses
+            let scoped = [vec![Token::LParen], binding, vec![Token::Identifier("x".to_string()), Token::RParen, Token::LineEnd]].concat();
+            expect!(check_nesting(&repeat(scoped))).to(be_ok());
+        }
+
+        #[test]
+        fn only_counts_the_levels_still_open() {
+            // Statements and parentheses that end give their levels back
+            let statements = format!("let x = 1; {}x", "x = x + 1 + 2; ".repeat(MAX_NESTING));
+            expect!(parse_source(&statements)).to(be_ok());
+            expect!(parse_source(&format!("{}3", "(1 + (2 + 3)); ".repeat(MAX_NESTING)))).to(be_ok());
+        }
+
+        proptest!(
+            #[test]
+            fn never_panics(source in "\\PC{0,64}") {
+                if let Ok(tokens) = tokenize(&source) {
+                    let _ = parse(&tokens);
+                }
+            }
+        );
+    }
 }
@@ -0,0 +1,179 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+use crate::parser::error::TokenizeError;
+use crate::parser::span::Span;
+use crate::parser::token::Token;
+use crate::parser::tokenize::{Lexer, TokenizeOptions};
+
+/// Tokenizes a source again after an edit, reading only the part of it the edit can have changed.
+///
+/// The edit replaced the bytes in `edit` of the old source, whose tokens are `old_tokens`, with
+/// `inserted` bytes of new text, which makes `source[edit.start..edit.start + inserted]`. That is
+/// the range and the length of the text of an editor change event.
+///
+/// The tokens that end before the edit are kept, since reading them never looked past their last
+/// character. Tokens are then read from there until one starts past the edit where a token of the
+/// old source started, after the same token: from that point on, the lexer would read the same
+/// tokens as before, so the old ones are reused with their spans moved. An edit that changes the
+/// boundaries of the tokens around it, like a space inserted in `++`, is read on until they match
+/// again.
+///
+/// The result is always that of `tokenize_with_spans` with the default options on `source`.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parser::tokenize::TokenizeOptions;
+/// # use rengo::parser::tokenize::tokenize_with_spans;
+/// # use rengo::parser::retokenize::retokenize;
+/// let options = TokenizeOptions::default();
+/// let tokens = tokenize_with_spans("x++; y", &options).unwrap();
+/// // "x++; y" becomes "x+ +; y"
+/// assert_eq!(
+///     retokenize(&tokens, "x+ +; y", 2..2, 1),
+///     tokenize_with_spans("x+ +; y", &options)
+/// );
+/// ```
+///
+/// ## Panics:
+/// If the edit does not fit in the old source, as far as `old_tokens` tell, or in `source`.
+pub fn retokenize(
+    old_tokens: &[(Token, Span)],
+    source: &str,
+    edit: Range<usize>,
+    inserted: usize,
+) -> Result<Vec<(Token, Span)>, TokenizeError> {
+    let inserted_end = edit.start + inserted;
+    assert!(edit.start <= edit.end && inserted_end <= source.len(), "the edit is out of the source");
+    let options = TokenizeOptions::default();
+    let kept = old_tokens.partition_point(|(_, span)| span.end < edit.start);
+    let lexer = match kept.checked_sub(1) {
+        Some(last) => Lexer::after(source, &old_tokens[last], &options),
+        None => Lexer::new(source, &options),
+    };
+    let mut tokens = old_tokens[..kept].to_vec();
+    for read in lexer {
+        let (token, span) = read?;
+        if span.start >= inserted_end {
+            let old_start = span.start - inserted_end + edit.end;
+            if let Ok(index) = old_tokens.binary_search_by_key(&old_start, |(_, span)| span.start) {
+                let old_previous = old_tokens[..index].last().map(|(token, _)| token);
+                if old_previous == tokens.last().map(|(token, _)| token) {
+                    let from = old_tokens[index].1;
+                    let rest = old_tokens[index..].iter().map(|(token, old)| (token.clone(), moved(*old, from, span)));
+                    tokens.extend(rest);
+                    return Ok(tokens);
+                }
+            }
+        }
+        tokens.push((token, span));
+    }
+    Ok(tokens)
+}
+
+/// Returns where the token at `span` is after the edit, given that the token at `from` moved to
+/// `to`. Both tokens come after the edit, so only the columns of the line of `from` change.
+fn moved(span: Span, from: Span, to: Span) -> Span {
+    Span {
+        start: span.start - from.start + to.start,
+        end: span.end - from.start + to.start,
+        line: span.line - from.line + to.line,
+        col: if span.line == from.line { span.col - from.col + to.col } else { span.col },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use expectest::prelude::*;
+    use proptest::prelude::*;
+    use super::*;
+    use crate::parser::tokenize::tokenize_with_spans;
+
+    /// Applies the edit to `old` and checks that retokenizing gives the same result as tokenizing
+    /// the new source from scratch.
+    fn check_edit(old: &str, edit: Range<usize>, text: &str) -> Result<Vec<(Token, Span)>, TokenizeError> {
+        let options = TokenizeOptions::default();
+        let old_tokens = tokenize_with_spans(old, &options).unwrap();
+        let mut source = String::from(old);
+        source.replace_range(edit.clone(), text);
+        let tokens = retokenize(&old_tokens, &source, edit, text.len());
+        assert_eq!(tokens, tokenize_with_spans(&source, &options), "editing {:?} into {:?}", old, source);
+        tokens
+    }
+
+    mod retokenize {
+        use super::*;
+
+        #[test]
+        fn splits_a_token_when_a_space_is_inserted() {
+            let tokens = check_edit("x++; y", 2..2, " ").unwrap();
+            expect!(tokens[1].clone()).to(be_equal_to((Token::Plus, Span { start: 1, end: 2, line: 1, col: 2 })));
+            expect!(tokens[2].clone()).to(be_equal_to((Token::Plus, Span { start: 3, end: 4, line: 1, col: 4 })));
+        }
+
+        #[test]
+        fn joins_tokens_when_the_space_between_them_is_removed() {
+            check_edit("x+ +; y", 2..3, "").unwrap();
+            check_edit("ab cd = 1", 2..3, "").unwrap();
+        }
+
+        #[test]
+        fn joins_a_token_with_text_inserted_right_after_it() {
+            check_edit("let x = 1; x", 5..5, "yz").unwrap();
+            check_edit("a < 1", 3..3, "<").unwrap();
+        }
+
+        #[test]
+        fn moves_the_lines_and_columns_of_the_tokens_after_the_edit() {
+            check_edit("let x = 1;\nx + x\n+ 2", 4..5, "long\n  name").unwrap();
+            check_edit("let x = 1;\nx + x\n+ 2", 8..11, "").unwrap();
+        }
+
+        #[test]
+        fn reads_a_subtraction_again_when_the_token_before_it_changes() {
+            // The `-` of `x -1` is a subtraction, and becomes the sign of `-1` after `+`
+            let tokens = check_edit("x -1; 2", 0..1, "+").unwrap();
+            expect!(tokens[1].0.clone()).to(be_equal_to(Token::Number(-1)));
+        }
+
+        #[test]
+        fn reads_on_until_a_string_ends() {
+            check_edit("a \"b c\" d", 2..5, "b \"").unwrap();
+            check_edit("\"a; b\" c", 0..1, "").unwrap_err();
+        }
+
+        #[test]
+        fn edits_the_start_and_the_end_of_the_source() {
+            check_edit("x + 1", 0..0, "y ").unwrap();
+            check_edit("x + 1", 5..5, "0").unwrap();
+            check_edit("x + 1", 0..5, "").unwrap();
+            check_edit("", 0..0, "let").unwrap();
+        }
+
+        #[test]
+        fn fails_like_tokenize_on_invalid_text() {
+            expect!(check_edit("x + 1", 2..3, "$")).to(be_err());
+            expect!(check_edit("x + 1", 4..5, "")).to(be_err().value(TokenizeError::DanglingPlus));
+        }
+
+        proptest!(
+            #[test]
+            fn matches_a_full_tokenize(
+                pieces in prop::collection::vec(
+                    prop_oneof![Just("a"), Just("1 "), Just(" "), Just("+"), Just("-"), Just("<"), Just(";\n"), Just("\"b c\"")],
+                    0..10,
+                ),
+                text in "[ab1 +\\-<;\"\\n]{0,4}",
+                start in 0usize..24,
+                length in 0usize..4,
+            ) {
+                // The pieces make a valid source unless a `+` is left at its end, and the edit may
+                // not keep it valid
+                let old = pieces.concat();
+                prop_assume!(tokenize_with_spans(&old, &TokenizeOptions::default()).is_ok());
+                let start = start.min(old.len());
+                let _ = check_edit(&old, start..(start + length).min(old.len()), &text);
+            }
+        );
+    }
+}
@@ -0,0 +1,120 @@
+/// The position of a token in the source it was read from.
+///
+/// Offsets are in bytes, so a span can slice the source; lines and columns are counted in
+/// characters from 1, as editors show them.
+///
+/// # Example
+///
+/// ```rust
+/// # use rengo::parser::tokenize::tokenize_with_spans;
+/// # use rengo::parser::tokenize::TokenizeOptions;
+/// # use rengo::parser::span::Span;
+/// let tokens = tokenize_with_spans("let x", &TokenizeOptions::default()).unwrap();
+/// assert_eq!(tokens[1].1, Span { start: 4, end: 5, line: 1, col: 5 });
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Span {
+    /// The byte offset of the first character of the token.
+    pub start: usize,
+
+    /// The byte offset right after the last character of the token.
+    pub end: usize,
+
+    /// The line of the first character of the token.
+    pub line: usize,
+
+    /// The column of the first character of the token.
+    pub col: usize,
+}
+
+impl Span {
+    /// Returns the text the span covers in `source`.
+    pub fn text(self, source: &str) -> &str {
+        &source[self.start..self.end]
+    }
+
+    /// Returns the span of the same token when `prefix` is inserted before the source it was read
+    /// from, e.g. to locate a token of the program in the whole file.
+    ///
+    /// ## Example:
+    /// ```rust
+    /// # use rengo::parser::span::Span;
+    /// let span = Span { start: 0, end: 3, line: 1, col: 1 };
+    /// assert_eq!(span.after("# doc\n  "), Span { start: 8, end: 11, line: 2, col: 3 });
+    /// ```
+    pub fn after(self, prefix: &str) -> Span {
+        let (lines, last_line) = match prefix.rsplit_once('\n') {
+            Some((before, last_line)) => (before.matches('\n').count() + 1, last_line),
+            None => (0, prefix),
+        };
+        Span {
+            start: self.start + prefix.len(),
+            end: self.end + prefix.len(),
+            line: self.line + lines,
+            // Only the first line of the source is moved to the right by the prefix
+            col: if self.line == 1 { self.col + last_line.chars().count() } else { self.col },
+        }
+    }
+}
+
+/// The metadata of AST nodes that may tell where in the source a node was read from, so that the
+/// code generated for it can be mapped back there.
+pub trait SourceSpan {
+    /// Returns the span of the node, or `None` if it is not known.
+    fn span(&self) -> Option<Span>;
+}
+
+impl SourceSpan for () {
+    fn span(&self) -> Option<Span> {
+        None
+    }
+}
+
+impl SourceSpan for Span {
+    fn span(&self) -> Option<Span> {
+        Some(*self)
+    }
+}
+
+impl SourceSpan for Option<Span> {
+    fn span(&self) -> Option<Span> {
+        *self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expectest::prelude::*;
+    use super::*;
+
+    mod after {
+        use super::*;
+
+        #[test]
+        fn moves_the_first_line_right() {
+            let span = Span { start: 2, end: 3, line: 1, col: 3 };
+            expect!(span.after("ab")).to(be_equal_to(Span { start: 4, end: 5, line: 1, col: 5 }));
+        }
+
+        #[test]
+        fn keeps_the_column_of_later_lines() {
+            let span = Span { start: 6, end: 7, line: 2, col: 2 };
+            expect!(span.after("# one\n# two\n")).to(be_equal_to(Span { start: 18, end: 19, line: 4, col: 2 }));
+        }
+
+        #[test]
+        fn keeps_the_span_without_a_prefix() {
+            let span = Span { start: 6, end: 7, line: 2, col: 2 };
+            expect!(span.after("")).to(be_equal_to(span));
+        }
+    }
+
+    mod text {
+        use super::*;
+
+        #[test]
+        fn slices_the_source() {
+            expect!(Span { start: 4, end: 7, line: 1, col: 5 }.text("let abc = 1")).to(be_equal_to("abc"));
+        }
+    }
+}
@@ -1,11 +1,13 @@
+use alloc::string::String;
+
 /// Represents the different types of tokens in an arithmetic expression.
 ///
 /// The `Token` enum is utilized during the lexical analysis (tokenization) phase of parsing
 /// arithmetic expressions. Each variant corresponds to a fundamental component of the expression,
 /// such as numbers, operators, parentheses, and identifiers. These tokens form the basic building
 /// blocks that the parser will use to construct the abstract syntax tree (AST).
-#[derive(Debug, PartialEq)]
-pub(crate) enum Token {
+#[derive(Debug, PartialEq, Clone)]
+pub enum Token {
     /// A numeric literal.
     ///
     /// Represents a constant integer value in the expression. This variant holds a 64-bit integer
@@ -14,6 +16,7 @@ pub(crate) enum Token {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::parser::token::Token;
     /// let token = Token::Number(42);
     /// ```
     Number(i64),
@@ -27,6 +30,7 @@ pub(crate) enum Token {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::parser::token::Token;
     /// let token = Token::Increment;
     /// ```
     Increment,
@@ -40,6 +44,7 @@ pub(crate) enum Token {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::parser::token::Token;
     /// let token = Token::Decrement;
     /// ```
     Decrement,
@@ -53,6 +58,7 @@ pub(crate) enum Token {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::parser::token::Token;
     /// let token = Token::LParen;
     /// ```
     LParen,
@@ -66,10 +72,50 @@ pub(crate) enum Token {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::parser::token::Token;
     /// let token = Token::RParen;
     /// ```
     RParen,
 
+    /// The left bracket (`[`).
+    ///
+    /// Opens an array literal, like `[1, 2, 3]`, or the index that follows an array, like the one in
+    /// `array[0]`. This token is produced by the tokenizer when it encounters the `[` character.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::LBracket;
+    /// ```
+    LBracket,
+
+    /// The right bracket (`]`).
+    ///
+    /// Closes an array literal or an index. This token is produced by the tokenizer when it
+    /// encounters the `]` character.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::RBracket;
+    /// ```
+    RBracket,
+
+    /// The comma (`,`).
+    ///
+    /// Represents the separator between the arguments of a call, like `min(a, b)`. This token is
+    /// produced by the tokenizer when it encounters the `,` character.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::Comma;
+    /// ```
+    Comma,
+
     /// An identifier (variable name).
     ///
     /// Represents a variable name in the expression. This token is produced by the tokenizer when it
@@ -78,10 +124,24 @@ pub(crate) enum Token {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::parser::token::Token;
     /// let token = Token::Identifier("x".to_string());
     /// ```
     Identifier(String),
 
+    /// A string literal (`"..."`).
+    ///
+    /// Holds the characters between the quotes, as written: there are no escape sequences, so a
+    /// string cannot contain a `"`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::Str("hello".to_string());
+    /// ```
+    Str(String),
+
     /// The `let` keyword.
     ///
     /// Represents the `let` keyword used for variable bindings in the expression. This token is
@@ -90,10 +150,108 @@ pub(crate) enum Token {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::parser::token::Token;
     /// let token = Token::Let;
     /// ```
     Let,
 
+    /// The `assert` keyword.
+    ///
+    /// Introduces an assertion, `assert(condition)`, that traps when the condition is zero. This
+    /// token is produced by the tokenizer when it encounters the `assert` keyword.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::Assert;
+    /// ```
+    Assert,
+
+    /// The `return` keyword.
+    ///
+    /// Introduces an early exit, `return <expression>`, that ends the program with the value of
+    /// the expression. This token is produced by the tokenizer when it encounters the `return`
+    /// keyword.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::Return;
+    /// ```
+    Return,
+
+    /// The `if` keyword.
+    ///
+    /// Introduces a conditional, `if <condition> then <statement> else <statement>`. This token is
+    /// produced by the tokenizer when it encounters the `if` keyword.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::If;
+    /// ```
+    If,
+
+    /// The `then` keyword, which separates the condition of an `if` from its first branch.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::Then;
+    /// ```
+    Then,
+
+    /// The `else` keyword, which separates the two branches of an `if`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::Else;
+    /// ```
+    Else,
+
+    /// The `print` keyword, which introduces a call to the built-in that prints a string.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::Print;
+    /// ```
+    Print,
+
+    /// The `fn` keyword.
+    ///
+    /// Introduces a function definition, `fn <name>(<parameters>) = <body>; <expression>`. This
+    /// token is produced by the tokenizer when it encounters the `fn` keyword.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::Fn;
+    /// ```
+    Fn,
+
+    /// The `mut` keyword.
+    ///
+    /// Follows `let` to make the binding mutable, `let mut <name> = <value>; <expression>`, so the
+    /// body can assign to it. This token is produced by the tokenizer when it encounters the `mut`
+    /// keyword.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::Mut;
+    /// ```
+    Mut,
+
     /// The assignment operator (`=`).
     ///
     /// Represents the assignment operation in the expression, which assigns a value to a variable.
@@ -102,6 +260,7 @@ pub(crate) enum Token {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::parser::token::Token;
     /// let token = Token::Assign;
     /// ```
     Assign,
@@ -114,7 +273,223 @@ pub(crate) enum Token {
     /// # Example
     ///
     /// ```rust
+    /// # use rengo::parser::token::Token;
     /// let token = Token::LineEnd;
     /// ```
     LineEnd,
+
+    /// The bitwise AND operator (`&`).
+    ///
+    /// Represents the bitwise conjunction of two expressions. This token is produced by the
+    /// tokenizer when it encounters the `&` character.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::BitAnd;
+    /// ```
+    BitAnd,
+
+    /// The bitwise OR operator (`|`).
+    ///
+    /// Represents the bitwise disjunction of two expressions. This token is produced by the
+    /// tokenizer when it encounters the `|` character.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::BitOr;
+    /// ```
+    BitOr,
+
+    /// The bitwise XOR operator (`^`).
+    ///
+    /// Represents the bitwise exclusive disjunction of two expressions. This token is produced by
+    /// the tokenizer when it encounters the `^` character.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::BitXor;
+    /// ```
+    BitXor,
+
+    /// The left shift operator (`<<`).
+    ///
+    /// Represents shifting the bits of an expression to the left. This token is produced by the
+    /// tokenizer when it encounters the `<<` sequence of characters.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::ShiftLeft;
+    /// ```
+    ShiftLeft,
+
+    /// The right shift operator (`>>`).
+    ///
+    /// Represents an arithmetic (sign-preserving) shift of the bits of an expression to the right.
+    /// This token is produced by the tokenizer when it encounters the `>>` sequence of characters.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::ShiftRight;
+    /// ```
+    ShiftRight,
+
+    /// The less-than operator (`<`).
+    ///
+    /// Represents comparing two expressions. This token is produced by the tokenizer when it
+    /// encounters a `<` that is not part of a `<<`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::Less;
+    /// ```
+    Less,
+
+    /// The greater-than operator (`>`).
+    ///
+    /// Represents comparing two expressions. This token is produced by the tokenizer when it
+    /// encounters a `>` that is not part of a `>>`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::Greater;
+    /// ```
+    Greater,
+
+    /// The equality operator (`==`).
+    ///
+    /// Represents comparing two expressions for equality. This token is produced by the tokenizer
+    /// when it encounters `==`, which is never read as two `=`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::Eq;
+    /// ```
+    Eq,
+
+    /// The addition operator (`+`).
+    ///
+    /// Represents adding two expressions. This token is produced by the tokenizer when it
+    /// encounters a `+` that is not part of a `++`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::Plus;
+    /// ```
+    Plus,
+
+    /// The subtraction operator (`-`).
+    ///
+    /// Represents subtracting an expression from another. This token is produced by the tokenizer
+    /// when it encounters a `-` that is neither part of a `--` nor the sign of a number literal.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::Minus;
+    /// ```
+    Minus,
+
+    /// The end of the input.
+    ///
+    /// A sentinel that `tokenize_with_eof` appends after the last token, so the parser can report
+    /// the end of the input like any other unexpected token. Plain `tokenize` never produces it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// let token = Token::Eof;
+    /// ```
+    Eof,
+}
+
+impl Token {
+    /// Returns the name of the variant of the token, leaving out the value it holds.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rengo::parser::token::Token;
+    /// assert_eq!(Token::Number(42).name(), "Number");
+    /// ```
+    pub fn name(&self) -> &'static str {
+        match self {
+            Token::Number(_) => "Number",
+            Token::Increment => "Increment",
+            Token::Decrement => "Decrement",
+            Token::LParen => "LParen",
+            Token::RParen => "RParen",
+            Token::LBracket => "LBracket",
+            Token::RBracket => "RBracket",
+            Token::Comma => "Comma",
+            Token::Identifier(_) => "Identifier",
+            Token::Str(_) => "Str",
+            Token::Let => "Let",
+            Token::Assert => "Assert",
+            Token::Return => "Return",
+            Token::If => "If",
+            Token::Then => "Then",
+            Token::Else => "Else",
+            Token::Print => "Print",
+            Token::Fn => "Fn",
+            Token::Mut => "Mut",
+            Token::Assign => "Assign",
+            Token::LineEnd => "LineEnd",
+            Token::BitAnd => "BitAnd",
+            Token::BitOr => "BitOr",
+            Token::BitXor => "BitXor",
+            Token::ShiftLeft => "ShiftLeft",
+            Token::ShiftRight => "ShiftRight",
+            Token::Less => "Less",
+            Token::Greater => "Greater",
+            Token::Eq => "Eq",
+            Token::Plus => "Plus",
+            Token::Minus => "Minus",
+            Token::Eof => "Eof",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expectest::prelude::*;
+    use crate::parser::tokenize::{KEYWORDS, OPERATORS};
+    use super::*;
+
+    mod name {
+        use super::*;
+
+        #[test]
+        fn leaves_out_the_value() {
+            expect!(Token::Number(42).name()).to(be_equal_to("Number"));
+            expect!(Token::Identifier(String::from("x")).name()).to(be_equal_to("Identifier"));
+            expect!(Token::Str(String::from("hi")).name()).to(be_equal_to("Str"));
+        }
+
+        #[test]
+        fn matches_the_debug_name_of_every_spelled_token() {
+            for (_, token) in OPERATORS.iter().chain(KEYWORDS) {
+                expect!(alloc::format!("{:?}", token)).to(be_equal_to(String::from(token.name())));
+            }
+        }
+    }
 }
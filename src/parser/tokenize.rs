@@ -1,5 +1,18 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::parser::error::TokenizeError;
+use crate::parser::span::Span;
 use crate::parser::token::Token;
 
+/// Options that change which inputs `tokenize_with_options` accepts.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TokenizeOptions {
+    /// Reject two numbers with no operator between them, like `5 5`, instead of leaving the
+    /// parser to make sense of them.
+    pub strict: bool,
+}
+
 /// Tokenizes an input string into a vector of tokens, which can be used for further parsing.
 ///
 /// ## Usage:
@@ -9,6 +22,8 @@ use crate::parser::token::Token;
 ///
 /// ### Example 1: Tokenizing a simple number
 /// ```rust
+/// # use rengo::parser::tokenize::tokenize;
+/// # use rengo::parser::token::Token;
 /// let input = "123";
 /// let result = tokenize(input);
 /// assert_eq!(result, Ok(vec![Token::Number(123)]));
@@ -16,6 +31,8 @@ use crate::parser::token::Token;
 ///
 /// ### Example 2: Tokenizing an increment operation
 /// ```rust
+/// # use rengo::parser::tokenize::tokenize;
+/// # use rengo::parser::token::Token;
 /// let input = "++";
 /// let result = tokenize(input);
 /// assert_eq!(result, Ok(vec![Token::Increment]));
@@ -23,61 +40,186 @@ use crate::parser::token::Token;
 ///
 /// ### Example 3: Handling invalid input
 /// ```rust
-/// let input = "+";
+/// # use rengo::parser::tokenize::tokenize;
+/// let input = "@";
 /// let result = tokenize(input);
 /// assert!(result.is_err());
 /// ```
 ///
+/// A leading UTF-8 byte order mark, as written by some editors, is skipped.
+///
 /// ## Parameters:
 /// - `input`: A string slice representing the input to be tokenized.
 ///
 /// ## Returns:
-/// A `Result` containing a vector of `Token`s if tokenization is successful, or a `String` error
-/// message if an invalid character or token is encountered.
+/// A `Result` containing a vector of `Token`s if tokenization is successful, or a `TokenizeError`
+/// if an invalid character or token is encountered.
 ///
 /// ## Errors:
 /// - Returns an error if the input contains invalid characters.
-/// - Returns an error if the input contains a standalone '+' or '-' instead of '++' or '--'.
-pub(crate) fn tokenize(input: &str) -> Result<Vec<Token>, String> {
-    let mut tokens = Vec::new();
-    let mut chars = input.chars().peekable();
-
-    while let Some(&c) = chars.peek() {
-        match c {
-            '0'..='9' => tokens.push(parse_number(&mut chars)?),
-            '-' => tokens.push(parse_minus(&mut chars)?),
-            '+' => tokens.push(parse_plus(&mut chars)?),
-            '(' => {
-                tokens.push(Token::LParen);
-                chars.next();
-            }
-            ')' => {
-                tokens.push(Token::RParen);
-                chars.next();
+/// - Returns an error if the input ends with a `+` that is not part of `++`.
+pub fn tokenize(input: &str) -> Result<Vec<Token>, TokenizeError> {
+    tokenize_with_options(input, &TokenizeOptions::default())
+}
+
+/// Tokenizes an input string like `tokenize`, with the checks selected by `options`.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parser::tokenize::TokenizeOptions;
+/// # use rengo::parser::tokenize::tokenize_with_options;
+/// let strict = TokenizeOptions { strict: true };
+/// assert!(tokenize_with_options("5 5", &strict).is_err());
+/// assert!(tokenize_with_options("5 5", &TokenizeOptions::default()).is_ok());
+/// ```
+pub fn tokenize_with_options(input: &str, options: &TokenizeOptions) -> Result<Vec<Token>, TokenizeError> {
+    let tokens = tokenize_with_spans(input, options)?;
+    Ok(tokens.into_iter().map(|(token, _)| token).collect())
+}
+
+/// Tokenizes an input string like `tokenize_with_options`, pairing each token with the `Span` it
+/// was read from.
+///
+/// Offsets are relative to `input`, byte order mark included, while columns do not count the byte
+/// order mark.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parser::tokenize::tokenize_with_spans;
+/// # use rengo::parser::tokenize::TokenizeOptions;
+/// # use rengo::parser::token::Token;
+/// # use rengo::parser::span::Span;
+/// let tokens = tokenize_with_spans("x++", &TokenizeOptions::default()).unwrap();
+/// assert_eq!(tokens, vec![
+///     (Token::Identifier("x".to_string()), Span { start: 0, end: 1, line: 1, col: 1 }),
+///     (Token::Increment, Span { start: 1, end: 3, line: 1, col: 2 }),
+/// ]);
+/// ```
+pub fn tokenize_with_spans(input: &str, options: &TokenizeOptions) -> Result<Vec<(Token, Span)>, TokenizeError> {
+    Lexer::new(input, options).collect()
+}
+
+/// Reads the tokens of an input one at a time, with the `Span` of each, as `tokenize_with_spans`
+/// returns them.
+///
+/// A lexer can also start in the middle of the input, right after a token read before, which is
+/// how `retokenize` reads again only the part of the input that an edit changed.
+pub(crate) struct Lexer<'a> {
+    /// The input without its byte order mark, if any.
+    text: &'a str,
+    /// The length of the byte order mark, which spans are shifted by.
+    bom: usize,
+    chars: core::str::Chars<'a>,
+    /// The position of `scanned`, the end of the text that lines and columns were counted up to.
+    scanned: usize,
+    line: usize,
+    col: usize,
+    /// The last token read, which tells a negative number from a subtraction.
+    previous: Option<Token>,
+    options: &'a TokenizeOptions,
+}
+
+impl<'a> Lexer<'a> {
+    /// Creates a lexer that reads `input` from the start.
+    pub(crate) fn new(input: &'a str, options: &'a TokenizeOptions) -> Self {
+        let text = input.strip_prefix('\u{FEFF}').unwrap_or(input);
+        Lexer {
+            text,
+            bom: input.len() - text.len(),
+            chars: text.chars(),
+            scanned: 0,
+            line: 1,
+            col: 1,
+            previous: None,
+            options,
+        }
+    }
+
+    /// Creates a lexer that reads `input` from the end of `previous`, a token read from the same
+    /// text before it.
+    pub(crate) fn after(input: &'a str, previous: &(Token, Span), options: &'a TokenizeOptions) -> Self {
+        let mut lexer = Lexer::new(input, options);
+        let (token, span) = previous;
+        lexer.chars = lexer.text[span.end - lexer.bom..].chars();
+        (lexer.scanned, lexer.line, lexer.col) = (span.start - lexer.bom, span.line, span.col);
+        lexer.previous = Some(token.clone());
+        lexer
+    }
+
+    /// Reads the token at the start of `chars`, which is not whitespace.
+    fn read(&mut self, c: char) -> Result<Token, TokenizeError> {
+        Ok(match c {
+            '0'..='9' => {
+                let number = parse_number(&mut self.chars).map_err(TokenizeError::InvalidNumber)?;
+                if let (true, Some(Token::Number(previous)), Token::Number(value)) =
+                    (self.options.strict, &self.previous, &number)
+                {
+                    return Err(TokenizeError::MissingOperator(*previous, *value));
+                }
+                number
             }
-            '=' => {
-                tokens.push(Token::Assign);
-                chars.next();
+            '-' if starts_negative_number(&self.chars, self.previous.as_ref()) => {
+                parse_negative_number(&mut self.chars).map_err(TokenizeError::InvalidNumber)?
             }
-            ';' => {
-                tokens.push(Token::LineEnd);
-                chars.next();
+            c if c.is_alphabetic() || c == '_' => parse_identifier_or_keyword(&mut self.chars),
+            '"' => parse_string(&mut self.chars)?,
+            _ => match parse_operator(&mut self.chars) {
+                Some(token) => token,
+                None => return Err(TokenizeError::InvalidCharacter(c)),
+            },
+        })
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<(Token, Span), TokenizeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while peek(&self.chars).is_some_and(char::is_whitespace) {
+            self.chars.next();
+        }
+        let Some(c) = peek(&self.chars) else {
+            // A `+` needs an operand after it, unlike `++`
+            return (self.previous.take() == Some(Token::Plus)).then_some(Err(TokenizeError::DanglingPlus));
+        };
+        let start = offset(self.text, &self.chars);
+        let token = match self.read(c) {
+            Ok(token) => token,
+            Err(error) => {
+                self.chars = "".chars();  // Nothing is read after an error
+                return Some(Err(error));
             }
-            c if c.is_whitespace() => {
-                chars.next(); // Skip whitespace
+        };
+        for c in self.text[self.scanned..start].chars() {
+            if c == '\n' {
+                (self.line, self.col) = (self.line + 1, 1);
+            } else {
+                self.col += 1;
             }
-            c if c.is_alphabetic() => tokens.push(parse_identifier_or_keyword(&mut chars)),
-            _ => return Err(format!("Invalid character: {}", c)),
         }
+        self.scanned = start;
+        let end = offset(self.text, &self.chars);
+        self.previous = Some(token.clone());
+        let span = Span { start: self.bom + start, end: self.bom + end, line: self.line, col: self.col };
+        Some(Ok((token, span)))
     }
+}
 
-    Ok(tokens)
+/// Returns the byte offset in `text` of the next character `chars` yields, `chars` being read from
+/// `text`.
+fn offset(text: &str, chars: &core::str::Chars) -> usize {
+    text.len() - chars.as_str().len()
+}
+
+/// Returns the next character `chars` yields without consuming it.
+fn peek(chars: &core::str::Chars) -> Option<char> {
+    chars.clone().next()
 }
 
-fn parse_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Token, String> {
+fn parse_number(chars: &mut core::str::Chars) -> Result<Token, String> {
     let mut num = String::new();
-    while let Some(&c) = chars.peek() {
-        if c.is_digit(10) {
+    while let Some(c) = peek(chars) {
+        if c.is_ascii_digit() {
             num.push(c);
             chars.next();
         } else {
@@ -86,7 +228,7 @@ fn parse_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Toke
     }
 
     // After parsing the number, check if the next character is alphabetic.
-    if let Some(&next_char) = chars.peek() {
+    if let Some(next_char) = peek(chars) {
         if next_char.is_alphabetic() {
             return Err(format!(
                 "Invalid sequence: Number '{}' followed by identifier starting with '{}'",
@@ -95,44 +237,103 @@ fn parse_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Toke
         }
     }
 
-    Ok(Token::Number(num.parse().unwrap()))
+    number_token(&num)
 }
 
-fn parse_minus(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Token, String> {
-    chars.next(); // Consume the first '-'
-    if chars.peek() == Some(&'-') {
-        chars.next(); // Consume the second '-'
-        Ok(Token::Decrement)
-    } else if let Some('0'..='9') = chars.peek() {
-        // Handle negative number
-        let mut num = String::from("-");
-        while let Some(&c) = chars.peek() {
-            if c.is_digit(10) {
-                num.push(c);
-                chars.next();
-            } else {
-                break;
-            }
-        }
-        Ok(Token::Number(num.parse().unwrap()))
-    } else {
-        Err("Invalid token: Expected '--' or a number".to_string())
+/// Returns the `Token::Number` spelled by `digits`, or an error message if it does not fit in an
+/// `i64`.
+fn number_token(digits: &str) -> Result<Token, String> {
+    digits
+        .parse()
+        .map(Token::Number)
+        .map_err(|_| format!("Number literal out of range: {}", digits))
+}
+
+/// Tokenizes an input string like `tokenize`, terminating the tokens with a `Token::Eof`.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::parser::tokenize::tokenize_with_eof;
+/// # use rengo::parser::token::Token;
+/// let result = tokenize_with_eof("123");
+/// assert_eq!(result, Ok(vec![Token::Number(123), Token::Eof]));
+/// ```
+pub fn tokenize_with_eof(input: &str) -> Result<Vec<Token>, TokenizeError> {
+    let mut tokens = tokenize(input)?;
+    tokens.push(Token::Eof);
+    Ok(tokens)
+}
+
+/// Every operator and punctuation token, with the longest spellings first so that the first entry
+/// matching the input is the longest one (maximal munch). `+++` is thus read as `++` then `+`.
+pub(crate) const OPERATORS: &[(&str, Token)] = &[
+    ("++", Token::Increment),
+    ("--", Token::Decrement),
+    ("<<", Token::ShiftLeft),
+    (">>", Token::ShiftRight),
+    ("==", Token::Eq),
+    ("<", Token::Less),
+    (">", Token::Greater),
+    ("+", Token::Plus),
+    ("-", Token::Minus),
+    ("&", Token::BitAnd),
+    ("|", Token::BitOr),
+    ("^", Token::BitXor),
+    ("(", Token::LParen),
+    (")", Token::RParen),
+    ("[", Token::LBracket),
+    ("]", Token::RBracket),
+    (",", Token::Comma),
+    ("=", Token::Assign),
+    (";", Token::LineEnd),
+];
+
+/// The length, in characters, of the longest spelling in `OPERATORS`.
+const MAX_OPERATOR_LENGTH: usize = 2;
+
+/// Consumes the longest operator at the start of the input, or returns `None` without consuming
+/// anything if the input does not start with an operator.
+fn parse_operator(chars: &mut core::str::Chars) -> Option<Token> {
+    let lookahead: String = chars.clone().take(MAX_OPERATOR_LENGTH).collect();
+    let (spelling, token) = OPERATORS
+        .iter()
+        .find(|(spelling, _)| lookahead.starts_with(spelling))?;
+    for _ in 0..spelling.chars().count() {
+        chars.next();
     }
+    Some(token.clone())
+}
+
+/// Returns `true` if the `-` at the start of the input is the sign of a number literal rather
+/// than the subtraction operator.
+///
+/// That is the case when it is directly followed by a digit and the previous token cannot end an
+/// operand, so `-1` and `1 << -1` hold negative literals while `3-1` and `x++ -1` are subtractions.
+fn starts_negative_number(chars: &core::str::Chars, previous: Option<&Token>) -> bool {
+    let follows_operand = matches!(
+        previous,
+        Some(Token::Number(_) | Token::Identifier(_) | Token::RParen | Token::RBracket | Token::Increment | Token::Decrement)
+    );
+    !follows_operand && matches!(chars.clone().nth(1), Some('0'..='9'))
 }
 
-fn parse_plus(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Token, String> {
-    chars.next(); // Consume the first '+'
-    if chars.peek() == Some(&'+') {
-        chars.next(); // Consume the second '+'
-        Ok(Token::Increment)
-    } else {
-        Err("Invalid token: Expected '++'".to_string())
+fn parse_negative_number(chars: &mut core::str::Chars) -> Result<Token, String> {
+    chars.next(); // Consume the '-'
+    let mut num = String::from("-");
+    while let Some(c) = peek(chars) {
+        if c.is_ascii_digit() {
+            num.push(c);
+            chars.next();
+        } else {
+            break;
+        }
     }
+    number_token(&num)
 }
 
-fn parse_identifier_or_keyword(chars: &mut std::iter::Peekable<std::str::Chars>) -> Token {
+fn parse_identifier_or_keyword(chars: &mut core::str::Chars) -> Token {
     let mut identifier = String::new();
-    while let Some(&c) = chars.peek() {
+    while let Some(c) = peek(chars) {
         if c.is_alphanumeric() || c == '_' {
             identifier.push(c);
             chars.next();
@@ -140,14 +341,62 @@ fn parse_identifier_or_keyword(chars: &mut std::iter::Peekable<std::str::Chars>)
             break;
         }
     }
-    match identifier.as_str() {
-        "let" => Token::Let,
-        _ => Token::Identifier(identifier),
+    keyword_of(&identifier).unwrap_or(Token::Identifier(identifier))
+}
+
+/// Parses a string literal, from its opening quote to its closing one.
+///
+/// The characters in between are taken as they are, since there are no escape sequences.
+///
+/// ## Returns:
+/// A `Token::Str` with the contents of the string, or `TokenizeError::UnterminatedString` if the
+/// input ends before the closing quote.
+fn parse_string(chars: &mut core::str::Chars) -> Result<Token, TokenizeError> {
+    chars.next(); // The opening quote
+    let mut contents = String::new();
+    for c in chars.by_ref() {
+        if c == '"' {
+            return Ok(Token::Str(contents));
+        }
+        contents.push(c);
     }
+    Err(TokenizeError::UnterminatedString(contents))
+}
+
+/// Every reserved word with its token. This is the single list of keywords: adding one to the
+/// language only takes a new entry here.
+pub(crate) const KEYWORDS: &[(&str, Token)] = &[
+    ("let", Token::Let),
+    ("assert", Token::Assert),
+    ("return", Token::Return),
+    ("if", Token::If),
+    ("then", Token::Then),
+    ("else", Token::Else),
+    ("print", Token::Print),
+    ("fn", Token::Fn),
+    ("mut", Token::Mut),
+];
+
+/// Returns the keyword token spelled by `word`, or `None` if `word` is an ordinary identifier.
+fn keyword_of(word: &str) -> Option<Token> {
+    KEYWORDS
+        .iter()
+        .find(|(spelling, _)| *spelling == word)
+        .map(|(_, token)| token.clone())
+}
+
+/// Returns the spelling of a keyword token, or `None` if the token is not a keyword.
+pub(crate) fn keyword_spelling(token: &Token) -> Option<&'static str> {
+    KEYWORDS
+        .iter()
+        .find(|(_, keyword)| keyword == token)
+        .map(|(spelling, _)| *spelling)
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::string::ToString;
+    use alloc::vec;
     use expectest::prelude::*;
     use proptest::prelude::*;
     use super::*;
@@ -159,52 +408,101 @@ mod tests {
             #[test]
             fn parses_any_number(n in 0i64..1000) {
                 let input = n.to_string();
-                let result = parse_number(&mut input.chars().peekable()).unwrap();
+                let result = parse_number(&mut input.chars()).unwrap();
                 prop_assert_eq!(result, Token::Number(n));
             }
         );
+
+        #[test]
+        fn rejects_numbers_out_of_range() {
+            let result = parse_number(&mut "9223372036854775808".chars());
+            expect!(result).to(be_err().value("Number literal out of range: 9223372036854775808".to_string()));
+            expect!(tokenize("1 + -99999999999999999999")).to(be_err());
+        }
     }
 
-    mod parse_minus {
+    mod parse_string {
         use super::*;
 
         #[test]
-        fn parses_decrement() {
-            let input = "--";
-            let result = parse_minus(&mut input.chars().peekable()).unwrap();
-            expect!(result).to(be_equal_to(Token::Decrement));
+        fn reads_the_contents_between_the_quotes() {
+            let mut chars = "\"hi there\" + 1".chars();
+            expect!(parse_string(&mut chars)).to(be_ok().value(Token::Str("hi there".to_string())));
+            expect!(chars.collect::<String>()).to(be_equal_to(" + 1".to_string()));
+        }
+
+        #[test]
+        fn keeps_backslashes_as_they_are() {
+            expect!(parse_string(&mut "\"a\\n\"".chars())).to(be_ok().value(Token::Str("a\\n".to_string())));
         }
 
+        #[test]
+        fn fails_without_a_closing_quote() {
+            expect!(parse_string(&mut "\"hi".chars()))
+                .to(be_err().value(TokenizeError::UnterminatedString("hi".to_string())));
+        }
+    }
+
+    mod parse_negative_number {
+        use super::*;
+
         #[test]
         fn parses_negative_number() {
             let input = "-123";
-            let result = parse_minus(&mut input.chars().peekable()).unwrap();
-            expect!(result).to(be_equal_to(Token::Number(-123)));
+            let result = parse_negative_number(&mut input.chars());
+            expect!(result).to(be_ok().value(Token::Number(-123)));
         }
 
         #[test]
-        fn fails_on_invalid_token() {
-            let input = "-+";
-            let result = parse_minus(&mut input.chars().peekable());
-            expect!(result).to(be_err());
+        fn parses_the_smallest_number() {
+            let result = parse_negative_number(&mut "-9223372036854775808".chars());
+            expect!(result).to(be_ok().value(Token::Number(i64::MIN)));
         }
     }
 
-    mod parse_plus {
+    mod parse_operator {
         use super::*;
 
+        fn parse_all(input: &str) -> Vec<Option<Token>> {
+            let mut chars = input.chars();
+            let mut tokens = Vec::new();
+            while peek(&chars).is_some() {
+                let token = parse_operator(&mut chars);
+                let done = token.is_none();
+                tokens.push(token);
+                if done {
+                    break;
+                }
+            }
+            tokens
+        }
+
         #[test]
-        fn parses_increment() {
-            let input = "++";
-            let result = parse_plus(&mut input.chars().peekable()).unwrap();
-            expect!(result).to(be_equal_to(Token::Increment));
+        fn parses_every_operator() {
+            for (spelling, token) in OPERATORS {
+                expect!(parse_all(spelling)).to(be_equal_to(vec![Some(token.clone())]));
+            }
         }
 
         #[test]
-        fn fails_on_invalid_token() {
-            let input = "+-";
-            let result = parse_plus(&mut input.chars().peekable());
-            expect!(result).to(be_err());
+        fn prefers_the_longest_operator() {
+            expect!(parse_all("++")).to(be_equal_to(vec![Some(Token::Increment)]));
+            expect!(parse_all("+++")).to(be_equal_to(vec![Some(Token::Increment), Some(Token::Plus)]));
+            expect!(parse_all("<<<<")).to(be_equal_to(vec![Some(Token::ShiftLeft), Some(Token::ShiftLeft)]));
+        }
+
+        #[test]
+        fn consumes_nothing_on_invalid_operator() {
+            let mut chars = "@-".chars();
+            expect!(parse_operator(&mut chars)).to(be_none());
+            expect!(chars.next()).to(be_some().value('@'));
+        }
+
+        #[test]
+        fn longest_spellings_come_first() {
+            let lengths = OPERATORS.iter().map(|(spelling, _)| spelling.len()).collect::<Vec<usize>>();
+            expect!(lengths.windows(2).all(|pair| pair[0] >= pair[1])).to(be_true());
+            expect!(lengths[0]).to(be_equal_to(MAX_OPERATOR_LENGTH));
         }
     }
 
@@ -214,21 +512,83 @@ mod tests {
         #[test]
         fn parses_let_keyword() {
             let input = "let";
-            let result = parse_identifier_or_keyword(&mut input.chars().peekable());
+            let result = parse_identifier_or_keyword(&mut input.chars());
             expect!(result).to(be_equal_to(Token::Let));
         }
 
         #[test]
         fn parses_identifier() {
             let input = "foo";
-            let result = parse_identifier_or_keyword(&mut input.chars().peekable());
+            let result = parse_identifier_or_keyword(&mut input.chars());
             expect!(result).to(be_equal_to(Token::Identifier("foo".to_string())));
         }
     }
 
+    mod keyword_of {
+        use super::*;
+
+        #[test]
+        fn recognizes_every_keyword() {
+            expect!(keyword_of("let")).to(be_some().value(Token::Let));
+            for (spelling, token) in KEYWORDS {
+                expect!(keyword_of(spelling)).to(be_some().value(token.clone()));
+                expect!(keyword_spelling(token)).to(be_some().value(*spelling));
+            }
+        }
+
+        #[test]
+        fn rejects_identifiers() {
+            expect!(keyword_of("lettuce")).to(be_none());
+            expect!(keyword_of("Let")).to(be_none());
+            expect!(keyword_of("x")).to(be_none());
+            expect!(keyword_spelling(&Token::Identifier("let".to_string()))).to(be_none());
+        }
+    }
+
     mod tokenize {
         use super::*;
 
+        #[test]
+        fn tokenizes_strings() {
+            expect!(tokenize("\"hi\"")).to(be_ok().value(vec![Token::Str("hi".to_string())]));
+            expect!(tokenize("(\"\", \"a b;c\")")).to(be_ok().value(vec![
+                Token::LParen,
+                Token::Str("".to_string()),
+                Token::Comma,
+                Token::Str("a b;c".to_string()),
+                Token::RParen,
+            ]));
+        }
+
+        #[test]
+        fn tokenizes_brackets() {
+            expect!(tokenize("[1, 2][0] -1")).to(be_ok().value(vec![
+                Token::LBracket,
+                Token::Number(1),
+                Token::Comma,
+                Token::Number(2),
+                Token::RBracket,
+                Token::LBracket,
+                Token::Number(0),
+                Token::RBracket,
+                Token::Minus,  // A subtraction, as `]` ends an operand
+                Token::Number(1),
+            ]));
+        }
+
+        #[test]
+        fn fails_on_unterminated_strings() {
+            expect!(tokenize("\"hi")).to(be_err().value(TokenizeError::UnterminatedString("hi".to_string())));
+        }
+
+        #[test]
+        fn tokenizes_identifiers_starting_with_an_underscore() {
+            expect!(tokenize("_ _tmp")).to(be_ok().value(vec![
+                Token::Identifier("_".to_string()),
+                Token::Identifier("_tmp".to_string()),
+            ]));
+        }
+
         #[test]
         fn tokenizes_numbers() {
             let input = "123";
@@ -264,6 +624,18 @@ mod tests {
             expect!(result).to(be_ok().value(vec![Token::Assign]));
         }
 
+        #[test]
+        fn tokenizes_equality_with_the_longest_match() {
+            expect!(tokenize("==")).to(be_ok().value(vec![Token::Eq]));
+            expect!(tokenize("===")).to(be_ok().value(vec![Token::Eq, Token::Assign]));
+            expect!(tokenize("= =")).to(be_ok().value(vec![Token::Assign, Token::Assign]));
+            expect!(tokenize("x == 1")).to(be_ok().value(vec![
+                Token::Identifier("x".to_string()),
+                Token::Eq,
+                Token::Number(1),
+            ]));
+        }
+
         #[test]
         fn tokenizes_line_end() {
             let input = ";";
@@ -271,6 +643,19 @@ mod tests {
             expect!(result).to(be_ok().value(vec![Token::LineEnd]));
         }
 
+        #[test]
+        fn tokenizes_bitwise_operators() {
+            let input = "& | ^ << >>";
+            let result = tokenize(input);
+            expect!(result).to(be_ok().value(vec![
+                Token::BitAnd,
+                Token::BitOr,
+                Token::BitXor,
+                Token::ShiftLeft,
+                Token::ShiftRight,
+            ]));
+        }
+
         #[test]
         fn ignores_whitespace() {
             let input = "  123  ";
@@ -296,21 +681,88 @@ mod tests {
         fn fails_on_invalid_character() {
             let input = "!";
             let result = tokenize(input);
-            expect!(result).to(be_err());
+            expect!(result).to(be_err().value(TokenizeError::InvalidCharacter('!')));
+        }
+
+        #[test]
+        fn rejects_plus_alone() {
+            expect!(tokenize("+")).to(be_err().value(TokenizeError::DanglingPlus));
+            expect!(tokenize("let x = 5; x + ")).to(be_err().value(TokenizeError::DanglingPlus));
+            expect!(tokenize("x+++")).to(be_err().value(TokenizeError::DanglingPlus));
+            expect!(tokenize("+ 1")).to(be_ok().value(vec![Token::Plus, Token::Number(1)]));
         }
 
         #[test]
-        fn fails_on_invalid_increment() {
-            let input = "+";
+        fn tokenizes_binary_plus_and_minus() {
+            let input = "1 + 2 - 3";
             let result = tokenize(input);
-            expect!(result).to(be_err());
+            expect!(result).to(be_ok().value(vec![
+                Token::Number(1),
+                Token::Plus,
+                Token::Number(2),
+                Token::Minus,
+                Token::Number(3),
+            ]));
         }
 
         #[test]
-        fn fails_on_invalid_decrement() {
-            let input = "-";
+        fn tokenizes_ambiguous_plus() {
+            let input = "1+++2";
             let result = tokenize(input);
-            expect!(result).to(be_err());
+            expect!(result).to(be_ok().value(vec![
+                Token::Number(1),
+                Token::Increment,
+                Token::Plus,
+                Token::Number(2),
+            ]));
+        }
+
+        #[test]
+        fn distinguishes_negative_numbers_from_subtraction() {
+            expect!(tokenize("-1")).to(be_ok().value(vec![Token::Number(-1)]));
+            expect!(tokenize("3-1")).to(be_ok().value(vec![Token::Number(3), Token::Minus, Token::Number(1)]));
+            expect!(tokenize("1 << -1")).to(be_ok().value(vec![
+                Token::Number(1),
+                Token::ShiftLeft,
+                Token::Number(-1),
+            ]));
+            expect!(tokenize("x-- -1")).to(be_ok().value(vec![
+                Token::Identifier("x".to_string()),
+                Token::Decrement,
+                Token::Minus,
+                Token::Number(1),
+            ]));
+        }
+
+        #[test]
+        fn fails_to_parse_a_lone_operator() {
+            // A lone `+` is rejected by `tokenize` already
+            let tokens = tokenize("-").unwrap();
+            expect!(crate::parser::parse::parse(&tokens)).to(be_err());
+        }
+
+        #[test]
+        fn tokenizes_comparisons() {
+            expect!(tokenize("1 < 2 > 3 <<4")).to(be_ok().value(vec![
+                Token::Number(1),
+                Token::Less,
+                Token::Number(2),
+                Token::Greater,
+                Token::Number(3),
+                Token::ShiftLeft,
+                Token::Number(4),
+            ]));
+        }
+
+        #[test]
+        fn tokenizes_assert_as_a_keyword() {
+            expect!(tokenize("assert(x) asserted")).to(be_ok().value(vec![
+                Token::Assert,
+                Token::LParen,
+                Token::Identifier("x".to_string()),
+                Token::RParen,
+                Token::Identifier("asserted".to_string()),
+            ]));
         }
 
         #[test]
@@ -319,5 +771,100 @@ mod tests {
             let result = tokenize(input);
             expect!(result).to(be_err());
         }
+
+        #[test]
+        fn skips_a_leading_byte_order_mark() {
+            let compile = |source: &str| {
+                let ast = crate::parser::parse::parse(&tokenize(source).unwrap()).unwrap();
+                crate::compiler::compile::compile_expression(&ast, &mut Default::default())
+            };
+            expect!(tokenize("\u{FEFF}420")).to(be_ok().value(vec![Token::Number(420)]));
+            expect!(compile("\u{FEFF}420")).to(be_equal_to(compile("420")));
+        }
+
+        #[test]
+        fn rejects_a_byte_order_mark_after_the_start() {
+            expect!(tokenize("420\u{FEFF}")).to(be_err());
+        }
+    }
+
+    mod tokenize_with_options {
+        use super::*;
+
+        #[test]
+        fn rejects_adjacent_numbers_when_strict() {
+            let strict = TokenizeOptions { strict: true };
+            expect!(tokenize_with_options("5 5", &strict))
+                .to(be_err().value(TokenizeError::MissingOperator(5, 5)));
+            expect!(tokenize_with_options("5 5", &TokenizeOptions::default()))
+                .to(be_ok().value(vec![Token::Number(5), Token::Number(5)]));
+        }
+
+        #[test]
+        fn accepts_numbers_separated_by_operators_when_strict() {
+            let strict = TokenizeOptions { strict: true };
+            expect!(tokenize_with_options("5 - 5; 6 -5", &strict)).to(be_ok());
+        }
+    }
+
+    mod tokenize_with_spans {
+        use super::*;
+
+        fn spans(input: &str) -> Vec<Span> {
+            tokenize_with_spans(input, &TokenizeOptions::default()).unwrap().into_iter().map(|(_, span)| span).collect()
+        }
+
+        #[test]
+        fn covers_the_quotes_of_strings() {
+            expect!(spans("\"hé\" x")).to(be_equal_to(vec![
+                Span { start: 0, end: 5, line: 1, col: 1 },
+                Span { start: 6, end: 7, line: 1, col: 6 },
+            ]));
+        }
+
+        #[test]
+        fn locates_each_token() {
+            expect!(spans("let x = -5;")).to(be_equal_to(vec![
+                Span { start: 0, end: 3, line: 1, col: 1 },
+                Span { start: 4, end: 5, line: 1, col: 5 },
+                Span { start: 6, end: 7, line: 1, col: 7 },
+                Span { start: 8, end: 10, line: 1, col: 9 },
+                Span { start: 10, end: 11, line: 1, col: 11 },
+            ]));
+        }
+
+        #[test]
+        fn counts_lines_and_characters() {
+            expect!(spans("\u{feff}é\n  +++1")).to(be_equal_to(vec![
+                Span { start: 3, end: 5, line: 1, col: 1 },
+                Span { start: 8, end: 10, line: 2, col: 3 },
+                Span { start: 10, end: 11, line: 2, col: 5 },
+                Span { start: 11, end: 12, line: 2, col: 6 },
+            ]));
+        }
+
+        proptest!(
+            #[test]
+            fn spans_the_spelling_of_each_token(a in any::<i64>(), b in 0i64..64) {
+                let input = format!("let value = {};  value << {} ;assert(value)", a, b);
+                for (token, span) in tokenize_with_spans(&input, &TokenizeOptions::default()).unwrap() {
+                    prop_assert_eq!(tokenize(span.text(&input)).unwrap(), vec![token]);
+                }
+            }
+        );
+    }
+
+    mod tokenize_with_eof {
+        use super::*;
+
+        #[test]
+        fn terminates_the_tokens_with_eof() {
+            expect!(tokenize_with_eof("x++")).to(be_ok().value(vec![
+                Token::Identifier("x".to_string()),
+                Token::Increment,
+                Token::Eof,
+            ]));
+            expect!(tokenize_with_eof("")).to(be_ok().value(vec![Token::Eof]));
+        }
     }
 }
@@ -0,0 +1,80 @@
+use alloc::format;
+use alloc::string::String;
+
+/// The base in which the result of a program is printed.
+///
+/// The default is `Decimal`; `Hex` is meant for inspecting the bits of a result, e.g. while
+/// debugging the code generator.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Radix {
+    /// Signed base 10, like `-1`.
+    #[default]
+    Decimal,
+
+    /// The two's complement bits in base 16 with a `0x` prefix, like `0xffffffffffffffff` for `-1`.
+    Hex,
+}
+
+/// Formats the result of a program the way the runtime prints it in the given radix, which is also
+/// how `--emit=run-interpreted` prints the result of the interpreter.
+///
+/// ## Example:
+/// ```rust
+/// # use rengo::radix::format_result;
+/// # use rengo::radix::Radix;
+/// assert_eq!(format_result(255, Radix::Hex), "0xff");
+/// assert_eq!(format_result(-1, Radix::Decimal), "-1");
+/// ```
+pub fn format_result(value: i64, radix: Radix) -> String {
+    match radix {
+        Radix::Decimal => format!("{}", value),
+        Radix::Hex => format!("{:#x}", value),
+    }
+}
+
+/// Parses a result printed in any radix, as produced by `format_result`.
+///
+/// ## Returns:
+/// The value, or `None` if the text is not a number in either radix.
+pub fn parse_result(text: &str) -> Option<i64> {
+    match text.strip_prefix("0x") {
+        Some(digits) => u64::from_str_radix(digits, 16).ok().map(|bits| bits as i64),
+        None => text.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expectest::prelude::*;
+    use proptest::prelude::*;
+    use super::*;
+
+    mod format_result {
+        use super::*;
+
+        #[test]
+        fn prints_hex_with_a_prefix() {
+            expect!(format_result(255, Radix::Hex)).to(be_equal_to("0xff"));
+            expect!(format_result(-1, Radix::Hex)).to(be_equal_to("0xffffffffffffffff"));
+            expect!(format_result(255, Radix::default())).to(be_equal_to("255"));
+        }
+    }
+
+    mod parse_result {
+        use super::*;
+
+        proptest!(
+            #[test]
+            fn reads_back_every_radix(value in any::<i64>()) {
+                expect!(parse_result(&format_result(value, Radix::Decimal))).to(be_some().value(value));
+                expect!(parse_result(&format_result(value, Radix::Hex))).to(be_some().value(value));
+            }
+        );
+
+        #[test]
+        fn fails_on_non_numbers() {
+            expect!(parse_result("0xg")).to(be_none());
+            expect!(parse_result("ff")).to(be_none());
+        }
+    }
+}
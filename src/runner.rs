@@ -0,0 +1,187 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// The exit code of a program whose result does not fit in an exit code, when linked with
+/// `--exit-code-mode=error`.
+pub const OUT_OF_RANGE_EXIT_CODE: i32 = 255;
+
+/// Represents an error found while running a compiled program.
+///
+/// The `RunError` enum describes why `run_executable` could not obtain the result of a program.
+#[derive(Debug)]
+pub enum RunError {
+    /// The executable could not be started.
+    ///
+    /// Holds the underlying I/O error, e.g. when the file does not exist.
+    Spawn(io::Error),
+
+    /// The program exited with a nonzero exit code.
+    ///
+    /// Holds the exit code.
+    ExitCode(i32),
+
+    /// The program was terminated without an exit code, e.g. by a signal.
+    Terminated,
+
+    /// The last line of the standard output of the program is not an integer.
+    ///
+    /// Holds the (lossily decoded) output.
+    InvalidOutput(String),
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::Spawn(error) => write!(f, "Failed to run the program: {}", error),
+            RunError::ExitCode(code) => write!(f, "Program exited with code {}", code),
+            RunError::Terminated => write!(f, "Program was terminated without an exit code"),
+            RunError::InvalidOutput(output) => write!(f, "Program printed a non-integer result: {:?}", output),
+        }
+    }
+}
+
+impl std::error::Error for RunError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RunError::Spawn(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Runs a compiled program and returns the result it printed.
+///
+/// The executables produced by the compiler print the value of the program on standard output (in
+/// decimal, or in hexadecimal with a `0x` prefix) and exit with code `0`. This function runs one of
+/// them and parses that value back. Anything the program printed before its value, with `print`, is
+/// ignored; see `run_executable_with_output` to get it too.
+///
+/// ## Parameters:
+/// - `path`: The path of the executable to run.
+///
+/// ## Returns:
+/// The integer printed by the program, or a `RunError` if the program could not be started, did
+/// not exit successfully, or printed something other than an integer.
+///
+/// ## Example:
+/// ```no_run
+/// # use std::path::Path;
+/// # use rengo::runner::run_executable;
+/// let result = run_executable(Path::new("build/out.exe"))?;
+/// # Ok::<(), rengo::runner::RunError>(())
+/// ```
+pub fn run_executable(path: &Path) -> Result<i64, RunError> {
+    run_executable_with_output(path).map(|(_, result)| result)
+}
+
+/// Runs a compiled program like `run_executable`, also returning the text it printed before its
+/// result.
+///
+/// ## Example:
+/// ```no_run
+/// # use std::path::Path;
+/// # use rengo::runner::run_executable_with_output;
+/// let (printed, result) = run_executable_with_output(Path::new("build/out.exe"))?;
+/// # Ok::<(), rengo::runner::RunError>(())
+/// ```
+pub fn run_executable_with_output(path: &Path) -> Result<(String, i64), RunError> {
+    let output = Command::new(path).output().map_err(RunError::Spawn)?;
+    parse_result(output.status.code(), &output.stdout)
+}
+
+/// Interprets the exit code and standard output of a finished program as the text it printed and
+/// its result, which is the last line of the output.
+fn parse_result(exit_code: Option<i32>, stdout: &[u8]) -> Result<(String, i64), RunError> {
+    match exit_code {
+        Some(0) => {}
+        Some(code) => return Err(RunError::ExitCode(code)),
+        None => return Err(RunError::Terminated),
+    }
+    let stdout = String::from_utf8_lossy(stdout);
+    let trimmed = stdout.trim_end();
+    let (printed, last_line) = match trimmed.rfind('\n') {
+        Some(newline) => trimmed.split_at(newline + 1),
+        None => ("", trimmed),
+    };
+    match crate::radix::parse_result(last_line.trim()) {
+        Some(result) => Ok((printed.to_string(), result)),
+        None => Err(RunError::InvalidOutput(stdout.into_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expectest::prelude::*;
+    use super::*;
+
+    mod parse_result {
+        use super::*;
+
+        #[test]
+        fn parses_the_printed_integer() {
+            expect!(parse_result(Some(0), b"42\n")).to(be_ok().value((String::new(), 42)));
+            expect!(parse_result(Some(0), b"-420")).to(be_ok().value((String::new(), -420)));
+            expect!(parse_result(Some(0), b"0xff\n")).to(be_ok().value((String::new(), 255)));
+        }
+
+        #[test]
+        fn separates_the_printed_text_from_the_result() {
+            expect!(parse_result(Some(0), b"hi\nthere\n0\n")).to(be_ok().value(("hi\nthere\n".to_string(), 0)));
+            expect!(matches!(parse_result(Some(0), b"42\nhi\n"), Err(RunError::InvalidOutput(_)))).to(be_true());
+        }
+
+        #[test]
+        fn fails_on_nonzero_exit_code() {
+            expect!(matches!(parse_result(Some(3), b"42\n"), Err(RunError::ExitCode(3)))).to(be_true());
+            expect!(matches!(parse_result(None, b""), Err(RunError::Terminated))).to(be_true());
+        }
+
+        #[test]
+        fn fails_on_non_integer_output() {
+            let result = parse_result(Some(0), b"forty-two\n");
+            expect!(matches!(result, Err(RunError::InvalidOutput(output)) if output == "forty-two\n")).to(be_true());
+        }
+    }
+
+    mod run_executable {
+        use super::*;
+
+        #[cfg(unix)]
+        #[test]
+        fn parses_the_output_of_a_program() {
+            use std::os::unix::fs::PermissionsExt;
+
+            let dir = std::env::temp_dir().join(format!("rengo-runner-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("prints_42");
+            std::fs::write(&path, "#!/bin/sh\necho 42\n").unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            let result = run_executable(&path);
+            std::fs::remove_dir_all(&dir).unwrap();
+            expect!(result.map_err(|error| error.to_string())).to(be_ok().value(42));
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn returns_the_text_printed_before_the_result() {
+            use std::os::unix::fs::PermissionsExt;
+
+            let dir = std::env::temp_dir().join(format!("rengo-runner-output-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("prints_hi");
+            std::fs::write(&path, "#!/bin/sh\necho hi\necho 0\n").unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            let result = run_executable_with_output(&path);
+            std::fs::remove_dir_all(&dir).unwrap();
+            expect!(result.map_err(|error| error.to_string())).to(be_ok().value(("hi\n".to_string(), 0)));
+        }
+
+        #[test]
+        fn fails_on_missing_executable() {
+            let result = run_executable(Path::new("build/does-not-exist.exe"));
+            expect!(matches!(result, Err(RunError::Spawn(_)))).to(be_true());
+        }
+    }
+}
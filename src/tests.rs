@@ -1,42 +1,703 @@
-use crate::ast::expression::Expression;
+use rengo::ast::expression::Expression;
 use crate::create_output_paths;
 
 fn parse_compile_and_assemble(program: String) -> Result<(), Box<dyn std::error::Error>> {
-    let ast: Expression<&str> = crate::parser::parse::parse(&crate::parser::tokenize::tokenize(&program)?)?;
-    let assembly = crate::compiler::compile::compile_expression(&ast, &mut Default::default());
+    parse_compile_and_link(program, &link_options(std::env::consts::OS))
+}
+
+fn parse_compile_and_link(program: String, options: &crate::cli::LinkOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let compile_options = rengo::compiler::options::CompileOptions { frame: true, ..Default::default() };
+    compile_and_link_with(program, compile_options, options)
+}
+
+/// Compiles with `--sanitize`, so the indices of the program are checked at runtime.
+fn parse_compile_sanitized_and_assemble(program: String) -> Result<(), Box<dyn std::error::Error>> {
+    let compile_options = rengo::compiler::options::CompileOptions { frame: true, sanitize: true, ..Default::default() };
+    compile_and_link_with(program, compile_options, &link_options(std::env::consts::OS))
+}
+
+fn compile_and_link_with(
+    program: String,
+    compile_options: rengo::compiler::options::CompileOptions,
+    options: &crate::cli::LinkOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ast: Expression<()> = rengo::parser::parse::parse(&rengo::parser::tokenize::tokenize(&program)?)?;
+    let unit = rengo::compiler::compile::compile_expression(&ast, &mut rengo::compiler::context::Ctx::new(compile_options))?;
     let asm_output_path = create_output_paths("build/test/", "out.asm")?;
     let obj_output_path = create_output_paths("build/test/", "out.obj")?;
     let exe_output_path = create_output_paths("build/test/", "out.exe")?;
-    let prelude = "section .text\n\
-                   global _start\n\
-                   _start:\n";
-    let asm = format!("{}\n{}", prelude, crate::asm::to_string::asm_to_string(assembly.unwrap()));
-    let ret = "ret\n";
-    let compiled_asm = format!("{}\n{}", asm, ret);
+    let compiled_asm = crate::generate_asm(unit, "_start", false, Default::default());
     crate::write_assembly(&asm_output_path, &compiled_asm)?;
-    crate::assemble::assemble(&asm_output_path, &obj_output_path)?;
-    crate::link(&obj_output_path, &exe_output_path)?;
+    rengo::assemble::assemble(&asm_output_path, &obj_output_path, std::env::consts::OS)?;
+    crate::link(&obj_output_path, &exe_output_path, options)?;
     Ok(())
 }
 
+fn link_options(os: &str) -> crate::cli::LinkOptions {
+    crate::cli::LinkOptions { os: os.to_string(), ..Default::default() }
+}
+
 fn execute(program: String) -> Result<i64, Box<dyn std::error::Error>> {
     let exe_output_path = create_output_paths("build/test/", "out.exe")?;
-    let output = std::process::Command::new(exe_output_path).output()?;
-    let stdout = String::from_utf8(output.stdout)?;
-    let exit_code = output.status.code().unwrap();
-    if exit_code != 0 {
-        return Err(format!("Program exited with code {}", exit_code).into());
-    }
-    let result = stdout.trim().parse::<i64>()?;
-    Ok(result)
+    rengo::runner::run_executable(&exe_output_path).map_err(|error| format!("{}: {}", program, error).into())
+}
+
+fn execute_with_output(program: String) -> Result<(String, i64), Box<dyn std::error::Error>> {
+    let exe_output_path = create_output_paths("build/test/", "out.exe")?;
+    rengo::runner::run_executable_with_output(&exe_output_path).map_err(|error| format!("{}: {}", program, error).into())
 }
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
     use expectest::prelude::*;
-    use proptest::prelude::*;
     use super::*;
 
+    /// A directory of its own under the system's temporary directory, removed with everything in it
+    /// once dropped, so that nothing is left behind even by a failing test.
+    struct TempDir(std::path::PathBuf);
+
+    impl TempDir {
+        fn new() -> TempDir {
+            static CREATED: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+            let count = CREATED.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            let dir = std::env::temp_dir().join(format!("rengo-test-{}-{}", std::process::id(), count));
+            std::fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+
+        /// Writes `source` to the file `name` in the directory, returning its path as an input.
+        fn write(&self, name: &str, source: &str) -> String {
+            let input = self.0.join(name);
+            std::fs::write(&input, source).unwrap();
+            input.to_str().unwrap().to_string()
+        }
+
+        /// Returns the arguments that compile `source`, written to `program.rg` in the directory.
+        fn args(&self, source: &str) -> crate::cli::Args {
+            crate::cli::Args { inputs: vec![self.write("program.rg", source)], ..Default::default() }
+        }
+
+        fn build_dir(&self) -> std::path::PathBuf {
+            self.0.join("build")
+        }
+
+        /// Runs the compiler with `args`, building in the directory. Returns what `run` returned, its
+        /// error as text, and what was written to standard output and standard error.
+        fn run(&self, args: &crate::cli::Args) -> (Result<u8, String>, String, String) {
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            let result = crate::run(args, &self.build_dir(), &mut out, &mut err).map_err(|error| error.to_string());
+            (result, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+        }
+    }
+
+    impl std::ops::Deref for TempDir {
+        type Target = std::path::Path;
+
+        fn deref(&self) -> &std::path::Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn emit_asm_creates_no_files() {
+        let dir = TempDir::new();
+        let (result, out, _) = dir.run(&crate::cli::Args { emit_asm: true, ..dir.args("420") });
+        expect!(result).to(be_ok());
+        expect!(out.contains("mov rax, 420")).to(be_true());
+        expect!(dir.build_dir().exists()).to(be_false());
+    }
+
+    #[test]
+    fn grammar_prints_the_grammar() {
+        let args = crate::cli::parse_args(&["rengo".to_string(), "--grammar".to_string()]).unwrap();
+        let (result, out, _) = TempDir::new().run(&args);
+        expect!(result).to(be_ok());
+        expect!(out).to(be_equal_to(rengo::parser::grammar::grammar().to_string()));
+    }
+
+    #[test]
+    fn explain_describes_the_error_code() {
+        let dir = TempDir::new();
+        let explain = |code: &str| {
+            let args = crate::cli::parse_args(&["rengo".to_string(), "--explain".to_string(), code.to_string()]).unwrap();
+            let (result, out, _) = dir.run(&args);
+            result.map(|_| out)
+        };
+        let text = explain("E001").unwrap();
+        expect!(text.to_lowercase().contains("undefined variable")).to(be_true());
+        expect!(explain("E999")).to(be_err().value("E999 is not a valid error code".to_string()));
+    }
+
+    #[test]
+    fn dump_ir_prints_the_instructions() {
+        let dir = TempDir::new();
+        let args = crate::cli::Args { dump_ir: true, stop_after: Some(crate::cli::Stage::Ir), ..dir.args("420++") };
+        let (result, out, _) = dir.run(&args);
+        expect!(result).to(be_ok());
+        expect!(out.lines().any(|line| line == "Mov(Registry(Rax), Constant(420))")).to(be_true());
+        expect!(out.lines().any(|line| line == "Inc(Registry(Rax))")).to(be_true());
+        expect!(dir.build_dir().exists()).to(be_false());
+    }
+
+    /// Returns the instructions `--dump-ir` prints for `source` with the extra command-line `flags`.
+    fn dump_ir(source: &str, flags: &[&str]) -> Vec<String> {
+        let dir = TempDir::new();
+        let mut command_line = vec!["rengo".to_string(), "--dump-ir".to_string(), "--stop-after=ir".to_string()];
+        command_line.extend(flags.iter().map(|flag| flag.to_string()));
+        command_line.push(dir.write("program.rg", source));
+        let (result, out, _) = dir.run(&crate::cli::parse_args(&command_line).unwrap());
+        expect!(result).to(be_ok());
+        out.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn align_loops_is_reserved_until_loops_are_compiled() {
+        // No compiled code has a loop label to pad yet
+        expect!(dump_ir("let x = 5; x++; x--", &["--align-loops", "16"])).to(be_equal_to(dump_ir("let x = 5; x++; x--", &[])));
+    }
+
+    #[test]
+    fn opt_level_selects_the_optimizations() {
+        // The stack frame is set up around the code of the program either way
+        let body = |lines: Vec<String>| -> Vec<String> {
+            lines.into_iter().filter(|line| !line.contains("Rbp") && !line.contains("Rsp")).collect()
+        };
+        expect!(body(dump_ir("420++--++", &["-O2"]))).to(be_equal_to(vec!["Mov(Registry(Rax), Constant(421))".to_string()]));
+        let plain = body(dump_ir("420++--++", &["-O0"]));
+        expect!(plain.iter().filter(|line| line.starts_with("Inc(")).count()).to(be_equal_to(2));
+        expect!(plain.iter().filter(|line| line.starts_with("Dec(")).count()).to(be_equal_to(1));
+        expect!(body(dump_ir("420++--++", &[]))).to(be_equal_to(plain));
+    }
+
+    #[test]
+    fn emit_tokens_json_locates_the_tokens_in_the_file() {
+        let dir = TempDir::new();
+        let (result, out, _) = dir.run(&crate::cli::Args { emit_tokens_json: true, ..dir.args("# binds five\n  let x = 5\n") });
+        expect!(result).to(be_ok());
+        expect!(out).to(be_equal_to(concat!(
+            r#"[{"token":"Let","line":2,"col":3,"len":3},"#,
+            r#"{"token":"Identifier","line":2,"col":7,"len":1},"#,
+            r#"{"token":"Assign","line":2,"col":9,"len":1},"#,
+            r#"{"token":"Number","line":2,"col":11,"len":1}]"#,
+            "\n",
+        ).to_string()));
+        expect!(dir.build_dir().exists()).to(be_false());
+    }
+
+    #[test]
+    fn doc_prints_the_leading_comments() {
+        let dir = TempDir::new();
+        let source = "# doubles the input\n// by shifting it\nlet x = 21; x << 1\n";
+        let (result, out, _) = dir.run(&crate::cli::Args { doc: true, emit_asm: true, ..dir.args(source) });
+        expect!(result).to(be_ok());
+        expect!(out.starts_with("doubles the input\nby shifting it\n")).to(be_true());
+        expect!(out.contains("mov rax, 21")).to(be_true());  // The program after the comments is compiled
+    }
+
+    #[test]
+    fn emit_asm_locates_a_failing_assertion_in_its_file() {
+        let dir = TempDir::new();
+        let (result, out, _) = dir.run(&crate::cli::Args {
+            emit_asm: true,
+            inputs: vec![dir.write("first.rg", "# checks the input\nlet x = 1; assert(x > 0)"), dir.write("second.rg", "assert(x > 1)")],
+            ..Default::default()
+        });
+        expect!(result).to(be_ok());
+        expect!(out.contains("call rengo_assert_failed ; line 2, column 12")).to(be_true());
+        expect!(out.contains("call rengo_assert_failed ; line 1, column 1")).to(be_true());
+    }
+
+    #[test]
+    fn doc_is_not_printed_by_default() {
+        let dir = TempDir::new();
+        let (result, out, _) = dir.run(&crate::cli::Args { emit_asm: true, ..dir.args("# doubles the input\n420") });
+        expect!(result).to(be_ok());
+        expect!(out.contains("doubles the input")).to(be_false());
+    }
+
+    #[test]
+    fn unused_bindings_only_warn_by_default() {
+        let dir = TempDir::new();
+        let args = crate::cli::Args { emit_asm: true, color: crate::diagnostics::ColorChoice::Never, ..dir.args("let x = 5; 7") };
+        let (result, out, err) = dir.run(&args);
+        expect!(result).to(be_ok());
+        expect!(out.contains("mov rax, 7")).to(be_true());
+        expect!(err).to(be_equal_to("warning: unused variable 'x'\n".to_string()));
+    }
+
+    #[test]
+    fn diagnostics_are_printed_in_source_order_whichever_pass_found_them() {
+        let dir = TempDir::new();
+        // The warning on line 1 is found by the lints, after the parser found the error on line 3
+        let args = crate::cli::Args {
+            emit_asm: true,
+            color: crate::diagnostics::ColorChoice::Never,
+            inputs: vec![dir.write("first.rg", "let x = 5; 7"), dir.write("second.rg", "# two lines\n# of documentation\nlet y = ; 1")],
+            ..Default::default()
+        };
+        let (result, out, err) = dir.run(&args);
+        expect!(result).to(be_err().value("aborting due to 1 parse error(s)".to_string()));
+        expect!(out.is_empty()).to(be_true());
+        expect!(err).to(be_equal_to(concat!(
+            "warning: unused variable 'x'\n",
+            "error[E014]: Unexpected token: LineEnd\n",
+            "For more information about this error, try `rengo --explain E014`.\n",
+        ).to_string()));
+    }
+
+    #[test]
+    fn diagnostics_of_earlier_files_are_printed_first() {
+        let dir = TempDir::new();
+        // The error is on line 3 of the first file, the warning on line 1 of the second one
+        let args = crate::cli::Args {
+            emit_asm: true,
+            color: crate::diagnostics::ColorChoice::Never,
+            inputs: vec![dir.write("first.rg", "# two lines\n# of documentation\nlet y = ; 1"), dir.write("second.rg", "let x = 5; 7")],
+            ..Default::default()
+        };
+        let (result, _, err) = dir.run(&args);
+        expect!(result).to(be_err().value("aborting due to 1 parse error(s)".to_string()));
+        expect!(err).to(be_equal_to(concat!(
+            "error[E014]: Unexpected token: LineEnd\n",
+            "warning: unused variable 'x'\n",
+            "For more information about this error, try `rengo --explain E014`.\n",
+        ).to_string()));
+    }
+
+    #[test]
+    fn parse_errors_are_all_reported() {
+        let dir = TempDir::new();
+        let args = crate::cli::Args { emit_asm: true, color: crate::diagnostics::ColorChoice::Never, ..dir.args("let x = ; x + & 1") };
+        let (result, _, err) = dir.run(&args);
+        expect!(result).to(be_err().value("aborting due to 2 parse error(s)".to_string()));
+        expect!(err.lines().filter(|line| line.starts_with("error[E014]")).count()).to(be_equal_to(2));
+    }
+
+    #[test]
+    fn strict_turns_warnings_into_errors() {
+        let dir = TempDir::new();
+        let args = crate::cli::Args {
+            emit_asm: true,
+            strict: true,
+            color: crate::diagnostics::ColorChoice::Never,
+            ..dir.args("let x = 5; 7")
+        };
+        let (result, out, err) = dir.run(&args);
+        expect!(result).to(be_err().value("1 warning(s) treated as errors (--strict)".to_string()));
+        expect!(out.is_empty()).to(be_true());
+        expect!(err).to(be_equal_to("warning: unused variable 'x'\n".to_string()));
+        dir.write("program.rg", "let x = 5; x");
+        expect!(dir.run(&args).0).to(be_ok());
+    }
+
+    #[test]
+    fn check_reports_errors_without_writing_anything() {
+        let dir = TempDir::new();
+        let input = dir.write("program.rg", "let value = 1; valu");
+        let (result, out, _) = dir.run(&crate::cli::parse_args(&["rengo".to_string(), "--check".to_string(), input]).unwrap());
+        expect!(result).to(be_err().value("undefined variable 'valu'; did you mean 'value'?".to_string()));
+        expect!(out.is_empty()).to(be_true());
+        expect!(dir.build_dir().exists()).to(be_false());
+    }
+
+    #[test]
+    fn check_only_reports_the_warnings_of_a_valid_program() {
+        let dir = TempDir::new();
+        let args = crate::cli::Args { check: true, color: crate::diagnostics::ColorChoice::Never, ..dir.args("let x = 5; 7") };
+        let (result, out, err) = dir.run(&args);
+        expect!(result).to(be_ok());
+        expect!(out.is_empty()).to(be_true());
+        expect!(err).to(be_equal_to("warning: unused variable 'x'\n".to_string()));
+        expect!(dir.build_dir().exists()).to(be_false());
+    }
+
+    /// A stream that claims to be a terminal, to check what is colorized with `--color auto`.
+    struct Terminal(Vec<u8>);
+
+    impl std::io::Write for Terminal {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl crate::diagnostics::Output for Terminal {
+        fn is_terminal(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn auto_color_follows_the_stream_written_to() {
+        let dir = TempDir::new();
+        let args = crate::cli::Args { check: true, ..dir.args("let x = 5; 7") };
+        let mut terminal = Terminal(Vec::new());
+        let mut file = Vec::new();
+        crate::run(&args, &dir.build_dir(), &mut Vec::new(), &mut terminal).unwrap();
+        crate::run(&args, &dir.build_dir(), &mut Terminal(Vec::new()), &mut file).unwrap();
+        expect!(String::from_utf8(terminal.0).unwrap().contains("\x1b[")).to(be_true());
+        expect!(String::from_utf8(file).unwrap()).to(be_equal_to("warning: unused variable 'x'\n".to_string()));
+    }
+
+    /// Returns the phases `--time` reports, in order, from what was written to standard error.
+    fn phases(err: &str) -> Vec<&str> {
+        err.lines().filter_map(|line| line.split(':').next()).collect()
+    }
+
+    #[test]
+    fn time_reports_the_phases_that_ran() {
+        let dir = TempDir::new();
+        // A directory where the object file goes makes assembling fail, with or without nasm
+        std::fs::create_dir_all(dir.build_dir().join("obj").join("out.obj")).unwrap();
+        let (emitted, _, emitted_err) = dir.run(&crate::cli::Args { time: true, emit_asm: true, ..dir.args("420") });
+        let (failed, _, failed_err) = dir.run(&crate::cli::Args { time: true, ..dir.args("420") });
+        expect!(emitted).to(be_ok());
+        expect!(phases(&emitted_err)).to(be_equal_to(vec!["tokenize", "parse", "compile"]));
+        expect!(failed.is_err()).to(be_true());
+        expect!(phases(&failed_err)).to(be_equal_to(vec!["tokenize", "parse", "compile", "assemble"]));
+    }
+
+    #[test]
+    #[ignore = "needs nasm and clang"]
+    fn time_reports_each_phase() {
+        let dir = TempDir::new();
+        let (result, _, err) = dir.run(&crate::cli::Args { time: true, ..dir.args("420") });
+        expect!(result).to(be_ok().value(0));
+        expect!(phases(&err)).to(be_equal_to(vec!["tokenize", "parse", "compile", "assemble", "link"]));
+    }
+
+    #[test]
+    fn time_is_silent_by_default() {
+        let dir = TempDir::new();
+        let (result, _, err) = dir.run(&crate::cli::Args { emit_asm: true, ..dir.args("420") });
+        expect!(result).to(be_ok());
+        expect!(err.is_empty()).to(be_true());
+    }
+
+    #[test]
+    fn run_interpreted_prints_the_result_without_a_toolchain() {
+        let dir = TempDir::new();
+        let (result, out, _) = dir.run(&crate::cli::Args { emit: crate::cli::Emit::RunInterpreted, ..dir.args("let x = 5; x++") });
+        expect!(result).to(be_ok().value(6));
+        expect!(out).to(be_equal_to("6\n".to_string()));
+        expect!(dir.build_dir().exists()).to(be_false());
+    }
+
+    #[test]
+    fn run_interpreted_prints_what_the_program_prints_first() {
+        let dir = TempDir::new();
+        let args = crate::cli::Args {
+            emit: crate::cli::Emit::RunInterpreted,
+            radix: rengo::radix::Radix::Hex,
+            ..dir.args("print(\"hi\"); 255")
+        };
+        let (result, out, _) = dir.run(&args);
+        expect!(result).to(be_ok());
+        expect!(out).to(be_equal_to("hi\n0xff\n".to_string()));
+    }
+
+    #[test]
+    fn run_interpreted_exits_with_the_result() {
+        let dir = TempDir::new();
+        let status = |source: &str, exit_code_mode| {
+            let args = crate::cli::Args { emit: crate::cli::Emit::RunInterpreted, exit_code_mode, ..dir.args(source) };
+            let (status, _, err) = dir.run(&args);
+            (status, err)
+        };
+        expect!(status("300", None)).to(be_equal_to((Ok(44), String::new())));
+        expect!(status("300", Some(crate::cli::ExitCodeMode::Error)))
+            .to(be_equal_to((Ok(255), "result out of the exit code range: 300\n".to_string())));
+        expect!(status("0--", Some(crate::cli::ExitCodeMode::Truncate))).to(be_equal_to((Ok(255), String::new())));
+        expect!(status("254", Some(crate::cli::ExitCodeMode::Error))).to(be_equal_to((Ok(254), String::new())));
+    }
+
+    #[test]
+    fn run_interpreted_prints_negative_results_in_hex_as_their_bits() {
+        let dir = TempDir::new();
+        let args = crate::cli::parse_args(&[
+            "rengo".to_string(),
+            "--radix".to_string(),
+            "hex".to_string(),
+            "--emit=run-interpreted".to_string(),
+            dir.write("program.rg", "0--"),
+        ])
+        .unwrap();
+        let (result, out, _) = dir.run(&args);
+        expect!(result).to(be_ok());
+        expect!(out).to(be_equal_to("0xffffffffffffffff\n".to_string()));
+    }
+
+    #[test]
+    #[ignore = "needs nasm and clang"]
+    fn executes_function_calls() {
+        let dir = TempDir::new();
+        let (result, _, _) = dir.run(&dir.args("fn add(a, b) = a + b; add(1, 2)"));
+        expect!(result).to(be_ok());
+        let executed = rengo::runner::run_executable(&dir.build_dir().join("out.exe")).map_err(|error| error.to_string());
+        expect!(executed).to(be_ok().value(3));
+    }
+
+    #[test]
+    #[ignore = "needs nasm and clang"]
+    fn run_interpreted_agrees_with_the_executable_on_recursion() {
+        let dir = TempDir::new();
+        let source = "fn f(n) = if n then f(n - 1) + 1 else 0; f(60)";
+        let args = crate::cli::Args { emit: crate::cli::Emit::RunInterpreted, ..dir.args(source) };
+        let (interpreted, _, _) = std::thread::scope(|scope| {
+            let evaluation = std::thread::Builder::new().stack_size(crate::STACK_SIZE);
+            evaluation.spawn_scoped(scope, || dir.run(&args)).unwrap().join().unwrap()
+        });
+        expect!(interpreted).to(be_ok().value(60));
+        let (result, _, _) = dir.run(&dir.args(source));
+        expect!(result).to(be_ok());
+        let compiled = rengo::runner::run_executable(&dir.build_dir().join("out.exe")).map_err(|error| error.to_string());
+        expect!(compiled).to(be_ok().value(60));
+    }
+
+    #[test]
+    fn emit_llvm_ir_prints_the_lowered_program() {
+        let dir = TempDir::new();
+        let (result, out, _) = dir.run(&crate::cli::Args { emit: crate::cli::Emit::LlvmIr, ..dir.args("let x = 5; x++--") });
+        expect!(result).to(be_ok());
+        expect!(out.contains("define i64 @main()")).to(be_true());
+        expect!(out.contains("%0 = add i64 5, 1")).to(be_true());
+        expect!(out.contains("%1 = sub i64 %0, 1")).to(be_true());
+        expect!(dir.build_dir().exists()).to(be_false());
+    }
+
+    #[test]
+    fn fmt_prints_the_program_in_its_canonical_layout() {
+        let dir = TempDir::new();
+        let format = |source: &str| {
+            let (result, out, _) = dir.run(&crate::cli::Args { fmt: true, ..dir.args(source) });
+            result.map(|_| out)
+        };
+        let once = format("// increments x\nlet   x=5 ;x ++\n");
+        let twice = once.clone().and_then(|once| format(&once));
+        expect!(once.clone()).to(be_ok().value("// increments x\nlet x = 5; x++\n".to_string()));
+        expect!(twice).to(be_equal_to(once));
+        expect!(dir.build_dir().exists()).to(be_false());
+    }
+
+    #[test]
+    fn later_input_files_see_the_bindings_of_earlier_ones() {
+        let dir = TempDir::new();
+        let args = crate::cli::Args {
+            inputs: vec![dir.write("first.rg", "// the base\nlet base = 40"), dir.write("second.rg", "-1; base++++")],
+            emit: crate::cli::Emit::RunInterpreted,
+            ..Default::default()
+        };
+        let (result, out, _) = dir.run(&args);
+        expect!(result).to(be_ok().value(42));
+        expect!(out).to(be_equal_to("42\n".to_string()));
+    }
+
+    #[test]
+    fn define_binds_a_name_before_the_program() {
+        let dir = TempDir::new();
+        let interpreted = crate::cli::Args {
+            emit: crate::cli::Emit::RunInterpreted,
+            defines: vec![("x".to_string(), 1), ("x".to_string(), 41)],
+            ..dir.args("x++")
+        };
+        let (result, out, _) = dir.run(&interpreted);
+        expect!(result).to(be_ok().value(42));
+        expect!(out).to(be_equal_to("42\n".to_string()));
+        let compiled = crate::cli::Args {
+            emit: crate::cli::Emit::Exe,
+            dump_ir: true,
+            stop_after: Some(crate::cli::Stage::Ir),
+            ..interpreted
+        };
+        let (result, out, _) = dir.run(&compiled);
+        expect!(result).to(be_ok());
+        expect!(out.lines().any(|line| line == "Mov(Registry(Rax), Constant(41))")).to(be_true());
+        expect!(out.lines().any(|line| line == "Inc(Registry(Rax))")).to(be_true());
+    }
+
+    #[test]
+    fn define_is_seen_by_the_compiler_but_not_by_the_lints() {
+        let dir = TempDir::new();
+        let check = |emit, deny_shadowing| {
+            dir.run(&crate::cli::Args {
+                emit,
+                stop_after: Some(crate::cli::Stage::Ir),
+                defines: vec![("x".to_string(), 1), ("unused".to_string(), 2)],
+                deny_shadowing,
+                color: crate::diagnostics::ColorChoice::Never,
+                ..dir.args("let x = x + 5; x")
+            })
+        };
+        // The definition the program never reads is not reported as unused
+        expect!(check(crate::cli::Emit::RunInterpreted, false)).to(be_equal_to((Ok(6), "6\n".to_string(), String::new())));
+        expect!(check(crate::cli::Emit::Exe, true).0).to(be_err().value("'x' is already bound in this scope".to_string()));
+    }
+
+    #[test]
+    fn emit_obj_exports_the_entry_symbol_for_c() {
+        let dir = TempDir::new();
+        let args = crate::cli::Args {
+            emit: crate::cli::Emit::Obj,
+            emit_asm: true,
+            target_os: Some("linux".to_string()),
+            ..dir.args("420")
+        };
+        let (result, asm, _) = dir.run(&args);
+        expect!(result).to(be_ok());
+        expect!(asm.lines().any(|line| line == "global rengo_main")).to(be_true());
+        expect!(asm.contains("_start")).to(be_false());
+    }
+
+    #[test]
+    #[ignore = "needs nasm"]
+    fn emit_obj_skips_linking() {
+        let dir = TempDir::new();
+        let (result, _, _) = dir.run(&crate::cli::Args { emit: crate::cli::Emit::Obj, ..dir.args("420") });
+        expect!(result).to(be_ok().value(0));
+        expect!(dir.build_dir().join("obj").join("out.obj").exists()).to(be_true());
+        expect!(dir.build_dir().join("out.exe").exists()).to(be_false());
+    }
+
+    #[test]
+    fn write_atomically_leaves_no_partial_file_when_writing_fails() {
+        let dir = TempDir::new();
+        let asm = dir.join("out.asm");
+        let result = crate::write_atomically(&asm, |file| {
+            file.write_all(b"section .text\n")?;
+            Err(std::io::Error::other("disk full"))
+        });
+        expect!(result.map_err(|error| error.to_string())).to(be_err().value("disk full".to_string()));
+        expect!(std::fs::read_dir(&*dir).unwrap().count()).to(be_equal_to(0));
+    }
+
+    #[test]
+    fn write_atomically_replaces_the_file_once_written() {
+        let dir = TempDir::new();
+        let asm = dir.join("out.asm");
+        std::fs::write(&asm, "old").unwrap();
+        let result = crate::write_atomically(&asm, |file| file.write_all(b"new"));
+        expect!(result.map_err(|error| error.to_string())).to(be_ok());
+        expect!(std::fs::read_to_string(&asm).unwrap()).to(be_equal_to("new".to_string()));
+        expect!(std::fs::read_dir(&*dir).unwrap().count()).to(be_equal_to(1));
+    }
+
+    #[test]
+    fn reports_a_missing_input_file() {
+        let dir = TempDir::new();
+        let missing = dir.join("missing.rg").to_str().unwrap().to_string();
+        let expected = std::fs::File::open(&missing).unwrap_err().to_string();
+        let (result, out, _) = dir.run(&crate::cli::Args { inputs: vec![missing], ..Default::default() });
+        expect!(result).to(be_err().value(expected));
+        expect!(out.is_empty()).to(be_true());
+        expect!(dir.build_dir().exists()).to(be_false());
+    }
+
+    #[test]
+    fn read_limited_refuses_an_input_over_the_size_limit() {
+        let cap = 64;
+        let input = "1".repeat(cap as usize + 1);
+        let result = crate::read_limited(input.as_bytes(), cap);
+        expect!(result.map_err(|error| error.to_string()))
+            .to(be_err().value("the input is larger than 64 bytes (see --max-program-size)".to_string()));
+        let result = crate::read_limited(&input.as_bytes()[1..], cap);
+        expect!(result.map_err(|error| error.to_string())).to(be_ok().value(input[1..].to_string()));
+    }
+
+    #[test]
+    fn clean_removes_the_intermediate_files() {
+        let dir = TempDir::new();
+        let asm = dir.join("out.asm");
+        let obj = dir.join("out.obj");
+        std::fs::write(&asm, "").unwrap();
+        std::fs::write(&obj, "").unwrap();
+        let result = crate::remove_intermediates(&[&asm, &obj]);
+        expect!(result.map_err(|error| error.to_string())).to(be_ok());
+        expect!(asm.exists()).to(be_false());
+        expect!(obj.exists()).to(be_false());
+    }
+
+    #[test]
+    fn clean_keeps_the_assembly_when_assembling_fails() {
+        let dir = TempDir::new();
+        // A directory where the object file goes makes assembling fail, with or without nasm
+        std::fs::create_dir_all(dir.build_dir().join("obj").join("out.obj")).unwrap();
+        let (result, _, _) = dir.run(&crate::cli::Args { clean: true, ..dir.args("420") });
+        expect!(result.is_err()).to(be_true());
+        expect!(dir.build_dir().join("s").join("out.asm").exists()).to(be_true());
+        expect!(dir.build_dir().join("out.exe").exists()).to(be_false());
+    }
+
+    #[test]
+    #[ignore = "needs nasm and clang"]
+    fn clean_removes_the_intermediate_files_once_linked() {
+        let dir = TempDir::new();
+        let (result, _, _) = dir.run(&crate::cli::Args { clean: true, ..dir.args("420") });
+        expect!(result).to(be_ok().value(0));
+        expect!(dir.build_dir().join("out.exe").exists()).to(be_true());
+        expect!(dir.build_dir().join("obj").join("out.obj").exists()).to(be_false());
+        expect!(dir.build_dir().join("s").join("out.asm").exists()).to(be_false());
+    }
+
+    #[test]
+    fn links_for_the_target_os() {
+        let obj = std::path::Path::new("out.obj");
+        let exe = std::path::Path::new("out.exe");
+        let windows = crate::link_args(obj, exe, &link_options("windows"));
+        expect!(windows.ends_with(&["-Xlinker".to_string(), "/subsystem:console".to_string()])).to(be_true());
+        let linux = crate::link_args(obj, exe, &link_options("linux"));
+        expect!(linux.contains(&"/subsystem:console".to_string())).to(be_false());
+    }
+
+    #[test]
+    fn links_the_runtime_for_the_radix() {
+        let obj = std::path::Path::new("out.obj");
+        let exe = std::path::Path::new("out.exe");
+        let hex = crate::link_args(obj, exe, &crate::cli::LinkOptions { radix: rengo::radix::Radix::Hex, ..link_options("linux") });
+        expect!(hex.contains(&"-DRENGO_RADIX_HEX".to_string())).to(be_true());
+        let decimal = crate::link_args(obj, exe, &link_options("linux"));
+        expect!(decimal.contains(&"-DRENGO_RADIX_HEX".to_string())).to(be_false());
+    }
+
+    #[test]
+    fn links_the_runtime_for_the_exit_code_mode() {
+        let obj = std::path::Path::new("out.obj");
+        let exe = std::path::Path::new("out.exe");
+        let mode = |exit_code_mode| crate::link_args(obj, exe, &crate::cli::LinkOptions { exit_code_mode, ..link_options("linux") });
+        expect!(mode(Some(crate::cli::ExitCodeMode::Error)).contains(&"-DRENGO_EXIT_CODE_ERROR".to_string())).to(be_true());
+        expect!(mode(Some(crate::cli::ExitCodeMode::Truncate)).contains(&"-DRENGO_EXIT_CODE_TRUNCATE".to_string())).to(be_true());
+        expect!(mode(None).iter().any(|arg| arg.starts_with("-DRENGO_EXIT_CODE"))).to(be_false());
+    }
+
+    #[test]
+    fn links_with_the_selected_pie_mode() {
+        let obj = std::path::Path::new("out.obj");
+        let exe = std::path::Path::new("out.exe");
+        let pie = crate::link_args(obj, exe, &crate::cli::LinkOptions { pie: Some(true), ..link_options("linux") });
+        expect!(pie.ends_with(&["-fPIE".to_string(), "-pie".to_string()])).to(be_true());
+        let no_pie = crate::link_args(obj, exe, &crate::cli::LinkOptions { pie: Some(false), ..link_options("macos") });
+        expect!(no_pie.contains(&"-no-pie".to_string())).to(be_true());
+        let default = crate::link_args(obj, exe, &link_options("linux"));
+        expect!(default.iter().any(|arg| arg.contains("pie") || arg.contains("PIE"))).to(be_false());
+    }
+
+    #[test]
+    fn reports_toolchain_failures() {
+        let missing = std::path::Path::new("build/does-not-exist.obj");
+        let exe = std::env::temp_dir().join(format!("rengo-link-{}.exe", std::process::id()));
+        let result = crate::link(missing, &exe, &link_options(std::env::consts::OS));
+        expect!(matches!(result, Err(rengo::error::RengoError::Link(_)))).to(be_true());
+    }
+
     #[test]
     fn executes_correctly() {
         let program = "420".to_string();
@@ -108,5 +769,144 @@ mod tests {
         parse_compile_and_assemble(program.clone()).unwrap();
         let result = execute(program).unwrap();
         expect!(result).to(be_equal_to(69));
+
+        let program = "6 & 3".to_string();
+        parse_compile_and_assemble(program.clone()).unwrap();
+        let result = execute(program).unwrap();
+        expect!(result).to(be_equal_to(2));
+
+        let program = "1 << 4".to_string();
+        parse_compile_and_assemble(program.clone()).unwrap();
+        let result = execute(program).unwrap();
+        expect!(result).to(be_equal_to(16));
+
+        let program = "-1 >> 1".to_string();
+        parse_compile_and_assemble(program.clone()).unwrap();
+        let result = execute(program).unwrap();
+        expect!(result).to(be_equal_to(-1));
+
+        let program = "let acc = 0; let acc = acc++; let acc = acc++; acc".to_string();
+        parse_compile_and_assemble(program.clone()).unwrap();
+        let result = execute(program).unwrap();
+        expect!(result).to(be_equal_to(2));
+
+        let program = "1; 2; 3".to_string();
+        parse_compile_and_assemble(program.clone()).unwrap();
+        let result = execute(program).unwrap();
+        expect!(result).to(be_equal_to(3));
+
+        // `x++` and `x--` leave `x` at 5, so the value is that of the last `x--`, not 5
+        let program = "let x = 5; x++; x--".to_string();
+        parse_compile_and_assemble(program.clone()).unwrap();
+        let result = execute(program).unwrap();
+        expect!(result).to(be_equal_to(4));
+
+        let program = "let mut x = 5; x = x++; x = x--; x".to_string();
+        parse_compile_and_assemble(program.clone()).unwrap();
+        let result = execute(program).unwrap();
+        expect!(result).to(be_equal_to(5));
+
+        let program = "10 - 4 + 1".to_string();
+        parse_compile_and_assemble(program.clone()).unwrap();
+        let result = execute(program).unwrap();
+        expect!(result).to(be_equal_to(7));
+
+        let program = "assert(1 < 2); 7".to_string();
+        parse_compile_and_assemble(program.clone()).unwrap();
+        let result = execute(program).unwrap();
+        expect!(result).to(be_equal_to(7));
+
+        let program = "print(\"hi\"); 7".to_string();
+        parse_compile_and_assemble(program.clone()).unwrap();
+        let output = execute_with_output(program).unwrap();
+        expect!(output).to(be_equal_to(("hi\n".to_string(), 7)));
+
+        let options = crate::cli::LinkOptions { exit_code_mode: Some(crate::cli::ExitCodeMode::Error), ..link_options(std::env::consts::OS) };
+        let program = "420".to_string();
+        parse_compile_and_link(program.clone(), &options).unwrap();
+        let error = execute(program).unwrap_err();
+        let exit_code = match error.downcast_ref::<rengo::runner::RunError>() {
+            Some(rengo::runner::RunError::ExitCode(code)) => Some(*code),
+            _ => None,
+        };
+        expect!(exit_code).to(be_some().value(rengo::runner::OUT_OF_RANGE_EXIT_CODE));
+
+        let program = "assert(2 < 1); 7".to_string();
+        parse_compile_and_assemble(program.clone()).unwrap();
+        let error = execute(program).unwrap_err();
+        let exit_code = match error.downcast_ref::<rengo::runner::RunError>() {
+            Some(rengo::runner::RunError::ExitCode(code)) => Some(*code),
+            _ => None,
+        };
+        expect!(exit_code).to(be_some().value(rengo::compiler::compile::ASSERT_EXIT_CODE));
+
+        let program = "let i = 2; [10, 20, 30][i]".to_string();
+        parse_compile_and_assemble(program.clone()).unwrap();
+        expect!(execute(program).unwrap()).to(be_equal_to(30));
+
+        let program = "let i = 2; [10, 20, 30][i]".to_string();
+        parse_compile_sanitized_and_assemble(program.clone()).unwrap();
+        expect!(execute(program).unwrap()).to(be_equal_to(30));
+
+        let program = "let i = 3; [10, 20, 30][i]".to_string();
+        parse_compile_sanitized_and_assemble(program.clone()).unwrap();
+        let error = execute(program).unwrap_err();
+        let exit_code = match error.downcast_ref::<rengo::runner::RunError>() {
+            Some(rengo::runner::RunError::ExitCode(code)) => Some(*code),
+            _ => None,
+        };
+        expect!(exit_code).to(be_some().value(rengo::compiler::compile::INDEX_EXIT_CODE));
+    }
+
+    #[test]
+    fn places_printed_strings_in_the_data_section() {
+        let ast = rengo::parse_source("print(\"hi\"); print(\"hi\"); 0").unwrap();
+        let unit = crate::compile(ast, &Default::default()).unwrap();
+        let asm = crate::generate_asm(unit, "_start", false, Default::default());
+        expect!(asm.contains("extern print_str\n")).to(be_true());
+        expect!(asm.contains("section .data\nstr0: db \"hi\", 0\nsection .text\n")).to(be_true());
+    }
+
+    #[test]
+    fn places_function_routines_after_the_program() {
+        let ast = rengo::parse_source("fn add(a, b) = (assert(a); a + b); add(1, 2)").unwrap();
+        let unit = crate::compile(ast, &Default::default()).unwrap();
+        let asm = crate::generate_asm(unit, "_start", false, Default::default());
+        expect!(asm.contains("extern fn0_add")).to(be_false());
+        expect!(asm.starts_with("extern rengo_assert_failed\nsection .text\n")).to(be_true());
+        let call = asm.find("call fn0_add").unwrap();
+        let ret = asm.find("\nret\n").unwrap();
+        let routine = asm.find("\nfn0_add:\n").unwrap();
+        expect!(call < ret && ret < routine).to(be_true());
+        expect!(asm.ends_with("ret\n")).to(be_true());
+    }
+
+    #[test]
+    fn declares_called_functions_as_external() {
+        let ast = rengo::parse_source("assert(1); assert(2)").unwrap();
+        let unit = crate::compile(ast, &Default::default()).unwrap();
+        let asm = crate::generate_asm(unit, "_start", false, Default::default());
+        expect!(asm.matches("extern rengo_assert_failed\n").count()).to(be_equal_to(1));
+        expect!(asm.starts_with("extern rengo_assert_failed\nsection .text\n")).to(be_true());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn sanitize_checks_indices_at_runtime() {
+        let ast = rengo::parse_source("let i = 1; [1, 2][i]").unwrap();
+        let args = crate::cli::parse_args(&["rengo".to_string(), "--sanitize".to_string(), "program.rg".to_string()]).unwrap();
+        let unit = crate::compile(ast, &args.compile_options()).unwrap();
+        let asm = crate::generate_asm(unit, "_start", false, Default::default());
+        expect!(asm.starts_with("extern rengo_index_out_of_bounds\n")).to(be_true());
+        expect!(asm.contains("jb inbounds0\n")).to(be_true());
+    }
+
+    #[test]
+    fn crlf_line_endings_end_every_line() {
+        let ast = rengo::parse_source("assert(1); 7").unwrap();
+        let unit = crate::compile(ast, &Default::default()).unwrap();
+        let asm = crate::generate_asm(unit, "_start", true, rengo::asm::style::LineEnding::CrLf);
+        expect!(asm.ends_with("ret\r\n")).to(be_true());
+        expect!(asm.matches('\n').count()).to(be_equal_to(asm.matches("\r\n").count()));
+        expect!(asm.starts_with("default rel\r\nextern rengo_assert_failed\r\nsection .text\r\n")).to(be_true());
+    }
+}
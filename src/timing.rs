@@ -0,0 +1,85 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Measures the wall-clock duration of each phase of the compiler and reports it (`--time`).
+///
+/// A disabled timer runs the phases untimed, so the pipeline can be written the same way whether
+/// `--time` is given or not.
+pub(crate) struct PhaseTimer<'a> {
+    /// Where to write the duration of each phase.
+    report: &'a mut dyn Write,
+
+    /// Whether to report the duration of each phase at all.
+    enabled: bool,
+}
+
+impl<'a> PhaseTimer<'a> {
+    /// Creates a timer that writes the duration of each phase to `report`, if `enabled`.
+    pub(crate) fn new(report: &'a mut dyn Write, enabled: bool) -> Self {
+        PhaseTimer { report, enabled }
+    }
+
+    /// Returns the stream the report is written to, so other diagnostics can be written to it in
+    /// order with the durations.
+    pub(crate) fn stream(&mut self) -> &mut dyn Write {
+        self.report
+    }
+
+    /// Runs a phase and reports how long it took, as `<phase>: <milliseconds> ms`.
+    ///
+    /// ## Parameters:
+    /// - `phase`: The name of the phase, as shown in the report.
+    /// - `run`: The work of the phase.
+    ///
+    /// ## Returns:
+    /// The result of `run`, or an error if the report could not be written.
+    pub(crate) fn time<R>(&mut self, phase: &str, run: impl FnOnce() -> R) -> Result<R, io::Error> {
+        let start = Instant::now();
+        let result = run();
+        if self.enabled {
+            writeln!(self.report, "{}", format_duration(phase, start.elapsed()))?;
+        }
+        Ok(result)
+    }
+}
+
+/// Formats the duration of a phase as a line of the `--time` report.
+fn format_duration(phase: &str, duration: Duration) -> String {
+    format!("{:<10} {:>10.3} ms", format!("{}:", phase), duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use expectest::prelude::*;
+    use super::*;
+
+    mod time {
+        use super::*;
+
+        #[test]
+        fn reports_the_phase_and_returns_its_result() {
+            let mut report = Vec::new();
+            let result = PhaseTimer::new(&mut report, true).time("parse", || 42).unwrap();
+            expect!(result).to(be_equal_to(42));
+            let report = String::from_utf8(report).unwrap();
+            expect!(report.starts_with("parse:")).to(be_true());
+            expect!(report.ends_with(" ms\n")).to(be_true());
+        }
+
+        #[test]
+        fn runs_untimed_when_disabled() {
+            let mut report = Vec::new();
+            expect!(PhaseTimer::new(&mut report, false).time("parse", || 42).unwrap()).to(be_equal_to(42));
+            expect!(report.is_empty()).to(be_true());
+        }
+    }
+
+    mod format_duration {
+        use super::*;
+
+        #[test]
+        fn aligns_the_milliseconds() {
+            expect!(format_duration("link", Duration::from_micros(1500))).to(be_equal_to("link:           1.500 ms".to_string()));
+        }
+    }
+}